@@ -1,8 +1,11 @@
 mod services;
 
-use services::{activity_tracker, capture, permissions, storage, system_info, window};
+use services::{
+    activity_tracker, ai, brain_index, capture, conversations, mcp, memory, permissions, pomodoro,
+    prompts, replay, secrets, storage, system_info, voice, web_search, window,
+};
 use tauri::Manager;
-use tauri::menu::{Menu, MenuItem};
+use tauri::menu::{Menu, MenuItem, PredefinedMenuItem};
 use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
 use tauri_plugin_autostart::MacosLauncher;
 
@@ -10,8 +13,15 @@ use tauri_plugin_autostart::MacosLauncher;
 pub fn run() {
     let mut builder = tauri::Builder::default()
         .manage(system_info::SystemMonitor::new())
+        .manage(replay::ReplayBuffer::new())
+        .manage(mcp::McpManager::default())
+        .manage(brain_index::BrainIndex::default())
+        .manage(voice::VoicePipeline::default())
+        .manage(voice::tts::SpeechOutput::default())
+        .manage(voice::microphones::MicrophoneTest::default())
         .plugin(tauri_plugin_opener::init())
-        .plugin(tauri_plugin_dialog::init());
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_notification::init());
 
     // Add macOS-specific plugins
     #[cfg(target_os = "macos")]
@@ -43,9 +53,65 @@ pub fn run() {
             storage::load_brain_data,
             storage::list_brain_files,
             storage::change_data_folder,
+            memory::list_memories,
+            memory::add_memory,
+            memory::update_memory,
+            memory::delete_memory,
+            memory::forget_memories,
+            memory::find_duplicate_memories,
+            memory::import_knowledge_markdown,
+            memory::get_memory_prompt,
+            brain_index::search_brain,
+            brain_index::rebuild_brain_index,
+            brain_index::get_relevant_memory_prompt,
+            conversations::create_conversation,
+            conversations::list_conversations,
+            conversations::get_conversation,
+            conversations::append_conversation_message,
+            conversations::search_conversations,
+            conversations::rename_conversation,
+            conversations::delete_conversation,
+            conversations::get_conversation_thumbnail,
+            conversations::export_conversation,
+            prompts::render_system_prompt,
+            prompts::preview_prompt_template,
+            prompts::list_prompt_templates,
+            prompts::load_prompt_template,
+            prompts::save_prompt_template,
+            prompts::list_personas,
+            prompts::save_persona,
+            prompts::delete_persona,
+            prompts::set_active_persona,
+            prompts::get_effective_persona,
+            web_search::web_search,
+            web_search::fetch_page_text,
+            voice::start_voice_listening,
+            voice::stop_voice_listening,
+            voice::get_voice_status,
+            voice::process_voice_file,
+            voice::microphones::list_microphones,
+            voice::microphones::get_default_microphone,
+            voice::microphones::select_microphone,
+            voice::microphones::start_microphone_test,
+            voice::microphones::stop_microphone_test,
+            voice::tts::speak,
+            voice::tts::speak_partial,
+            voice::tts::stop_speaking,
+            voice::tts::set_tts_muted,
+            voice::tts::get_speech_status,
+            voice::tts::list_tts_voices,
+            voice::tts::export_speech_wav,
+            secrets::set_api_key,
+            secrets::delete_api_key,
+            secrets::get_api_key_status,
             capture::capture_screen,
             capture::get_screen_info,
             capture::get_available_screens,
+            replay::start_replay_buffer,
+            replay::stop_replay_buffer,
+            replay::get_replay_status,
+            replay::save_replay_clip,
+            replay::get_replay_frames,
             permissions::check_permissions,
             permissions::check_screen_recording_permission,
             permissions::check_microphone_permission,
@@ -54,11 +120,50 @@ pub fn run() {
             permissions::open_privacy_settings,
             permissions::test_screen_capture,
             system_info::get_system_stats,
+            system_info::history::get_system_history,
+            system_info::alerts::list_alert_rules,
+            system_info::alerts::save_alert_rule,
+            system_info::alerts::delete_alert_rule,
+            system_info::alerts::reset_alert_rules,
+            system_info::alerts::get_alert_history,
+            system_info::alerts::clear_alert_history,
+            ai::ai_chat,
+            ai::ai_chat_stream,
+            ai::ai_test_connection,
+            ai::list_local_models,
+            ai::check_local_health,
+            ai::save_provider_profile,
+            ai::delete_provider_profile,
+            ai::set_fallback_chain,
+            ai::usage::get_ai_usage,
+            mcp::list_mcp_servers,
+            mcp::save_mcp_server,
+            mcp::delete_mcp_server,
+            mcp::set_mcp_server_enabled,
+            mcp::reconnect_mcp_server,
+            mcp::list_mcp_resources,
+            mcp::respond_mcp_approval,
             activity_tracker::track_activity,
             activity_tracker::get_activity_stats,
             activity_tracker::reset_activity_today,
             storage::load_pomodoro_settings,
             storage::save_pomodoro_settings,
+            pomodoro::get_pomodoro_state,
+            pomodoro::start_pomodoro,
+            pomodoro::pause_pomodoro,
+            pomodoro::skip_pomodoro_phase,
+            pomodoro::previous_pomodoro_phase,
+            pomodoro::reset_pomodoro_phase,
+            pomodoro::reset_pomodoro,
+            pomodoro::set_pomodoro_task,
+            pomodoro::focus::get_pomodoro_focus,
+            pomodoro::history::get_pomodoro_history,
+            pomodoro::history::get_pomodoro_stats,
+            pomodoro::profiles::list_pomodoro_profiles,
+            pomodoro::profiles::save_pomodoro_profile,
+            pomodoro::profiles::delete_pomodoro_profile,
+            pomodoro::profiles::set_active_pomodoro_profile,
+            pomodoro::sounds::preview_pomodoro_sound,
             storage::load_color_theme,
             storage::save_color_theme,
             storage::load_cozy_theme,
@@ -85,7 +190,32 @@ pub fn run() {
             // Initialize activity tracker with captures directory from user config
             let config = storage::load_config(app.handle().clone());
             let captures_dir = storage::get_captures_dir(&config);
-            app.manage(activity_tracker::ActivityTracker::new(captures_dir));
+            app.manage(activity_tracker::ActivityTracker::new(captures_dir.clone()));
+            app.manage(ai::usage::UsageTracker::new(captures_dir));
+
+            // Resume the pomodoro timer where it was left
+            app.manage(pomodoro::Pomodoro::load(app.handle()));
+            pomodoro::start_ticker(app.handle().clone());
+            pomodoro::focus::start_sampler(app.handle().clone());
+            app.manage(system_info::history::MetricsHistory::load(app.handle()));
+            app.manage(system_info::alerts::SystemAlerts::load(app.handle()));
+            system_info::history::start_sampler(app.handle().clone());
+
+            // Start the replay buffer if the user opted in
+            if config.replay_enabled {
+                if let Err(e) = replay::start_from_config(app.handle(), &config) {
+                    eprintln!("Failed to start replay buffer: {}", e);
+                }
+            }
+
+            // Drop conversations older than the retention setting
+            conversations::prune(app.handle(), &config);
+
+            // Tell the UI when microphones are plugged in or removed
+            voice::microphones::watch_devices(app.handle().clone());
+
+            // Connect enabled MCP servers in the background
+            tauri::async_runtime::spawn(mcp::connect_enabled(app.handle().clone()));
 
             // Initialize autostart based on config (enable by default on first run)
             {
@@ -101,9 +231,31 @@ pub fn run() {
             // Set up system tray icon with menu
             let show_item = MenuItem::with_id(app, "show", "Show WOPR", true, None::<&str>)?;
             let hide_item = MenuItem::with_id(app, "hide", "Hide WOPR", true, None::<&str>)?;
+            let pomodoro_item =
+                MenuItem::with_id(app, "pomodoro_toggle", "Start Pomodoro", true, None::<&str>)?;
+            let pomodoro_skip_item = MenuItem::with_id(
+                app,
+                "pomodoro_skip",
+                "Skip Pomodoro Phase",
+                true,
+                None::<&str>,
+            )?;
             let quit_item = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
 
-            let menu = Menu::with_items(app, &[&show_item, &hide_item, &quit_item])?;
+            let menu = Menu::with_items(
+                app,
+                &[
+                    &show_item,
+                    &hide_item,
+                    &PredefinedMenuItem::separator(app)?,
+                    &pomodoro_item,
+                    &pomodoro_skip_item,
+                    &PredefinedMenuItem::separator(app)?,
+                    &quit_item,
+                ],
+            )?;
+            app.state::<pomodoro::Pomodoro>()
+                .set_tray_item(pomodoro_item);
 
             let _tray = TrayIconBuilder::with_id("main")
                 .icon(app.default_window_icon().unwrap().clone())
@@ -122,6 +274,12 @@ pub fn run() {
                                 let _ = window.hide();
                             }
                         }
+                        "pomodoro_toggle" => {
+                            pomodoro::toggle(app);
+                        }
+                        "pomodoro_skip" => {
+                            pomodoro::skip_pomodoro_phase(app.clone());
+                        }
                         "quit" => {
                             app.exit(0);
                         }
@@ -149,6 +307,9 @@ pub fn run() {
                 })
                 .build(app)?;
 
+            // Show a timer restored from the last run in the tray
+            pomodoro::get_pomodoro_state(app.handle().clone());
+
             // Restore window state on startup
             if let Some(window) = app.get_webview_window("main") {
                 let config = window::load_window_config(app.handle());
@@ -200,6 +200,16 @@ fn get_today_date() -> String {
     format!("{}", days)
 }
 
+/// Sample the foreground app between tracking ticks, keeping `current_app` fresh
+/// Used by the pomodoro focus tracking, which needs finer samples than the 60s tracking tick.
+pub fn sample_active_app(state: &ActivityTracker) -> Option<String> {
+    let current_app = get_active_app();
+    if let (Some(app), Ok(mut last_app)) = (&current_app, state.last_app.lock()) {
+        *last_app = Some(app.clone());
+    }
+    current_app
+}
+
 #[tauri::command]
 pub fn track_activity(state: tauri::State<ActivityTracker>) -> Result<Option<String>, String> {
     let now = SystemTime::now()
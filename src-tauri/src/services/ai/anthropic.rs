@@ -0,0 +1,369 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use super::{
+    check_status, http_client, read_sse, AiError, AiProvider, Completion, CompletionRequest, OnToken,
+    TokenUsage, ToolCall, DEFAULT_IDLE_TIMEOUT,
+};
+
+const DEFAULT_BASE_URL: &str = "https://api.anthropic.com/v1";
+const API_VERSION: &str = "2023-06-01";
+
+/// Models that support the server-side web search tool
+const SEARCH_SUPPORTED_MODELS: &[&str] = &[
+    "claude-sonnet-4-20250514",
+    "claude-3-5-sonnet-20241022",
+    "claude-3-5-haiku-20241022",
+];
+
+/// Anthropic messages API
+pub struct AnthropicProvider {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    model: String,
+}
+
+impl AnthropicProvider {
+    pub fn new(model: &str, api_key: String) -> Self {
+        Self::with_base_url(DEFAULT_BASE_URL, model, api_key)
+    }
+
+    pub fn with_base_url(base_url: &str, model: &str, api_key: String) -> Self {
+        Self {
+            client: http_client(DEFAULT_IDLE_TIMEOUT),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            model: model.to_string(),
+        }
+    }
+
+    /// Give up when nothing arrives for this long
+    pub fn with_idle_timeout(self, timeout: Duration) -> Self {
+        Self {
+            client: http_client(timeout),
+            ..self
+        }
+    }
+
+    fn body(&self, request: &CompletionRequest, stream: bool) -> Value {
+        let image_index = request.image_message_index();
+
+        // Anthropic only knows user and assistant roles, the system prompt is separate.
+        // Tool results go back as user messages; consecutive results share one message.
+        let mut messages: Vec<Value> = Vec::new();
+        let mut in_tool_results = false;
+        for (i, message) in request.messages.iter().enumerate() {
+            if let Some(result) = &message.tool_result {
+                let mut content = vec![json!({ "type": "text", "text": result.content })];
+                content.extend(result.image.as_deref().map(image_block));
+                let block = json!({
+                    "type": "tool_result",
+                    "tool_use_id": result.call_id,
+                    "content": content
+                });
+
+                match messages.last_mut() {
+                    Some(last) if in_tool_results => {
+                        if let Some(blocks) = last["content"].as_array_mut() {
+                            blocks.push(block);
+                        }
+                    }
+                    _ => messages.push(json!({ "role": "user", "content": [block] })),
+                }
+                in_tool_results = true;
+                continue;
+            }
+            in_tool_results = false;
+
+            let role = if message.role == "assistant" { "assistant" } else { "user" };
+            if !message.tool_calls.is_empty() {
+                let mut content: Vec<Value> = Vec::new();
+                if !message.content.is_empty() {
+                    content.push(json!({ "type": "text", "text": message.content }));
+                }
+                content.extend(message.tool_calls.iter().map(|call| {
+                    json!({ "type": "tool_use", "id": call.id, "name": call.name, "input": call.arguments })
+                }));
+                messages.push(json!({ "role": "assistant", "content": content }));
+            } else if Some(i) == image_index {
+                let mut content: Vec<Value> = request.images.iter().map(|image| image_block(image)).collect();
+                content.push(json!({ "type": "text", "text": message.content }));
+                messages.push(json!({ "role": role, "content": content }));
+            } else {
+                messages.push(json!({ "role": role, "content": message.content }));
+            }
+        }
+
+        let mut body = json!({
+            "model": self.model,
+            "max_tokens": request.max_tokens,
+            "system": request.system,
+            "messages": messages,
+            "stream": stream,
+        });
+
+        if let Some(temperature) = request.temperature {
+            body["temperature"] = json!(temperature);
+        }
+
+        let mut tools: Vec<Value> = request
+            .tools
+            .iter()
+            .map(|tool| {
+                json!({
+                    "name": tool.name,
+                    "description": tool.description,
+                    "input_schema": tool.parameters
+                })
+            })
+            .collect();
+        if request.web_search && SEARCH_SUPPORTED_MODELS.contains(&self.model.as_str()) {
+            tools.push(json!({
+                "type": "web_search_20250305",
+                "name": "web_search",
+                "max_uses": 3
+            }));
+        }
+        if !tools.is_empty() {
+            body["tools"] = json!(tools);
+        }
+
+        body
+    }
+
+    async fn send(&self, body: &Value) -> Result<reqwest::Response, AiError> {
+        let response = self
+            .client
+            .post(format!("{}/messages", self.base_url))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", API_VERSION)
+            .json(body)
+            .send()
+            .await?;
+
+        check_status(response, "Anthropic").await
+    }
+}
+
+fn image_block(image: &str) -> Value {
+    json!({
+        "type": "image",
+        "source": {
+            "type": "base64",
+            "media_type": "image/jpeg",
+            "data": image
+        }
+    })
+}
+
+/// A `tool_use` block being assembled from `input_json_delta` events
+struct PartialToolUse {
+    index: u64,
+    id: String,
+    name: String,
+    input: String,
+}
+
+#[async_trait]
+impl AiProvider for AnthropicProvider {
+    fn name(&self) -> &'static str {
+        "anthropic"
+    }
+
+    async fn complete(&self, request: &CompletionRequest) -> Result<Completion, AiError> {
+        let response = self.send(&self.body(request, false)).await?;
+        let data: Value = response.json().await?;
+
+        // With web search the answer can be split over several text blocks
+        let text = data["content"]
+            .as_array()
+            .map(|blocks| {
+                blocks
+                    .iter()
+                    .filter_map(|block| block["text"].as_str())
+                    .collect::<Vec<_>>()
+                    .join("")
+            })
+            .unwrap_or_default();
+
+        let tool_calls = data["content"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|block| block["type"] == "tool_use")
+            .map(|block| ToolCall {
+                id: block["id"].as_str().unwrap_or_default().to_string(),
+                name: block["name"].as_str().unwrap_or_default().to_string(),
+                arguments: block["input"].clone(),
+            })
+            .collect();
+
+        Ok(Completion {
+            text,
+            tool_calls,
+            usage: TokenUsage {
+                input_tokens: data["usage"]["input_tokens"].as_u64().unwrap_or(0),
+                output_tokens: data["usage"]["output_tokens"].as_u64().unwrap_or(0),
+            },
+        })
+    }
+
+    async fn stream(
+        &self,
+        request: &CompletionRequest,
+        on_token: &OnToken<'_>,
+    ) -> Result<Completion, AiError> {
+        let response = self.send(&self.body(request, true)).await?;
+
+        let mut completion = Completion::default();
+        let mut tool_uses: Vec<PartialToolUse> = Vec::new();
+        read_sse(response, |data| {
+            if let Ok(parsed) = serde_json::from_str::<Value>(data) {
+                match parsed["type"].as_str() {
+                    Some("content_block_start") if parsed["content_block"]["type"] == "tool_use" => {
+                        tool_uses.push(PartialToolUse {
+                            index: parsed["index"].as_u64().unwrap_or(0),
+                            id: parsed["content_block"]["id"].as_str().unwrap_or_default().to_string(),
+                            name: parsed["content_block"]["name"].as_str().unwrap_or_default().to_string(),
+                            input: String::new(),
+                        });
+                    }
+                    Some("content_block_delta") => {
+                        if let Some(text) = parsed["delta"]["text"].as_str() {
+                            completion.text.push_str(text);
+                            on_token(text);
+                        }
+                        if let Some(json) = parsed["delta"]["partial_json"].as_str() {
+                            let index = parsed["index"].as_u64().unwrap_or(0);
+                            if let Some(tool_use) = tool_uses.iter_mut().find(|t| t.index == index) {
+                                tool_use.input.push_str(json);
+                            }
+                        }
+                    }
+                    // Input tokens arrive up front, output tokens with the final delta
+                    Some("message_start") => {
+                        completion.usage.input_tokens =
+                            parsed["message"]["usage"]["input_tokens"].as_u64().unwrap_or(0);
+                    }
+                    Some("message_delta") => {
+                        completion.usage.output_tokens =
+                            parsed["usage"]["output_tokens"].as_u64().unwrap_or(0);
+                    }
+                    _ => {}
+                }
+            }
+        })
+        .await?;
+
+        completion.tool_calls = tool_uses
+            .into_iter()
+            .map(|tool_use| ToolCall {
+                id: tool_use.id,
+                name: tool_use.name,
+                arguments: if tool_use.input.trim().is_empty() {
+                    json!({})
+                } else {
+                    serde_json::from_str(&tool_use.input).unwrap_or_else(|_| json!({}))
+                },
+            })
+            .collect();
+
+        Ok(completion)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_http::{block_on, Reply, TestServer};
+    use std::sync::Mutex;
+
+    fn request() -> CompletionRequest {
+        serde_json::from_value(json!({
+            "system": "Be brief.",
+            "messages": [{ "role": "user", "content": "Hi" }]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn complete_joins_text_blocks_and_reads_tool_use() {
+        let server = TestServer::replying(Reply::json(
+            200,
+            json!({
+                "content": [
+                    { "type": "text", "text": "Hello" },
+                    { "type": "text", "text": " there" },
+                    { "type": "tool_use", "id": "toolu_1", "name": "get_system_stats", "input": {} }
+                ],
+                "usage": { "input_tokens": 9, "output_tokens": 4 }
+            }),
+        ));
+        let provider = AnthropicProvider::with_base_url(&server.url, "claude-test", "sk-ant-test".to_string());
+
+        let completion = block_on(provider.complete(&request())).unwrap();
+
+        assert_eq!(completion.text, "Hello there");
+        assert_eq!(completion.tool_calls.len(), 1);
+        assert_eq!(completion.tool_calls[0].id, "toolu_1");
+        assert_eq!(completion.usage.input_tokens, 9);
+        assert_eq!(completion.usage.output_tokens, 4);
+
+        let sent = &server.requests()[0];
+        assert_eq!(sent.path, "/messages");
+        assert_eq!(sent.header("x-api-key"), Some("sk-ant-test"));
+        assert_eq!(sent.header("anthropic-version"), Some(API_VERSION));
+        let body = sent.json();
+        assert_eq!(body["system"], "Be brief.");
+        assert_eq!(body["messages"], json!([{ "role": "user", "content": "Hi" }]));
+    }
+
+    #[test]
+    fn stream_emits_tokens_in_order_and_collects_usage() {
+        let server = TestServer::replying(Reply::sse(&[
+            json!({ "type": "message_start", "message": { "usage": { "input_tokens": 15 } } }),
+            json!({ "type": "content_block_start", "index": 0, "content_block": { "type": "text", "text": "" } }),
+            json!({ "type": "content_block_delta", "index": 0, "delta": { "type": "text_delta", "text": "One" } }),
+            json!({ "type": "content_block_delta", "index": 0, "delta": { "type": "text_delta", "text": " two" } }),
+            json!({ "type": "content_block_start", "index": 1, "content_block": { "type": "tool_use", "id": "toolu_2", "name": "web_search" } }),
+            json!({ "type": "content_block_delta", "index": 1, "delta": { "type": "input_json_delta", "partial_json": "{\"query\": " } }),
+            json!({ "type": "content_block_delta", "index": 1, "delta": { "type": "input_json_delta", "partial_json": "\"tauri\"}" } }),
+            json!({ "type": "content_block_delta", "index": 0, "delta": { "type": "text_delta", "text": " three" } }),
+            json!({ "type": "message_delta", "usage": { "output_tokens": 6 } }),
+        ]));
+        let provider = AnthropicProvider::with_base_url(&server.url, "claude-test", "key".to_string());
+        let tokens = Mutex::new(Vec::new());
+
+        let completion = block_on(provider.stream(&request(), &|text: &str| {
+            tokens.lock().unwrap().push(text.to_string())
+        }))
+        .unwrap();
+
+        assert_eq!(tokens.into_inner().unwrap(), ["One", " two", " three"]);
+        assert_eq!(completion.text, "One two three");
+        assert_eq!(completion.tool_calls[0].name, "web_search");
+        assert_eq!(completion.tool_calls[0].arguments, json!({ "query": "tauri" }));
+        assert_eq!(completion.usage.input_tokens, 15);
+        assert_eq!(completion.usage.output_tokens, 6);
+        assert_eq!(server.requests()[0].json()["stream"], true);
+    }
+
+    #[test]
+    fn error_status_uses_the_provider_message() {
+        let server = TestServer::replying(Reply::json(
+            529,
+            json!({ "type": "error", "error": { "type": "overloaded_error", "message": "Overloaded" } }),
+        ));
+        let provider = AnthropicProvider::with_base_url(&server.url, "claude-test", "key".to_string());
+
+        match block_on(provider.stream(&request(), &|_: &str| {})) {
+            Err(AiError::Http { status, message }) => {
+                assert_eq!(status, 529);
+                assert_eq!(message, "Overloaded");
+            }
+            other => panic!("expected an HTTP error, got {:?}", other.map(|c| c.text)),
+        }
+    }
+}
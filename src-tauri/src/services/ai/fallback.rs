@@ -0,0 +1,147 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tauri::AppHandle;
+
+use super::{create_provider, usage, AiError, CompletionRequest, OnToken, TokenUsage, ToolCall};
+use crate::services::secrets;
+use crate::services::storage::{AppConfig, ProviderProfile};
+
+/// First retry delay; doubled on every further attempt
+const BASE_BACKOFF_MS: u64 = 500;
+const MAX_BACKOFF_MS: u64 = 8000;
+
+/// A response together with the profile that produced it
+#[derive(Debug, Clone, Serialize)]
+pub struct AiResponse {
+    pub text: String,
+    pub profile: String,
+    pub provider: String,
+    pub model: String,
+    /// Tool calls the model is waiting on (empty for a final answer)
+    pub tool_calls: Vec<ToolCall>,
+    pub usage: TokenUsage,
+    /// Estimated cost in USD
+    pub cost_usd: f64,
+}
+
+impl AiError {
+    /// Transient failures worth retrying on the same profile
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            AiError::Http { status, .. } => *status == 429 || (500..=599).contains(status),
+            AiError::Timeout | AiError::Network(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Failures caused by the profile itself (bad key, unknown model, ...)
+    /// that another profile might not have
+    pub fn is_profile_error(&self) -> bool {
+        match self {
+            AiError::Http { status, .. } => matches!(status, 401 | 403 | 404),
+            AiError::MissingApiKey | AiError::UnknownProvider(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Profiles to try, in order
+/// Uses the fallback chain when one is configured, otherwise the top-level provider settings
+pub fn resolve_chain(config: &AppConfig) -> Vec<ProviderProfile> {
+    let chain: Vec<ProviderProfile> = config
+        .fallback_chain
+        .iter()
+        .filter_map(|name| config.provider_profiles.iter().find(|p| &p.name == name))
+        .cloned()
+        .collect();
+
+    if chain.is_empty() {
+        vec![ProviderProfile::from_config(config)]
+    } else {
+        chain
+    }
+}
+
+fn backoff(attempt: u32) -> Duration {
+    let ms = BASE_BACKOFF_MS.saturating_mul(1 << attempt.min(10));
+    Duration::from_millis(ms.min(MAX_BACKOFF_MS))
+}
+
+/// Run a request against each profile in turn
+/// Retryable errors are retried with exponential backoff before moving on to the next profile.
+/// When streaming, a profile that already sent tokens is not abandoned mid-answer.
+pub async fn run_chain(
+    app: &AppHandle,
+    profiles: &[ProviderProfile],
+    request: &CompletionRequest,
+    on_token: Option<&OnToken<'_>>,
+) -> Result<AiResponse, AiError> {
+    let mut last_error = AiError::UnknownProvider("no provider profiles configured".to_string());
+
+    for profile in profiles {
+        let provider = match create_provider(
+            &profile.provider,
+            &profile.model,
+            secrets::get_api_key(app, profile.key_name()),
+            profile.base_url.as_deref(),
+            Duration::from_secs(profile.timeout_secs.max(1)),
+        ) {
+            Ok(provider) => provider,
+            Err(e) => {
+                eprintln!("Skipping AI profile '{}': {}", profile.name, e);
+                last_error = e;
+                continue;
+            }
+        };
+
+        let mut profile_request = request.clone();
+        if let Some(max_tokens) = profile.max_tokens {
+            profile_request.max_tokens = profile_request.max_tokens.min(max_tokens);
+        }
+
+        let emitted = AtomicBool::new(false);
+        let mut attempt = 0;
+        loop {
+            // The profile's timeout is applied by the client to the first byte and each gap
+            // between chunks, so an answer that keeps streaming is never cut off
+            let result = match on_token {
+                Some(on_token) => {
+                    let tracked = |text: &str| {
+                        emitted.store(true, Ordering::SeqCst);
+                        on_token(text);
+                    };
+                    provider.stream(&profile_request, &tracked).await
+                }
+                None => provider.complete(&profile_request).await,
+            };
+
+            match result {
+                Ok(completion) => {
+                    return Ok(AiResponse {
+                        text: completion.text,
+                        profile: profile.name.clone(),
+                        provider: profile.provider.clone(),
+                        model: profile.model.clone(),
+                        tool_calls: completion.tool_calls,
+                        usage: completion.usage,
+                        cost_usd: usage::estimate_cost(&profile.provider, &profile.model, &completion.usage),
+                    })
+                }
+                Err(e) if emitted.load(Ordering::SeqCst) => return Err(e),
+                Err(e) if e.is_retryable() && attempt < profile.max_retries => {
+                    tokio::time::sleep(backoff(attempt)).await;
+                    attempt += 1;
+                }
+                Err(e) if e.is_retryable() || e.is_profile_error() => {
+                    eprintln!("AI profile '{}' failed, trying next: {}", profile.name, e);
+                    last_error = e;
+                    break;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    Err(last_error)
+}
@@ -0,0 +1,322 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use super::{
+    check_status, http_client, read_sse, AiError, AiProvider, Completion, CompletionRequest, OnToken,
+    TokenUsage, ToolCall, DEFAULT_IDLE_TIMEOUT,
+};
+
+const DEFAULT_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+
+/// Google Gemini generateContent API
+pub struct GeminiProvider {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    model: String,
+}
+
+impl GeminiProvider {
+    pub fn new(model: &str, api_key: String) -> Self {
+        Self::with_base_url(DEFAULT_BASE_URL, model, api_key)
+    }
+
+    pub fn with_base_url(base_url: &str, model: &str, api_key: String) -> Self {
+        Self {
+            client: http_client(DEFAULT_IDLE_TIMEOUT),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            model: model.to_string(),
+        }
+    }
+
+    /// Give up when nothing arrives for this long
+    pub fn with_idle_timeout(self, timeout: Duration) -> Self {
+        Self {
+            client: http_client(timeout),
+            ..self
+        }
+    }
+
+    fn body(&self, request: &CompletionRequest) -> Value {
+        let image_index = request.image_message_index();
+
+        // Function responses go back in a user turn; consecutive responses share one turn
+        let mut contents: Vec<Value> = Vec::new();
+        let mut in_tool_results = false;
+        for (i, message) in request.messages.iter().enumerate() {
+            if let Some(result) = &message.tool_result {
+                let response = serde_json::from_str::<Value>(&result.content)
+                    .unwrap_or_else(|_| json!(result.content));
+                let mut parts = vec![json!({
+                    "functionResponse": { "name": result.name, "response": { "content": response } }
+                })];
+                parts.extend(result.image.as_deref().map(inline_image));
+
+                match contents.last_mut() {
+                    Some(last) if in_tool_results => {
+                        if let Some(existing) = last["parts"].as_array_mut() {
+                            existing.extend(parts);
+                        }
+                    }
+                    _ => contents.push(json!({ "role": "user", "parts": parts })),
+                }
+                in_tool_results = true;
+                continue;
+            }
+            in_tool_results = false;
+
+            let role = if message.role == "assistant" { "model" } else { "user" };
+            let mut parts: Vec<Value> = if Some(i) == image_index {
+                request.images.iter().map(|image| inline_image(image)).collect()
+            } else {
+                vec![]
+            };
+            if !message.content.is_empty() || message.tool_calls.is_empty() {
+                parts.push(json!({ "text": message.content }));
+            }
+            parts.extend(message.tool_calls.iter().map(|call| {
+                json!({ "functionCall": { "name": call.name, "args": call.arguments } })
+            }));
+            contents.push(json!({ "role": role, "parts": parts }));
+        }
+
+        let mut body = json!({
+            "contents": contents,
+            "systemInstruction": { "parts": [{ "text": request.system }] },
+            "generationConfig": {
+                "maxOutputTokens": request.max_tokens,
+                "temperature": request.temperature.unwrap_or(0.7)
+            }
+        });
+
+        // All current Gemini models support Google Search grounding, but it can't be
+        // combined with function declarations, so search wins when both are asked for
+        if request.web_search {
+            body["tools"] = json!([{ "google_search": {} }]);
+        } else if !request.tools.is_empty() {
+            let declarations: Vec<Value> = request
+                .tools
+                .iter()
+                .map(|tool| {
+                    let mut declaration = json!({ "name": tool.name, "description": tool.description });
+                    if tool.has_parameters() {
+                        declaration["parameters"] = gemini_schema(&tool.parameters);
+                    }
+                    declaration
+                })
+                .collect();
+            body["tools"] = json!([{ "functionDeclarations": declarations }]);
+        }
+
+        body
+    }
+
+    async fn send(&self, method: &str, body: &Value) -> Result<reqwest::Response, AiError> {
+        // The key goes in a header rather than the query string so it never ends up in URLs
+        let response = self
+            .client
+            .post(format!("{}/models/{}:{}", self.base_url, self.model, method))
+            .header("x-goog-api-key", &self.api_key)
+            .json(body)
+            .send()
+            .await?;
+
+        check_status(response, "Gemini").await
+    }
+}
+
+/// Concatenate the text parts of the first candidate
+fn candidate_text(data: &Value) -> String {
+    data["candidates"][0]["content"]["parts"]
+        .as_array()
+        .map(|parts| {
+            parts
+                .iter()
+                .filter_map(|part| part["text"].as_str())
+                .collect::<Vec<_>>()
+                .join("")
+        })
+        .unwrap_or_default()
+}
+
+/// Drop JSON schema keywords Gemini rejects (MCP servers often include them)
+fn gemini_schema(schema: &Value) -> Value {
+    match schema {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .filter(|(key, _)| !matches!(key.as_str(), "$schema" | "additionalProperties" | "$ref" | "$defs"))
+                .map(|(key, value)| (key.clone(), gemini_schema(value)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(gemini_schema).collect()),
+        other => other.clone(),
+    }
+}
+
+fn inline_image(image: &str) -> Value {
+    json!({ "inlineData": { "mimeType": "image/jpeg", "data": image } })
+}
+
+/// Function calls in the first candidate
+/// Gemini doesn't always send call ids, so missing ones are numbered from `first_index`
+fn candidate_tool_calls(data: &Value, first_index: usize) -> Vec<ToolCall> {
+    data["candidates"][0]["content"]["parts"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|part| part["functionCall"].is_object())
+        .enumerate()
+        .map(|(i, part)| {
+            let call = &part["functionCall"];
+            ToolCall {
+                id: call["id"]
+                    .as_str()
+                    .map(|id| id.to_string())
+                    .unwrap_or_else(|| format!("call_{}", first_index + i)),
+                name: call["name"].as_str().unwrap_or_default().to_string(),
+                arguments: call["args"].clone(),
+            }
+        })
+        .collect()
+}
+
+fn parse_usage(data: &Value) -> TokenUsage {
+    TokenUsage {
+        input_tokens: data["usageMetadata"]["promptTokenCount"].as_u64().unwrap_or(0),
+        output_tokens: data["usageMetadata"]["candidatesTokenCount"].as_u64().unwrap_or(0),
+    }
+}
+
+#[async_trait]
+impl AiProvider for GeminiProvider {
+    fn name(&self) -> &'static str {
+        "gemini"
+    }
+
+    async fn complete(&self, request: &CompletionRequest) -> Result<Completion, AiError> {
+        let response = self.send("generateContent", &self.body(request)).await?;
+        let data: Value = response.json().await?;
+
+        Ok(Completion {
+            text: candidate_text(&data),
+            tool_calls: candidate_tool_calls(&data, 0),
+            usage: parse_usage(&data),
+        })
+    }
+
+    async fn stream(
+        &self,
+        request: &CompletionRequest,
+        on_token: &OnToken<'_>,
+    ) -> Result<Completion, AiError> {
+        let response = self
+            .send("streamGenerateContent?alt=sse", &self.body(request))
+            .await?;
+
+        let mut completion = Completion::default();
+        read_sse(response, |data| {
+            if let Ok(parsed) = serde_json::from_str::<Value>(data) {
+                let text = candidate_text(&parsed);
+                if !text.is_empty() {
+                    completion.text.push_str(&text);
+                    on_token(&text);
+                }
+                let tool_calls = candidate_tool_calls(&parsed, completion.tool_calls.len());
+                completion.tool_calls.extend(tool_calls);
+                // Every chunk carries running totals, the last one wins
+                if parsed["usageMetadata"].is_object() {
+                    completion.usage = parse_usage(&parsed);
+                }
+            }
+        })
+        .await?;
+
+        Ok(completion)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_http::{block_on, Reply, TestServer};
+    use std::sync::Mutex;
+
+    fn request() -> CompletionRequest {
+        serde_json::from_value(json!({
+            "system": "Be brief.",
+            "messages": [{ "role": "user", "content": "Hi" }]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn complete_reads_the_first_candidate() {
+        let server = TestServer::replying(Reply::json(
+            200,
+            json!({
+                "candidates": [{ "content": { "parts": [
+                    { "text": "Hi " },
+                    { "text": "there" },
+                    { "functionCall": { "name": "get_activity_stats", "args": { "day": "today" } } }
+                ] } }],
+                "usageMetadata": { "promptTokenCount": 8, "candidatesTokenCount": 2 }
+            }),
+        ));
+        let provider = GeminiProvider::with_base_url(&server.url, "gemini-test", "g-key".to_string());
+
+        let completion = block_on(provider.complete(&request())).unwrap();
+
+        assert_eq!(completion.text, "Hi there");
+        assert_eq!(completion.tool_calls.len(), 1);
+        assert_eq!(completion.tool_calls[0].id, "call_0");
+        assert_eq!(completion.tool_calls[0].arguments, json!({ "day": "today" }));
+        assert_eq!(completion.usage.input_tokens, 8);
+        assert_eq!(completion.usage.output_tokens, 2);
+
+        let sent = &server.requests()[0];
+        assert_eq!(sent.path, "/models/gemini-test:generateContent");
+        assert_eq!(sent.header("x-goog-api-key"), Some("g-key"));
+    }
+
+    #[test]
+    fn stream_emits_tokens_in_order_and_keeps_the_last_usage() {
+        let chunk = |text: &str, output_tokens: u64| {
+            json!({
+                "candidates": [{ "content": { "parts": [{ "text": text }] } }],
+                "usageMetadata": { "promptTokenCount": 5, "candidatesTokenCount": output_tokens }
+            })
+        };
+        let server = TestServer::replying(Reply::sse(&[chunk("Good", 1), chunk(" morning", 2), chunk("!", 3)]));
+        let provider = GeminiProvider::with_base_url(&server.url, "gemini-test", "g-key".to_string());
+        let tokens = Mutex::new(Vec::new());
+
+        let completion = block_on(provider.stream(&request(), &|text: &str| {
+            tokens.lock().unwrap().push(text.to_string())
+        }))
+        .unwrap();
+
+        assert_eq!(tokens.into_inner().unwrap(), ["Good", " morning", "!"]);
+        assert_eq!(completion.text, "Good morning!");
+        assert_eq!(completion.usage.output_tokens, 3);
+        assert_eq!(server.requests()[0].path, "/models/gemini-test:streamGenerateContent?alt=sse");
+    }
+
+    #[test]
+    fn error_status_uses_the_provider_message() {
+        let server = TestServer::replying(Reply::json(
+            400,
+            json!({ "error": { "code": 400, "message": "API key not valid.", "status": "INVALID_ARGUMENT" } }),
+        ));
+        let provider = GeminiProvider::with_base_url(&server.url, "gemini-test", "bad".to_string());
+
+        match block_on(provider.complete(&request())) {
+            Err(AiError::Http { status, message }) => {
+                assert_eq!(status, 400);
+                assert_eq!(message, "API key not valid.");
+            }
+            other => panic!("expected an HTTP error, got {:?}", other.map(|c| c.text)),
+        }
+    }
+}
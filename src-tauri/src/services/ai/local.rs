@@ -0,0 +1,341 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::{Duration, Instant};
+
+use super::{AiError, AiProvider, Completion, CompletionRequest, OnToken, OpenAiProvider};
+
+/// Ollama's default address; llama.cpp and LM Studio users change this in settings
+pub const DEFAULT_LOCAL_BASE_URL: &str = "http://localhost:11434";
+
+/// Name fragments of well known vision-capable local models
+const VISION_MODEL_HINTS: &[&str] = &[
+    "llava",
+    "bakllava",
+    "moondream",
+    "vision",
+    "-vl",
+    "vl-",
+    "qwen2.5vl",
+    "minicpm-v",
+    "gemma3",
+    "pixtral",
+    "granite3.2-vision",
+];
+
+/// Local model server speaking the OpenAI chat completions protocol
+/// (Ollama, llama.cpp server, LM Studio, vLLM, ...)
+pub struct LocalProvider {
+    inner: OpenAiProvider,
+}
+
+impl LocalProvider {
+    pub fn new(base_url: &str, model: &str, api_key: Option<String>) -> Self {
+        Self {
+            inner: OpenAiProvider::compatible(&format!("{}/v1", server_root(base_url)), model, api_key),
+        }
+    }
+
+    /// Give up when nothing arrives for this long; local models can be slow to load
+    pub fn with_idle_timeout(self, timeout: Duration) -> Self {
+        Self {
+            inner: self.inner.with_idle_timeout(timeout),
+        }
+    }
+}
+
+#[async_trait]
+impl AiProvider for LocalProvider {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    async fn complete(&self, request: &CompletionRequest) -> Result<Completion, AiError> {
+        self.inner.complete(request).await
+    }
+
+    async fn stream(&self, request: &CompletionRequest, on_token: &OnToken<'_>) -> Result<Completion, AiError> {
+        self.inner.stream(request, on_token).await
+    }
+}
+
+/// Strip trailing slashes and an optional `/v1` so users can paste either form
+pub fn server_root(base_url: &str) -> String {
+    let trimmed = base_url.trim().trim_end_matches('/');
+    trimmed.strip_suffix("/v1").unwrap_or(trimmed).to_string()
+}
+
+/// A model offered by a local server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalModelInfo {
+    pub id: String,
+    /// Whether the model accepts images (used for screen analysis)
+    pub vision: bool,
+    /// Size on disk in bytes, when the server reports it
+    pub size_bytes: Option<u64>,
+}
+
+/// Result of probing a local server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalHealth {
+    pub reachable: bool,
+    /// "ollama" or "openai-compatible"
+    pub server_kind: Option<String>,
+    pub latency_ms: u64,
+    pub model_count: usize,
+    pub error: Option<String>,
+}
+
+fn probe_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(3))
+        .timeout(Duration::from_secs(10))
+        .build()
+        .unwrap_or_default()
+}
+
+async fn get_json(client: &reqwest::Client, url: &str) -> Result<Value, AiError> {
+    let response = client.get(url).send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(AiError::Http {
+            status: status.as_u16(),
+            message: format!("{} returned {}", url, status.as_u16()),
+        });
+    }
+    Ok(response.json().await?)
+}
+
+/// Guess vision support from the model name
+fn name_suggests_vision(id: &str) -> bool {
+    let id = id.to_lowercase();
+    VISION_MODEL_HINTS.iter().any(|hint| id.contains(hint))
+}
+
+/// Ask Ollama for a model's capabilities (`/api/show`), falling back to its families list
+async fn ollama_has_vision(client: &reqwest::Client, root: &str, name: &str) -> Option<bool> {
+    let response = client
+        .post(format!("{}/api/show", root))
+        .json(&serde_json::json!({ "model": name }))
+        .send()
+        .await
+        .ok()?;
+    let data: Value = response.json().await.ok()?;
+
+    if let Some(capabilities) = data["capabilities"].as_array() {
+        return Some(capabilities.iter().any(|c| c == "vision"));
+    }
+    data["details"]["families"]
+        .as_array()
+        .map(|families| families.iter().any(|f| f == "clip" || f == "mllama"))
+}
+
+/// List models from Ollama's native `/api/tags`
+async fn list_ollama_models(client: &reqwest::Client, root: &str) -> Result<Vec<LocalModelInfo>, AiError> {
+    let data = get_json(client, &format!("{}/api/tags", root)).await?;
+    let entries = data["models"]
+        .as_array()
+        .ok_or_else(|| AiError::InvalidResponse("missing models list".to_string()))?;
+
+    let mut models = Vec::new();
+    for entry in entries {
+        let Some(id) = entry["name"].as_str() else { continue };
+        let vision = match ollama_has_vision(client, root, id).await {
+            Some(vision) => vision,
+            None => name_suggests_vision(id),
+        };
+        models.push(LocalModelInfo {
+            id: id.to_string(),
+            vision,
+            size_bytes: entry["size"].as_u64(),
+        });
+    }
+
+    Ok(models)
+}
+
+/// List models from the OpenAI-compatible `/v1/models`
+async fn list_openai_models(client: &reqwest::Client, root: &str) -> Result<Vec<LocalModelInfo>, AiError> {
+    let data = get_json(client, &format!("{}/v1/models", root)).await?;
+    let entries = data["data"]
+        .as_array()
+        .ok_or_else(|| AiError::InvalidResponse("missing data list".to_string()))?;
+
+    Ok(entries
+        .iter()
+        .filter_map(|entry| entry["id"].as_str())
+        .map(|id| LocalModelInfo {
+            id: id.to_string(),
+            vision: name_suggests_vision(id),
+            size_bytes: None,
+        })
+        .collect())
+}
+
+/// Discover models, preferring Ollama's richer API and falling back to `/v1/models`
+/// Returns the server kind alongside the models
+pub async fn discover_models(base_url: &str) -> Result<(String, Vec<LocalModelInfo>), AiError> {
+    let client = probe_client();
+    let root = server_root(base_url);
+
+    match list_ollama_models(&client, &root).await {
+        Ok(models) => Ok(("ollama".to_string(), models)),
+        Err(_) => {
+            let models = list_openai_models(&client, &root).await?;
+            Ok(("openai-compatible".to_string(), models))
+        }
+    }
+}
+
+/// Check whether the local server answers and how many models it offers
+/// Only the model lists are fetched, so this stays fast with many models installed
+pub async fn health_check(base_url: &str) -> LocalHealth {
+    let client = probe_client();
+    let root = server_root(base_url);
+    let started = Instant::now();
+
+    let result = match get_json(&client, &format!("{}/api/tags", root)).await {
+        Ok(data) => Ok(("ollama", data["models"].as_array().map(|m| m.len()).unwrap_or(0))),
+        Err(_) => get_json(&client, &format!("{}/v1/models", root))
+            .await
+            .map(|data| ("openai-compatible", data["data"].as_array().map(|m| m.len()).unwrap_or(0))),
+    };
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    match result {
+        Ok((kind, model_count)) => LocalHealth {
+            reachable: true,
+            server_kind: Some(kind.to_string()),
+            latency_ms,
+            model_count,
+            error: None,
+        },
+        Err(e) => LocalHealth {
+            reachable: false,
+            server_kind: None,
+            latency_ms,
+            model_count: 0,
+            error: Some(e.to_string()),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_http::{block_on, closed_url, Reply, TestServer};
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    fn request() -> CompletionRequest {
+        serde_json::from_value(json!({
+            "system": "Be brief.",
+            "messages": [{ "role": "user", "content": "Hi" }]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn server_root_accepts_either_form() {
+        assert_eq!(server_root("http://localhost:11434/"), "http://localhost:11434");
+        assert_eq!(server_root(" http://localhost:8080/v1/ "), "http://localhost:8080");
+    }
+
+    #[test]
+    fn complete_and_stream_use_the_v1_endpoint_without_a_key() {
+        let server = TestServer::start(|request| {
+            if request.json()["stream"] == true {
+                Reply::sse(&[
+                    json!({ "choices": [{ "delta": { "content": "lo" } }] }),
+                    json!({ "choices": [{ "delta": { "content": "cal" } }] }),
+                ])
+            } else {
+                Reply::json(200, json!({ "choices": [{ "message": { "content": "local" } }] }))
+            }
+        });
+        // A pasted /v1 suffix must not be doubled
+        let provider = LocalProvider::new(&format!("{}/v1", server.url), "llama3", None);
+
+        let completion = block_on(provider.complete(&request())).unwrap();
+        assert_eq!(completion.text, "local");
+
+        let tokens = Mutex::new(Vec::new());
+        let completion = block_on(provider.stream(&request(), &|text: &str| {
+            tokens.lock().unwrap().push(text.to_string())
+        }))
+        .unwrap();
+        assert_eq!(tokens.into_inner().unwrap(), ["lo", "cal"]);
+        assert_eq!(completion.text, "local");
+
+        let requests = server.requests();
+        assert!(requests.iter().all(|r| r.path == "/v1/chat/completions"));
+        assert!(requests.iter().all(|r| r.header("authorization").is_none()));
+    }
+
+    #[test]
+    fn error_status_maps_to_http_error() {
+        let server = TestServer::replying(Reply::json(404, json!({ "error": { "message": "model \"nope\" not found" } })));
+        let provider = LocalProvider::new(&server.url, "nope", None);
+
+        match block_on(provider.complete(&request())) {
+            Err(AiError::Http { status, message }) => {
+                assert_eq!(status, 404);
+                assert_eq!(message, "model \"nope\" not found");
+            }
+            other => panic!("expected an HTTP error, got {:?}", other.map(|c| c.text)),
+        }
+    }
+
+    #[test]
+    fn unreachable_server_is_a_network_error() {
+        let provider = LocalProvider::new(&closed_url(), "llama3", None);
+        assert!(matches!(block_on(provider.complete(&request())), Err(AiError::Network(_))));
+
+        let health = block_on(health_check(&closed_url()));
+        assert!(!health.reachable);
+        assert!(health.error.is_some());
+    }
+
+    #[test]
+    fn discovers_ollama_models_with_capabilities() {
+        let server = TestServer::start(|request| match request.path.as_str() {
+            "/api/tags" => Reply::json(
+                200,
+                json!({ "models": [
+                    { "name": "llava:7b", "size": 4_700_000_000u64 },
+                    { "name": "qwen2.5:3b", "size": 1_900_000_000u64 }
+                ] }),
+            ),
+            "/api/show" if request.json()["model"] == "llava:7b" => {
+                Reply::json(200, json!({ "capabilities": ["completion", "vision"] }))
+            }
+            "/api/show" => Reply::json(200, json!({ "details": { "families": ["qwen2"] } })),
+            _ => Reply::json(404, json!({})),
+        });
+
+        let (kind, models) = block_on(discover_models(&server.url)).unwrap();
+
+        assert_eq!(kind, "ollama");
+        assert_eq!(models.len(), 2);
+        assert!(models[0].vision);
+        assert_eq!(models[0].size_bytes, Some(4_700_000_000));
+        assert!(!models[1].vision);
+    }
+
+    #[test]
+    fn falls_back_to_openai_compatible_model_list() {
+        let server = TestServer::start(|request| match request.path.as_str() {
+            "/v1/models" => Reply::json(200, json!({ "data": [{ "id": "qwen2-vl-7b" }, { "id": "phi-3" }] })),
+            _ => Reply::text(404, "text/plain", "not found"),
+        });
+
+        let (kind, models) = block_on(discover_models(&server.url)).unwrap();
+        assert_eq!(kind, "openai-compatible");
+        assert_eq!(models.iter().map(|m| m.vision).collect::<Vec<_>>(), [true, false]);
+
+        let health = block_on(health_check(&server.url));
+        assert!(health.reachable);
+        assert_eq!(health.server_kind.as_deref(), Some("openai-compatible"));
+        assert_eq!(health.model_count, 2);
+    }
+}
@@ -0,0 +1,464 @@
+mod anthropic;
+pub mod fallback;
+mod gemini;
+pub mod local;
+mod openai;
+pub mod tools;
+pub mod usage;
+
+pub use anthropic::AnthropicProvider;
+pub use fallback::AiResponse;
+pub use gemini::GeminiProvider;
+pub use local::LocalProvider;
+pub use openai::OpenAiProvider;
+pub use tools::{ToolCall, ToolDefinition, ToolResult};
+pub use usage::AiFeature;
+
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+use tauri::ipc::Channel;
+use tauri::AppHandle;
+
+use super::conversations;
+use super::secrets;
+use super::storage::{self, ProviderProfile};
+
+/// A single chat message
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChatMessage {
+    /// "user", "assistant", "system" or "tool"
+    pub role: String,
+    #[serde(default)]
+    pub content: String,
+    /// Tools the assistant asked to call
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    /// Result of a tool call (role "tool")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_result: Option<ToolResult>,
+}
+
+/// Provider-agnostic completion request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompletionRequest {
+    /// System prompt
+    pub system: String,
+    /// Conversation history, oldest first
+    pub messages: Vec<ChatMessage>,
+    /// Base64 JPEG images attached to the last user message
+    #[serde(default)]
+    pub images: Vec<String>,
+    /// Maximum tokens to generate
+    #[serde(default = "default_max_tokens")]
+    pub max_tokens: u32,
+    /// Sampling temperature (None = provider default)
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    /// Whether the provider's built-in web search may be used
+    #[serde(default)]
+    pub web_search: bool,
+    /// What the request is for, used for usage accounting
+    #[serde(default)]
+    pub feature: AiFeature,
+    /// Whether the model may call the app's tools
+    #[serde(default)]
+    pub use_tools: bool,
+    /// Tools offered to the model, filled in by the backend when `use_tools` is set
+    #[serde(default, skip_deserializing)]
+    pub tools: Vec<ToolDefinition>,
+    /// Conversation the reply is saved to (None = not saved)
+    #[serde(default)]
+    pub conversation_id: Option<String>,
+}
+
+fn default_max_tokens() -> u32 {
+    1024
+}
+
+impl CompletionRequest {
+    /// Index of the message that images should be attached to
+    fn image_message_index(&self) -> Option<usize> {
+        if self.images.is_empty() {
+            None
+        } else {
+            self.messages.iter().rposition(|m| m.role == "user")
+        }
+    }
+}
+
+/// Errors returned by AI providers
+#[derive(Debug)]
+pub enum AiError {
+    /// No API key is configured for a provider that needs one
+    MissingApiKey,
+    /// The configured provider name isn't known
+    UnknownProvider(String),
+    /// The provider answered with a non-success status
+    Http { status: u16, message: String },
+    /// The request timed out
+    Timeout,
+    /// Connection or transport failure
+    Network(String),
+    /// The provider answered with something we couldn't parse
+    InvalidResponse(String),
+    /// A spend cap was reached
+    BudgetExceeded,
+    /// A spend cap is set but the usage log it is checked against can't be read
+    UsageUnavailable(String),
+}
+
+impl fmt::Display for AiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AiError::MissingApiKey => {
+                write!(f, "API key not configured. Please add your API key in settings.")
+            }
+            AiError::UnknownProvider(name) => write!(f, "Unknown provider: {}", name),
+            AiError::Http { message, .. } => write!(f, "{}", message),
+            AiError::Timeout => write!(f, "AI request timed out"),
+            AiError::Network(e) => write!(f, "Network error: {}", e),
+            AiError::InvalidResponse(e) => write!(f, "Invalid AI response: {}", e),
+            AiError::BudgetExceeded => {
+                write!(f, "AI spend cap reached. Screen analysis is paused until the budget resets.")
+            }
+            AiError::UsageUnavailable(e) => {
+                write!(f, "AI requests are paused because the spend caps can't be checked: {}", e)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AiError {}
+
+impl From<reqwest::Error> for AiError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            AiError::Timeout
+        } else if e.is_decode() {
+            AiError::InvalidResponse(e.to_string())
+        } else {
+            AiError::Network(e.to_string())
+        }
+    }
+}
+
+impl From<AiError> for String {
+    fn from(e: AiError) -> Self {
+        e.to_string()
+    }
+}
+
+/// Token counts reported by a provider
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+/// Text, tool calls and token usage of a finished request
+#[derive(Debug, Clone, Default)]
+pub struct Completion {
+    pub text: String,
+    pub tool_calls: Vec<ToolCall>,
+    pub usage: TokenUsage,
+}
+
+/// Callback receiving streamed response text
+pub type OnToken<'a> = dyn Fn(&str) + Send + Sync + 'a;
+
+/// Common interface implemented by every AI backend
+#[async_trait]
+pub trait AiProvider: Send + Sync {
+    /// Provider identifier as used in `AppConfig.ai_provider`
+    fn name(&self) -> &'static str;
+
+    /// Run a request and return the full response
+    async fn complete(&self, request: &CompletionRequest) -> Result<Completion, AiError>;
+
+    /// Run a request, calling `on_token` for each text chunk as it arrives
+    /// Returns the full response once the stream ends
+    async fn stream(
+        &self,
+        request: &CompletionRequest,
+        on_token: &OnToken<'_>,
+    ) -> Result<Completion, AiError>;
+}
+
+/// Build a provider from its name, model and key
+/// `base_url` is only used by the local provider, which doesn't need a key.
+/// `idle_timeout` bounds the wait for the first byte and between chunks, not the whole answer.
+pub fn create_provider(
+    provider: &str,
+    model: &str,
+    api_key: Option<String>,
+    base_url: Option<&str>,
+    idle_timeout: Duration,
+) -> Result<Box<dyn AiProvider>, AiError> {
+    let api_key = api_key.filter(|k| !k.is_empty());
+    let required_key = || api_key.clone().ok_or(AiError::MissingApiKey);
+
+    match provider {
+        "openai" => Ok(Box::new(
+            OpenAiProvider::new(model, Some(required_key()?)).with_idle_timeout(idle_timeout),
+        )),
+        "anthropic" => Ok(Box::new(
+            AnthropicProvider::new(model, required_key()?).with_idle_timeout(idle_timeout),
+        )),
+        "gemini" => Ok(Box::new(
+            GeminiProvider::new(model, required_key()?).with_idle_timeout(idle_timeout),
+        )),
+        "local" => Ok(Box::new(
+            LocalProvider::new(base_url.unwrap_or(local::DEFAULT_LOCAL_BASE_URL), model, api_key)
+                .with_idle_timeout(idle_timeout),
+        )),
+        other => Err(AiError::UnknownProvider(other.to_string())),
+    }
+}
+
+/// How long to wait for the first byte of a response, and then between chunks
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Shared HTTP client settings for provider calls
+/// There is no cap on the total time, so long streamed answers aren't cut off
+fn http_client(idle_timeout: Duration) -> reqwest::Client {
+    reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(10))
+        .read_timeout(idle_timeout)
+        .build()
+        .unwrap_or_default()
+}
+
+/// Turn a non-success response into an `AiError`, using the provider's message when present
+async fn check_status(
+    response: reqwest::Response,
+    provider: &str,
+) -> Result<reqwest::Response, AiError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let body: serde_json::Value = response.json().await.unwrap_or_default();
+    let message = body["error"]["message"]
+        .as_str()
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| format!("{} API error: {}", provider, status.as_u16()));
+
+    Err(AiError::Http {
+        status: status.as_u16(),
+        message,
+    })
+}
+
+/// Read a server-sent events body, calling `on_data` with every `data:` payload
+pub(crate) async fn read_sse(
+    response: reqwest::Response,
+    mut on_data: impl FnMut(&str) + Send,
+) -> Result<(), AiError> {
+    let mut stream = response.bytes_stream();
+    // Raw bytes are buffered so multi-byte characters split across chunks decode correctly
+    let mut buffer: Vec<u8> = Vec::new();
+
+    while let Some(chunk) = stream.next().await {
+        buffer.extend_from_slice(&chunk?);
+
+        while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            if let Some(data) = line.trim().strip_prefix("data:") {
+                let data = data.trim();
+                if !data.is_empty() && data != "[DONE]" {
+                    on_data(data);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Events sent to the frontend while a response streams in
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase", tag = "event", content = "data")]
+pub enum AiStreamEvent {
+    /// A chunk of response text
+    Token { text: String },
+    /// The model called a tool
+    ToolCall { name: String },
+    /// The full response once streaming has finished, with the profile that answered
+    Done { text: String, profile: String },
+}
+
+/// Profiles to use for a command
+/// Explicit provider/model overrides bypass the fallback chain
+fn resolve_profiles(
+    app: &AppHandle,
+    provider: Option<String>,
+    model: Option<String>,
+) -> Vec<ProviderProfile> {
+    let mut config = storage::load_config(app.clone());
+    if provider.is_none() && model.is_none() {
+        return fallback::resolve_chain(&config);
+    }
+
+    if let Some(provider) = provider {
+        config.ai_provider = provider;
+    }
+    if let Some(model) = model {
+        config.ai_model = model;
+    }
+    vec![ProviderProfile::from_config(&config)]
+}
+
+/// Refuse screen analysis once a spend cap has been reached, and everything while caps can't be checked
+fn check_budget(app: &AppHandle, request: &CompletionRequest) -> Result<(), AiError> {
+    let vision = request.feature == AiFeature::ScreenAnalysis || !request.images.is_empty();
+    let config = storage::load_config(app.clone());
+    usage::check_caps(app, &config, vision)
+}
+
+/// Send a request to the configured AI provider and return the full response
+#[tauri::command]
+pub async fn ai_chat(
+    app: AppHandle,
+    request: CompletionRequest,
+    provider: Option<String>,
+    model: Option<String>,
+) -> Result<AiResponse, String> {
+    check_budget(&app, &request)?;
+    let profiles = resolve_profiles(&app, provider, model);
+    let response = tools::run_with_tools(&app, &profiles, &request, None, &|_| {}).await?;
+    conversations::record_response(&app, &request, &response);
+    Ok(response)
+}
+
+/// Stream a response from the configured AI provider over a channel
+#[tauri::command]
+pub async fn ai_chat_stream(
+    app: AppHandle,
+    request: CompletionRequest,
+    on_event: Channel<AiStreamEvent>,
+    provider: Option<String>,
+    model: Option<String>,
+) -> Result<AiResponse, String> {
+    check_budget(&app, &request)?;
+    let profiles = resolve_profiles(&app, provider, model);
+
+    let token_channel = on_event.clone();
+    let on_token = move |text: &str| {
+        let _ = token_channel.send(AiStreamEvent::Token {
+            text: text.to_string(),
+        });
+    };
+    let tool_channel = on_event.clone();
+    let on_tool_call = move |call: &ToolCall| {
+        let _ = tool_channel.send(AiStreamEvent::ToolCall {
+            name: call.name.clone(),
+        });
+    };
+    let response =
+        tools::run_with_tools(&app, &profiles, &request, Some(&on_token), &on_tool_call).await?;
+    conversations::record_response(&app, &request, &response);
+
+    let _ = on_event.send(AiStreamEvent::Done {
+        text: response.text.clone(),
+        profile: response.profile.clone(),
+    });
+    Ok(response)
+}
+
+/// Check that a provider is reachable with the given (or stored) API key
+#[tauri::command]
+pub async fn ai_test_connection(
+    app: AppHandle,
+    provider: String,
+    model: String,
+    api_key: Option<String>,
+    base_url: Option<String>,
+) -> Result<bool, String> {
+    let config = storage::load_config(app.clone());
+    let api_key = api_key
+        .filter(|k| !k.is_empty())
+        .or_else(|| secrets::get_api_key(&app, &provider));
+    let base_url = base_url.unwrap_or(config.local_base_url);
+    let provider = create_provider(&provider, &model, api_key, Some(&base_url), DEFAULT_IDLE_TIMEOUT)?;
+
+    let request = CompletionRequest {
+        system: "You are a connection test.".to_string(),
+        messages: vec![ChatMessage {
+            role: "user".to_string(),
+            content: "Hello".to_string(),
+            ..Default::default()
+        }],
+        images: vec![],
+        max_tokens: 16,
+        temperature: None,
+        web_search: false,
+        feature: AiFeature::Chat,
+        use_tools: false,
+        tools: vec![],
+        conversation_id: None,
+    };
+
+    Ok(provider.complete(&request).await.is_ok())
+}
+
+/// List models offered by a local server (Ollama, llama.cpp, LM Studio)
+#[tauri::command]
+pub async fn list_local_models(
+    app: AppHandle,
+    base_url: Option<String>,
+) -> Result<Vec<local::LocalModelInfo>, String> {
+    let base_url = base_url.unwrap_or_else(|| storage::load_config(app).local_base_url);
+    let (_, models) = local::discover_models(&base_url).await?;
+    Ok(models)
+}
+
+/// Check whether a local model server is reachable
+#[tauri::command]
+pub async fn check_local_health(app: AppHandle, base_url: Option<String>) -> local::LocalHealth {
+    let base_url = base_url.unwrap_or_else(|| storage::load_config(app).local_base_url);
+    local::health_check(&base_url).await
+}
+
+/// Add or replace a provider profile
+#[tauri::command]
+pub fn save_provider_profile(app: AppHandle, profile: ProviderProfile) -> Result<(), String> {
+    if profile.name.trim().is_empty() {
+        return Err("Profile name is required".to_string());
+    }
+
+    let mut config = storage::load_config(app.clone());
+    match config.provider_profiles.iter_mut().find(|p| p.name == profile.name) {
+        Some(existing) => *existing = profile,
+        None => config.provider_profiles.push(profile),
+    }
+    storage::save_config(app, config)
+}
+
+/// Delete a provider profile and drop it from the fallback chain
+#[tauri::command]
+pub fn delete_provider_profile(app: AppHandle, name: String) -> Result<(), String> {
+    let mut config = storage::load_config(app.clone());
+    config.provider_profiles.retain(|p| p.name != name);
+    config.fallback_chain.retain(|n| n != &name);
+    storage::save_config(app, config)
+}
+
+/// Set the order in which profiles are tried
+#[tauri::command]
+pub fn set_fallback_chain(app: AppHandle, chain: Vec<String>) -> Result<(), String> {
+    let mut config = storage::load_config(app.clone());
+    if let Some(unknown) = chain
+        .iter()
+        .find(|name| !config.provider_profiles.iter().any(|p| &p.name == *name))
+    {
+        return Err(format!("Unknown provider profile: {}", unknown));
+    }
+
+    config.fallback_chain = chain;
+    storage::save_config(app, config)
+}
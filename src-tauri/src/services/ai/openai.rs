@@ -0,0 +1,450 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use super::{
+    check_status, http_client, read_sse, AiError, AiProvider, Completion, CompletionRequest, OnToken,
+    TokenUsage, ToolCall, DEFAULT_IDLE_TIMEOUT,
+};
+
+const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+
+/// Models with built-in web search (these don't accept a temperature)
+const SEARCH_MODELS: &[&str] = &["gpt-4o-search-preview", "gpt-4o-mini-search-preview"];
+
+/// Search model used when web search is requested with a regular model
+const FALLBACK_SEARCH_MODEL: &str = "gpt-4o-mini-search-preview";
+
+/// OpenAI chat completions API
+pub struct OpenAiProvider {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+    model: String,
+    /// Whether this is the hosted OpenAI API (enables search model switching)
+    hosted: bool,
+}
+
+impl OpenAiProvider {
+    pub fn new(model: &str, api_key: Option<String>) -> Self {
+        Self::with_base_url(DEFAULT_BASE_URL, model, api_key)
+    }
+
+    pub fn with_base_url(base_url: &str, model: &str, api_key: Option<String>) -> Self {
+        Self {
+            client: http_client(DEFAULT_IDLE_TIMEOUT),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            model: model.to_string(),
+            hosted: true,
+        }
+    }
+
+    /// Give up when nothing arrives for this long
+    pub fn with_idle_timeout(self, timeout: Duration) -> Self {
+        Self {
+            client: http_client(timeout),
+            ..self
+        }
+    }
+
+    /// Point the provider at any OpenAI-compatible endpoint
+    pub fn compatible(base_url: &str, model: &str, api_key: Option<String>) -> Self {
+        Self {
+            hosted: false,
+            ..Self::with_base_url(base_url, model, api_key)
+        }
+    }
+
+    fn body(&self, request: &CompletionRequest, stream: bool) -> Value {
+        // Switch to a search model when web search is wanted, using the mini model for cost
+        let model = if self.hosted
+            && request.web_search
+            && !SEARCH_MODELS.contains(&self.model.as_str())
+        {
+            FALLBACK_SEARCH_MODEL
+        } else {
+            self.model.as_str()
+        };
+
+        let image_index = request.image_message_index();
+        let mut messages = vec![json!({ "role": "system", "content": request.system })];
+        // Tool messages only carry text, so screenshots follow them in a user message
+        let mut tool_images: Vec<String> = Vec::new();
+        for (i, message) in request.messages.iter().enumerate() {
+            if message.tool_result.is_none() && !tool_images.is_empty() {
+                messages.push(tool_image_message(&tool_images));
+                tool_images.clear();
+            }
+
+            if let Some(result) = &message.tool_result {
+                messages.push(json!({
+                    "role": "tool",
+                    "tool_call_id": result.call_id,
+                    "content": result.content
+                }));
+                tool_images.extend(result.image.clone());
+            } else if !message.tool_calls.is_empty() {
+                let tool_calls: Vec<Value> = message
+                    .tool_calls
+                    .iter()
+                    .map(|call| {
+                        json!({
+                            "id": call.id,
+                            "type": "function",
+                            "function": { "name": call.name, "arguments": call.arguments.to_string() }
+                        })
+                    })
+                    .collect();
+                let content = if message.content.is_empty() {
+                    Value::Null
+                } else {
+                    json!(message.content)
+                };
+                messages.push(json!({ "role": "assistant", "content": content, "tool_calls": tool_calls }));
+            } else if Some(i) == image_index {
+                let mut content: Vec<Value> = request.images.iter().map(|image| image_part(image)).collect();
+                content.push(json!({ "type": "text", "text": message.content }));
+                messages.push(json!({ "role": message.role, "content": content }));
+            } else {
+                messages.push(json!({ "role": message.role, "content": message.content }));
+            }
+        }
+        if !tool_images.is_empty() {
+            messages.push(tool_image_message(&tool_images));
+        }
+
+        let mut body = json!({
+            "model": model,
+            "messages": messages,
+            "max_tokens": request.max_tokens,
+            "stream": stream,
+        });
+
+        // Ask for token counts in the final stream chunk
+        if stream {
+            body["stream_options"] = json!({ "include_usage": true });
+        }
+
+        // Search models support neither temperature nor function calling
+        if !SEARCH_MODELS.contains(&model) {
+            body["temperature"] = json!(request.temperature.unwrap_or(0.7));
+
+            if !request.tools.is_empty() {
+                let tools: Vec<Value> = request
+                    .tools
+                    .iter()
+                    .map(|tool| {
+                        json!({
+                            "type": "function",
+                            "function": {
+                                "name": tool.name,
+                                "description": tool.description,
+                                "parameters": tool.parameters
+                            }
+                        })
+                    })
+                    .collect();
+                body["tools"] = json!(tools);
+            }
+        }
+
+        body
+    }
+
+    async fn send(&self, body: &Value) -> Result<reqwest::Response, AiError> {
+        let mut builder = self
+            .client
+            .post(format!("{}/chat/completions", self.base_url))
+            .json(body);
+        if let Some(key) = &self.api_key {
+            builder = builder.bearer_auth(key);
+        }
+
+        check_status(builder.send().await?, "OpenAI").await
+    }
+}
+
+fn image_part(image: &str) -> Value {
+    json!({
+        "type": "image_url",
+        "image_url": {
+            "url": format!("data:image/jpeg;base64,{}", image),
+            "detail": "low"
+        }
+    })
+}
+
+fn tool_image_message(images: &[String]) -> Value {
+    let mut content: Vec<Value> = images.iter().map(|image| image_part(image)).collect();
+    content.push(json!({ "type": "text", "text": "Screenshot returned by the capture_screen tool." }));
+    json!({ "role": "user", "content": content })
+}
+
+/// Arguments arrive as a JSON string; fall back to an empty object if the model garbled them
+fn parse_arguments(arguments: &str) -> Value {
+    if arguments.trim().is_empty() {
+        return json!({});
+    }
+    serde_json::from_str(arguments).unwrap_or_else(|_| json!({}))
+}
+
+/// A tool call being assembled from stream deltas
+#[derive(Default)]
+struct PartialToolCall {
+    id: String,
+    name: String,
+    arguments: String,
+}
+
+fn parse_usage(data: &Value) -> TokenUsage {
+    TokenUsage {
+        input_tokens: data["usage"]["prompt_tokens"].as_u64().unwrap_or(0),
+        output_tokens: data["usage"]["completion_tokens"].as_u64().unwrap_or(0),
+    }
+}
+
+#[async_trait]
+impl AiProvider for OpenAiProvider {
+    fn name(&self) -> &'static str {
+        "openai"
+    }
+
+    async fn complete(&self, request: &CompletionRequest) -> Result<Completion, AiError> {
+        let response = self.send(&self.body(request, false)).await?;
+        let data: Value = response.json().await?;
+
+        let message = &data["choices"][0]["message"];
+        let tool_calls = message["tool_calls"]
+            .as_array()
+            .map(|calls| {
+                calls
+                    .iter()
+                    .map(|call| ToolCall {
+                        id: call["id"].as_str().unwrap_or_default().to_string(),
+                        name: call["function"]["name"].as_str().unwrap_or_default().to_string(),
+                        arguments: parse_arguments(call["function"]["arguments"].as_str().unwrap_or_default()),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(Completion {
+            text: message["content"].as_str().unwrap_or_default().to_string(),
+            tool_calls,
+            usage: parse_usage(&data),
+        })
+    }
+
+    async fn stream(
+        &self,
+        request: &CompletionRequest,
+        on_token: &OnToken<'_>,
+    ) -> Result<Completion, AiError> {
+        let response = self.send(&self.body(request, true)).await?;
+
+        let mut completion = Completion::default();
+        let mut partial_calls: Vec<PartialToolCall> = Vec::new();
+        read_sse(response, |data| {
+            if let Ok(parsed) = serde_json::from_str::<Value>(data) {
+                let delta = &parsed["choices"][0]["delta"];
+                if let Some(text) = delta["content"].as_str() {
+                    if !text.is_empty() {
+                        completion.text.push_str(text);
+                        on_token(text);
+                    }
+                }
+                // Tool calls stream in pieces keyed by index; only the first piece has id and name
+                for call in delta["tool_calls"].as_array().into_iter().flatten() {
+                    let index = call["index"].as_u64().unwrap_or(0) as usize;
+                    if partial_calls.len() <= index {
+                        partial_calls.resize_with(index + 1, PartialToolCall::default);
+                    }
+                    let partial = &mut partial_calls[index];
+                    if let Some(id) = call["id"].as_str() {
+                        partial.id = id.to_string();
+                    }
+                    if let Some(name) = call["function"]["name"].as_str() {
+                        partial.name.push_str(name);
+                    }
+                    if let Some(arguments) = call["function"]["arguments"].as_str() {
+                        partial.arguments.push_str(arguments);
+                    }
+                }
+                if parsed["usage"].is_object() {
+                    completion.usage = parse_usage(&parsed);
+                }
+            }
+        })
+        .await?;
+
+        completion.tool_calls = partial_calls
+            .into_iter()
+            .filter(|partial| !partial.name.is_empty())
+            .map(|partial| ToolCall {
+                id: partial.id,
+                name: partial.name,
+                arguments: parse_arguments(&partial.arguments),
+            })
+            .collect();
+
+        Ok(completion)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_http::{block_on, Reply, TestServer};
+    use std::sync::Mutex;
+
+    fn request() -> CompletionRequest {
+        serde_json::from_value(json!({
+            "system": "Be brief.",
+            "messages": [{ "role": "user", "content": "Hi" }]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn complete_returns_text_tool_calls_and_usage() {
+        let server = TestServer::replying(Reply::json(
+            200,
+            json!({
+                "choices": [{ "message": {
+                    "content": "Hello!",
+                    "tool_calls": [{
+                        "id": "call_1",
+                        "type": "function",
+                        "function": { "name": "get_system_stats", "arguments": "{\"verbose\":true}" }
+                    }]
+                }}],
+                "usage": { "prompt_tokens": 12, "completion_tokens": 3 }
+            }),
+        ));
+        let provider = OpenAiProvider::with_base_url(&server.url, "gpt-test", Some("sk-test".to_string()));
+
+        let completion = block_on(provider.complete(&request())).unwrap();
+
+        assert_eq!(completion.text, "Hello!");
+        assert_eq!(completion.tool_calls.len(), 1);
+        assert_eq!(completion.tool_calls[0].name, "get_system_stats");
+        assert_eq!(completion.tool_calls[0].arguments, json!({ "verbose": true }));
+        assert_eq!(completion.usage.input_tokens, 12);
+        assert_eq!(completion.usage.output_tokens, 3);
+
+        let sent = &server.requests()[0];
+        assert_eq!(sent.method, "POST");
+        assert_eq!(sent.path, "/chat/completions");
+        assert_eq!(sent.header("authorization"), Some("Bearer sk-test"));
+        let body = sent.json();
+        assert_eq!(body["model"], "gpt-test");
+        assert_eq!(body["stream"], false);
+        assert_eq!(body["messages"][0], json!({ "role": "system", "content": "Be brief." }));
+        assert_eq!(body["messages"][1], json!({ "role": "user", "content": "Hi" }));
+    }
+
+    #[test]
+    fn stream_emits_tokens_in_order_and_assembles_tool_calls() {
+        let server = TestServer::replying(Reply::sse(&[
+            json!({ "choices": [{ "delta": { "content": "Hel" } }] }),
+            json!({ "choices": [{ "delta": { "content": "lo" } }] }),
+            json!({ "choices": [{ "delta": { "content": ", world" } }] }),
+            json!({ "choices": [{ "delta": { "tool_calls": [{
+                "index": 0, "id": "call_1", "function": { "name": "web_search", "arguments": "{\"query\":" }
+            }] } }] }),
+            json!({ "choices": [{ "delta": { "tool_calls": [{
+                "index": 0, "function": { "arguments": "\"rust\"}" }
+            }] } }] }),
+            json!({ "choices": [], "usage": { "prompt_tokens": 20, "completion_tokens": 7 } }),
+        ]));
+        let provider = OpenAiProvider::with_base_url(&server.url, "gpt-test", Some("sk-test".to_string()));
+        let tokens = Mutex::new(Vec::new());
+
+        let completion = block_on(provider.stream(&request(), &|text: &str| {
+            tokens.lock().unwrap().push(text.to_string())
+        }))
+        .unwrap();
+
+        assert_eq!(tokens.into_inner().unwrap(), ["Hel", "lo", ", world"]);
+        assert_eq!(completion.text, "Hello, world");
+        assert_eq!(completion.tool_calls.len(), 1);
+        assert_eq!(completion.tool_calls[0].id, "call_1");
+        assert_eq!(completion.tool_calls[0].arguments, json!({ "query": "rust" }));
+        assert_eq!(completion.usage.output_tokens, 7);
+
+        let body = server.requests()[0].json();
+        assert_eq!(body["stream"], true);
+        assert_eq!(body["stream_options"]["include_usage"], true);
+    }
+
+    #[test]
+    fn error_status_uses_the_provider_message() {
+        let server = TestServer::replying(Reply::json(
+            401,
+            json!({ "error": { "message": "Incorrect API key provided", "type": "invalid_request_error" } }),
+        ));
+        let provider = OpenAiProvider::with_base_url(&server.url, "gpt-test", Some("bad".to_string()));
+
+        match block_on(provider.complete(&request())) {
+            Err(AiError::Http { status, message }) => {
+                assert_eq!(status, 401);
+                assert_eq!(message, "Incorrect API key provided");
+            }
+            other => panic!("expected an HTTP error, got {:?}", other.map(|c| c.text)),
+        }
+    }
+
+    #[test]
+    fn error_status_without_a_body_is_described() {
+        let server = TestServer::replying(Reply::text(503, "text/plain", "upstream unavailable"));
+        let provider = OpenAiProvider::with_base_url(&server.url, "gpt-test", None);
+
+        match block_on(provider.stream(&request(), &|_: &str| {})) {
+            Err(AiError::Http { status, message }) => {
+                assert_eq!(status, 503);
+                assert_eq!(message, "OpenAI API error: 503");
+            }
+            other => panic!("expected an HTTP error, got {:?}", other.map(|c| c.text)),
+        }
+    }
+
+    #[test]
+    fn stream_outlasting_the_timeout_completes_while_chunks_keep_coming() {
+        let events: Vec<Value> = (0..8)
+            .map(|i| json!({ "choices": [{ "delta": { "content": i.to_string() } }] }))
+            .collect();
+        let server = TestServer::replying(Reply::sse(&events).delayed(Duration::from_millis(60)));
+        let provider = OpenAiProvider::with_base_url(&server.url, "gpt-test", None)
+            .with_idle_timeout(Duration::from_millis(300));
+
+        let completion = block_on(provider.stream(&request(), &|_: &str| {})).unwrap();
+        assert_eq!(completion.text, "01234567");
+    }
+
+    #[test]
+    fn silence_before_the_first_byte_times_out() {
+        let server = TestServer::replying(
+            Reply::json(200, json!({ "choices": [{ "message": { "content": "late" } }] }))
+                .delayed(Duration::from_millis(800)),
+        );
+        let provider = OpenAiProvider::with_base_url(&server.url, "gpt-test", None)
+            .with_idle_timeout(Duration::from_millis(200));
+
+        assert!(matches!(block_on(provider.complete(&request())), Err(AiError::Timeout)));
+    }
+
+    #[test]
+    fn web_search_switches_to_a_search_model_without_temperature() {
+        let server = TestServer::replying(Reply::json(200, json!({ "choices": [{ "message": { "content": "" } }] })));
+        let provider = OpenAiProvider::with_base_url(&server.url, "gpt-4o", None);
+        let mut request = request();
+        request.web_search = true;
+
+        block_on(provider.complete(&request)).unwrap();
+
+        let body = server.requests()[0].json();
+        assert_eq!(body["model"], FALLBACK_SEARCH_MODEL);
+        assert!(body.get("temperature").is_none());
+    }
+}
@@ -0,0 +1,331 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager};
+
+use super::{fallback, usage, AiError, AiFeature, AiResponse, ChatMessage, CompletionRequest, OnToken, TokenUsage};
+use crate::services::storage::{self, ProviderProfile};
+use crate::services::{activity_tracker, brain_index, capture, mcp, system_info, web_search};
+
+/// Model/tool round trips allowed for a single request
+const MAX_TOOL_ROUNDS: usize = 5;
+
+/// Entries kept per list in tool results, to keep prompts small
+const MAX_LIST_ENTRIES: usize = 15;
+
+/// A function the model may call
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolDefinition {
+    pub name: String,
+    pub description: String,
+    /// JSON schema of the arguments object
+    pub parameters: Value,
+}
+
+impl ToolDefinition {
+    /// Whether the tool takes no arguments (Gemini rejects empty object schemas)
+    pub fn has_parameters(&self) -> bool {
+        self.parameters["properties"]
+            .as_object()
+            .map(|properties| !properties.is_empty())
+            .unwrap_or(false)
+    }
+}
+
+/// A tool call requested by the model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolCall {
+    /// Provider call id (generated for Gemini, which doesn't send one)
+    pub id: String,
+    pub name: String,
+    pub arguments: Value,
+}
+
+/// Output of a tool call, sent back to the model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolResult {
+    pub call_id: String,
+    pub name: String,
+    /// JSON or plain text result
+    pub content: String,
+    /// Base64 JPEG produced by the tool (screen captures)
+    #[serde(default)]
+    pub image: Option<String>,
+}
+
+/// Callback told about each tool call before it runs
+pub type OnToolCall<'a> = dyn Fn(&ToolCall) + Send + Sync + 'a;
+
+#[derive(Debug, Deserialize)]
+struct LoadBrainDataArgs {
+    filename: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchBrainArgs {
+    query: String,
+    #[serde(default)]
+    k: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct CaptureScreenArgs {
+    #[serde(default)]
+    monitor_index: Option<usize>,
+}
+
+fn no_parameters() -> Value {
+    json!({ "type": "object", "properties": {} })
+}
+
+/// Tools offered to the model: the app's own plus those of connected MCP servers
+pub fn definitions(app: &AppHandle) -> Vec<ToolDefinition> {
+    let mut definitions = builtin_definitions();
+    definitions.extend(mcp::tool_definitions(app));
+    definitions
+}
+
+fn builtin_definitions() -> Vec<ToolDefinition> {
+    vec![
+        ToolDefinition {
+            name: "get_system_stats".to_string(),
+            description: "Current CPU (per core, with frequencies and load average), memory and swap, disk and network usage (per interface, in bytes per second), process and thread counts, plus the processes using the most memory."
+                .to_string(),
+            parameters: no_parameters(),
+        },
+        ToolDefinition {
+            name: "get_activity_stats".to_string(),
+            description: "Screen time per application for today and all time, in seconds, and the app in focus."
+                .to_string(),
+            parameters: no_parameters(),
+        },
+        ToolDefinition {
+            name: "get_pomodoro_settings".to_string(),
+            description: "The user's pomodoro timer settings (work, short break and long break lengths)."
+                .to_string(),
+            parameters: no_parameters(),
+        },
+        ToolDefinition {
+            name: "list_brain_files".to_string(),
+            description: "List the files in the assistant's memory folder.".to_string(),
+            parameters: no_parameters(),
+        },
+        ToolDefinition {
+            name: "load_brain_data".to_string(),
+            description: "Read a file from the assistant's memory folder.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "filename": {
+                        "type": "string",
+                        "description": "File name as returned by list_brain_files"
+                    }
+                },
+                "required": ["filename"]
+            }),
+        },
+        ToolDefinition {
+            name: "search_brain".to_string(),
+            description: "Search the assistant's memories and notes for passages related to a question.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "query": {
+                        "type": "string",
+                        "description": "What to look for"
+                    },
+                    "k": {
+                        "type": "integer",
+                        "description": "Number of results, default 5"
+                    }
+                },
+                "required": ["query"]
+            }),
+        },
+        ToolDefinition {
+            name: "capture_screen".to_string(),
+            description: "Take a screenshot of the user's screen to look at what they are doing.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "monitor_index": {
+                        "type": "integer",
+                        "description": "Monitor to capture, 0 is the primary monitor"
+                    }
+                }
+            }),
+        },
+    ]
+}
+
+fn parse_args<T: for<'de> Deserialize<'de>>(call: &ToolCall) -> Result<T, String> {
+    // Some models send `null` instead of an empty object for argument-less calls
+    let arguments = if call.arguments.is_null() {
+        json!({})
+    } else {
+        call.arguments.clone()
+    };
+    serde_json::from_value(arguments).map_err(|e| format!("Invalid arguments for {}: {}", call.name, e))
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String, String> {
+    serde_json::to_string(value).map_err(|e| e.to_string())
+}
+
+/// Run a tool, returning its content and an optional image
+fn run_tool(app: &AppHandle, call: &ToolCall) -> Result<(String, Option<String>), String> {
+    match call.name.as_str() {
+        "get_system_stats" => {
+            let mut stats = system_info::get_system_stats(
+                app.clone(),
+                app.state::<system_info::SystemMonitor>(),
+            )?;
+            stats.processes.truncate(MAX_LIST_ENTRIES);
+            stats.networks.truncate(MAX_LIST_ENTRIES);
+            Ok((to_json(&stats)?, None))
+        }
+        "get_activity_stats" => {
+            let state = app
+                .try_state::<activity_tracker::ActivityTracker>()
+                .ok_or("Activity tracking is not available")?;
+            let mut stats = activity_tracker::get_activity_stats(state)?;
+            stats.today.truncate(MAX_LIST_ENTRIES);
+            stats.all_time.truncate(MAX_LIST_ENTRIES);
+            Ok((to_json(&stats)?, None))
+        }
+        "get_pomodoro_settings" => Ok((to_json(&storage::load_pomodoro_settings(app.clone()))?, None)),
+        "list_brain_files" => Ok((to_json(&storage::list_brain_files(app.clone())?)?, None)),
+        "load_brain_data" => {
+            let args: LoadBrainDataArgs = parse_args(call)?;
+            // Only plain file names, the model must not read outside the brain folder
+            if args.filename.is_empty()
+                || args.filename.contains(['/', '\\'])
+                || args.filename.starts_with('.')
+            {
+                return Err(format!("Invalid file name: {}", args.filename));
+            }
+            match storage::load_brain_data(app.clone(), args.filename.clone())? {
+                Some(content) => Ok((content, None)),
+                None => Err(format!("No such file: {}", args.filename)),
+            }
+        }
+        "capture_screen" => {
+            let args: CaptureScreenArgs = parse_args(call)?;
+            let config = storage::load_config(app.clone());
+            usage::check_caps(app, &config, true).map_err(|e| e.to_string())?;
+            let monitor_index = args.monitor_index.or(config.selected_monitor);
+            let image = capture::capture_screen(monitor_index)?;
+            Ok(("Screenshot attached.".to_string(), Some(image)))
+        }
+        other => Err(format!("Unknown tool: {}", other)),
+    }
+}
+
+/// Search the brain index (async, so it can't live in `run_tool`)
+async fn search_brain(app: &AppHandle, call: &ToolCall) -> Result<(String, Option<String>), String> {
+    let args: SearchBrainArgs = parse_args(call)?;
+    let k = args.k.unwrap_or(5).clamp(1, MAX_LIST_ENTRIES);
+    let hits = brain_index::search(app, &args.query, k).await?;
+    Ok((to_json(&hits)?, None))
+}
+
+/// Run a tool call; failures are reported to the model rather than aborting the request
+pub async fn execute(app: &AppHandle, call: &ToolCall) -> ToolResult {
+    let output = if mcp::is_mcp_tool(&call.name) {
+        mcp::call_tool(app, &call.name, &call.arguments).await
+    } else if web_search::is_web_tool(&call.name) {
+        web_search::call_tool(app, &call.name, &call.arguments).await
+    } else if call.name == "search_brain" {
+        search_brain(app, call).await
+    } else {
+        run_tool(app, call)
+    };
+    let (content, image) = match output {
+        Ok(output) => output,
+        Err(e) => (json!({ "error": e }).to_string(), None),
+    };
+
+    ToolResult {
+        call_id: call.id.clone(),
+        name: call.name.clone(),
+        content,
+        image,
+    }
+}
+
+/// Run a request through the fallback chain, executing tool calls until the model answers
+/// Every round is recorded in the usage log; the returned response carries the totals.
+pub async fn run_with_tools(
+    app: &AppHandle,
+    profiles: &[ProviderProfile],
+    request: &CompletionRequest,
+    on_token: Option<&OnToken<'_>>,
+    on_tool_call: &OnToolCall<'_>,
+) -> Result<AiResponse, AiError> {
+    let mut request = request.clone();
+    if request.use_tools {
+        request.tools = definitions(app);
+    }
+    // The app's own search tool works with any model, so it replaces the vendor's built-in search
+    if request.web_search && web_search::uses_backend_search(&storage::load_config(app.clone())) {
+        request.feature = AiFeature::of(&request);
+        request.web_search = false;
+        request.tools.extend(web_search::tool_definitions());
+    }
+    let mut profiles = profiles.to_vec();
+
+    let mut texts: Vec<String> = Vec::new();
+    let mut total_usage = TokenUsage::default();
+    let mut total_cost = 0.0;
+
+    let mut round = 0;
+    loop {
+        let mut response = match fallback::run_chain(app, &profiles, &request, on_token).await {
+            Ok(response) => response,
+            // Local models without function calling reject the tools list
+            Err(AiError::Http { status: 400, .. }) if round == 0 && !request.tools.is_empty() => {
+                request.tools.clear();
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        usage::record(app, &request, &response);
+
+        round += 1;
+        total_usage.input_tokens += response.usage.input_tokens;
+        total_usage.output_tokens += response.usage.output_tokens;
+        total_cost += response.cost_usd;
+        if !response.text.is_empty() {
+            texts.push(response.text.clone());
+        }
+
+        if response.tool_calls.is_empty() || round >= MAX_TOOL_ROUNDS {
+            response.text = texts.join("\n\n");
+            response.usage = total_usage;
+            response.cost_usd = total_cost;
+            return Ok(response);
+        }
+
+        // Keep the conversation on the profile that asked for the tools
+        profiles.retain(|p| p.name == response.profile);
+        if !response.text.is_empty() {
+            if let Some(on_token) = on_token {
+                on_token("\n\n");
+            }
+        }
+
+        request.messages.push(ChatMessage {
+            role: "assistant".to_string(),
+            content: response.text.clone(),
+            tool_calls: response.tool_calls.clone(),
+            ..Default::default()
+        });
+        for call in &response.tool_calls {
+            on_tool_call(call);
+            request.messages.push(ChatMessage {
+                role: "tool".to_string(),
+                tool_result: Some(execute(app, call).await),
+                ..Default::default()
+            });
+        }
+    }
+}
@@ -0,0 +1,510 @@
+use chrono::{Datelike, Days, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::{AiError, AiResponse, CompletionRequest, TokenUsage};
+use crate::services::storage::{self, AppConfig};
+
+/// Records older than this are dropped when the log is saved
+const RETENTION_DAYS: u64 = 400;
+
+/// USD per million tokens (input, output), matched on the longest model prefix
+/// Local models are free; unknown hosted models are recorded with zero cost.
+const PRICE_TABLE: &[(&str, &str, f64, f64)] = &[
+    ("openai", "gpt-4o-mini", 0.15, 0.60),
+    ("openai", "gpt-4o", 2.50, 10.00),
+    ("openai", "gpt-4.1-nano", 0.10, 0.40),
+    ("openai", "gpt-4.1-mini", 0.40, 1.60),
+    ("openai", "gpt-4.1", 2.00, 8.00),
+    ("openai", "gpt-4-turbo", 10.00, 30.00),
+    ("openai", "o4-mini", 1.10, 4.40),
+    ("openai", "o3-mini", 1.10, 4.40),
+    ("openai", "o3", 2.00, 8.00),
+    ("anthropic", "claude-3-haiku", 0.25, 1.25),
+    ("anthropic", "claude-3-5-haiku", 0.80, 4.00),
+    ("anthropic", "claude-haiku-4", 1.00, 5.00),
+    ("anthropic", "claude-3-5-sonnet", 3.00, 15.00),
+    ("anthropic", "claude-3-7-sonnet", 3.00, 15.00),
+    ("anthropic", "claude-sonnet-4", 3.00, 15.00),
+    ("anthropic", "claude-3-opus", 15.00, 75.00),
+    ("anthropic", "claude-opus-4", 15.00, 75.00),
+    ("anthropic", "claude-opus-4-5", 5.00, 25.00),
+    ("gemini", "gemini-1.5-flash", 0.075, 0.30),
+    ("gemini", "gemini-1.5-pro", 1.25, 5.00),
+    ("gemini", "gemini-2.0-flash-lite", 0.075, 0.30),
+    ("gemini", "gemini-2.0-flash", 0.10, 0.40),
+    ("gemini", "gemini-2.5-flash-lite", 0.10, 0.40),
+    ("gemini", "gemini-2.5-flash", 0.30, 2.50),
+    ("gemini", "gemini-2.5-pro", 1.25, 10.00),
+];
+
+/// What a request was made for
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum AiFeature {
+    #[default]
+    Chat,
+    ScreenAnalysis,
+    WebSearch,
+}
+
+impl AiFeature {
+    /// Feature a request is billed under; chat with web search counts as web search
+    pub fn of(request: &CompletionRequest) -> Self {
+        match request.feature {
+            AiFeature::Chat if request.web_search => AiFeature::WebSearch,
+            feature => feature,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            AiFeature::Chat => "chat",
+            AiFeature::ScreenAnalysis => "screen_analysis",
+            AiFeature::WebSearch => "web_search",
+        }
+    }
+}
+
+/// A single completed AI request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageRecord {
+    /// Unix seconds
+    pub timestamp: u64,
+    pub provider: String,
+    pub model: String,
+    pub profile: String,
+    pub feature: AiFeature,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub images: u32,
+    /// Estimated cost in USD
+    pub cost_usd: f64,
+}
+
+/// Totals for one provider, model or feature
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UsageTotals {
+    pub key: String,
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub images: u64,
+    pub cost_usd: f64,
+}
+
+impl UsageTotals {
+    fn add(&mut self, record: &UsageRecord) {
+        self.requests += 1;
+        self.input_tokens += record.input_tokens;
+        self.output_tokens += record.output_tokens;
+        self.images += record.images as u64;
+        self.cost_usd += record.cost_usd;
+    }
+}
+
+/// Spending against the configured caps
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetStatus {
+    pub daily_spend_usd: f64,
+    pub monthly_spend_usd: f64,
+    pub daily_cap_usd: Option<f64>,
+    pub monthly_cap_usd: Option<f64>,
+    /// Whether screen analysis is paused because a cap was reached
+    pub vision_paused: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageSummary {
+    pub range: String,
+    pub total: UsageTotals,
+    pub by_provider: Vec<UsageTotals>,
+    pub by_model: Vec<UsageTotals>,
+    pub by_feature: Vec<UsageTotals>,
+    pub budget: BudgetStatus,
+}
+
+/// Managed state holding the usage log
+pub struct UsageTracker {
+    pub records: Mutex<Vec<UsageRecord>>,
+    pub data_path: Mutex<PathBuf>,
+    /// Why the log couldn't be loaded; nothing is recorded or saved over it while set,
+    /// and requests are refused while a spend cap is configured
+    load_error: Option<String>,
+}
+
+impl UsageTracker {
+    pub fn new(data_dir: PathBuf) -> Self {
+        let data_path = data_dir.join("ai_usage.json");
+        let (records, load_error) = match read_records(&data_path) {
+            Ok(records) => (records, None),
+            Err(e) => {
+                eprintln!("{}", e);
+                (Vec::new(), Some(e))
+            }
+        };
+
+        Self {
+            records: Mutex::new(records),
+            data_path: Mutex::new(data_path),
+            load_error,
+        }
+    }
+
+    /// Whether a request may go ahead under the spend caps; `vision` requests stop once a cap is reached
+    fn check(&self, config: &AppConfig, vision: bool) -> Result<(), AiError> {
+        let capped = config.daily_spend_cap.is_some() || config.monthly_spend_cap.is_some();
+        if let Some(e) = self.load_error.as_ref().filter(|_| capped) {
+            return Err(AiError::UsageUnavailable(e.clone()));
+        }
+        if !vision {
+            return Ok(());
+        }
+        let records = self.records.lock().map_err(|e| AiError::UsageUnavailable(e.to_string()))?;
+        if budget_status(&records, config, Local::now().date_naive()).vision_paused {
+            return Err(AiError::BudgetExceeded);
+        }
+        Ok(())
+    }
+
+    fn save(&self, records: &[UsageRecord]) -> Result<(), String> {
+        let path = self.data_path.lock().map_err(|e| e.to_string())?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string(records).map_err(|e| e.to_string())?;
+        fs::write(&*path, json).map_err(|e| e.to_string())
+    }
+}
+
+/// A log that exists but doesn't parse is an error rather than an empty one: saving over it
+/// would reset the spend totals the caps are checked against.
+fn read_records(path: &Path) -> Result<Vec<UsageRecord>, String> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_str(&content).map_err(|e| {
+        format!(
+            "{} is damaged ({}); AI usage isn't recorded until it is fixed or removed",
+            path.display(),
+            e
+        )
+    })
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// Local calendar date of a unix timestamp, so caps reset at the user's midnight
+fn local_date(timestamp: u64) -> Option<NaiveDate> {
+    chrono::DateTime::from_timestamp(timestamp as i64, 0).map(|d| d.with_timezone(&Local).date_naive())
+}
+
+fn same_month(a: NaiveDate, b: NaiveDate) -> bool {
+    a.year() == b.year() && a.month() == b.month()
+}
+
+/// Estimated cost in USD of a request
+pub fn estimate_cost(provider: &str, model: &str, usage: &TokenUsage) -> f64 {
+    let model = model.to_lowercase();
+    let price = PRICE_TABLE
+        .iter()
+        .filter(|(p, prefix, _, _)| *p == provider && model.starts_with(prefix))
+        .max_by_key(|(_, prefix, _, _)| prefix.len());
+
+    match price {
+        Some((_, _, input, output)) => {
+            (usage.input_tokens as f64 * input + usage.output_tokens as f64 * output) / 1_000_000.0
+        }
+        None => 0.0,
+    }
+}
+
+fn budget_status(records: &[UsageRecord], config: &AppConfig, today: NaiveDate) -> BudgetStatus {
+    let mut daily_spend_usd = 0.0;
+    let mut monthly_spend_usd = 0.0;
+    for record in records {
+        let Some(date) = local_date(record.timestamp) else {
+            continue;
+        };
+        if date == today {
+            daily_spend_usd += record.cost_usd;
+        }
+        if same_month(date, today) {
+            monthly_spend_usd += record.cost_usd;
+        }
+    }
+
+    let over = |spend: f64, cap: Option<f64>| cap.map(|cap| spend >= cap).unwrap_or(false);
+    BudgetStatus {
+        daily_spend_usd,
+        monthly_spend_usd,
+        daily_cap_usd: config.daily_spend_cap,
+        monthly_cap_usd: config.monthly_spend_cap,
+        vision_paused: over(daily_spend_usd, config.daily_spend_cap)
+            || over(monthly_spend_usd, config.monthly_spend_cap),
+    }
+}
+
+/// Refuse a request the spend caps don't allow
+/// `vision` requests (screen analysis, images) are paused once a cap is reached; every request is
+/// refused while a cap is set but the usage log couldn't be read.
+pub fn check_caps(app: &AppHandle, config: &AppConfig, vision: bool) -> Result<(), AiError> {
+    match app.try_state::<UsageTracker>() {
+        Some(tracker) => tracker.check(config, vision),
+        None => Ok(()),
+    }
+}
+
+/// Append a finished request to the usage log
+/// Emits `ai://budget-exceeded` when this request pushed spending over a cap.
+pub fn record(app: &AppHandle, request: &CompletionRequest, response: &AiResponse) {
+    let Some(state) = app.try_state::<UsageTracker>() else {
+        return;
+    };
+    if let Some(e) = &state.load_error {
+        eprintln!("AI usage not recorded: {}", e);
+        return;
+    }
+    let config = storage::load_config(app.clone());
+
+    let record = UsageRecord {
+        timestamp: now_secs(),
+        provider: response.provider.clone(),
+        model: response.model.clone(),
+        profile: response.profile.clone(),
+        feature: AiFeature::of(request),
+        input_tokens: response.usage.input_tokens,
+        output_tokens: response.usage.output_tokens,
+        images: request.images.len() as u32,
+        cost_usd: response.cost_usd,
+    };
+
+    let Ok(mut records) = state.records.lock() else {
+        return;
+    };
+    let today = Local::now().date_naive();
+    let was_paused = budget_status(&records, &config, today).vision_paused;
+
+    let cutoff = record.timestamp.saturating_sub(RETENTION_DAYS * 86400);
+    records.retain(|r| r.timestamp >= cutoff);
+    records.push(record);
+
+    let status = budget_status(&records, &config, today);
+    if let Err(e) = state.save(&records) {
+        eprintln!("Failed to save AI usage: {}", e);
+    }
+    drop(records);
+
+    if status.vision_paused && !was_paused {
+        let _ = app.emit("ai://budget-exceeded", &status);
+    }
+}
+
+fn sorted_totals(map: HashMap<String, UsageTotals>) -> Vec<UsageTotals> {
+    let mut totals: Vec<UsageTotals> = map.into_values().collect();
+    totals.sort_by(|a, b| {
+        b.cost_usd
+            .partial_cmp(&a.cost_usd)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(b.requests.cmp(&a.requests))
+    });
+    totals
+}
+
+/// Get AI usage for a range: "today", "week" (last 7 days), "month" (calendar month) or "all"
+#[tauri::command]
+pub fn get_ai_usage(
+    app: AppHandle,
+    state: tauri::State<UsageTracker>,
+    range: String,
+) -> Result<UsageSummary, String> {
+    if let Some(e) = &state.load_error {
+        return Err(e.clone());
+    }
+    let config = storage::load_config(app);
+    let records = state.records.lock().map_err(|e| e.to_string())?;
+
+    let today = Local::now().date_naive();
+    let week_start = today - Days::new(6);
+    let in_range = |record: &UsageRecord| match (range.as_str(), local_date(record.timestamp)) {
+        ("today", Some(date)) => date == today,
+        ("week", Some(date)) => date >= week_start,
+        ("month", Some(date)) => same_month(date, today),
+        ("all", _) => true,
+        _ => false,
+    };
+    if !matches!(range.as_str(), "today" | "week" | "month" | "all") {
+        return Err(format!("Unknown usage range: {}", range));
+    }
+
+    let mut total = UsageTotals {
+        key: "total".to_string(),
+        ..Default::default()
+    };
+    let mut by_provider: HashMap<String, UsageTotals> = HashMap::new();
+    let mut by_model: HashMap<String, UsageTotals> = HashMap::new();
+    let mut by_feature: HashMap<String, UsageTotals> = HashMap::new();
+
+    for record in records.iter().filter(|r| in_range(r)) {
+        total.add(record);
+        for (map, key) in [
+            (&mut by_provider, record.provider.clone()),
+            (&mut by_model, format!("{}/{}", record.provider, record.model)),
+            (&mut by_feature, record.feature.as_str().to_string()),
+        ] {
+            map.entry(key.clone())
+                .or_insert_with(|| UsageTotals {
+                    key,
+                    ..Default::default()
+                })
+                .add(record);
+        }
+    }
+
+    Ok(UsageSummary {
+        range,
+        total,
+        by_provider: sorted_totals(by_provider),
+        by_model: sorted_totals(by_model),
+        by_feature: sorted_totals(by_feature),
+        budget: budget_status(&records, &config, today),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_files::temp_dir;
+    use chrono::TimeZone;
+
+    fn assert_cost(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+    }
+
+    fn usage(input_tokens: u64, output_tokens: u64) -> TokenUsage {
+        TokenUsage {
+            input_tokens,
+            output_tokens,
+        }
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    /// Midday on `day`, local time
+    fn at(day: NaiveDate) -> u64 {
+        Local.from_local_datetime(&day.and_hms_opt(12, 0, 0).unwrap()).unwrap().timestamp() as u64
+    }
+
+    fn spent(timestamp: u64, cost_usd: f64) -> UsageRecord {
+        UsageRecord {
+            timestamp,
+            provider: "openai".to_string(),
+            model: "gpt-4o".to_string(),
+            profile: "default".to_string(),
+            feature: AiFeature::Chat,
+            input_tokens: 0,
+            output_tokens: 0,
+            images: 0,
+            cost_usd,
+        }
+    }
+
+    fn caps(daily: Option<f64>, monthly: Option<f64>) -> AppConfig {
+        AppConfig {
+            daily_spend_cap: daily,
+            monthly_spend_cap: monthly,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn cost_uses_the_longest_matching_model_prefix() {
+        let million = usage(1_000_000, 1_000_000);
+        assert_cost(estimate_cost("openai", "gpt-4o-mini-2024-07-18", &million), 0.75);
+        assert_cost(estimate_cost("openai", "GPT-4o-2024-08-06", &million), 12.5);
+        assert_cost(estimate_cost("anthropic", "claude-opus-4-5-20251101", &million), 30.0);
+        assert_cost(estimate_cost("anthropic", "claude-opus-4-1", &million), 90.0);
+        assert_cost(estimate_cost("gemini", "gemini-2.0-flash-lite", &usage(2_000_000, 0)), 0.15);
+    }
+
+    #[test]
+    fn unknown_and_local_models_are_free() {
+        let million = usage(1_000_000, 1_000_000);
+        assert_eq!(estimate_cost("openai", "my-finetune", &million), 0.0);
+        assert_eq!(estimate_cost("local", "gpt-4o", &million), 0.0);
+    }
+
+    #[test]
+    fn budget_counts_today_and_this_month() {
+        let today = date(2026, 3, 15);
+        let records = vec![
+            spent(at(today), 1.0),
+            spent(at(today), 0.5),
+            spent(at(date(2026, 3, 1)), 4.0),
+            spent(at(date(2026, 2, 28)), 100.0),
+            spent(at(date(2025, 3, 15)), 100.0),
+        ];
+
+        let status = budget_status(&records, &caps(Some(2.0), Some(10.0)), today);
+        assert_cost(status.daily_spend_usd, 1.5);
+        assert_cost(status.monthly_spend_usd, 5.5);
+        assert_eq!(status.daily_cap_usd, Some(2.0));
+        assert_eq!(status.monthly_cap_usd, Some(10.0));
+        assert!(!status.vision_paused);
+
+        assert!(budget_status(&records, &caps(None, Some(5.5)), today).vision_paused);
+    }
+
+    #[test]
+    fn reaching_a_cap_pauses_vision() {
+        let today = date(2026, 3, 15);
+        let records = vec![spent(at(today), 2.0)];
+        assert!(budget_status(&records, &caps(Some(2.0), None), today).vision_paused);
+        assert!(!budget_status(&records, &caps(Some(2.01), None), today).vision_paused);
+        assert!(!budget_status(&records, &caps(None, None), today).vision_paused);
+        // Yesterday's spend doesn't count against today's cap
+        assert!(!budget_status(&records, &caps(Some(2.0), None), date(2026, 3, 16)).vision_paused);
+    }
+
+    #[test]
+    fn damaged_log_blocks_requests_while_capped() {
+        let dir = temp_dir("usage-damaged");
+        fs::write(dir.join("ai_usage.json"), "[{\"timestamp\": 1").unwrap();
+        let tracker = UsageTracker::new(dir.clone());
+        assert!(tracker.load_error.as_deref().unwrap().contains("damaged"));
+
+        assert!(matches!(
+            tracker.check(&caps(Some(5.0), None), false),
+            Err(AiError::UsageUnavailable(_))
+        ));
+        // Without caps there is nothing to enforce
+        assert!(tracker.check(&caps(None, None), true).is_ok());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn caps_pause_only_vision() {
+        let dir = temp_dir("usage-capped");
+        let tracker = UsageTracker::new(dir.clone());
+        assert!(tracker.load_error.is_none());
+        tracker.records.lock().unwrap().push(spent(now_secs(), 3.0));
+
+        let config = caps(Some(2.0), None);
+        assert!(tracker.check(&config, false).is_ok());
+        assert!(matches!(tracker.check(&config, true), Err(AiError::BudgetExceeded)));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,485 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use super::ai::local;
+use super::memory;
+use super::storage::{self, AppConfig};
+
+const INDEX_FILE: &str = "brain_index.json";
+/// Dimensions of the built-in keyword embedder
+const HASH_DIMS: usize = 384;
+/// Target size of a brain file chunk
+const CHUNK_CHARS: usize = 1000;
+/// Texts sent per embeddings request
+const EMBED_BATCH: usize = 32;
+/// Brain files that are not indexed as documents (memories are indexed from the store)
+const SKIPPED_FILES: &[&str] = &["memory.json", "knowledge.md"];
+/// Extensions of brain files worth indexing
+const TEXT_EXTENSIONS: &[&str] = &["md", "txt"];
+
+/// One embedded piece of text
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexedItem {
+    /// "memory:<id>" or "file:<name>#<chunk>"
+    id: String,
+    kind: String,
+    /// Memory id or file name
+    source: String,
+    text: String,
+    /// SHA-256 of the text, used to skip unchanged items on rebuild
+    hash: String,
+    vector: Vec<f32>,
+}
+
+/// On-disk index, tied to the embedder that produced it
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct EmbeddingIndex {
+    /// "builtin" (keyword fallback) or "local"
+    provider: String,
+    model: String,
+    items: Vec<IndexedItem>,
+}
+
+/// Managed state; the async mutex keeps rebuilds from running concurrently
+#[derive(Default)]
+pub struct BrainIndex {
+    index: tokio::sync::Mutex<Option<EmbeddingIndex>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BrainIndexStatus {
+    pub provider: String,
+    pub model: String,
+    pub items: usize,
+    /// Items embedded by the last update
+    pub embedded: usize,
+    /// Items dropped by the last update
+    pub removed: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BrainHit {
+    /// "memory" or "file"
+    pub kind: String,
+    /// Memory id or file name
+    pub source: String,
+    pub text: String,
+    /// Cosine similarity to the query
+    pub score: f32,
+}
+
+/// A piece of text that should be in the index
+struct SourceItem {
+    id: String,
+    kind: String,
+    source: String,
+    text: String,
+}
+
+fn index_path(app: &AppHandle) -> PathBuf {
+    storage::get_internal_app_dir(app).join(INDEX_FILE)
+}
+
+fn load_index(app: &AppHandle) -> EmbeddingIndex {
+    fs::read_to_string(index_path(app))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_index(app: &AppHandle, index: &EmbeddingIndex) -> Result<(), String> {
+    let path = index_path(app);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_string(index).map_err(|e| e.to_string())?;
+    fs::write(&path, json).map_err(|e| e.to_string())
+}
+
+fn hash_text(text: &str) -> String {
+    Sha256::digest(text.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Split a paragraph longer than `CHUNK_CHARS` at line, sentence or word breaks
+fn split_paragraph(paragraph: &str) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut rest = paragraph;
+    while rest.len() > CHUNK_CHARS {
+        let mut limit = CHUNK_CHARS;
+        while !rest.is_char_boundary(limit) {
+            limit -= 1;
+        }
+        let window = &rest[..limit];
+        // Only break early if the piece stays reasonably large; otherwise cut mid-word
+        let min = CHUNK_CHARS / 2;
+        let cut = [window.rfind('\n'), window.rfind(". ").map(|i| i + 1), window.rfind(char::is_whitespace)]
+            .into_iter()
+            .flatten()
+            .find(|&i| i >= min)
+            .unwrap_or(limit);
+        pieces.push(rest[..cut].trim());
+        rest = rest[cut..].trim_start();
+    }
+    if !rest.is_empty() {
+        pieces.push(rest);
+    }
+    pieces
+}
+
+/// Split a document into paragraph-aligned chunks of at most `CHUNK_CHARS`
+fn chunk_text(text: &str) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let paragraphs = text.split("\n\n").map(str::trim).filter(|p| !p.is_empty());
+    for paragraph in paragraphs.flat_map(split_paragraph) {
+        if !current.is_empty() && current.len() + paragraph.len() > CHUNK_CHARS {
+            chunks.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push_str("\n\n");
+        }
+        current.push_str(paragraph);
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// Everything that should currently be indexed: memories and text files in the brain folder
+fn collect_sources(app: &AppHandle, config: &AppConfig) -> Result<Vec<SourceItem>, String> {
+    let mut items: Vec<SourceItem> = memory::load_memories(app)?
+        .into_iter()
+        .map(|entry| SourceItem {
+            id: format!("memory:{}", entry.id),
+            kind: "memory".to_string(),
+            source: entry.id,
+            text: entry.text,
+        })
+        .collect();
+
+    let Ok(dir) = fs::read_dir(storage::get_brain_dir(config)) else {
+        return Ok(items);
+    };
+    for entry in dir.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        let is_text = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| TEXT_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+            .unwrap_or(false);
+        if !path.is_file() || !is_text || SKIPPED_FILES.contains(&name.as_str()) {
+            continue;
+        }
+
+        let Ok(content) = fs::read_to_string(&path) else { continue };
+        for (i, chunk) in chunk_text(&content).into_iter().enumerate() {
+            items.push(SourceItem {
+                id: format!("file:{}#{}", name, i),
+                kind: "file".to_string(),
+                source: name.clone(),
+                text: chunk,
+            });
+        }
+    }
+    Ok(items)
+}
+
+/// FNV-1a, stable across runs and platforms
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|v| *v /= norm);
+    }
+}
+
+/// Keyword fallback used when no embedding model is configured: hashed word and word-pair counts.
+/// This is not a language model. It only finds text that shares words with the query ("car"
+/// won't find "vehicle"); search by meaning needs the "local" provider.
+fn hash_embed(text: &str) -> Vec<f32> {
+    let words: Vec<String> = text
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.len() > 1)
+        .map(|w| w.to_string())
+        .collect();
+
+    let mut counts: HashMap<String, f32> = HashMap::new();
+    for word in &words {
+        *counts.entry(word.clone()).or_default() += 1.0;
+    }
+    for pair in words.windows(2) {
+        *counts.entry(format!("{} {}", pair[0], pair[1])).or_default() += 0.5;
+    }
+
+    let mut vector = vec![0.0f32; HASH_DIMS];
+    for (term, count) in counts {
+        let hash = fnv1a(&term);
+        let sign = if hash & (1 << 63) == 0 { 1.0 } else { -1.0 };
+        vector[(hash % HASH_DIMS as u64) as usize] += sign * (1.0 + count.ln());
+    }
+    normalize(&mut vector);
+    vector
+}
+
+/// Embed texts with an OpenAI-compatible `/v1/embeddings` endpoint
+async fn remote_embed(base_url: &str, model: &str, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+    let client = reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(5))
+        .timeout(Duration::from_secs(120))
+        .build()
+        .unwrap_or_default();
+    let url = format!("{}/v1/embeddings", local::server_root(base_url));
+
+    let mut vectors = Vec::with_capacity(texts.len());
+    for batch in texts.chunks(EMBED_BATCH) {
+        let response = client
+            .post(&url)
+            .json(&json!({ "model": model, "input": batch }))
+            .send()
+            .await
+            .map_err(|e| format!("Embeddings server unreachable: {}", e))?;
+        if !response.status().is_success() {
+            return Err(format!("Embeddings server returned {}", response.status().as_u16()));
+        }
+
+        let data: Value = response.json().await.map_err(|e| e.to_string())?;
+        let mut entries: Vec<&Value> = data["data"].as_array().into_iter().flatten().collect();
+        entries.sort_by_key(|entry| entry["index"].as_u64().unwrap_or(0));
+        if entries.len() != batch.len() {
+            return Err("Embeddings server returned the wrong number of vectors".to_string());
+        }
+
+        for entry in entries {
+            let mut vector: Vec<f32> = entry["embedding"]
+                .as_array()
+                .ok_or("Embeddings response has no vector")?
+                .iter()
+                .map(|v| v.as_f64().unwrap_or(0.0) as f32)
+                .collect();
+            normalize(&mut vector);
+            vectors.push(vector);
+        }
+    }
+    Ok(vectors)
+}
+
+/// (provider, model) used for embeddings
+fn embedder(config: &AppConfig) -> (String, String) {
+    match config.embedding_provider.as_str() {
+        "local" => ("local".to_string(), config.embedding_model.clone()),
+        _ => ("builtin".to_string(), "keywords-384".to_string()),
+    }
+}
+
+async fn embed(config: &AppConfig, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+    if config.embedding_provider == "local" {
+        let base_url = config.embedding_base_url.as_deref().unwrap_or(&config.local_base_url);
+        remote_embed(base_url, &config.embedding_model, texts).await
+    } else {
+        Ok(texts.iter().map(|text| hash_embed(text)).collect())
+    }
+}
+
+/// Bring the index up to date, embedding only new or changed items
+async fn update_index(app: &AppHandle, index: &mut EmbeddingIndex, full: bool) -> Result<BrainIndexStatus, String> {
+    let config = storage::load_config(app.clone());
+    let (provider, model) = embedder(&config);
+    // Before touching the index, so an unreadable memory file leaves it as it was
+    let sources = collect_sources(app, &config)?;
+
+    // Vectors from another embedder aren't comparable, start over
+    if full || index.provider != provider || index.model != model {
+        *index = EmbeddingIndex {
+            provider: provider.clone(),
+            model: model.clone(),
+            items: Vec::new(),
+        };
+    }
+
+    let mut existing: HashMap<String, IndexedItem> =
+        index.items.drain(..).map(|item| (item.id.clone(), item)).collect();
+    let was_empty = existing.is_empty();
+
+    let mut items = Vec::with_capacity(sources.len());
+    let mut pending: Vec<(SourceItem, String)> = Vec::new();
+    for source in sources {
+        let hash = hash_text(&source.text);
+        match existing.remove(&source.id) {
+            Some(item) if item.hash == hash => items.push(item),
+            _ => pending.push((source, hash)),
+        }
+    }
+    // Whatever is left no longer exists in the brain folder
+    let removed = existing.len();
+
+    let texts: Vec<String> = pending.iter().map(|(source, _)| source.text.clone()).collect();
+    let vectors = if texts.is_empty() { Vec::new() } else { embed(&config, &texts).await? };
+    let embedded = vectors.len();
+    for ((source, hash), vector) in pending.into_iter().zip(vectors) {
+        items.push(IndexedItem {
+            id: source.id,
+            kind: source.kind,
+            source: source.source,
+            text: source.text,
+            hash,
+            vector,
+        });
+    }
+
+    index.items = items;
+    if embedded > 0 || removed > 0 || was_empty {
+        save_index(app, index)?;
+    }
+
+    Ok(BrainIndexStatus {
+        provider,
+        model,
+        items: index.items.len(),
+        embedded,
+        removed,
+    })
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    // Vectors are stored normalized
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// Most relevant memories and brain file chunks for a query
+pub async fn search(app: &AppHandle, query: &str, k: usize) -> Result<Vec<BrainHit>, String> {
+    let state = app.state::<BrainIndex>();
+    let mut guard = state.index.lock().await;
+    let index = guard.get_or_insert_with(|| load_index(app));
+    update_index(app, index, false).await?;
+
+    let config = storage::load_config(app.clone());
+    let query_vector = embed(&config, &[query.to_string()])
+        .await?
+        .pop()
+        .ok_or("No query embedding")?;
+
+    let mut hits: Vec<BrainHit> = index
+        .items
+        .iter()
+        .map(|item| BrainHit {
+            kind: item.kind.clone(),
+            source: item.source.clone(),
+            text: item.text.clone(),
+            score: cosine(&query_vector, &item.vector),
+        })
+        .collect();
+    hits.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    hits.truncate(k);
+    Ok(hits)
+}
+
+/// Search memories and brain files by meaning, or only by shared words with the builtin embedder
+#[tauri::command]
+pub async fn search_brain(app: AppHandle, query: String, k: Option<usize>) -> Result<Vec<BrainHit>, String> {
+    search(&app, &query, k.unwrap_or(5).max(1)).await
+}
+
+/// Update the index; `full` re-embeds everything
+#[tauri::command]
+pub async fn rebuild_brain_index(app: AppHandle, full: Option<bool>) -> Result<BrainIndexStatus, String> {
+    let state = app.state::<BrainIndex>();
+    let mut guard = state.index.lock().await;
+    let index = guard.get_or_insert_with(|| load_index(&app));
+    update_index(&app, index, full.unwrap_or(false)).await
+}
+
+/// Memories for the system prompt, picked by relevance to the question
+/// Pinned memories come first, then the closest matches, within `max_chars`.
+#[tauri::command]
+pub async fn get_relevant_memory_prompt(
+    app: AppHandle,
+    query: String,
+    max_chars: Option<usize>,
+    k: Option<usize>,
+) -> Result<String, String> {
+    let max_chars = max_chars.unwrap_or(2000);
+    let hits = search(&app, &query, k.unwrap_or(8).max(1)).await?;
+
+    let pinned: Vec<memory::MemoryEntry> = memory::load_memories(&app)?
+        .into_iter()
+        .filter(|entry| entry.pinned)
+        .collect();
+
+    let mut rendered = memory::render_prompt(&pinned, max_chars);
+    for hit in hits.iter().filter(|hit| hit.score > 0.0) {
+        if hit.kind == "memory" && pinned.iter().any(|entry| entry.id == hit.source) {
+            continue;
+        }
+        let line = match hit.kind.as_str() {
+            "file" => format!("- ({}) {}", hit.source, hit.text.replace('\n', " ")),
+            _ => format!("- {}", hit.text),
+        };
+        let separator = usize::from(!rendered.is_empty());
+        if rendered.len() + separator + line.len() <= max_chars {
+            if !rendered.is_empty() {
+                rendered.push('\n');
+            }
+            rendered.push_str(&line);
+        }
+    }
+    Ok(rendered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_paragraphs_share_a_chunk() {
+        assert_eq!(chunk_text("One.\n\n\n\nTwo.\n\n"), ["One.\n\nTwo."]);
+    }
+
+    #[test]
+    fn long_paragraphs_are_split_at_sentences() {
+        let sentence = "This sentence is about forty characters. ";
+        let paragraph = sentence.repeat(100);
+        let chunks = chunk_text(&paragraph);
+
+        assert!(chunks.len() >= 4);
+        assert!(chunks.iter().all(|chunk| chunk.len() <= CHUNK_CHARS));
+        assert!(chunks.iter().all(|chunk| chunk.ends_with('.')));
+        assert_eq!(chunks.concat().len(), paragraph.trim().len() - (chunks.len() - 1));
+    }
+
+    #[test]
+    fn unbroken_text_is_cut_on_char_boundaries() {
+        let paragraph = "é".repeat(CHUNK_CHARS);
+        let chunks = chunk_text(&paragraph);
+
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks.iter().all(|chunk| chunk.len() <= CHUNK_CHARS));
+        assert_eq!(chunks.concat(), paragraph);
+    }
+
+    #[test]
+    fn keyword_embedder_matches_shared_words_only() {
+        let score = |a: &str, b: &str| -> f32 {
+            hash_embed(a).iter().zip(hash_embed(b)).map(|(x, y)| x * y).sum()
+        };
+
+        assert!(score("my car is red", "the red car") > 0.5);
+        assert!(score("my car is red", "a crimson vehicle") < 0.1);
+    }
+}
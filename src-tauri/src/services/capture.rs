@@ -36,6 +36,22 @@ pub struct ScreenCaptureInfo {
 /// Quality is reduced to keep the data size manageable for AI APIs
 #[tauri::command]
 pub fn capture_screen(monitor_index: Option<usize>) -> Result<String, String> {
+    let rgba_image = capture_rgba(monitor_index)?;
+
+    // Resize to max 1280px width to reduce data size
+    let resized = resize_to_width(rgba_image, 1280);
+
+    // Encode as JPEG with moderate quality
+    let jpeg = encode_jpeg(resized, 60)?;
+
+    // Convert to base64
+    let base64_string = STANDARD.encode(jpeg);
+
+    Ok(base64_string)
+}
+
+/// Capture a screen as a raw RGBA image
+pub fn capture_rgba(monitor_index: Option<usize>) -> Result<image::RgbaImage, String> {
     // Get all screens
     let screens = Screen::all().map_err(|e| format!("Failed to get screens: {}", e))?;
 
@@ -50,38 +66,38 @@ pub fn capture_screen(monitor_index: Option<usize>) -> Result<String, String> {
         .capture()
         .map_err(|e| format!("Failed to capture screen: {}", e))?;
 
-    // Convert to smaller JPEG for efficiency
-    let rgba_image = image::RgbaImage::from_raw(image.width(), image.height(), image.to_vec())
-        .ok_or("Failed to create image buffer")?;
+    image::RgbaImage::from_raw(image.width(), image.height(), image.to_vec())
+        .ok_or_else(|| "Failed to create image buffer".to_string())
+}
 
-    // Resize to max 1280px width to reduce data size
-    let resized = if rgba_image.width() > 1280 {
-        let scale = 1280.0 / rgba_image.width() as f32;
+/// Downscale an image so it is at most `max_width` pixels wide
+pub fn resize_to_width(rgba_image: image::RgbaImage, max_width: u32) -> image::RgbaImage {
+    if rgba_image.width() > max_width {
+        let scale = max_width as f32 / rgba_image.width() as f32;
         let new_height = (rgba_image.height() as f32 * scale) as u32;
         image::imageops::resize(
             &rgba_image,
-            1280,
+            max_width,
             new_height,
             image::imageops::FilterType::Triangle,
         )
     } else {
         rgba_image
-    };
+    }
+}
 
+/// Encode an RGBA image as JPEG bytes
+pub fn encode_jpeg(rgba_image: image::RgbaImage, quality: u8) -> Result<Vec<u8>, String> {
     // Convert to RGB (JPEG doesn't support alpha)
-    let rgb_image = image::DynamicImage::ImageRgba8(resized).to_rgb8();
+    let rgb_image = image::DynamicImage::ImageRgba8(rgba_image).to_rgb8();
 
-    // Encode as JPEG with moderate quality
     let mut buffer = Cursor::new(Vec::new());
-    let encoder = JpegEncoder::new_with_quality(&mut buffer, 60);
+    let encoder = JpegEncoder::new_with_quality(&mut buffer, quality);
     rgb_image
         .write_with_encoder(encoder)
         .map_err(|e| format!("Failed to encode JPEG: {}", e))?;
 
-    // Convert to base64
-    let base64_string = STANDARD.encode(buffer.into_inner());
-
-    Ok(base64_string)
+    Ok(buffer.into_inner())
 }
 
 /// Capture screen and return dimensions info
@@ -0,0 +1,750 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::AppHandle;
+
+use super::ai::{AiResponse, CompletionRequest};
+use super::capture;
+use super::storage::{self, AppConfig};
+
+const THUMBNAILS_DIR: &str = "thumbnails";
+const EXPORTS_DIR: &str = "exports";
+/// Width of stored screenshot thumbnails
+const THUMBNAIL_WIDTH: u32 = 320;
+/// Longest automatic title
+const MAX_TITLE_CHARS: usize = 48;
+/// Characters of context around a search match
+const SNIPPET_CHARS: usize = 60;
+
+/// A screenshot kept with a message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attachment {
+    /// "screenshot"
+    pub kind: String,
+    /// JPEG file name in the thumbnails folder
+    pub thumbnail: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationMessage {
+    /// "user", "assistant" or "system"
+    pub role: String,
+    pub content: String,
+    /// Unix milliseconds
+    pub timestamp: u64,
+    /// Provider and model that produced an assistant message
+    #[serde(default)]
+    pub provider: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Conversation {
+    pub id: String,
+    pub title: String,
+    /// Set once the user renames the conversation, so it isn't retitled automatically
+    #[serde(default)]
+    pub custom_title: bool,
+    /// Unix milliseconds
+    pub created_at: u64,
+    /// Unix milliseconds
+    pub updated_at: u64,
+    pub messages: Vec<ConversationMessage>,
+}
+
+/// Conversation without its messages, for lists
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationSummary {
+    pub id: String,
+    pub title: String,
+    pub created_at: u64,
+    pub updated_at: u64,
+    pub message_count: usize,
+    /// Start of the last message
+    pub preview: String,
+}
+
+/// A message from the frontend; `image` is a base64 JPEG stored as a thumbnail
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewConversationMessage {
+    pub role: String,
+    pub content: String,
+    #[serde(default)]
+    pub provider: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub image: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationSearchHit {
+    pub id: String,
+    pub title: String,
+    pub updated_at: u64,
+    /// Index of the matching message (None = the title matched)
+    pub message_index: Option<usize>,
+    pub snippet: String,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+fn conversations_dir(app: &AppHandle) -> PathBuf {
+    storage::get_conversations_dir(&storage::load_config(app.clone()))
+}
+
+fn conversation_path(app: &AppHandle, id: &str) -> Result<PathBuf, String> {
+    conversation_file(&conversations_dir(app), id)
+}
+
+/// Conversation ids are generated uuids; anything else could escape the folder
+fn conversation_file(dir: &Path, id: &str) -> Result<PathBuf, String> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(format!("Invalid conversation id: {}", id));
+    }
+    Ok(dir.join(format!("{}.json", id)))
+}
+
+fn load_conversation(app: &AppHandle, id: &str) -> Result<Conversation, String> {
+    let content = fs::read_to_string(conversation_path(app, id)?)
+        .map_err(|_| format!("Conversation not found: {}", id))?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse conversation: {}", e))
+}
+
+fn save_conversation(app: &AppHandle, conversation: &Conversation) -> Result<(), String> {
+    let path = conversation_path(app, &conversation.id)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_string_pretty(conversation).map_err(|e| e.to_string())?;
+    fs::write(&path, json).map_err(|e| e.to_string())
+}
+
+/// All stored conversations, unordered
+fn load_all(app: &AppHandle) -> Vec<Conversation> {
+    read_all(&conversations_dir(app))
+}
+
+fn read_all(dir: &Path) -> Vec<Conversation> {
+    let Ok(dir) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    dir.flatten()
+        .filter(|entry| entry.path().extension().map(|e| e == "json").unwrap_or(false))
+        .filter_map(|entry| fs::read_to_string(entry.path()).ok())
+        .filter_map(|content| serde_json::from_str(&content).ok())
+        .collect()
+}
+
+/// Delete a conversation and its thumbnails
+fn remove_conversation(dir: &Path, conversation: &Conversation) -> Result<(), String> {
+    let thumbnails = dir.join(THUMBNAILS_DIR);
+    for attachment in conversation.messages.iter().flat_map(|m| &m.attachments) {
+        let _ = fs::remove_file(thumbnails.join(&attachment.thumbnail));
+    }
+    fs::remove_file(conversation_file(dir, &conversation.id)?).map_err(|e| e.to_string())
+}
+
+/// Delete conversations untouched for longer than the configured retention
+pub fn prune(app: &AppHandle, config: &AppConfig) {
+    if let Some(days) = config.conversation_retention_days {
+        prune_dir(&conversations_dir(app), days, now_ms());
+    }
+}
+
+fn prune_dir(dir: &Path, days: u32, now: u64) {
+    let cutoff = now.saturating_sub(days as u64 * 24 * 60 * 60 * 1000);
+    for conversation in read_all(dir).iter().filter(|c| c.updated_at < cutoff) {
+        if let Err(e) = remove_conversation(dir, conversation) {
+            eprintln!("Failed to prune conversation {}: {}", conversation.id, e);
+        }
+    }
+}
+
+/// Store a screenshot as a small JPEG thumbnail, returning its file name
+fn save_thumbnail(app: &AppHandle, base64_image: &str) -> Result<String, String> {
+    let bytes = STANDARD
+        .decode(base64_image)
+        .map_err(|e| format!("Invalid image data: {}", e))?;
+    let image = image::load_from_memory(&bytes)
+        .map_err(|e| format!("Failed to decode image: {}", e))?
+        .to_rgba8();
+    let jpeg = capture::encode_jpeg(capture::resize_to_width(image, THUMBNAIL_WIDTH), 70)?;
+
+    let dir = conversations_dir(app).join(THUMBNAILS_DIR);
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let name = format!("{}.jpg", uuid::Uuid::new_v4());
+    fs::write(dir.join(&name), jpeg).map_err(|e| e.to_string())?;
+    Ok(name)
+}
+
+/// Title from the first user message: its first line, cut at a word boundary
+fn generate_title(text: &str) -> String {
+    let line = text.lines().map(str::trim).find(|l| !l.is_empty()).unwrap_or("");
+    let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+    if line.chars().count() <= MAX_TITLE_CHARS {
+        return line;
+    }
+
+    let cut: String = line.chars().take(MAX_TITLE_CHARS).collect();
+    let cut = match cut.rfind(' ') {
+        Some(space) if space > MAX_TITLE_CHARS / 2 => &cut[..space],
+        _ => cut.as_str(),
+    };
+    format!("{}…", cut.trim_end_matches(|c: char| c.is_ascii_punctuation()))
+}
+
+fn untitled(created_at: u64) -> String {
+    format!("Conversation {}", format_time(created_at)).trim().to_string()
+}
+
+/// Add a message; `retitle` lets the first user message name the conversation
+fn append(
+    app: &AppHandle,
+    id: &str,
+    message: NewConversationMessage,
+    retitle: bool,
+) -> Result<ConversationMessage, String> {
+    let mut conversation = load_conversation(app, id)?;
+
+    let mut attachments = Vec::new();
+    if let Some(image) = message.image.as_deref().filter(|i| !i.is_empty()) {
+        attachments.push(Attachment {
+            kind: "screenshot".to_string(),
+            thumbnail: save_thumbnail(app, image)?,
+        });
+    }
+
+    let entry = ConversationMessage {
+        role: message.role,
+        content: message.content,
+        timestamp: now_ms(),
+        provider: message.provider,
+        model: message.model,
+        attachments,
+    };
+
+    add_message(&mut conversation, entry.clone(), retitle);
+    save_conversation(app, &conversation)?;
+    Ok(entry)
+}
+
+fn add_message(conversation: &mut Conversation, entry: ConversationMessage, retitle: bool) {
+    let has_user_message = conversation.messages.iter().any(|m| m.role == "user");
+    if retitle && !conversation.custom_title && !has_user_message && entry.role == "user" {
+        let title = generate_title(&entry.content);
+        if !title.is_empty() {
+            conversation.title = title;
+        }
+    }
+
+    conversation.updated_at = entry.timestamp;
+    conversation.messages.push(entry);
+}
+
+/// Messages to save for a reply
+/// A screenshot went out with the last user message, so it is kept on that message; the
+/// frontend only saves what the user typed, so requests with images add their prompt here.
+fn response_messages(request: &CompletionRequest, response: &AiResponse) -> Vec<NewConversationMessage> {
+    let mut messages = Vec::new();
+    if let Some(image) = request.images.first() {
+        let prompt = request.messages.iter().rev().find(|m| m.role == "user");
+        messages.push(NewConversationMessage {
+            role: "user".to_string(),
+            content: prompt.map(|m| m.content.clone()).unwrap_or_default(),
+            provider: None,
+            model: None,
+            image: Some(image.clone()),
+        });
+    }
+    messages.push(NewConversationMessage {
+        role: "assistant".to_string(),
+        content: response.text.clone(),
+        provider: Some(response.provider.clone()),
+        model: Some(response.model.clone()),
+        image: None,
+    });
+    messages
+}
+
+/// Save an AI reply to the conversation named in the request, with any screenshot it looked at
+pub fn record_response(app: &AppHandle, request: &CompletionRequest, response: &AiResponse) {
+    let Some(id) = request.conversation_id.as_deref() else {
+        return;
+    };
+    // The prompt of a screen analysis isn't something the user wrote, so it doesn't name the conversation
+    for message in response_messages(request, response) {
+        if let Err(e) = append(app, id, message, false) {
+            eprintln!("Failed to save reply to conversation {}: {}", id, e);
+            return;
+        }
+    }
+}
+
+fn summarize(conversation: &Conversation) -> ConversationSummary {
+    let preview = conversation
+        .messages
+        .last()
+        .map(|m| m.content.chars().take(100).collect())
+        .unwrap_or_default();
+    ConversationSummary {
+        id: conversation.id.clone(),
+        title: conversation.title.clone(),
+        created_at: conversation.created_at,
+        updated_at: conversation.updated_at,
+        message_count: conversation.messages.len(),
+        preview,
+    }
+}
+
+/// Text around the first case-insensitive match of `query`
+fn snippet(text: &str, query: &str) -> Option<String> {
+    // Lowercasing can turn one character into several, so remember which character of
+    // `text` each lowercased one came from and cut `text` itself
+    let mut lower = String::new();
+    let mut origin = Vec::new();
+    for (i, c) in text.chars().enumerate() {
+        for l in c.to_lowercase() {
+            lower.push(l);
+            origin.push(i);
+        }
+    }
+    let query: String = query.chars().flat_map(char::to_lowercase).collect();
+    if query.is_empty() {
+        return None;
+    }
+
+    let byte_index = lower.find(&query)?;
+    let match_start = lower[..byte_index].chars().count();
+    let match_end = match_start + query.chars().count();
+    let start = origin[match_start].saturating_sub(SNIPPET_CHARS);
+    let end = origin[match_end - 1] + 1 + SNIPPET_CHARS;
+
+    let mut snippet: String = text.chars().skip(start).take(end - start).collect();
+    snippet = snippet.replace('\n', " ");
+    if start > 0 {
+        snippet.insert(0, '…');
+    }
+    if end < text.chars().count() {
+        snippet.push('…');
+    }
+    Some(snippet)
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn format_time(timestamp: u64) -> String {
+    chrono::DateTime::from_timestamp_millis(timestamp as i64)
+        .map(|d| d.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+fn speaker(message: &ConversationMessage) -> String {
+    let name = match message.role.as_str() {
+        "user" => "You",
+        "assistant" => "Assistant",
+        _ => "System",
+    };
+    match &message.model {
+        Some(model) => format!("{} ({})", name, model),
+        None => name.to_string(),
+    }
+}
+
+fn to_markdown(conversation: &Conversation) -> String {
+    let mut out = format!("# {}\n\n_{}_\n", conversation.title, format_time(conversation.created_at));
+    for message in &conversation.messages {
+        out.push_str(&format!(
+            "\n## {} · {}\n\n{}\n",
+            speaker(message),
+            format_time(message.timestamp),
+            message.content
+        ));
+        for attachment in &message.attachments {
+            out.push_str(&format!("\n![{}](../{}/{})\n", attachment.kind, THUMBNAILS_DIR, attachment.thumbnail));
+        }
+    }
+    out
+}
+
+/// Self-contained HTML page; thumbnails are embedded so the file can be shared on its own
+fn to_html(thumbnails: &Path, conversation: &Conversation) -> String {
+    let mut body = String::new();
+    for message in &conversation.messages {
+        body.push_str(&format!(
+            "<section class=\"{}\"><h2>{} <small>{}</small></h2><p>{}</p>",
+            escape_html(&message.role),
+            escape_html(&speaker(message)),
+            format_time(message.timestamp),
+            escape_html(&message.content).replace('\n', "<br>")
+        ));
+        for attachment in &message.attachments {
+            if let Ok(bytes) = fs::read(thumbnails.join(&attachment.thumbnail)) {
+                body.push_str(&format!(
+                    "<img alt=\"{}\" src=\"data:image/jpeg;base64,{}\">",
+                    escape_html(&attachment.kind),
+                    STANDARD.encode(bytes)
+                ));
+            }
+        }
+        body.push_str("</section>\n");
+    }
+
+    format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{title}</title>\n<style>\
+body{{font-family:sans-serif;max-width:760px;margin:2em auto;padding:0 1em;line-height:1.5}}\
+section{{margin:1em 0;padding:.5em 1em;border-radius:8px;background:#f4f4f4}}\
+section.user{{background:#e3efff}}h2{{font-size:1em;margin:.3em 0}}small{{color:#777;font-weight:normal}}\
+img{{max-width:100%;border-radius:4px}}</style></head>\n<body><h1>{title}</h1>\n{body}</body></html>\n",
+        title = escape_html(&conversation.title),
+        body = body
+    )
+}
+
+/// File name for an export, based on the title
+fn export_name(conversation: &Conversation, extension: &str) -> String {
+    let slug: String = conversation
+        .title
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+        .collect();
+    let slug = slug.split('-').filter(|s| !s.is_empty()).collect::<Vec<_>>().join("-");
+    let slug = if slug.is_empty() { "conversation".to_string() } else { slug };
+    format!("{}_{}.{}", slug.chars().take(40).collect::<String>(), conversation.created_at, extension)
+}
+
+/// Start a new conversation
+#[tauri::command]
+pub fn create_conversation(app: AppHandle, title: Option<String>) -> Result<Conversation, String> {
+    let now = now_ms();
+    let title = title.map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+    let conversation = Conversation {
+        id: uuid::Uuid::new_v4().to_string(),
+        custom_title: title.is_some(),
+        title: title.unwrap_or_else(|| untitled(now)),
+        created_at: now,
+        updated_at: now,
+        messages: Vec::new(),
+    };
+    save_conversation(&app, &conversation)?;
+    Ok(conversation)
+}
+
+/// List conversations, most recently active first
+#[tauri::command]
+pub fn list_conversations(app: AppHandle) -> Vec<ConversationSummary> {
+    prune(&app, &storage::load_config(app.clone()));
+    let mut conversations: Vec<ConversationSummary> = load_all(&app).iter().map(summarize).collect();
+    conversations.sort_by_key(|c| std::cmp::Reverse(c.updated_at));
+    conversations
+}
+
+/// Get a conversation with all its messages
+#[tauri::command]
+pub fn get_conversation(app: AppHandle, id: String) -> Result<Conversation, String> {
+    load_conversation(&app, &id)
+}
+
+/// Add a message to a conversation
+#[tauri::command]
+pub fn append_conversation_message(
+    app: AppHandle,
+    id: String,
+    message: NewConversationMessage,
+) -> Result<ConversationMessage, String> {
+    append(&app, &id, message, true)
+}
+
+/// Find conversations whose title or messages contain `query` (case-insensitive)
+/// Returns one hit per matching message, newest conversations first
+#[tauri::command]
+pub fn search_conversations(app: AppHandle, query: String) -> Vec<ConversationSearchHit> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    let mut conversations = load_all(&app);
+    conversations.sort_by_key(|c| std::cmp::Reverse(c.updated_at));
+
+    let mut hits = Vec::new();
+    for conversation in &conversations {
+        let hit = |message_index, text| ConversationSearchHit {
+            id: conversation.id.clone(),
+            title: conversation.title.clone(),
+            updated_at: conversation.updated_at,
+            message_index,
+            snippet: text,
+        };
+        if let Some(text) = snippet(&conversation.title, &query) {
+            hits.push(hit(None, text));
+        }
+        for (i, message) in conversation.messages.iter().enumerate() {
+            if let Some(text) = snippet(&message.content, &query) {
+                hits.push(hit(Some(i), text));
+            }
+        }
+    }
+    hits
+}
+
+/// Rename a conversation; it keeps this title from then on
+#[tauri::command]
+pub fn rename_conversation(app: AppHandle, id: String, title: String) -> Result<(), String> {
+    let title = title.trim().to_string();
+    if title.is_empty() {
+        return Err("Title is empty".to_string());
+    }
+    let mut conversation = load_conversation(&app, &id)?;
+    conversation.title = title;
+    conversation.custom_title = true;
+    save_conversation(&app, &conversation)
+}
+
+/// Delete a conversation and its thumbnails
+#[tauri::command]
+pub fn delete_conversation(app: AppHandle, id: String) -> Result<(), String> {
+    let conversation = load_conversation(&app, &id)?;
+    remove_conversation(&conversations_dir(&app), &conversation)
+}
+
+/// Get a stored screenshot thumbnail as base64 JPEG
+#[tauri::command]
+pub fn get_conversation_thumbnail(app: AppHandle, name: String) -> Result<String, String> {
+    if name.contains(['/', '\\']) || name.starts_with('.') {
+        return Err(format!("Invalid thumbnail name: {}", name));
+    }
+    let bytes = fs::read(conversations_dir(&app).join(THUMBNAILS_DIR).join(&name))
+        .map_err(|_| format!("Thumbnail not found: {}", name))?;
+    Ok(STANDARD.encode(bytes))
+}
+
+/// Export a conversation as "markdown" or "html" into the conversations/exports folder
+/// Returns the path of the written file
+#[tauri::command]
+pub fn export_conversation(app: AppHandle, id: String, format: String) -> Result<String, String> {
+    let conversation = load_conversation(&app, &id)?;
+    let (content, extension) = match format.as_str() {
+        "markdown" | "md" => (to_markdown(&conversation), "md"),
+        "html" => (to_html(&conversations_dir(&app).join(THUMBNAILS_DIR), &conversation), "html"),
+        other => return Err(format!("Unknown export format: {}", other)),
+    };
+
+    let dir = conversations_dir(&app).join(EXPORTS_DIR);
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let path = dir.join(export_name(&conversation, extension));
+    fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::ai::TokenUsage;
+    use crate::services::test_files::temp_dir;
+
+    fn message(role: &str, content: &str) -> ConversationMessage {
+        ConversationMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+            timestamp: 1_700_000_000_000,
+            provider: None,
+            model: None,
+            attachments: Vec::new(),
+        }
+    }
+
+    fn conversation(id: &str, updated_at: u64, messages: Vec<ConversationMessage>) -> Conversation {
+        Conversation {
+            id: id.to_string(),
+            title: "Plans & <ideas>".to_string(),
+            custom_title: false,
+            created_at: 1_700_000_000_000,
+            updated_at,
+            messages,
+        }
+    }
+
+    #[test]
+    fn title_is_the_first_line_cut_at_a_word() {
+        assert_eq!(generate_title("\n  How do   I rebase?  \nmore"), "How do I rebase?");
+        assert_eq!(generate_title("   "), "");
+
+        let long = "Please explain how the borrow checker decides when a reference lives long enough";
+        let title = generate_title(long);
+        assert_eq!(title, "Please explain how the borrow checker decides…");
+        assert!(title.chars().count() <= MAX_TITLE_CHARS + 1);
+
+        // No space late enough to cut at: cut mid-word, trailing punctuation dropped
+        let title = generate_title(&format!("{},,,{}", "a".repeat(46), "b".repeat(20)));
+        assert_eq!(title, format!("{}…", "a".repeat(46)));
+    }
+
+    #[test]
+    fn first_user_message_names_the_conversation() {
+        let mut c = conversation("c1", 0, Vec::new());
+        add_message(&mut c, message("assistant", "Hello there"), true);
+        assert_eq!(c.title, "Plans & <ideas>");
+        add_message(&mut c, message("user", "Rust lifetimes"), false);
+        assert_eq!(c.title, "Plans & <ideas>");
+
+        let mut c = conversation("c2", 0, Vec::new());
+        add_message(&mut c, message("user", "Rust lifetimes"), true);
+        add_message(&mut c, message("user", "Something else"), true);
+        assert_eq!(c.title, "Rust lifetimes");
+        assert_eq!(c.updated_at, 1_700_000_000_000);
+
+        let mut c = conversation("c3", 0, Vec::new());
+        c.custom_title = true;
+        add_message(&mut c, message("user", "Rust lifetimes"), true);
+        assert_eq!(c.title, "Plans & <ideas>");
+    }
+
+    #[test]
+    fn snippet_is_cut_around_the_match() {
+        assert_eq!(snippet("Hello World", "world").as_deref(), Some("Hello World"));
+        assert_eq!(snippet("Hello World", "mars"), None);
+        assert_eq!(snippet("Hello", ""), None);
+        assert_eq!(snippet("line one\nLINE two", "line two").as_deref(), Some("line one LINE two"));
+
+        let text = format!("{}needle{}", "a".repeat(100), "b".repeat(100));
+        let expected = format!("…{}needle{}…", "a".repeat(60), "b".repeat(60));
+        assert_eq!(snippet(&text, "NEEDLE").as_deref(), Some(expected.as_str()));
+    }
+
+    #[test]
+    fn snippet_offsets_survive_lowercasing_that_adds_characters() {
+        // 'İ' lowercases to two characters, which used to shift the cut by one per 'İ'
+        let text = format!("{}{}needle{}", "İ".repeat(80), "x".repeat(60), "y".repeat(100));
+        let expected = format!("…{}needle{}…", "x".repeat(60), "y".repeat(60));
+        assert_eq!(snippet(&text, "needle").as_deref(), Some(expected.as_str()));
+
+        let text = format!("{}İstanbul{}", "x".repeat(70), "z".repeat(70));
+        let expected = format!("…{}İstanbul{}…", "x".repeat(60), "z".repeat(60));
+        assert_eq!(snippet(&text, "i̇stanbul").as_deref(), Some(expected.as_str()));
+    }
+
+    #[test]
+    fn markdown_export_lists_messages_and_thumbnails() {
+        let mut reply = message("assistant", "Try **git rebase -i**");
+        reply.model = Some("gpt-4o".to_string());
+        let mut question = message("user", "How do I squash?");
+        question.attachments.push(Attachment {
+            kind: "screenshot".to_string(),
+            thumbnail: "shot.jpg".to_string(),
+        });
+        let markdown = to_markdown(&conversation("c1", 0, vec![question, reply]));
+
+        assert!(markdown.starts_with("# Plans & <ideas>\n"));
+        assert!(markdown.contains("## You · "));
+        assert!(markdown.contains("\n\nHow do I squash?\n"));
+        assert!(markdown.contains("\n![screenshot](../thumbnails/shot.jpg)\n"));
+        assert!(markdown.contains("## Assistant (gpt-4o) · "));
+        assert!(markdown.find("How do I squash?") < markdown.find("Try **git rebase -i**"));
+    }
+
+    #[test]
+    fn html_export_escapes_and_embeds_thumbnails() {
+        let dir = temp_dir("conversation-html");
+        fs::write(dir.join("shot.jpg"), [0xff, 0xd8, 0xff]).unwrap();
+        let mut question = message("user", "Is <b> & \"this\" safe?\nSecond line");
+        question.attachments.push(Attachment {
+            kind: "screenshot".to_string(),
+            thumbnail: "shot.jpg".to_string(),
+        });
+        question.attachments.push(Attachment {
+            kind: "screenshot".to_string(),
+            thumbnail: "missing.jpg".to_string(),
+        });
+        let html = to_html(&dir, &conversation("c1", 0, vec![question]));
+
+        assert!(html.contains("<title>Plans &amp; &lt;ideas&gt;</title>"));
+        assert!(html.contains("Is &lt;b&gt; &amp; &quot;this&quot; safe?<br>Second line"));
+        assert!(html.contains("<section class=\"user\">"));
+        assert!(html.contains(&format!("src=\"data:image/jpeg;base64,{}\"", STANDARD.encode([0xff, 0xd8, 0xff]))));
+        assert_eq!(html.matches("<img").count(), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn prune_removes_old_conversations_and_their_thumbnails() {
+        let dir = temp_dir("conversation-prune");
+        let day = 24 * 60 * 60 * 1000;
+        let now = 100 * day;
+        let mut old_message = message("user", "old");
+        old_message.attachments.push(Attachment {
+            kind: "screenshot".to_string(),
+            thumbnail: "old.jpg".to_string(),
+        });
+        fs::create_dir_all(dir.join(THUMBNAILS_DIR)).unwrap();
+        fs::write(dir.join(THUMBNAILS_DIR).join("old.jpg"), "jpg").unwrap();
+        for c in [
+            conversation("old", now - 31 * day, vec![old_message]),
+            conversation("recent", now - 29 * day, vec![message("user", "recent")]),
+        ] {
+            fs::write(conversation_file(&dir, &c.id).unwrap(), serde_json::to_string(&c).unwrap()).unwrap();
+        }
+
+        prune_dir(&dir, 30, now);
+        let left: Vec<String> = read_all(&dir).into_iter().map(|c| c.id).collect();
+        assert_eq!(left, ["recent"]);
+        assert!(!dir.join(THUMBNAILS_DIR).join("old.jpg").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn screenshot_is_kept_on_the_user_message() {
+        let request: CompletionRequest = serde_json::from_value(serde_json::json!({
+            "system": "",
+            "messages": [
+                { "role": "user", "content": "earlier" },
+                { "role": "assistant", "content": "reply" },
+                { "role": "user", "content": "What do you see?" }
+            ],
+            "images": ["aW1hZ2U="],
+            "conversation_id": "c1"
+        }))
+        .unwrap();
+        let response = AiResponse {
+            text: "A terminal".to_string(),
+            profile: "default".to_string(),
+            provider: "openai".to_string(),
+            model: "gpt-4o".to_string(),
+            tool_calls: Vec::new(),
+            usage: TokenUsage::default(),
+            cost_usd: 0.0,
+        };
+
+        let messages = response_messages(&request, &response);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].role, "user");
+        assert_eq!(messages[0].content, "What do you see?");
+        assert_eq!(messages[0].image.as_deref(), Some("aW1hZ2U="));
+        assert_eq!(messages[1].role, "assistant");
+        assert_eq!(messages[1].image, None);
+
+        // Chat replies: the frontend already saved what the user typed
+        let request = CompletionRequest {
+            images: Vec::new(),
+            ..request
+        };
+        let messages = response_messages(&request, &response);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].model.as_deref(), Some("gpt-4o"));
+    }
+}
@@ -0,0 +1,128 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use super::{rpc_result, McpTransport};
+use crate::services::ai;
+
+const SESSION_HEADER: &str = "mcp-session-id";
+
+/// MCP server reached over the streamable HTTP transport
+/// Only loopback addresses are allowed, remote servers are out of scope.
+pub struct HttpTransport {
+    client: reqwest::Client,
+    url: String,
+    session_id: Mutex<Option<String>>,
+    next_id: AtomicU64,
+}
+
+impl HttpTransport {
+    pub fn new(url: &str) -> Result<Self, String> {
+        let parsed = reqwest::Url::parse(url).map_err(|e| format!("Invalid MCP server URL: {}", e))?;
+        let is_local = match parsed.host_str() {
+            Some("localhost") => true,
+            Some(host) => host
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .parse::<IpAddr>()
+                .map(|ip| ip.is_loopback())
+                .unwrap_or(false),
+            None => false,
+        };
+        if !is_local {
+            return Err("MCP HTTP servers must run on localhost".to_string());
+        }
+
+        Ok(Self {
+            client: reqwest::Client::builder()
+                .connect_timeout(Duration::from_secs(5))
+                .build()
+                .unwrap_or_default(),
+            url: url.to_string(),
+            session_id: Mutex::new(None),
+            next_id: AtomicU64::new(1),
+        })
+    }
+
+    async fn post(&self, message: &Value, timeout: Duration) -> Result<reqwest::Response, String> {
+        let mut builder = self
+            .client
+            .post(&self.url)
+            .header("Accept", "application/json, text/event-stream")
+            .timeout(timeout)
+            .json(message);
+        if let Some(session_id) = self.session_id.lock().map_err(|e| e.to_string())?.clone() {
+            builder = builder.header(SESSION_HEADER, session_id);
+        }
+
+        let response = builder
+            .send()
+            .await
+            .map_err(|e| format!("MCP server unreachable: {}", e))?;
+        if !response.status().is_success() {
+            return Err(format!("MCP server returned {}", response.status().as_u16()));
+        }
+
+        // The server assigns a session on initialize; later requests must echo it
+        if let Some(session_id) = response.headers().get(SESSION_HEADER).and_then(|v| v.to_str().ok()) {
+            *self.session_id.lock().map_err(|e| e.to_string())? = Some(session_id.to_string());
+        }
+
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl McpTransport for HttpTransport {
+    async fn request(&self, method: &str, params: Value, timeout: Duration) -> Result<Value, String> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        let response = self.post(&message, timeout).await?;
+
+        let is_stream = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.starts_with("text/event-stream"))
+            .unwrap_or(false);
+
+        if !is_stream {
+            let body: Value = response.json().await.map_err(|e| e.to_string())?;
+            return rpc_result(body);
+        }
+
+        // Streamed replies may carry server notifications before the response itself
+        let mut reply = None;
+        ai::read_sse(response, |data| {
+            if let Ok(parsed) = serde_json::from_str::<Value>(data) {
+                if parsed["id"].as_u64() == Some(id) {
+                    reply = Some(parsed);
+                }
+            }
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+
+        rpc_result(reply.ok_or_else(|| format!("MCP server sent no reply to '{}'", method))?)
+    }
+
+    async fn notify(&self, method: &str, params: Value) -> Result<(), String> {
+        let message = json!({ "jsonrpc": "2.0", "method": method, "params": params });
+        self.post(&message, Duration::from_secs(10)).await.map(|_| ())
+    }
+
+    async fn close(&self) {
+        let session_id = self.session_id.lock().ok().and_then(|s| s.clone());
+        if let Some(session_id) = session_id {
+            let _ = self
+                .client
+                .delete(&self.url)
+                .header(SESSION_HEADER, session_id)
+                .send()
+                .await;
+        }
+    }
+}
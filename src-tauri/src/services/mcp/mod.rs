@@ -0,0 +1,801 @@
+mod http;
+mod stdio;
+
+pub use http::HttpTransport;
+pub use stdio::StdioTransport;
+
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::oneshot;
+
+use super::ai::ToolDefinition;
+use super::capture;
+use super::storage::{self, McpServerConfig};
+
+const PROTOCOL_VERSION: &str = "2025-03-26";
+/// Timeout for handshake and listing requests
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Timeout for tool calls, which may do real work
+const TOOL_CALL_TIMEOUT: Duration = Duration::from_secs(120);
+/// How long to wait for the user to approve a tool call before denying it
+const APPROVAL_TIMEOUT: Duration = Duration::from_secs(120);
+/// Prefix of tool names exposed to the AI for MCP tools
+const TOOL_PREFIX: &str = "mcp__";
+/// Provider limit on function name length
+const MAX_TOOL_NAME_LEN: usize = 64;
+/// Hex digits of the hash that keeps truncated tool names apart
+const NAME_HASH_LEN: usize = 8;
+/// `auto_approve` entry that trusts every tool on a server
+const APPROVE_ALL: &str = "*";
+
+/// JSON-RPC connection to an MCP server
+#[async_trait]
+pub trait McpTransport: Send + Sync {
+    /// Send a request and wait for its result
+    async fn request(&self, method: &str, params: Value, timeout: Duration) -> Result<Value, String>;
+
+    /// Send a notification (no response)
+    async fn notify(&self, method: &str, params: Value) -> Result<(), String>;
+
+    /// Shut the connection down
+    async fn close(&self);
+}
+
+/// Extract the result of a JSON-RPC response, turning an error object into `Err`
+fn rpc_result(response: Value) -> Result<Value, String> {
+    if let Some(error) = response.get("error") {
+        return Err(format!(
+            "MCP error {}: {}",
+            error["code"].as_i64().unwrap_or(0),
+            error["message"].as_str().unwrap_or("unknown error")
+        ));
+    }
+    Ok(response.get("result").cloned().unwrap_or(Value::Null))
+}
+
+/// A tool offered by an MCP server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpTool {
+    pub name: String,
+    pub description: String,
+    pub input_schema: Value,
+    /// Declared read-only by the server (`readOnlyHint`). Only a hint for display: the
+    /// server chooses it, so calls still need approval unless the user allowed the tool
+    pub read_only: bool,
+}
+
+/// A resource offered by an MCP server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpResource {
+    pub server: String,
+    pub uri: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub mime_type: Option<String>,
+}
+
+/// A live connection and what the server offered during the handshake
+pub struct McpConnection {
+    transport: Box<dyn McpTransport>,
+    tools: Vec<McpTool>,
+    has_resources: bool,
+}
+
+struct PendingApproval {
+    server: String,
+    tool: String,
+    sender: oneshot::Sender<bool>,
+}
+
+/// Managed state holding MCP connections and outstanding approval prompts
+#[derive(Default)]
+pub struct McpManager {
+    connections: Mutex<HashMap<String, Arc<McpConnection>>>,
+    errors: Mutex<HashMap<String, String>>,
+    approvals: Mutex<HashMap<String, PendingApproval>>,
+    next_approval: AtomicU64,
+}
+
+impl McpManager {
+    fn connection(&self, name: &str) -> Option<Arc<McpConnection>> {
+        self.connections.lock().ok()?.get(name).cloned()
+    }
+
+    fn all_connections(&self) -> Vec<(String, Arc<McpConnection>)> {
+        self.connections
+            .lock()
+            .map(|connections| connections.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+            .unwrap_or_default()
+    }
+
+    fn set_error(&self, name: &str, error: Option<String>) {
+        if let Ok(mut errors) = self.errors.lock() {
+            match error {
+                Some(error) => errors.insert(name.to_string(), error),
+                None => errors.remove(name),
+            };
+        }
+    }
+}
+
+/// Status of a configured server, for the settings UI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpServerStatus {
+    pub config: McpServerConfig,
+    pub connected: bool,
+    pub error: Option<String>,
+    pub tools: Vec<McpTool>,
+    pub has_resources: bool,
+}
+
+/// Sent to the frontend when a side-effecting tool needs the user's approval
+#[derive(Debug, Clone, Serialize)]
+pub struct McpApprovalRequest {
+    pub id: String,
+    pub server: String,
+    pub tool: String,
+    pub description: String,
+    pub arguments: Value,
+}
+
+fn open_transport(config: &McpServerConfig) -> Result<Box<dyn McpTransport>, String> {
+    match config.transport.as_str() {
+        "stdio" => {
+            let command = config
+                .command
+                .as_deref()
+                .filter(|c| !c.is_empty())
+                .ok_or("A command is required for stdio MCP servers")?;
+            Ok(Box::new(StdioTransport::spawn(command, &config.args, &config.env)?))
+        }
+        "http" => {
+            let url = config
+                .url
+                .as_deref()
+                .filter(|u| !u.is_empty())
+                .ok_or("A URL is required for HTTP MCP servers")?;
+            Ok(Box::new(HttpTransport::new(url)?))
+        }
+        other => Err(format!("Unknown MCP transport: {}", other)),
+    }
+}
+
+/// Fetch every page of a list method
+async fn list_all(transport: &dyn McpTransport, method: &str, key: &str) -> Result<Vec<Value>, String> {
+    let mut items = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let params = match &cursor {
+            Some(cursor) => json!({ "cursor": cursor }),
+            None => json!({}),
+        };
+        let result = transport.request(method, params, REQUEST_TIMEOUT).await?;
+        items.extend(result[key].as_array().cloned().unwrap_or_default());
+
+        match result["nextCursor"].as_str() {
+            Some(next) if !next.is_empty() => cursor = Some(next.to_string()),
+            _ => return Ok(items),
+        }
+    }
+}
+
+/// Spawn or reach a server, run the MCP handshake and list its tools
+async fn open_connection(config: &McpServerConfig) -> Result<McpConnection, String> {
+    let transport = open_transport(config)?;
+
+    let handshake = async {
+        let init = transport
+            .request(
+                "initialize",
+                json!({
+                    "protocolVersion": PROTOCOL_VERSION,
+                    "capabilities": {},
+                    "clientInfo": { "name": "wopr", "version": env!("CARGO_PKG_VERSION") }
+                }),
+                REQUEST_TIMEOUT,
+            )
+            .await?;
+        transport.notify("notifications/initialized", json!({})).await?;
+
+        let capabilities = &init["capabilities"];
+        let tools = if capabilities.get("tools").is_some() {
+            list_all(transport.as_ref(), "tools/list", "tools")
+                .await?
+                .into_iter()
+                .filter_map(|tool| {
+                    Some(McpTool {
+                        name: tool["name"].as_str()?.to_string(),
+                        description: tool["description"].as_str().unwrap_or_default().to_string(),
+                        input_schema: tool
+                            .get("inputSchema")
+                            .cloned()
+                            .unwrap_or_else(|| json!({ "type": "object", "properties": {} })),
+                        read_only: tool["annotations"]["readOnlyHint"].as_bool().unwrap_or(false),
+                    })
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        Ok::<_, String>((tools, capabilities.get("resources").is_some()))
+    };
+
+    match handshake.await {
+        Ok((tools, has_resources)) => Ok(McpConnection {
+            transport,
+            tools,
+            has_resources,
+        }),
+        Err(e) => {
+            transport.close().await;
+            Err(e)
+        }
+    }
+}
+
+/// Close a server's connection, if open
+pub async fn disconnect(app: &AppHandle, name: &str) {
+    let manager = app.state::<McpManager>();
+    let connection = manager
+        .connections
+        .lock()
+        .ok()
+        .and_then(|mut connections| connections.remove(name));
+    manager.set_error(name, None);
+
+    if let Some(connection) = connection {
+        connection.transport.close().await;
+    }
+}
+
+/// (Re)connect a configured server
+pub async fn connect(app: &AppHandle, config: &McpServerConfig) -> Result<(), String> {
+    disconnect(app, &config.name).await;
+
+    let manager = app.state::<McpManager>();
+    match open_connection(config).await {
+        Ok(connection) => {
+            manager
+                .connections
+                .lock()
+                .map_err(|e| e.to_string())?
+                .insert(config.name.clone(), Arc::new(connection));
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("Failed to connect MCP server '{}': {}", config.name, e);
+            manager.set_error(&config.name, Some(e.clone()));
+            Err(e)
+        }
+    }
+}
+
+/// Connect every enabled server (called at startup)
+pub async fn connect_enabled(app: AppHandle) {
+    let config = storage::load_config(app.clone());
+    for server in config.mcp_servers.iter().filter(|s| s.enabled) {
+        let _ = connect(&app, server).await;
+    }
+}
+
+/// Replace characters providers don't accept in function names
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+        .collect()
+}
+
+/// Name under which an MCP tool is offered to the AI. Names over the provider limit are
+/// cut short and end in a hash of the full server and tool names, so they stay distinct.
+fn exposed_name(server: &str, tool: &str) -> String {
+    let mut name = format!("{}{}__{}", TOOL_PREFIX, sanitize(server), sanitize(tool));
+    if name.len() > MAX_TOOL_NAME_LEN {
+        let hash = Sha256::digest(format!("{}\0{}", server, tool).as_bytes());
+        let suffix: String = hash.iter().map(|b| format!("{:02x}", b)).collect();
+        name.truncate(MAX_TOOL_NAME_LEN - NAME_HASH_LEN - 1);
+        name.push('_');
+        name.push_str(&suffix[..NAME_HASH_LEN]);
+    }
+    name
+}
+
+/// Exposed names shared by more than one tool, e.g. "my.server" and "my_server" both
+/// offering `search`. They are left out rather than guessing which one the AI meant.
+fn ambiguous_names<'a>(tools: impl Iterator<Item = (&'a str, &'a str)>) -> HashSet<String> {
+    let mut seen = HashSet::new();
+    let mut ambiguous = HashSet::new();
+    for (server, tool) in tools {
+        let name = exposed_name(server, tool);
+        if !seen.insert(name.clone()) {
+            ambiguous.insert(name);
+        }
+    }
+    ambiguous
+}
+
+fn connection_tools(connections: &[(String, Arc<McpConnection>)]) -> impl Iterator<Item = (&str, &str)> {
+    connections.iter().flat_map(|(server, connection)| {
+        connection.tools.iter().map(move |tool| (server.as_str(), tool.name.as_str()))
+    })
+}
+
+/// Find the server and tool behind an exposed tool name
+fn resolve_tool(app: &AppHandle, exposed: &str) -> Result<(String, Arc<McpConnection>, McpTool), String> {
+    let connections = app.state::<McpManager>().all_connections();
+    if ambiguous_names(connection_tools(&connections)).contains(exposed) {
+        return Err(format!(
+            "{} matches tools on more than one MCP server; rename one of the servers",
+            exposed
+        ));
+    }
+
+    connections
+        .into_iter()
+        .find_map(|(server, connection)| {
+            let tool = connection
+                .tools
+                .iter()
+                .find(|tool| exposed_name(&server, &tool.name) == exposed)?
+                .clone();
+            Some((server, connection, tool))
+        })
+        .ok_or_else(|| format!("Unknown MCP tool: {}", exposed))
+}
+
+/// Whether the user allowed a tool (or its whole server) to run without asking
+fn is_auto_approved(server: &McpServerConfig, tool: &str) -> bool {
+    server.auto_approve.iter().any(|allowed| allowed == tool || allowed == APPROVE_ALL)
+}
+
+/// Tool definitions for every connected server, plus resource access when any server has resources
+pub fn tool_definitions(app: &AppHandle) -> Vec<ToolDefinition> {
+    let Some(manager) = app.try_state::<McpManager>() else {
+        return Vec::new();
+    };
+
+    let connections = manager.all_connections();
+    let ambiguous = ambiguous_names(connection_tools(&connections));
+    let mut definitions = Vec::new();
+    let mut any_resources = false;
+    for (server, connection) in &connections {
+        any_resources |= connection.has_resources;
+        for tool in &connection.tools {
+            let name = exposed_name(server, &tool.name);
+            if ambiguous.contains(&name) {
+                eprintln!(
+                    "Not offering MCP tool '{}' from '{}': its name clashes with another server's",
+                    tool.name, server
+                );
+                continue;
+            }
+            let description = if tool.description.is_empty() {
+                format!("{} tool from the {} MCP server.", tool.name, server)
+            } else {
+                format!("[{}] {}", server, tool.description)
+            };
+            definitions.push(ToolDefinition {
+                name,
+                description,
+                parameters: tool.input_schema.clone(),
+            });
+        }
+    }
+
+    if any_resources {
+        definitions.push(ToolDefinition {
+            name: "mcp_list_resources".to_string(),
+            description: "List documents and other resources offered by connected MCP servers.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "server": { "type": "string", "description": "Only list this server's resources" }
+                }
+            }),
+        });
+        definitions.push(ToolDefinition {
+            name: "mcp_read_resource".to_string(),
+            description: "Read a resource listed by mcp_list_resources.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "server": { "type": "string" },
+                    "uri": { "type": "string" }
+                },
+                "required": ["server", "uri"]
+            }),
+        });
+    }
+
+    definitions
+}
+
+/// Whether a tool name belongs to the MCP bridge
+pub fn is_mcp_tool(name: &str) -> bool {
+    name.starts_with(TOOL_PREFIX) || name == "mcp_list_resources" || name == "mcp_read_resource"
+}
+
+/// Ask the user to approve a tool call; resolves to false on denial or timeout
+async fn request_approval(app: &AppHandle, server: &str, tool: &McpTool, arguments: &Value) -> bool {
+    let manager = app.state::<McpManager>();
+    let id = manager.next_approval.fetch_add(1, Ordering::SeqCst).to_string();
+    let (sender, receiver) = oneshot::channel();
+
+    match manager.approvals.lock() {
+        Ok(mut approvals) => approvals.insert(
+            id.clone(),
+            PendingApproval {
+                server: server.to_string(),
+                tool: tool.name.clone(),
+                sender,
+            },
+        ),
+        Err(_) => return false,
+    };
+
+    let request = McpApprovalRequest {
+        id: id.clone(),
+        server: server.to_string(),
+        tool: tool.name.clone(),
+        description: tool.description.clone(),
+        arguments: arguments.clone(),
+    };
+    if app.emit("mcp://approval-request", request).is_err() {
+        return false;
+    }
+
+    match tokio::time::timeout(APPROVAL_TIMEOUT, receiver).await {
+        Ok(Ok(approved)) => approved,
+        _ => {
+            if let Ok(mut approvals) = manager.approvals.lock() {
+                approvals.remove(&id);
+            }
+            false
+        }
+    }
+}
+
+/// Convert an image content block to base64 JPEG
+fn image_to_jpeg(data: &str) -> Option<String> {
+    let bytes = STANDARD.decode(data).ok()?;
+    let image = image::load_from_memory(&bytes).ok()?.to_rgba8();
+    let jpeg = capture::encode_jpeg(capture::resize_to_width(image, 1280), 70).ok()?;
+    Some(STANDARD.encode(jpeg))
+}
+
+/// Flatten a `tools/call` result into text and an optional image
+fn call_result(result: &Value) -> (String, Option<String>) {
+    let mut texts = Vec::new();
+    let mut image = None;
+
+    for block in result["content"].as_array().into_iter().flatten() {
+        match block["type"].as_str() {
+            Some("text") => texts.push(block["text"].as_str().unwrap_or_default().to_string()),
+            Some("image") if image.is_none() => {
+                image = block["data"].as_str().and_then(image_to_jpeg);
+            }
+            Some("resource") => {
+                if let Some(text) = block["resource"]["text"].as_str() {
+                    texts.push(text.to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+    if texts.is_empty() {
+        if let Some(structured) = result.get("structuredContent") {
+            texts.push(structured.to_string());
+        }
+    }
+
+    let mut text = texts.join("\n");
+    if result["isError"].as_bool().unwrap_or(false) {
+        text = format!("Error: {}", text);
+    }
+    (text, image)
+}
+
+async fn list_resources(app: &AppHandle, server: Option<&str>) -> Result<Vec<McpResource>, String> {
+    let connections = app.state::<McpManager>().all_connections();
+
+    let mut resources = Vec::new();
+    for (name, connection) in connections {
+        if !connection.has_resources || server.map(|s| s != name).unwrap_or(false) {
+            continue;
+        }
+        for resource in list_all(connection.transport.as_ref(), "resources/list", "resources").await? {
+            let Some(uri) = resource["uri"].as_str() else { continue };
+            resources.push(McpResource {
+                server: name.clone(),
+                uri: uri.to_string(),
+                name: resource["name"].as_str().unwrap_or(uri).to_string(),
+                description: resource["description"].as_str().map(|s| s.to_string()),
+                mime_type: resource["mimeType"].as_str().map(|s| s.to_string()),
+            });
+        }
+    }
+    Ok(resources)
+}
+
+async fn read_resource(app: &AppHandle, server: &str, uri: &str) -> Result<String, String> {
+    let connection = app
+        .state::<McpManager>()
+        .connection(server)
+        .ok_or_else(|| format!("MCP server '{}' is not connected", server))?;
+    let result = connection
+        .transport
+        .request("resources/read", json!({ "uri": uri }), REQUEST_TIMEOUT)
+        .await?;
+
+    Ok(result["contents"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|content| match content["text"].as_str() {
+            Some(text) => text.to_string(),
+            None => format!(
+                "[binary content, {}]",
+                content["mimeType"].as_str().unwrap_or("unknown type")
+            ),
+        })
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+/// Run an MCP tool call from the AI, asking for approval first when it may have side effects
+pub async fn call_tool(app: &AppHandle, name: &str, arguments: &Value) -> Result<(String, Option<String>), String> {
+    match name {
+        "mcp_list_resources" => {
+            let resources = list_resources(app, arguments["server"].as_str()).await?;
+            return Ok((serde_json::to_string(&resources).map_err(|e| e.to_string())?, None));
+        }
+        "mcp_read_resource" => {
+            let server = arguments["server"].as_str().ok_or("server is required")?;
+            let uri = arguments["uri"].as_str().ok_or("uri is required")?;
+            return Ok((read_resource(app, server, uri).await?, None));
+        }
+        _ => {}
+    }
+
+    let (server, connection, tool) = resolve_tool(app, name)?;
+
+    let config = storage::load_config(app.clone());
+    let auto_approved = config
+        .mcp_servers
+        .iter()
+        .find(|s| s.name == server)
+        .map(|s| is_auto_approved(s, &tool.name))
+        .unwrap_or(false);
+    if !auto_approved && !request_approval(app, &server, &tool, arguments).await {
+        return Err(format!("The user declined to run {} on {}", tool.name, server));
+    }
+
+    let arguments = if arguments.is_object() { arguments.clone() } else { json!({}) };
+    let result = connection
+        .transport
+        .request(
+            "tools/call",
+            json!({ "name": tool.name, "arguments": arguments }),
+            TOOL_CALL_TIMEOUT,
+        )
+        .await?;
+
+    Ok(call_result(&result))
+}
+
+/// List configured MCP servers with their connection state and tools
+#[tauri::command]
+pub fn list_mcp_servers(app: AppHandle) -> Vec<McpServerStatus> {
+    let config = storage::load_config(app.clone());
+    let manager = app.state::<McpManager>();
+    let errors = manager.errors.lock().map(|e| e.clone()).unwrap_or_default();
+
+    config
+        .mcp_servers
+        .into_iter()
+        .map(|server| {
+            let connection = manager.connection(&server.name);
+            McpServerStatus {
+                connected: connection.is_some(),
+                error: errors.get(&server.name).cloned(),
+                tools: connection.as_ref().map(|c| c.tools.clone()).unwrap_or_default(),
+                has_resources: connection.map(|c| c.has_resources).unwrap_or(false),
+                config: server,
+            }
+        })
+        .collect()
+}
+
+/// Add or replace an MCP server and connect it if enabled
+#[tauri::command]
+pub async fn save_mcp_server(app: AppHandle, server: McpServerConfig) -> Result<(), String> {
+    if server.name.trim().is_empty() {
+        return Err("Server name is required".to_string());
+    }
+    if !matches!(server.transport.as_str(), "stdio" | "http") {
+        return Err(format!("Unknown MCP transport: {}", server.transport));
+    }
+
+    let mut config = storage::load_config(app.clone());
+    match config.mcp_servers.iter_mut().find(|s| s.name == server.name) {
+        Some(existing) => *existing = server.clone(),
+        None => config.mcp_servers.push(server.clone()),
+    }
+    storage::save_config(app.clone(), config)?;
+
+    if server.enabled {
+        connect(&app, &server).await
+    } else {
+        disconnect(&app, &server.name).await;
+        Ok(())
+    }
+}
+
+/// Remove an MCP server
+#[tauri::command]
+pub async fn delete_mcp_server(app: AppHandle, name: String) -> Result<(), String> {
+    disconnect(&app, &name).await;
+
+    let mut config = storage::load_config(app.clone());
+    config.mcp_servers.retain(|s| s.name != name);
+    storage::save_config(app, config)
+}
+
+/// Enable (and connect) or disable (and disconnect) an MCP server
+#[tauri::command]
+pub async fn set_mcp_server_enabled(app: AppHandle, name: String, enabled: bool) -> Result<(), String> {
+    let mut config = storage::load_config(app.clone());
+    let server = config
+        .mcp_servers
+        .iter_mut()
+        .find(|s| s.name == name)
+        .ok_or_else(|| format!("Unknown MCP server: {}", name))?;
+    server.enabled = enabled;
+    let server = server.clone();
+    storage::save_config(app.clone(), config)?;
+
+    if enabled {
+        connect(&app, &server).await
+    } else {
+        disconnect(&app, &name).await;
+        Ok(())
+    }
+}
+
+/// Reconnect an MCP server, refreshing its tool list
+#[tauri::command]
+pub async fn reconnect_mcp_server(app: AppHandle, name: String) -> Result<(), String> {
+    let config = storage::load_config(app.clone());
+    let server = config
+        .mcp_servers
+        .iter()
+        .find(|s| s.name == name)
+        .ok_or_else(|| format!("Unknown MCP server: {}", name))?;
+    connect(&app, server).await
+}
+
+/// List resources offered by connected MCP servers
+#[tauri::command]
+pub async fn list_mcp_resources(app: AppHandle, server: Option<String>) -> Result<Vec<McpResource>, String> {
+    list_resources(&app, server.as_deref()).await
+}
+
+/// Answer an approval prompt; `always` stops asking for this tool on this server
+#[tauri::command]
+pub fn respond_mcp_approval(app: AppHandle, id: String, approved: bool, always: bool) -> Result<(), String> {
+    let pending = app
+        .state::<McpManager>()
+        .approvals
+        .lock()
+        .map_err(|e| e.to_string())?
+        .remove(&id)
+        .ok_or("This approval request has expired")?;
+
+    if approved && always {
+        let mut config = storage::load_config(app.clone());
+        if let Some(server) = config.mcp_servers.iter_mut().find(|s| s.name == pending.server) {
+            if !server.auto_approve.contains(&pending.tool) {
+                server.auto_approve.push(pending.tool.clone());
+            }
+        }
+        storage::save_config(app, config)?;
+    }
+
+    let _ = pending.sender.send(approved);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_http::block_on;
+
+    /// Minimal stdio MCP server: one read-only tool, one that isn't
+    #[cfg(unix)]
+    const FIXTURE_SERVER: &str = r#"
+while IFS= read -r line; do
+  id=$(printf '%s' "$line" | sed -n 's/.*"id":\([0-9]*\).*/\1/p')
+  [ -z "$id" ] && continue
+  case "$line" in
+    *'"method":"initialize"'*) result='{"protocolVersion":"2025-03-26","capabilities":{"tools":{}}}' ;;
+    *'"method":"tools/list"'*) result='{"tools":[{"name":"read_file","annotations":{"readOnlyHint":true}},{"name":"delete_file","description":"Delete a file"}]}' ;;
+    *'"method":"tools/call"'*) result='{"content":[{"type":"text","text":"done"}]}' ;;
+    *) result='{}' ;;
+  esac
+  printf '{"jsonrpc":"2.0","id":%s,"result":%s}\n' "$id" "$result"
+done
+"#;
+
+    fn server(auto_approve: &[&str]) -> McpServerConfig {
+        serde_json::from_value(json!({ "name": "files", "auto_approve": auto_approve })).unwrap()
+    }
+
+    #[test]
+    fn short_names_are_kept_whole() {
+        assert_eq!(exposed_name("my files", "read.file"), "mcp__my_files__read_file");
+    }
+
+    #[test]
+    fn long_names_fit_the_limit_and_stay_distinct() {
+        let server = "a-server-with-a-rather-long-descriptive-name";
+        let first = exposed_name(server, "list_everything_in_the_first_directory");
+        let second = exposed_name(server, "list_everything_in_the_second_directory");
+
+        assert_eq!(first.len(), MAX_TOOL_NAME_LEN);
+        assert_eq!(second.len(), MAX_TOOL_NAME_LEN);
+        assert_ne!(first, second);
+        assert_eq!(first, exposed_name(server, "list_everything_in_the_first_directory"));
+    }
+
+    #[test]
+    fn clashing_names_are_ambiguous() {
+        let tools = [("my.files", "read"), ("my_files", "read"), ("my_files", "write")];
+        let ambiguous = ambiguous_names(tools.into_iter());
+
+        assert_eq!(ambiguous, HashSet::from(["mcp__my_files__read".to_string()]));
+    }
+
+    #[test]
+    fn approval_needs_the_tool_or_the_whole_server_allowed() {
+        assert!(!is_auto_approved(&server(&[]), "read_file"));
+        assert!(!is_auto_approved(&server(&["delete_file"]), "read_file"));
+        assert!(is_auto_approved(&server(&["read_file"]), "read_file"));
+        assert!(is_auto_approved(&server(&[APPROVE_ALL]), "delete_file"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn stdio_server_handshake_lists_and_calls_tools() {
+        let config: McpServerConfig = serde_json::from_value(json!({
+            "name": "files",
+            "command": "sh",
+            "args": ["-c", FIXTURE_SERVER],
+        }))
+        .unwrap();
+
+        block_on(async {
+            let connection = open_connection(&config).await.unwrap();
+            let tools: Vec<(&str, bool)> =
+                connection.tools.iter().map(|tool| (tool.name.as_str(), tool.read_only)).collect();
+            assert_eq!(tools, [("read_file", true), ("delete_file", false)]);
+            assert!(!connection.has_resources);
+
+            let result = connection
+                .transport
+                .request("tools/call", json!({ "name": "read_file", "arguments": {} }), REQUEST_TIMEOUT)
+                .await
+                .unwrap();
+            assert_eq!(call_result(&result), ("done".to_string(), None));
+
+            connection.transport.close().await;
+        });
+    }
+}
@@ -0,0 +1,146 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::oneshot;
+
+use super::{rpc_result, McpTransport};
+
+type Pending = Arc<Mutex<HashMap<u64, oneshot::Sender<Value>>>>;
+
+/// MCP server spawned as a child process, speaking newline-delimited JSON-RPC on stdin/stdout
+pub struct StdioTransport {
+    stdin: Arc<tokio::sync::Mutex<ChildStdin>>,
+    child: tokio::sync::Mutex<Child>,
+    pending: Pending,
+    next_id: AtomicU64,
+}
+
+impl StdioTransport {
+    pub fn spawn(command: &str, args: &[String], env: &HashMap<String, String>) -> Result<Self, String> {
+        let mut cmd = Command::new(command);
+        cmd.args(args)
+            .envs(env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true);
+
+        // Don't flash a console window for every server on Windows
+        #[cfg(target_os = "windows")]
+        cmd.creation_flags(0x0800_0000);
+
+        let mut child = cmd
+            .spawn()
+            .map_err(|e| format!("Failed to start MCP server '{}': {}", command, e))?;
+        let stdin = child.stdin.take().ok_or("MCP server has no stdin")?;
+        let stdout = child.stdout.take().ok_or("MCP server has no stdout")?;
+
+        let stdin = Arc::new(tokio::sync::Mutex::new(stdin));
+        let pending: Pending = Arc::new(Mutex::new(HashMap::new()));
+
+        let reader_stdin = stdin.clone();
+        let reader_pending = pending.clone();
+        tauri::async_runtime::spawn(async move {
+            let mut lines = BufReader::new(stdout).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let Ok(message) = serde_json::from_str::<Value>(&line) else {
+                    continue;
+                };
+                handle_incoming(message, &reader_pending, &reader_stdin).await;
+            }
+            // The server exited: dropping the senders fails every waiting request
+            if let Ok(mut pending) = reader_pending.lock() {
+                pending.clear();
+            }
+        });
+
+        Ok(Self {
+            stdin,
+            child: tokio::sync::Mutex::new(child),
+            pending,
+            next_id: AtomicU64::new(1),
+        })
+    }
+}
+
+async fn write_message(stdin: &tokio::sync::Mutex<ChildStdin>, message: &Value) -> Result<(), String> {
+    let mut line = message.to_string();
+    line.push('\n');
+
+    let mut stdin = stdin.lock().await;
+    stdin
+        .write_all(line.as_bytes())
+        .await
+        .map_err(|e| format!("Failed to write to MCP server: {}", e))?;
+    stdin.flush().await.map_err(|e| e.to_string())
+}
+
+/// Route a message from the server: responses go to their waiting request,
+/// server requests get an answer (only `ping` is supported)
+async fn handle_incoming(message: Value, pending: &Pending, stdin: &tokio::sync::Mutex<ChildStdin>) {
+    let is_response = message.get("result").is_some() || message.get("error").is_some();
+
+    if is_response {
+        let Some(id) = message["id"].as_u64() else {
+            return;
+        };
+        let sender = pending.lock().ok().and_then(|mut pending| pending.remove(&id));
+        if let Some(sender) = sender {
+            let _ = sender.send(message);
+        }
+    } else if let (Some(id), Some(method)) = (message.get("id"), message["method"].as_str()) {
+        let reply = if method == "ping" {
+            json!({ "jsonrpc": "2.0", "id": id, "result": {} })
+        } else {
+            json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": -32601, "message": format!("Method not supported: {}", method) }
+            })
+        };
+        let _ = write_message(stdin, &reply).await;
+    }
+}
+
+#[async_trait]
+impl McpTransport for StdioTransport {
+    async fn request(&self, method: &str, params: Value, timeout: Duration) -> Result<Value, String> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let (sender, receiver) = oneshot::channel();
+        self.pending.lock().map_err(|e| e.to_string())?.insert(id, sender);
+
+        let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        if let Err(e) = write_message(&self.stdin, &message).await {
+            if let Ok(mut pending) = self.pending.lock() {
+                pending.remove(&id);
+            }
+            return Err(e);
+        }
+
+        match tokio::time::timeout(timeout, receiver).await {
+            Ok(Ok(response)) => rpc_result(response),
+            Ok(Err(_)) => Err("MCP server exited".to_string()),
+            Err(_) => {
+                if let Ok(mut pending) = self.pending.lock() {
+                    pending.remove(&id);
+                }
+                Err(format!("MCP request '{}' timed out", method))
+            }
+        }
+    }
+
+    async fn notify(&self, method: &str, params: Value) -> Result<(), String> {
+        let message = json!({ "jsonrpc": "2.0", "method": method, "params": params });
+        write_message(&self.stdin, &message).await
+    }
+
+    async fn close(&self) {
+        let _ = self.child.lock().await.kill().await;
+    }
+}
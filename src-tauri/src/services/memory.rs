@@ -0,0 +1,485 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::AppHandle;
+
+use super::storage;
+
+const MEMORY_FILE: &str = "memory.json";
+/// Markdown file used before typed memories existed
+const LEGACY_KNOWLEDGE_FILE: &str = "knowledge.md";
+/// Default size of the memory section in the system prompt
+const DEFAULT_PROMPT_CHARS: usize = 2000;
+/// Word overlap above which two longer entries count as the same memory
+const DUPLICATE_SIMILARITY: f32 = 0.8;
+
+/// Held across every load-change-save of the memory file, so concurrent edits aren't lost
+static MEMORY_FILE_LOCK: Mutex<()> = Mutex::new(());
+
+/// A single remembered fact
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MemoryEntry {
+    pub id: String,
+    pub text: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Where the memory came from: "user", "assistant", "screen" or "import"
+    #[serde(default = "default_source")]
+    pub source: String,
+    /// Unix milliseconds
+    pub created_at: u64,
+    /// Unix milliseconds
+    pub updated_at: u64,
+    /// Pinned memories get the prompt budget before the others
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+fn default_source() -> String {
+    "user".to_string()
+}
+
+/// Result of adding a memory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddMemoryResult {
+    pub entry: MemoryEntry,
+    /// True when an existing entry matched and nothing new was stored
+    pub duplicate: bool,
+}
+
+/// Fields to change on an entry (None = keep)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MemoryUpdate {
+    #[serde(default)]
+    pub text: Option<String>,
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
+    #[serde(default)]
+    pub pinned: Option<bool>,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+fn memory_path(app: &AppHandle) -> PathBuf {
+    storage::get_brain_dir(&storage::load_config(app.clone())).join(MEMORY_FILE)
+}
+
+/// Load all memories, importing the legacy knowledge.md the first time
+pub fn load_memories(app: &AppHandle) -> Result<Vec<MemoryEntry>, String> {
+    let _guard = MEMORY_FILE_LOCK.lock().map_err(|e| e.to_string())?;
+    read_memories(app)
+}
+
+/// Load, change and save the memories in one step. Nothing is written when the
+/// file can't be read or `change` fails or leaves the entries as they were.
+fn modify_memories<T>(
+    app: &AppHandle,
+    change: impl FnOnce(&mut Vec<MemoryEntry>) -> Result<T, String>,
+) -> Result<T, String> {
+    let _guard = MEMORY_FILE_LOCK.lock().map_err(|e| e.to_string())?;
+    let path = memory_path(app);
+    if !path.exists() {
+        // Imports knowledge.md the first time
+        read_memories(app)?;
+    }
+    modify_memory_file(&path, change)
+}
+
+fn modify_memory_file<T>(
+    path: &Path,
+    change: impl FnOnce(&mut Vec<MemoryEntry>) -> Result<T, String>,
+) -> Result<T, String> {
+    let mut entries = if path.exists() { read_memory_file(path)? } else { Vec::new() };
+    let before = entries.clone();
+    let result = change(&mut entries)?;
+    if entries != before {
+        save_memory_file(path, &entries)?;
+    }
+    Ok(result)
+}
+
+/// A file that exists but doesn't parse is an error rather than an empty list: the next
+/// save would otherwise replace it and lose every memory in it.
+fn read_memories(app: &AppHandle) -> Result<Vec<MemoryEntry>, String> {
+    let path = memory_path(app);
+    if !path.exists() {
+        let imported = import_markdown(app, Vec::new());
+        if !imported.is_empty() {
+            save_memory_file(&path, &imported)?;
+        }
+        return Ok(imported);
+    }
+
+    read_memory_file(&path)
+}
+
+fn read_memory_file(path: &Path) -> Result<Vec<MemoryEntry>, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_str(&content).map_err(|e| {
+        format!(
+            "{} is damaged ({}); memories are read-only until it is fixed or removed",
+            path.display(),
+            e
+        )
+    })
+}
+
+fn save_memory_file(path: &Path, entries: &[MemoryEntry]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_string_pretty(entries).map_err(|e| e.to_string())?;
+    fs::write(path, json).map_err(|e| e.to_string())
+}
+
+/// Lowercase words with punctuation stripped
+fn words(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_string())
+        .collect()
+}
+
+/// Whether two memory texts say the same thing
+/// Short texts must match word for word; longer ones may differ slightly.
+fn is_duplicate(a: &str, b: &str) -> bool {
+    let (a, b) = (words(a), words(b));
+    if a == b {
+        return true;
+    }
+    if a.len() < 4 || b.len() < 4 {
+        return false;
+    }
+
+    let a: HashSet<&String> = a.iter().collect();
+    let b: HashSet<&String> = b.iter().collect();
+    let shared = a.intersection(&b).count() as f32;
+    let total = a.union(&b).count() as f32;
+    total > 0.0 && shared / total >= DUPLICATE_SIMILARITY
+}
+
+fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim().to_lowercase();
+        if !tag.is_empty() && !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    normalized
+}
+
+/// Add entries for knowledge.md lines that aren't already remembered
+fn import_markdown(app: &AppHandle, mut entries: Vec<MemoryEntry>) -> Vec<MemoryEntry> {
+    let config = storage::load_config(app.clone());
+    let Ok(content) = fs::read_to_string(storage::get_brain_dir(&config).join(LEGACY_KNOWLEDGE_FILE)) else {
+        return entries;
+    };
+
+    let now = now_ms();
+    for line in content.lines() {
+        let text = line.trim().trim_start_matches(['-', '*']).trim();
+        if text.is_empty() || text.starts_with('#') || entries.iter().any(|e| is_duplicate(&e.text, text)) {
+            continue;
+        }
+        entries.push(MemoryEntry {
+            id: uuid::Uuid::new_v4().to_string(),
+            text: text.to_string(),
+            tags: Vec::new(),
+            source: "import".to_string(),
+            created_at: now,
+            updated_at: now,
+            pinned: false,
+        });
+    }
+    entries
+}
+
+/// Render memories for the system prompt within `max_chars`
+/// Pinned entries get the budget first, then the most recently updated ones.
+pub fn render_prompt(entries: &[MemoryEntry], max_chars: usize) -> String {
+    let mut ordered: Vec<&MemoryEntry> = entries.iter().collect();
+    ordered.sort_by(|a, b| b.pinned.cmp(&a.pinned).then(b.updated_at.cmp(&a.updated_at)));
+
+    let mut rendered = String::new();
+    for entry in ordered {
+        let line = format!("- {}\n", entry.text);
+        // Skip rather than stop, so a shorter entry further down can still fit
+        if rendered.len() + line.len() <= max_chars {
+            rendered.push_str(&line);
+        }
+    }
+    rendered.trim_end().to_string()
+}
+
+/// List all memories, newest first
+#[tauri::command]
+pub fn list_memories(app: AppHandle) -> Result<Vec<MemoryEntry>, String> {
+    let mut entries = load_memories(&app)?;
+    entries.sort_by_key(|e| std::cmp::Reverse(e.updated_at));
+    Ok(entries)
+}
+
+/// Remember something, unless an equivalent memory already exists
+#[tauri::command]
+pub fn add_memory(
+    app: AppHandle,
+    text: String,
+    tags: Option<Vec<String>>,
+    source: Option<String>,
+    pinned: Option<bool>,
+) -> Result<AddMemoryResult, String> {
+    let text = text.trim().to_string();
+    if text.is_empty() {
+        return Err("Memory text is empty".to_string());
+    }
+
+    modify_memories(&app, |entries| {
+        let now = now_ms();
+
+        if let Some(existing) = entries.iter_mut().find(|e| is_duplicate(&e.text, &text)) {
+            // Refresh the match so it stays near the top of the prompt
+            existing.updated_at = now;
+            if pinned == Some(true) {
+                existing.pinned = true;
+            }
+            return Ok(AddMemoryResult {
+                entry: existing.clone(),
+                duplicate: true,
+            });
+        }
+
+        let entry = MemoryEntry {
+            id: uuid::Uuid::new_v4().to_string(),
+            text,
+            tags: normalize_tags(tags.unwrap_or_default()),
+            source: source.unwrap_or_else(default_source),
+            created_at: now,
+            updated_at: now,
+            pinned: pinned.unwrap_or(false),
+        };
+        entries.push(entry.clone());
+
+        Ok(AddMemoryResult {
+            entry,
+            duplicate: false,
+        })
+    })
+}
+
+/// Edit a memory
+#[tauri::command]
+pub fn update_memory(app: AppHandle, id: String, update: MemoryUpdate) -> Result<MemoryEntry, String> {
+    modify_memories(&app, |entries| {
+        let entry = entries
+            .iter_mut()
+            .find(|e| e.id == id)
+            .ok_or_else(|| format!("Memory not found: {}", id))?;
+
+        if let Some(text) = update.text {
+            let text = text.trim().to_string();
+            if text.is_empty() {
+                return Err("Memory text is empty".to_string());
+            }
+            entry.text = text;
+        }
+        if let Some(tags) = update.tags {
+            entry.tags = normalize_tags(tags);
+        }
+        if let Some(pinned) = update.pinned {
+            entry.pinned = pinned;
+        }
+        entry.updated_at = now_ms();
+
+        Ok(entry.clone())
+    })
+}
+
+/// Delete a memory by id
+#[tauri::command]
+pub fn delete_memory(app: AppHandle, id: String) -> Result<(), String> {
+    modify_memories(&app, |entries| {
+        let before = entries.len();
+        entries.retain(|e| e.id != id);
+        if entries.len() == before {
+            return Err(format!("Memory not found: {}", id));
+        }
+        Ok(())
+    })
+}
+
+/// Delete every memory whose text contains `query` (case-insensitive)
+/// Returns the number of memories removed
+#[tauri::command]
+pub fn forget_memories(app: AppHandle, query: String) -> Result<usize, String> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Ok(0);
+    }
+
+    modify_memories(&app, |entries| {
+        let before = entries.len();
+        entries.retain(|e| !e.text.to_lowercase().contains(&query));
+        Ok(before - entries.len())
+    })
+}
+
+/// Groups of memories that look like duplicates of each other
+#[tauri::command]
+pub fn find_duplicate_memories(app: AppHandle) -> Result<Vec<Vec<MemoryEntry>>, String> {
+    let entries = load_memories(&app)?;
+    let mut grouped = vec![false; entries.len()];
+    let mut groups = Vec::new();
+
+    for i in 0..entries.len() {
+        if grouped[i] {
+            continue;
+        }
+        let mut group = vec![entries[i].clone()];
+        for j in (i + 1)..entries.len() {
+            if !grouped[j] && is_duplicate(&entries[i].text, &entries[j].text) {
+                grouped[j] = true;
+                group.push(entries[j].clone());
+            }
+        }
+        if group.len() > 1 {
+            groups.push(group);
+        }
+    }
+    Ok(groups)
+}
+
+/// Import knowledge.md into the memory store, skipping lines already remembered
+/// Returns the number of memories added
+#[tauri::command]
+pub fn import_knowledge_markdown(app: AppHandle) -> Result<usize, String> {
+    modify_memories(&app, |entries| {
+        let before = entries.len();
+        *entries = import_markdown(&app, std::mem::take(entries));
+        Ok(entries.len() - before)
+    })
+}
+
+/// Render memories for the system prompt, bounded to `max_chars`
+#[tauri::command]
+pub fn get_memory_prompt(app: AppHandle, max_chars: Option<usize>) -> Result<String, String> {
+    Ok(render_prompt(&load_memories(&app)?, max_chars.unwrap_or(DEFAULT_PROMPT_CHARS)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_files::{temp_file, temp_path};
+
+    fn entry(text: &str, updated_at: u64, pinned: bool) -> MemoryEntry {
+        MemoryEntry {
+            id: text.to_string(),
+            text: text.to_string(),
+            tags: Vec::new(),
+            source: default_source(),
+            created_at: updated_at,
+            updated_at,
+            pinned,
+        }
+    }
+
+    #[test]
+    fn damaged_file_is_an_error_and_left_alone() {
+        let path = temp_file("damaged-memory.json", "[{\"id\": \"1\", \"text\": ");
+
+        let error = read_memory_file(&path).unwrap_err();
+        assert!(error.contains("damaged"), "{}", error);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[{\"id\": \"1\", \"text\": ");
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn damaged_file_is_never_overwritten() {
+        let damaged = "[{\"id\": \"1\", \"text\": ";
+        let path = temp_file("damaged-memory-write.json", damaged);
+
+        let result = modify_memory_file(&path, |entries| {
+            entries.push(entry("Likes tea", 1, false));
+            Ok(())
+        });
+        assert!(result.unwrap_err().contains("damaged"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), damaged);
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn changes_are_saved_and_no_ops_are_not() {
+        let path = temp_path("memory-write.json");
+
+        modify_memory_file(&path, |entries| {
+            entries.push(entry("Likes tea", 1, false));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(read_memory_file(&path).unwrap(), vec![entry("Likes tea", 1, false)]);
+
+        // Nothing changed, so the file isn't touched (a failed change doesn't write either)
+        fs::write(&path, "[]").unwrap();
+        modify_memory_file(&path, |_| Ok(())).unwrap();
+        let failed: Result<(), String> = modify_memory_file(&path, |entries| {
+            entries.clear();
+            Err("no".to_string())
+        });
+        assert!(failed.is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[]");
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn prompt_stays_within_its_size_with_pinned_first() {
+        let entries = vec![
+            entry("Old fact", 1, false),
+            entry("Newest fact", 3, false),
+            entry("Pinned fact", 0, true),
+            entry(&"x".repeat(50), 2, false),
+        ];
+
+        let rendered = render_prompt(&entries, 40);
+        assert!(rendered.len() <= 40);
+        // The long entry is skipped and shorter, older ones still fit
+        assert_eq!(rendered, "- Pinned fact\n- Newest fact\n- Old fact");
+        assert_eq!(render_prompt(&entries, 5), "");
+    }
+
+    #[test]
+    fn reads_entries_with_defaults() {
+        let path = temp_file(
+            "memory.json",
+            r#"[{"id": "1", "text": "Likes tea", "created_at": 1, "updated_at": 2}]"#,
+        );
+
+        let entries = read_memory_file(&path).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].source, "user");
+        assert!(!entries[0].pinned);
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn near_duplicates_need_enough_words() {
+        assert!(is_duplicate("Likes tea.", "likes TEA"));
+        assert!(!is_duplicate("Likes tea", "Likes green tea"));
+        assert!(is_duplicate(
+            "The user works at a small bakery in Lisbon on weekdays",
+            "The user works at a small bakery in Lisbon on weekdays now"
+        ));
+    }
+}
@@ -1,6 +1,22 @@
 pub mod activity_tracker;
+pub mod ai;
+pub mod brain_index;
 pub mod capture;
+pub mod conversations;
+pub mod mcp;
+pub mod memory;
 pub mod permissions;
+pub mod pomodoro;
+pub mod prompts;
+pub mod replay;
+pub mod secrets;
 pub mod storage;
 pub mod system_info;
+#[cfg(test)]
+mod test_files;
+#[cfg(test)]
+mod test_http;
+pub mod voice;
+pub mod web_search;
 pub mod window;
+pub mod worker;
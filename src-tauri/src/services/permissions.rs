@@ -46,11 +46,16 @@ pub fn check_screen_recording_permission() -> PermissionState {
 /// Check microphone permission status
 #[tauri::command]
 pub fn check_microphone_permission() -> PermissionState {
-    // Microphone is handled by the browser's Web Speech API
-    // The browser will prompt for permission automatically
-    // We can't check this from Rust, so we return Unknown
-    // and handle it in the frontend
-    PermissionState::Unknown
+    #[cfg(target_os = "linux")]
+    {
+        check_linux_microphone()
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        // Other platforms prompt when the microphone is first opened,
+        // so the state is only known to the frontend
+        PermissionState::Unknown
+    }
 }
 
 /// Request to open system preferences for screen recording
@@ -123,6 +128,64 @@ pub fn open_privacy_settings() -> Result<(), String> {
     }
 }
 
+/// Whether the current user may read and write `path`
+#[cfg(target_os = "linux")]
+fn linux_accessible(path: &std::path::Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+    let Ok(path) = std::ffi::CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    unsafe { libc::access(path.as_ptr(), libc::R_OK | libc::W_OK) == 0 }
+}
+
+/// Linux has no permission prompt: the microphone is reachable if we can talk to the
+/// sound server (PipeWire or PulseAudio) or open an ALSA capture device directly
+#[cfg(target_os = "linux")]
+fn check_linux_microphone() -> PermissionState {
+    use cpal::traits::HostTrait;
+    use std::path::PathBuf;
+
+    let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(format!("/run/user/{}", unsafe { libc::getuid() })));
+    let sockets: Vec<PathBuf> = ["pipewire-0", "pulse/native"]
+        .iter()
+        .map(|name| runtime_dir.join(name))
+        .filter(|path| path.exists())
+        .collect();
+
+    // ALSA capture nodes are named pcmC<card>D<device>c
+    let captures: Vec<PathBuf> = std::fs::read_dir("/dev/snd")
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    let name = path.file_name().unwrap_or_default().to_string_lossy();
+                    name.starts_with("pcmC") && name.ends_with('c')
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if sockets.is_empty() && captures.is_empty() {
+        return PermissionState::Unknown;
+    }
+    let reachable = sockets
+        .iter()
+        .chain(captures.iter())
+        .any(|path| linux_accessible(path));
+    if !reachable {
+        // Usually a user missing from the "audio" group, or a sandbox without sound access
+        return PermissionState::Denied;
+    }
+    if cpal::default_host().default_input_device().is_some() {
+        PermissionState::Granted
+    } else {
+        PermissionState::Unknown
+    }
+}
+
 #[cfg(target_os = "macos")]
 fn check_macos_screen_recording() -> PermissionState {
     // Try to capture a tiny portion of screen to test permission
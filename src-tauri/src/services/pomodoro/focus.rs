@@ -0,0 +1,173 @@
+//! Which apps a focus session was spent in, and nudges when a distracting one comes up
+//! The foreground app is sampled through `ActivityTracker` while a focus phase is running.
+//! Apps on the `focus_distractions` list count against the focus score and raise
+//! `pomodoro://distraction` when they come to the foreground.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::{lock, save_state, Pomodoro, PomodoroPhase};
+use crate::services::activity_tracker::{self, ActivityTracker};
+use crate::services::storage;
+
+/// How often the foreground app is sampled during focus
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+/// Most time one sample is credited with, so a stall or sleep isn't counted as app time
+const MAX_SAMPLE_SECS: u64 = 15;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppFocus {
+    pub name: String,
+    pub seconds: u64,
+    /// Share of the tracked time, 0 – 100
+    pub percent: f32,
+    /// On the distraction list
+    pub distraction: bool,
+}
+
+/// App breakdown of a focus session
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FocusReport {
+    /// Most used first
+    pub apps: Vec<AppFocus>,
+    pub tracked_secs: u64,
+    pub distracted_secs: u64,
+    /// Share of tracked time spent outside distracting apps, 0 – 100
+    /// None when no app could be sampled (e.g. on Linux)
+    pub focus_score: Option<u8>,
+}
+
+/// Payload of `pomodoro://distraction`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Distraction {
+    pub app: String,
+    pub task: Option<String>,
+    pub remaining_secs: u64,
+}
+
+/// Case-insensitive; an entry matches any app whose name contains it ("discord" matches "Discord PTB")
+pub fn is_distraction(app: &str, distractions: &[String]) -> bool {
+    let app = app.to_lowercase();
+    distractions
+        .iter()
+        .map(|entry| entry.trim().to_lowercase())
+        .any(|entry| !entry.is_empty() && app.contains(&entry))
+}
+
+pub fn report(apps: &HashMap<String, u64>, distractions: &[String]) -> FocusReport {
+    let tracked_secs: u64 = apps.values().sum();
+    let mut apps: Vec<AppFocus> = apps
+        .iter()
+        .map(|(name, &seconds)| AppFocus {
+            name: name.clone(),
+            seconds,
+            percent: seconds as f32 / tracked_secs.max(1) as f32 * 100.0,
+            distraction: is_distraction(name, distractions),
+        })
+        .collect();
+    apps.sort_by(|a, b| b.seconds.cmp(&a.seconds).then_with(|| a.name.cmp(&b.name)));
+
+    let distracted_secs = apps
+        .iter()
+        .filter(|app| app.distraction)
+        .map(|app| app.seconds)
+        .sum();
+    let focus_score = (tracked_secs > 0).then(|| {
+        (tracked_secs.saturating_sub(distracted_secs) as f32 / tracked_secs as f32 * 100.0).round()
+            as u8
+    });
+
+    FocusReport {
+        apps,
+        tracked_secs,
+        distracted_secs,
+        focus_score,
+    }
+}
+
+/// The focus session being counted, if the timer is running one
+fn running_session(app: &AppHandle) -> Option<u64> {
+    let pomodoro = app.state::<Pomodoro>();
+    let state = pomodoro.state.lock().ok()?;
+    if state.running && state.phase == PomodoroPhase::Work {
+        state.session_started_at
+    } else {
+        None
+    }
+}
+
+/// Sample the foreground app during focus phases, adding up time per app and
+/// emitting `pomodoro://distraction` when a listed app comes to the front
+pub fn start_sampler(app: AppHandle) {
+    std::thread::spawn(move || {
+        let mut last_sample: Option<Instant> = None;
+        let mut last_app: Option<String> = None;
+        loop {
+            std::thread::sleep(SAMPLE_INTERVAL);
+            let Some(session) = running_session(&app) else {
+                last_sample = None;
+                last_app = None;
+                continue;
+            };
+            let Some(tracker) = app.try_state::<ActivityTracker>() else {
+                continue;
+            };
+
+            // Sampled outside the lock, it can take a moment (osascript on macOS)
+            let current = activity_tracker::sample_active_app(&tracker);
+            let elapsed = last_sample
+                .map(|at| at.elapsed())
+                .unwrap_or(SAMPLE_INTERVAL)
+                .as_secs()
+                .min(MAX_SAMPLE_SECS);
+            last_sample = Some(Instant::now());
+            let Some(current) = current else {
+                continue;
+            };
+
+            let pomodoro = app.state::<Pomodoro>();
+            // Saved like any other change: in order, and after the state lock is released
+            let writing = lock(&pomodoro.writer);
+            let state = {
+                let mut state = lock(&pomodoro.state);
+                if !state.running || state.session_started_at != Some(session) {
+                    continue;
+                }
+                *state.session_apps.entry(current.clone()).or_insert(0) += elapsed;
+                state.clone()
+            };
+            save_state(&app, &state);
+            drop(writing);
+
+            if last_app.as_ref() != Some(&current) {
+                let distractions = storage::load_config(app.clone()).focus_distractions;
+                if is_distraction(&current, &distractions) {
+                    let _ = app.emit(
+                        "pomodoro://distraction",
+                        Distraction {
+                            app: current.clone(),
+                            task: state.task.clone(),
+                            remaining_secs: state.remaining_secs,
+                        },
+                    );
+                }
+                last_app = Some(current);
+            }
+        }
+    });
+}
+
+/// App breakdown and focus score of the current focus session so far
+#[tauri::command]
+pub fn get_pomodoro_focus(app: AppHandle) -> FocusReport {
+    let distractions = storage::load_config(app.clone()).focus_distractions;
+    let apps = app
+        .state::<Pomodoro>()
+        .state
+        .lock()
+        .map(|state| state.session_apps.clone())
+        .unwrap_or_default();
+    report(&apps, &distractions)
+}
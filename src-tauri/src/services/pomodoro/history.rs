@@ -0,0 +1,422 @@
+//! Log of focus sessions and the stats drawn from it
+//! Kept as `pomodoro_history.json` next to `pomodoro_settings.json`.
+
+use chrono::{Days, Local, NaiveDate, Timelike};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+use crate::services::storage;
+
+/// Range used when stats are asked for without one
+const DEFAULT_RANGE_DAYS: u64 = 30;
+
+/// One focus session, from first start to completion or interruption
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PomodoroSession {
+    pub id: String,
+    /// Unix ms
+    pub started_at: u64,
+    pub ended_at: u64,
+    pub planned_secs: u64,
+    /// Time actually spent counting down, pauses excluded
+    pub actual_secs: u64,
+    /// Ran to the end rather than being skipped or reset
+    pub completed: bool,
+    pub task: Option<String>,
+    /// Apps used and focus score, when the foreground app could be sampled
+    #[serde(default)]
+    pub focus: Option<super::focus::FocusReport>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyPomodoros {
+    /// YYYY-MM-DD, local time
+    pub date: String,
+    pub completed: u32,
+    pub interrupted: u32,
+    pub focus_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PomodoroStats {
+    /// Inclusive range, YYYY-MM-DD
+    pub from: String,
+    pub to: String,
+    pub completed: u32,
+    pub interrupted: u32,
+    /// Completed share of all sessions, 0.0 – 1.0
+    pub completion_rate: f32,
+    pub focus_secs: u64,
+    /// Every day of the range, including empty ones
+    pub daily: Vec<DailyPomodoros>,
+    /// Completed sessions by starting hour (0 – 23)
+    pub by_hour: Vec<u32>,
+    /// Hour with the most completed sessions
+    pub best_hour: Option<u32>,
+    /// Days in a row with a completed session, up to today (or yesterday when today has none yet)
+    pub current_streak: u32,
+    /// Longest run of such days in the whole history
+    pub longest_streak: u32,
+}
+
+fn history_path(app: &AppHandle) -> PathBuf {
+    let config = storage::load_config(app.clone());
+    storage::get_captures_dir(&config).join("pomodoro_history.json")
+}
+
+/// A log that exists but doesn't parse is an error rather than an empty list: the next
+/// session would otherwise be written over it and the whole history lost.
+pub fn load(app: &AppHandle) -> Result<Vec<PomodoroSession>, String> {
+    read_history(&history_path(app))
+}
+
+fn read_history(path: &Path) -> Result<Vec<PomodoroSession>, String> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_str(&content).map_err(|e| {
+        format!(
+            "{} is damaged ({}); sessions are not logged until it is fixed or removed",
+            path.display(),
+            e
+        )
+    })
+}
+
+/// Add a finished session to the log
+pub fn record(app: &AppHandle, session: PomodoroSession) -> Result<(), String> {
+    let config = storage::load_config(app.clone());
+    storage::ensure_data_directories(&config)?;
+
+    let mut sessions = load(app)?;
+    sessions.push(session);
+    let json = serde_json::to_string_pretty(&sessions).map_err(|e| e.to_string())?;
+    fs::write(history_path(app), json).map_err(|e| e.to_string())
+}
+
+fn local_time(ms: u64) -> Option<chrono::DateTime<Local>> {
+    chrono::DateTime::from_timestamp_millis(ms as i64).map(|d| d.with_timezone(&Local))
+}
+
+fn parse_date(date: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+        .map_err(|_| format!("Invalid date '{}', expected YYYY-MM-DD", date))
+}
+
+/// Inclusive range from optional YYYY-MM-DD bounds, defaulting to the last 30 days
+fn resolve_range(
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<(NaiveDate, NaiveDate), String> {
+    let to = match to.filter(|d| !d.trim().is_empty()) {
+        Some(date) => parse_date(&date)?,
+        None => Local::now().date_naive(),
+    };
+    let from = match from.filter(|d| !d.trim().is_empty()) {
+        Some(date) => parse_date(&date)?,
+        None => to - Days::new(DEFAULT_RANGE_DAYS - 1),
+    };
+    if from > to {
+        return Err("Start date is after end date".to_string());
+    }
+    Ok((from, to))
+}
+
+fn in_range(session: &PomodoroSession, from: NaiveDate, to: NaiveDate) -> bool {
+    local_time(session.started_at)
+        .map(|time| (from..=to).contains(&time.date_naive()))
+        .unwrap_or(false)
+}
+
+/// Current and longest runs of consecutive days
+fn streaks(days: &BTreeSet<NaiveDate>, today: NaiveDate) -> (u32, u32) {
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for &day in days {
+        run = match previous {
+            Some(previous) if previous.succ_opt() == Some(day) => run + 1,
+            _ => 1,
+        };
+        longest = longest.max(run);
+        previous = Some(day);
+    }
+
+    let mut day = if days.contains(&today) {
+        today
+    } else {
+        today.pred_opt().unwrap_or(today)
+    };
+    let mut current = 0;
+    while days.contains(&day) {
+        current += 1;
+        match day.pred_opt() {
+            Some(pred) => day = pred,
+            None => break,
+        }
+    }
+    (current, longest)
+}
+
+/// List logged sessions in a date range (default: the last 30 days), oldest first
+#[tauri::command]
+pub fn get_pomodoro_history(
+    app: AppHandle,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<Vec<PomodoroSession>, String> {
+    let (from, to) = resolve_range(from, to)?;
+    Ok(load(&app)?
+        .into_iter()
+        .filter(|session| in_range(session, from, to))
+        .collect())
+}
+
+/// Daily counts, completion rate, best time of day and streaks for a date range (default: the last 30 days)
+#[tauri::command]
+pub fn get_pomodoro_stats(
+    app: AppHandle,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<PomodoroStats, String> {
+    let (from, to) = resolve_range(from, to)?;
+    Ok(stats(&load(&app)?, from, to, Local::now().date_naive()))
+}
+
+/// Stats over `from` to `to`; streaks look at every session and run up to `today`
+fn stats(
+    sessions: &[PomodoroSession],
+    from: NaiveDate,
+    to: NaiveDate,
+    today: NaiveDate,
+) -> PomodoroStats {
+    let mut daily: HashMap<NaiveDate, DailyPomodoros> = HashMap::new();
+    let mut by_hour = vec![0u32; 24];
+    let mut completed_days = BTreeSet::new();
+    let (mut completed, mut interrupted, mut focus_secs) = (0, 0, 0);
+
+    for session in sessions {
+        let Some(started) = local_time(session.started_at) else {
+            continue;
+        };
+        let date = started.date_naive();
+        if session.completed {
+            completed_days.insert(date);
+        }
+        if !(from..=to).contains(&date) {
+            continue;
+        }
+
+        let day = daily.entry(date).or_insert_with(|| DailyPomodoros {
+            date: date.format("%Y-%m-%d").to_string(),
+            completed: 0,
+            interrupted: 0,
+            focus_secs: 0,
+        });
+        day.focus_secs += session.actual_secs;
+        focus_secs += session.actual_secs;
+        if session.completed {
+            day.completed += 1;
+            completed += 1;
+            by_hour[started.hour() as usize] += 1;
+        } else {
+            day.interrupted += 1;
+            interrupted += 1;
+        }
+    }
+
+    let days = from.iter_days().take_while(|day| *day <= to);
+    let daily = days
+        .map(|date| {
+            daily.remove(&date).unwrap_or_else(|| DailyPomodoros {
+                date: date.format("%Y-%m-%d").to_string(),
+                completed: 0,
+                interrupted: 0,
+                focus_secs: 0,
+            })
+        })
+        .collect();
+
+    let best_hour = by_hour
+        .iter()
+        .enumerate()
+        .filter(|(_, &count)| count > 0)
+        .max_by_key(|(_, &count)| count)
+        .map(|(hour, _)| hour as u32);
+    let (current_streak, longest_streak) = streaks(&completed_days, today);
+    let total = completed + interrupted;
+
+    PomodoroStats {
+        from: from.format("%Y-%m-%d").to_string(),
+        to: to.format("%Y-%m-%d").to_string(),
+        completed,
+        interrupted,
+        completion_rate: if total > 0 {
+            completed as f32 / total as f32
+        } else {
+            0.0
+        },
+        focus_secs,
+        daily,
+        by_hour,
+        best_hour,
+        current_streak,
+        longest_streak,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_files::{temp_file, temp_path};
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 3, day).unwrap()
+    }
+
+    /// A session started at a local date and hour
+    fn session(day: u32, hour: u32, completed: bool) -> PomodoroSession {
+        let started = date(day)
+            .and_hms_opt(hour, 0, 0)
+            .unwrap()
+            .and_local_timezone(Local)
+            .earliest()
+            .unwrap()
+            .timestamp_millis() as u64;
+        PomodoroSession {
+            id: format!("{}-{}", day, hour),
+            started_at: started,
+            ended_at: started + 1_500_000,
+            planned_secs: 1500,
+            actual_secs: if completed { 1500 } else { 600 },
+            completed,
+            task: None,
+            focus: None,
+        }
+    }
+
+    #[test]
+    fn streaks_count_consecutive_days() {
+        let days: BTreeSet<NaiveDate> = [1, 2, 3, 6, 7].into_iter().map(date).collect();
+
+        assert_eq!(streaks(&days, date(7)), (2, 3));
+        // Today without a session yet doesn't break the run that ended yesterday
+        assert_eq!(streaks(&days, date(8)), (2, 3));
+        // A day missed does
+        assert_eq!(streaks(&days, date(9)), (0, 3));
+        assert_eq!(streaks(&BTreeSet::new(), date(9)), (0, 0));
+    }
+
+    #[test]
+    fn range_defaults_to_the_last_30_days() {
+        let today = Local::now().date_naive();
+        assert_eq!(
+            resolve_range(None, Some(String::new())).unwrap(),
+            (today - Days::new(29), today)
+        );
+        assert_eq!(
+            resolve_range(
+                Some("2026-03-01".to_string()),
+                Some(" 2026-03-05 ".to_string())
+            )
+            .unwrap(),
+            (date(1), date(5))
+        );
+        assert!(resolve_range(
+            Some("2026-03-05".to_string()),
+            Some("2026-03-01".to_string())
+        )
+        .unwrap_err()
+        .contains("after"));
+        assert!(resolve_range(Some("March 1".to_string()), None)
+            .unwrap_err()
+            .contains("YYYY-MM-DD"));
+    }
+
+    #[test]
+    fn stats_fill_every_day_and_find_the_best_hour() {
+        let sessions = [
+            session(1, 9, true),
+            session(1, 9, true),
+            session(1, 14, false),
+            session(3, 14, true),
+            // Outside the range, but still part of the streaks
+            session(5, 9, true),
+        ];
+
+        let stats = stats(&sessions, date(1), date(4), date(5));
+        assert_eq!(
+            (stats.from.as_str(), stats.to.as_str()),
+            ("2026-03-01", "2026-03-04")
+        );
+        assert_eq!((stats.completed, stats.interrupted), (3, 1));
+        assert_eq!(stats.completion_rate, 0.75);
+        assert_eq!(stats.focus_secs, 3 * 1500 + 600);
+
+        let days: Vec<(&str, u32, u32)> = stats
+            .daily
+            .iter()
+            .map(|day| (day.date.as_str(), day.completed, day.interrupted))
+            .collect();
+        assert_eq!(
+            days,
+            [
+                ("2026-03-01", 2, 1),
+                ("2026-03-02", 0, 0),
+                ("2026-03-03", 1, 0),
+                ("2026-03-04", 0, 0)
+            ]
+        );
+        assert_eq!(stats.by_hour[9], 2);
+        assert_eq!(stats.by_hour[14], 1);
+        assert_eq!(stats.best_hour, Some(9));
+        assert_eq!((stats.current_streak, stats.longest_streak), (1, 1));
+    }
+
+    #[test]
+    fn empty_range_has_no_rate_or_best_hour() {
+        let stats = stats(&[], date(1), date(1), date(1));
+        assert_eq!(stats.completion_rate, 0.0);
+        assert_eq!(stats.best_hour, None);
+        assert_eq!(stats.daily.len(), 1);
+    }
+
+    #[test]
+    fn missing_history_is_empty() {
+        let path = temp_path("pomodoro-missing.json");
+        assert!(read_history(&path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn damaged_history_is_an_error() {
+        let path = temp_file("pomodoro-damaged.json", "[{\"id\": \"a\",");
+        let error = read_history(&path).unwrap_err();
+        assert!(error.contains("damaged"));
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn history_round_trips() {
+        let session = PomodoroSession {
+            id: "a".to_string(),
+            started_at: 1_000,
+            ended_at: 1_500_000,
+            planned_secs: 1500,
+            actual_secs: 1500,
+            completed: true,
+            task: Some("Write".to_string()),
+            focus: None,
+        };
+        let json = serde_json::to_string(&vec![session]).unwrap();
+        let path = temp_file("pomodoro-history.json", &json);
+        let sessions = read_history(&path).unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].task.as_deref(), Some("Write"));
+        let _ = fs::remove_file(&path);
+    }
+}
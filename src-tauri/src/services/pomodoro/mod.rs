@@ -0,0 +1,640 @@
+//! Pomodoro timer
+//! The timer runs in the backend so a reload or a hidden window doesn't reset the session.
+//! A running phase is stored as its end time, so it keeps counting across restarts.
+//! Phase lengths and order come from the active profile; see `profiles`.
+//! Every focus session is logged when it completes or is cut short; see `history`.
+//! The apps used during focus are sampled alongside; see `focus`.
+
+pub mod focus;
+pub mod history;
+pub mod profiles;
+pub mod sounds;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::menu::MenuItem;
+use tauri::{AppHandle, Emitter, Manager, Wry};
+
+use super::storage;
+use profiles::{PomodoroProfile, DEFAULT_PROFILE};
+
+/// How often the ticker checks the clock; ticks are only emitted when the seconds change
+const TICK_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PomodoroPhase {
+    #[default]
+    Work,
+    ShortBreak,
+    LongBreak,
+}
+
+impl PomodoroPhase {
+    fn label(self) -> &'static str {
+        match self {
+            PomodoroPhase::Work => "Focus",
+            PomodoroPhase::ShortBreak => "Short break",
+            PomodoroPhase::LongBreak => "Long break",
+        }
+    }
+}
+
+fn default_profile_name() -> String {
+    DEFAULT_PROFILE.to_string()
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PomodoroState {
+    pub phase: PomodoroPhase,
+    /// Counting down
+    pub running: bool,
+    /// Stopped part-way through the phase
+    pub paused: bool,
+    pub remaining_secs: u64,
+    /// Length of the current phase
+    pub phase_secs: u64,
+    /// Work sessions completed since the last full reset
+    pub completed_sessions: u32,
+    pub sessions_until_long_break: u32,
+    /// Focus time since the last full reset
+    pub focus_secs: u64,
+    /// When the running phase ends (Unix ms)
+    pub ends_at: Option<u64>,
+    /// When the current focus session was first started (Unix ms)
+    #[serde(default)]
+    pub session_started_at: Option<u64>,
+    /// What the focus sessions are for, logged with each one
+    #[serde(default)]
+    pub task: Option<String>,
+    /// Seconds in each foreground app during the current focus session
+    #[serde(default)]
+    pub session_apps: HashMap<String, u64>,
+    /// Profile the timer follows
+    #[serde(default = "default_profile_name")]
+    pub profile: String,
+    /// Position of the current phase in the profile's sequence
+    #[serde(default)]
+    pub step: usize,
+    /// The phase before this one was a focus phase that ran out and was counted
+    #[serde(default)]
+    pub follows_completed: bool,
+}
+
+impl PomodoroState {
+    fn new(profile: &PomodoroProfile) -> Self {
+        let mut state = Self::default();
+        state.begin(0, profile);
+        state
+    }
+
+    /// Enter a step of the profile, stopped at its full length
+    fn begin(&mut self, step: usize, profile: &PomodoroProfile) {
+        let phase = profile.phase(step);
+        self.step = step % profile.len();
+        self.phase = phase.kind;
+        self.phase_secs = phase.minutes.max(1) as u64 * 60;
+        self.remaining_secs = self.phase_secs;
+        self.follow(profile);
+        self.running = false;
+        self.paused = false;
+        self.ends_at = None;
+        self.session_started_at = None;
+        self.session_apps.clear();
+    }
+
+    /// Take on a profile without touching the current phase
+    fn follow(&mut self, profile: &PomodoroProfile) {
+        self.profile = profile.name.clone();
+        self.step = profile.locate(self.phase, self.step);
+        self.sessions_until_long_break = profile.work_phases();
+    }
+
+    /// Bring `remaining_secs` and `focus_secs` up to `now`
+    fn sync(&mut self, now: u64) {
+        let Some(ends_at) = self.ends_at else {
+            return;
+        };
+        let remaining = ends_at.saturating_sub(now).div_ceil(1000);
+        if self.phase == PomodoroPhase::Work {
+            self.focus_secs += self.remaining_secs.saturating_sub(remaining);
+        }
+        self.remaining_secs = remaining;
+    }
+
+    fn start(&mut self, now: u64) {
+        if self.remaining_secs == 0 {
+            self.remaining_secs = self.phase_secs;
+        }
+        self.running = true;
+        self.paused = false;
+        self.ends_at = Some(now + self.remaining_secs * 1000);
+        if self.phase == PomodoroPhase::Work && self.session_started_at.is_none() {
+            self.session_started_at = Some(now);
+        }
+    }
+
+    fn pause(&mut self) {
+        if self.running {
+            self.running = false;
+            self.paused = true;
+            self.ends_at = None;
+        }
+    }
+
+    /// The current phase ran out: count it if it was focus and move to the next one, stopped
+    fn complete(&mut self, profile: &PomodoroProfile) {
+        let counted = self.phase == PomodoroPhase::Work;
+        if counted {
+            self.completed_sessions += 1;
+        }
+        self.begin(self.step + 1, profile);
+        self.follows_completed = counted;
+    }
+
+    /// Move to the next phase without counting the current one
+    fn skip(&mut self, profile: &PomodoroProfile) {
+        self.begin(self.step + 1, profile);
+        self.follows_completed = false;
+    }
+
+    /// Step back to the previous phase; going back into a counted focus phase takes it back
+    fn back(&mut self, profile: &PomodoroProfile) {
+        if self.step == 0 && self.completed_sessions == 0 {
+            self.begin(0, profile);
+            return;
+        }
+        if self.follows_completed {
+            self.completed_sessions = self.completed_sessions.saturating_sub(1);
+        }
+        self.begin(self.step + profile.len() - 1, profile);
+        self.follows_completed = false;
+    }
+
+    /// Pick up a saved state at `now`; a phase that ran out meanwhile is completed
+    /// Returns the state as it was when that phase ended, for the session log.
+    fn restore(&mut self, profile: &PomodoroProfile, now: u64) -> Option<PomodoroState> {
+        self.follow(profile);
+        if !self.running {
+            return None;
+        }
+        self.sync(now);
+        if self.remaining_secs > 0 {
+            return None;
+        }
+        let finished = self.clone();
+        self.complete(profile);
+        Some(finished)
+    }
+
+    /// Fresh phase that hasn't been started, so it can follow profile changes
+    fn untouched(&self) -> bool {
+        !self.running && !self.paused
+    }
+}
+
+/// Managed state for the pomodoro timer
+pub struct Pomodoro {
+    state: Mutex<PomodoroState>,
+    /// Held from a change until it is saved and published, so saves land in the order the changes were made.
+    /// The state lock itself is only held for the change, never across file access.
+    writer: Mutex<()>,
+    /// Tray entry whose text follows the timer
+    tray_item: Mutex<Option<MenuItem<Wry>>>,
+}
+
+impl Pomodoro {
+    /// Restore the saved timer; a phase that ended while the app was closed counts as completed
+    pub fn load(app: &AppHandle) -> Self {
+        let profile = profiles::active(app);
+        let saved = fs::read_to_string(state_path(app))
+            .ok()
+            .and_then(|content| serde_json::from_str::<PomodoroState>(&content).ok());
+
+        let state = match saved {
+            Some(mut state) => {
+                if let Some(finished) = state.restore(&profile, now_ms()) {
+                    log_session(app, &finished, &state);
+                }
+                state
+            }
+            None => PomodoroState::new(&profile),
+        };
+        Self {
+            state: Mutex::new(state),
+            writer: Mutex::new(()),
+            tray_item: Mutex::new(None),
+        }
+    }
+
+    /// Attach the tray menu entry that starts and pauses the timer
+    pub fn set_tray_item(&self, item: MenuItem<Wry>) {
+        if let Ok(mut tray_item) = self.tray_item.lock() {
+            *tray_item = Some(item);
+        }
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn state_path(app: &AppHandle) -> PathBuf {
+    let config = storage::load_config(app.clone());
+    storage::get_captures_dir(&config).join("pomodoro_state.json")
+}
+
+fn save_state(app: &AppHandle, state: &PomodoroState) {
+    let config = storage::load_config(app.clone());
+    let result = storage::ensure_data_directories(&config).and_then(|_| {
+        let json = serde_json::to_string_pretty(state).map_err(|e| e.to_string())?;
+        fs::write(state_path(app), json).map_err(|e| e.to_string())
+    });
+    if let Err(e) = result {
+        eprintln!("Failed to save pomodoro state: {}", e);
+    }
+}
+
+fn format_remaining(secs: u64) -> String {
+    format!("{:02}:{:02}", secs / 60, secs % 60)
+}
+
+/// Keep the tray entry and tooltip in step with the timer
+fn update_tray(app: &AppHandle, state: &PomodoroState) {
+    let text = if state.running {
+        "Pause Pomodoro"
+    } else if state.paused {
+        "Resume Pomodoro"
+    } else {
+        "Start Pomodoro"
+    };
+    if let Ok(item) = app.state::<Pomodoro>().tray_item.lock() {
+        if let Some(item) = item.as_ref() {
+            let _ = item.set_text(text);
+        }
+    }
+
+    if let Some(tray) = app.tray_by_id("main") {
+        let tooltip = if state.running || state.paused {
+            format!(
+                "WOPR - {} {}{}",
+                state.phase.label(),
+                format_remaining(state.remaining_secs),
+                if state.paused { " (paused)" } else { "" }
+            )
+        } else {
+            "WOPR".to_string()
+        };
+        let _ = tray.set_tooltip(Some(tooltip));
+    }
+}
+
+/// Log the focus session `before` was in, once `after` has left it
+fn log_session(app: &AppHandle, before: &PomodoroState, after: &PomodoroState) {
+    let Some(started_at) = before.session_started_at else {
+        return;
+    };
+    if after.session_started_at == Some(started_at) {
+        return;
+    }
+    // Only a session that ran out is completed; skipping or resetting interrupts it
+    let completed = before.remaining_secs == 0;
+    let focus = (!before.session_apps.is_empty()).then(|| {
+        let distractions = storage::load_config(app.clone()).focus_distractions;
+        focus::report(&before.session_apps, &distractions)
+    });
+    let session = history::PomodoroSession {
+        id: uuid::Uuid::new_v4().to_string(),
+        started_at,
+        ended_at: before.ends_at.filter(|_| completed).unwrap_or_else(now_ms),
+        planned_secs: before.phase_secs,
+        actual_secs: before.phase_secs.saturating_sub(before.remaining_secs),
+        completed,
+        task: before.task.clone(),
+        focus,
+    };
+    if let Err(e) = history::record(app, session) {
+        eprintln!("Failed to log pomodoro session: {}", e);
+    }
+}
+
+/// Tell the UI and tray about the current state
+fn publish(app: &AppHandle, state: &PomodoroState, phase_changed: bool) {
+    let _ = app.emit("pomodoro://tick", state);
+    if phase_changed {
+        let _ = app.emit("pomodoro://phase", state);
+    }
+    update_tray(app, state);
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+/// Apply a change, then save and publish the result once the state lock is released
+fn update(
+    app: &AppHandle,
+    change: impl FnOnce(&mut PomodoroState, &PomodoroProfile),
+) -> PomodoroState {
+    let profile = profiles::active(app);
+    let pomodoro = app.state::<Pomodoro>();
+    let _writing = lock(&pomodoro.writer);
+    let (before, after) = {
+        let mut state = lock(&pomodoro.state);
+        state.sync(now_ms());
+        let before = state.clone();
+        change(&mut state, &profile);
+        (before, state.clone())
+    };
+    log_session(app, &before, &after);
+    save_state(app, &after);
+    publish(
+        app,
+        &after,
+        (before.phase, before.step, before.completed_sessions)
+            != (after.phase, after.step, after.completed_sessions),
+    );
+    after
+}
+
+/// The running phase ran out: move to the next one and play the profile's sound
+fn finish_phase(app: &AppHandle) {
+    let mut sound = None;
+    update(app, |state, profile| {
+        // A command may have paused or skipped the phase since the ticker looked
+        if !state.running || state.remaining_secs > 0 {
+            return;
+        }
+        let finished = state.phase;
+        state.complete(profile);
+        if profile.auto_starts_after(finished) {
+            state.start(now_ms());
+        }
+        sound = profile.end_sound(finished).map(str::to_string);
+    });
+    if let Some(sound) = sound {
+        sounds::play(&sound);
+    }
+}
+
+/// Count down the running phase; emits `pomodoro://tick` every second and `pomodoro://phase` when a phase ends
+/// A phase that runs out plays the profile's sound and starts the next one if the profile says so.
+pub fn start_ticker(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(TICK_INTERVAL);
+        let pomodoro = app.state::<Pomodoro>();
+        let ran_out = {
+            // Keeps a tick from being published over a change that is being published
+            let _writing = lock(&pomodoro.writer);
+            // Only the clock is read under the state lock; publishing happens after it is released
+            let (state, previous) = {
+                let Ok(mut state) = pomodoro.state.lock() else {
+                    continue;
+                };
+                if !state.running {
+                    continue;
+                }
+                let previous = state.remaining_secs;
+                state.sync(now_ms());
+                (state.clone(), previous)
+            };
+            if state.remaining_secs > 0 && state.remaining_secs != previous {
+                publish(&app, &state, false);
+            }
+            state.remaining_secs == 0
+        };
+        if ran_out {
+            finish_phase(&app);
+        }
+    });
+}
+
+/// The active profile was switched or edited: an unstarted phase follows it right away,
+/// one in progress keeps its length and the profile takes over from the next phase
+pub fn profile_changed(app: &AppHandle) -> PomodoroState {
+    if app.try_state::<Pomodoro>().is_none() {
+        return PomodoroState::new(&profiles::active(app));
+    }
+    update(app, |state, profile| {
+        state.follow(profile);
+        if state.untouched() {
+            state.begin(state.step, profile);
+        }
+    })
+}
+
+/// Start or pause, for the tray menu
+pub fn toggle(app: &AppHandle) -> PomodoroState {
+    update(app, |state, _| {
+        if state.running {
+            state.pause();
+        } else {
+            state.start(now_ms());
+        }
+    })
+}
+
+/// Get the timer state
+#[tauri::command]
+pub fn get_pomodoro_state(app: AppHandle) -> PomodoroState {
+    // Read-only: the clock is brought up to date on a copy, nothing is saved or emitted
+    let mut state = lock(&app.state::<Pomodoro>().state).clone();
+    state.sync(now_ms());
+    state
+}
+
+/// Start or resume the current phase
+#[tauri::command]
+pub fn start_pomodoro(app: AppHandle) -> PomodoroState {
+    update(&app, |state, _| state.start(now_ms()))
+}
+
+/// Pause the current phase
+#[tauri::command]
+pub fn pause_pomodoro(app: AppHandle) -> PomodoroState {
+    update(&app, |state, _| state.pause())
+}
+
+/// End the current phase early and move to the next one; a skipped focus phase isn't counted
+#[tauri::command]
+pub fn skip_pomodoro_phase(app: AppHandle) -> PomodoroState {
+    update(&app, |state, profile| state.skip(profile))
+}
+
+/// Go back to the previous phase
+#[tauri::command]
+pub fn previous_pomodoro_phase(app: AppHandle) -> PomodoroState {
+    update(&app, |state, profile| state.back(profile))
+}
+
+/// Restart the current phase from its full length, stopped
+#[tauri::command]
+pub fn reset_pomodoro_phase(app: AppHandle) -> PomodoroState {
+    update(&app, |state, profile| state.begin(state.step, profile))
+}
+
+/// Back to the first focus phase with no sessions or focus time counted
+#[tauri::command]
+pub fn reset_pomodoro(app: AppHandle) -> PomodoroState {
+    update(&app, |state, profile| *state = PomodoroState::new(profile))
+}
+
+/// Set the task that focus sessions are logged under; empty clears it
+#[tauri::command]
+pub fn set_pomodoro_task(app: AppHandle, task: Option<String>) -> PomodoroState {
+    let task = task
+        .map(|task| task.trim().to_string())
+        .filter(|task| !task.is_empty());
+    update(&app, |state, _| state.task = task)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::storage::PomodoroSettings;
+
+    /// Focus 25, short break 5, with a long break of 15 after every 2nd focus phase
+    fn profile() -> PomodoroProfile {
+        PomodoroProfile::from_settings(&PomodoroSettings {
+            work_minutes: 25,
+            break_minutes: 5,
+            long_break_minutes: 15,
+            sessions_until_long_break: 2,
+            ..Default::default()
+        })
+    }
+
+    /// Start the phase at `now` and let it run out
+    fn run_out(state: &mut PomodoroState, profile: &PomodoroProfile, now: u64) -> u64 {
+        state.start(now);
+        let end = now + state.remaining_secs * 1000;
+        state.sync(end);
+        assert_eq!(state.remaining_secs, 0);
+        state.complete(profile);
+        end
+    }
+
+    #[test]
+    fn starts_stopped_at_the_first_focus_phase() {
+        let state = PomodoroState::new(&profile());
+        assert_eq!(state.phase, PomodoroPhase::Work);
+        assert_eq!(state.remaining_secs, 25 * 60);
+        assert_eq!(state.sessions_until_long_break, 2);
+        assert!(!state.running && !state.paused);
+        assert_eq!(state.ends_at, None);
+    }
+
+    #[test]
+    fn long_break_comes_after_the_set_number_of_focus_phases() {
+        let profile = profile();
+        let mut state = PomodoroState::new(&profile);
+        let mut phases = Vec::new();
+        let mut now = 0;
+        for _ in 0..5 {
+            now = run_out(&mut state, &profile, now);
+            phases.push(state.phase);
+        }
+
+        use PomodoroPhase::*;
+        assert_eq!(phases, [ShortBreak, Work, LongBreak, Work, ShortBreak]);
+        assert_eq!(state.completed_sessions, 3);
+        assert_eq!(state.focus_secs, 3 * 25 * 60);
+        // Finished phases wait to be started
+        assert!(!state.running);
+    }
+
+    #[test]
+    fn pausing_keeps_the_time_left() {
+        let profile = profile();
+        let mut state = PomodoroState::new(&profile);
+        state.start(0);
+        state.sync(60_000);
+        state.pause();
+        assert!(state.paused && !state.running);
+        assert_eq!(state.remaining_secs, 24 * 60);
+
+        // Time spent paused doesn't count
+        state.start(10 * 60_000);
+        assert_eq!(state.ends_at, Some(10 * 60_000 + 24 * 60_000));
+        state.sync(11 * 60_000);
+        assert_eq!(state.remaining_secs, 23 * 60);
+        assert_eq!(state.focus_secs, 2 * 60);
+    }
+
+    #[test]
+    fn skipped_focus_is_not_counted() {
+        let profile = profile();
+        let mut state = PomodoroState::new(&profile);
+        state.start(0);
+        state.skip(&profile);
+        assert_eq!(state.phase, PomodoroPhase::ShortBreak);
+        assert_eq!(state.completed_sessions, 0);
+
+        // Going back to it has nothing to take back
+        state.back(&profile);
+        assert_eq!(state.phase, PomodoroPhase::Work);
+        assert_eq!(state.completed_sessions, 0);
+    }
+
+    #[test]
+    fn going_back_into_completed_focus_takes_it_back() {
+        let profile = profile();
+        let mut state = PomodoroState::new(&profile);
+        run_out(&mut state, &profile, 0);
+        assert_eq!(state.completed_sessions, 1);
+
+        state.back(&profile);
+        assert_eq!(state.phase, PomodoroPhase::Work);
+        assert_eq!(state.step, 0);
+        assert_eq!(state.completed_sessions, 0);
+        assert_eq!(state.remaining_secs, 25 * 60);
+
+        // Back from the start stays at the start
+        state.back(&profile);
+        assert_eq!(state.step, 0);
+    }
+
+    #[test]
+    fn restore_completes_a_phase_that_ran_out_while_closed() {
+        let profile = profile();
+        let mut state = PomodoroState::new(&profile);
+        state.start(0);
+        let saved = serde_json::to_string(&state).unwrap();
+
+        let mut restored: PomodoroState = serde_json::from_str(&saved).unwrap();
+        let finished = restored.restore(&profile, 30 * 60_000).unwrap();
+        assert_eq!(finished.phase, PomodoroPhase::Work);
+        assert_eq!(finished.remaining_secs, 0);
+        assert_eq!(restored.phase, PomodoroPhase::ShortBreak);
+        assert_eq!(restored.completed_sessions, 1);
+
+        // Still running: keeps counting from its end time
+        let mut restored: PomodoroState = serde_json::from_str(&saved).unwrap();
+        assert!(restored.restore(&profile, 5 * 60_000).is_none());
+        assert!(restored.running);
+        assert_eq!(restored.remaining_secs, 20 * 60);
+    }
+
+    #[test]
+    fn restore_leaves_a_paused_phase_alone() {
+        let profile = profile();
+        let mut state = PomodoroState::new(&profile);
+        state.start(0);
+        state.sync(60_000);
+        state.pause();
+
+        assert!(state.restore(&profile, 24 * 3600 * 1000).is_none());
+        assert!(state.paused);
+        assert_eq!(state.remaining_secs, 24 * 60);
+    }
+}
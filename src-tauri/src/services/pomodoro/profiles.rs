@@ -0,0 +1,376 @@
+//! Named timer profiles: any sequence of phases, with their own sounds and auto-start
+//! `pomodoro_settings.json` stays the "Default" profile; the others are kept in
+//! `pomodoro_profiles.json` with the name of the one in use.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+use super::{sounds, PomodoroPhase, PomodoroState};
+use crate::services::storage::{self, PomodoroSettings, PomodoroSounds};
+
+/// Name of the profile backed by `pomodoro_settings.json`
+pub const DEFAULT_PROFILE: &str = "Default";
+/// Longest phase a profile can have
+const MAX_PHASE_MINUTES: u32 = 240;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfilePhase {
+    pub kind: PomodoroPhase,
+    pub minutes: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PomodoroProfile {
+    /// Unique name
+    pub name: String,
+    /// Run in order, then from the start again
+    pub phases: Vec<ProfilePhase>,
+    #[serde(default)]
+    pub sounds: PomodoroSounds,
+    /// Start a break as soon as focus ends
+    #[serde(default)]
+    pub auto_start_breaks: bool,
+    /// Start focus as soon as a break ends
+    #[serde(default)]
+    pub auto_start_work: bool,
+}
+
+impl PomodoroProfile {
+    /// The default profile: focus and short breaks, with a long break closing each cycle
+    pub(super) fn from_settings(settings: &PomodoroSettings) -> Self {
+        let work = ProfilePhase {
+            kind: PomodoroPhase::Work,
+            minutes: settings.work_minutes,
+        };
+        let mut phases = Vec::new();
+        for session in 1..=settings.sessions_until_long_break.max(1) {
+            phases.push(work.clone());
+            phases.push(if session == settings.sessions_until_long_break.max(1) {
+                ProfilePhase {
+                    kind: PomodoroPhase::LongBreak,
+                    minutes: settings.long_break_minutes,
+                }
+            } else {
+                ProfilePhase {
+                    kind: PomodoroPhase::ShortBreak,
+                    minutes: settings.break_minutes,
+                }
+            });
+        }
+        Self {
+            name: DEFAULT_PROFILE.to_string(),
+            phases,
+            sounds: settings.sounds.clone(),
+            auto_start_breaks: settings.auto_start_breaks,
+            auto_start_work: settings.auto_start_work,
+        }
+    }
+
+    /// Phase at a step of the sequence, wrapping around
+    pub(super) fn phase(&self, step: usize) -> ProfilePhase {
+        self.phases
+            .get(step % self.phases.len().max(1))
+            .cloned()
+            .unwrap_or(ProfilePhase {
+                kind: PomodoroPhase::Work,
+                minutes: 25,
+            })
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.phases.len().max(1)
+    }
+
+    /// Focus phases per pass through the sequence
+    pub(super) fn work_phases(&self) -> u32 {
+        self.phases
+            .iter()
+            .filter(|phase| phase.kind == PomodoroPhase::Work)
+            .count()
+            .max(1) as u32
+    }
+
+    /// Step for a phase of this kind: `hint` if it fits, else the first one, else the start
+    pub(super) fn locate(&self, kind: PomodoroPhase, hint: usize) -> usize {
+        if self.phases.get(hint).map(|phase| phase.kind) == Some(kind) {
+            return hint;
+        }
+        self.phases
+            .iter()
+            .position(|phase| phase.kind == kind)
+            .unwrap_or(0)
+    }
+
+    /// Whether the phase after one of this kind starts by itself
+    pub(super) fn auto_starts_after(&self, finished: PomodoroPhase) -> bool {
+        if finished == PomodoroPhase::Work {
+            self.auto_start_breaks
+        } else {
+            self.auto_start_work
+        }
+    }
+
+    /// Sound for the end of a phase of this kind
+    pub(super) fn end_sound(&self, finished: PomodoroPhase) -> Option<&str> {
+        if finished == PomodoroPhase::Work {
+            self.sounds.work_end.as_deref()
+        } else {
+            self.sounds.break_end.as_deref()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ProfileStore {
+    /// Profile in use (None = Default)
+    #[serde(default)]
+    active: Option<String>,
+    #[serde(default)]
+    profiles: Vec<PomodoroProfile>,
+}
+
+fn profiles_path(app: &AppHandle) -> PathBuf {
+    let config = storage::load_config(app.clone());
+    storage::get_captures_dir(&config).join("pomodoro_profiles.json")
+}
+
+/// A file that exists but doesn't parse is an error rather than an empty store: the next
+/// save would otherwise replace it and lose every profile in it.
+fn load_store(app: &AppHandle) -> Result<ProfileStore, String> {
+    read_store(&profiles_path(app))
+}
+
+fn read_store(path: &Path) -> Result<ProfileStore, String> {
+    if !path.exists() {
+        return Ok(ProfileStore::default());
+    }
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_str(&content).map_err(|e| {
+        format!(
+            "{} is damaged ({}); profiles can't be changed until it is fixed or removed",
+            path.display(),
+            e
+        )
+    })
+}
+
+fn save_store(app: &AppHandle, store: &ProfileStore) -> Result<(), String> {
+    let config = storage::load_config(app.clone());
+    storage::ensure_data_directories(&config)?;
+    let json = serde_json::to_string_pretty(store).map_err(|e| e.to_string())?;
+    fs::write(profiles_path(app), json).map_err(|e| e.to_string())
+}
+
+fn is_default(name: &str) -> bool {
+    name.trim().eq_ignore_ascii_case(DEFAULT_PROFILE)
+}
+
+/// The profile in use; Default when none is chosen, the chosen one is gone or the profiles
+/// can't be read
+pub fn active(app: &AppHandle) -> PomodoroProfile {
+    let store = load_store(app).unwrap_or_else(|e| {
+        eprintln!("Failed to load pomodoro profiles: {}", e);
+        ProfileStore::default()
+    });
+    store
+        .active
+        .and_then(|name| store.profiles.into_iter().find(|p| p.name == name))
+        .unwrap_or_else(|| {
+            PomodoroProfile::from_settings(&storage::load_pomodoro_settings(app.clone()))
+        })
+}
+
+fn validate(profile: &PomodoroProfile) -> Result<(), String> {
+    if profile.name.trim().is_empty() {
+        return Err("Profile name is required".to_string());
+    }
+    if is_default(&profile.name) {
+        return Err(format!(
+            "'{}' is the built-in profile, change it through the pomodoro settings",
+            DEFAULT_PROFILE
+        ));
+    }
+    if !profile.phases.iter().any(|p| p.kind == PomodoroPhase::Work) {
+        return Err("A profile needs at least one focus phase".to_string());
+    }
+    if let Some(phase) = profile
+        .phases
+        .iter()
+        .find(|p| p.minutes == 0 || p.minutes > MAX_PHASE_MINUTES)
+    {
+        return Err(format!(
+            "Phase length {} is out of range (1 – {} minutes)",
+            phase.minutes, MAX_PHASE_MINUTES
+        ));
+    }
+    for sound in [&profile.sounds.work_end, &profile.sounds.break_end]
+        .into_iter()
+        .flatten()
+    {
+        sounds::validate(sound)?;
+    }
+    Ok(())
+}
+
+/// All profiles, Default first
+#[tauri::command]
+pub fn list_pomodoro_profiles(app: AppHandle) -> Result<Vec<PomodoroProfile>, String> {
+    let default = PomodoroProfile::from_settings(&storage::load_pomodoro_settings(app.clone()));
+    Ok(std::iter::once(default)
+        .chain(load_store(&app)?.profiles)
+        .collect())
+}
+
+/// Add or replace a profile; changes to the one in use apply from the next phase
+#[tauri::command]
+pub fn save_pomodoro_profile(app: AppHandle, profile: PomodoroProfile) -> Result<(), String> {
+    validate(&profile)?;
+    let mut profile = profile;
+    profile.name = profile.name.trim().to_string();
+
+    let mut store = load_store(&app)?;
+    let is_active = store.active.as_ref() == Some(&profile.name);
+    match store.profiles.iter_mut().find(|p| p.name == profile.name) {
+        Some(existing) => *existing = profile,
+        None => store.profiles.push(profile),
+    }
+    save_store(&app, &store)?;
+
+    if is_active {
+        super::profile_changed(&app);
+    }
+    Ok(())
+}
+
+/// Delete a profile (the one in use falls back to Default)
+#[tauri::command]
+pub fn delete_pomodoro_profile(app: AppHandle, name: String) -> Result<(), String> {
+    if is_default(&name) {
+        return Err(format!("The {} profile can't be deleted", DEFAULT_PROFILE));
+    }
+    let mut store = load_store(&app)?;
+    store.profiles.retain(|p| p.name != name);
+    let was_active = store.active.as_ref() == Some(&name);
+    if was_active {
+        store.active = None;
+    }
+    save_store(&app, &store)?;
+
+    if was_active {
+        super::profile_changed(&app);
+    }
+    Ok(())
+}
+
+/// Switch profile; "Default" (or None) goes back to the pomodoro settings
+/// A phase that hasn't started follows the new profile right away, one in progress runs out first.
+#[tauri::command]
+pub fn set_active_pomodoro_profile(
+    app: AppHandle,
+    name: Option<String>,
+) -> Result<PomodoroState, String> {
+    let name = name.filter(|name| !name.trim().is_empty() && !is_default(name));
+    let mut store = load_store(&app)?;
+    if let Some(name) = &name {
+        if !store.profiles.iter().any(|p| &p.name == name) {
+            return Err(format!("Unknown pomodoro profile: {}", name));
+        }
+    }
+    store.active = name;
+    save_store(&app, &store)?;
+    Ok(super::profile_changed(&app))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_files::{temp_file, temp_path};
+
+    fn settings(sessions_until_long_break: u32) -> PomodoroSettings {
+        PomodoroSettings {
+            work_minutes: 25,
+            break_minutes: 5,
+            long_break_minutes: 15,
+            sessions_until_long_break,
+            ..Default::default()
+        }
+    }
+
+    fn kinds(profile: &PomodoroProfile) -> Vec<PomodoroPhase> {
+        profile.phases.iter().map(|phase| phase.kind).collect()
+    }
+
+    #[test]
+    fn default_profile_closes_each_cycle_with_a_long_break() {
+        use PomodoroPhase::*;
+        let profile = PomodoroProfile::from_settings(&settings(3));
+
+        assert_eq!(profile.name, DEFAULT_PROFILE);
+        assert_eq!(
+            kinds(&profile),
+            [Work, ShortBreak, Work, ShortBreak, Work, LongBreak]
+        );
+        assert_eq!(profile.phases[1].minutes, 5);
+        assert_eq!(profile.phases[5].minutes, 15);
+        assert_eq!(profile.work_phases(), 3);
+
+        // Zero sessions still makes a cycle of one
+        assert_eq!(
+            kinds(&PomodoroProfile::from_settings(&settings(0))),
+            [Work, LongBreak]
+        );
+    }
+
+    #[test]
+    fn phases_wrap_around() {
+        let profile = PomodoroProfile::from_settings(&settings(2));
+        assert_eq!(profile.len(), 4);
+        assert_eq!(profile.phase(4).kind, PomodoroPhase::Work);
+        assert_eq!(profile.phase(7).kind, PomodoroPhase::LongBreak);
+    }
+
+    #[test]
+    fn locate_keeps_a_fitting_step_or_finds_the_first_of_its_kind() {
+        let profile = PomodoroProfile::from_settings(&settings(3));
+
+        assert_eq!(profile.locate(PomodoroPhase::Work, 2), 2);
+        assert_eq!(profile.locate(PomodoroPhase::ShortBreak, 2), 1);
+        assert_eq!(profile.locate(PomodoroPhase::LongBreak, 40), 5);
+
+        let focus_only = PomodoroProfile {
+            phases: vec![ProfilePhase {
+                kind: PomodoroPhase::Work,
+                minutes: 50,
+            }],
+            ..profile
+        };
+        assert_eq!(focus_only.locate(PomodoroPhase::LongBreak, 3), 0);
+    }
+
+    #[test]
+    fn auto_start_and_sounds_follow_the_finished_phase() {
+        let mut profile = PomodoroProfile::from_settings(&settings(4));
+        profile.auto_start_breaks = true;
+        profile.sounds.work_end = Some("bell".to_string());
+
+        assert!(profile.auto_starts_after(PomodoroPhase::Work));
+        assert!(!profile.auto_starts_after(PomodoroPhase::ShortBreak));
+        assert_eq!(profile.end_sound(PomodoroPhase::Work), Some("bell"));
+        assert_eq!(profile.end_sound(PomodoroPhase::LongBreak), None);
+    }
+
+    #[test]
+    fn damaged_store_is_an_error() {
+        assert!(read_store(&temp_path("profiles-missing.json"))
+            .unwrap()
+            .profiles
+            .is_empty());
+
+        let path = temp_file("profiles-damaged.json", "{\"profiles\": [");
+        assert!(read_store(&path).unwrap_err().contains("damaged"));
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,182 @@
+//! Sounds played when a phase ends
+//! The built-in sounds are synthesized; any other value is the path of a WAV file.
+//! They play through the backend so they are heard with the window hidden.
+
+use std::collections::VecDeque;
+use std::f32::consts::TAU;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::services::voice::audio;
+
+pub const BUILTIN_SOUNDS: [&str; 3] = ["chime", "bell", "beep"];
+/// Playback level, sounds shouldn't be startling
+const VOLUME: f32 = 0.5;
+/// Longest a sound file may play
+const MAX_SECS: f32 = 10.0;
+
+/// A decaying sine with a few partials, `(frequency multiple, level)`
+fn tone(rate: u32, frequency: f32, secs: f32, decay: f32, partials: &[(f32, f32)]) -> Vec<f32> {
+    let len = (rate as f32 * secs) as usize;
+    (0..len)
+        .map(|i| {
+            let t = i as f32 / rate as f32;
+            // Short fade-in so the start doesn't click
+            let envelope = (t * 200.0).min(1.0) * (-decay * t).exp();
+            partials
+                .iter()
+                .map(|(multiple, level)| level * (TAU * frequency * multiple * t).sin())
+                .sum::<f32>()
+                * envelope
+        })
+        .collect()
+}
+
+fn silence(rate: u32, secs: f32) -> Vec<f32> {
+    vec![0.0; (rate as f32 * secs) as usize]
+}
+
+fn builtin(name: &str, rate: u32) -> Option<Vec<f32>> {
+    let samples = match name {
+        // Two rising notes
+        "chime" => [
+            tone(rate, 880.0, 0.3, 6.0, &[(1.0, 0.8), (2.0, 0.2)]),
+            tone(rate, 1318.5, 0.9, 4.0, &[(1.0, 0.8), (2.0, 0.2)]),
+        ]
+        .concat(),
+        // Inharmonic partials ringing out
+        "bell" => tone(
+            rate,
+            523.3,
+            2.0,
+            2.0,
+            &[(1.0, 0.6), (2.76, 0.25), (5.4, 0.15)],
+        ),
+        "beep" => {
+            let beep = tone(rate, 1000.0, 0.12, 0.0, &[(1.0, 0.6)]);
+            [
+                beep.clone(),
+                silence(rate, 0.08),
+                beep.clone(),
+                silence(rate, 0.08),
+                beep,
+            ]
+            .concat()
+        }
+        _ => return None,
+    };
+    Some(samples)
+}
+
+/// A built-in name or a WAV file that can be decoded
+pub fn validate(sound: &str) -> Result<(), String> {
+    if BUILTIN_SOUNDS.contains(&sound) {
+        return Ok(());
+    }
+    let path = Path::new(sound);
+    let is_wav = path
+        .extension()
+        .map(|ext| ext.eq_ignore_ascii_case("wav"))
+        .unwrap_or(false);
+    if !is_wav {
+        return Err(format!(
+            "Unknown sound '{}', use {} or a .wav file",
+            sound,
+            BUILTIN_SOUNDS.join(", ")
+        ));
+    }
+    if !path.is_file() {
+        return Err(format!("Sound file not found: {}", sound));
+    }
+    let (samples, _) = audio::decode_wav(path)?;
+    if samples.is_empty() {
+        return Err(format!("Sound file is empty: {}", sound));
+    }
+    Ok(())
+}
+
+/// Play a sound to the end on the current thread
+fn play_blocking(sound: &str) -> Result<(), String> {
+    let buffer = Arc::new(Mutex::new(VecDeque::new()));
+    // The stream stops when dropped, so it stays here until the sound is done
+    let (stream, rate) = audio::open_speaker(buffer.clone())?;
+
+    let mut samples = match builtin(sound, rate) {
+        Some(samples) => samples,
+        None => {
+            // Resampled once, straight from the file's rate to the device's
+            let (samples, file_rate) = audio::decode_wav(Path::new(sound))?;
+            audio::Resampler::new(file_rate, rate).process(&samples)
+        }
+    };
+    samples.truncate((rate as f32 * MAX_SECS) as usize);
+    let duration = Duration::from_secs_f32(samples.len() as f32 / rate as f32);
+    buffer
+        .lock()
+        .map_err(|e| e.to_string())?
+        .extend(samples.into_iter().map(|s| s * VOLUME));
+
+    let started = Instant::now();
+    while started.elapsed() < duration + Duration::from_secs(1) {
+        if buffer.lock().map(|b| b.is_empty()).unwrap_or(true) {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    // Let the device play out what it already took from the buffer
+    std::thread::sleep(Duration::from_millis(150));
+    drop(stream);
+    Ok(())
+}
+
+/// Play a sound in the background
+pub fn play(sound: &str) {
+    let sound = sound.to_string();
+    std::thread::spawn(move || {
+        if let Err(e) = play_blocking(&sound) {
+            eprintln!("Failed to play pomodoro sound: {}", e);
+        }
+    });
+}
+
+/// Play a sound so it can be tried before it is chosen
+#[tauri::command]
+pub fn preview_pomodoro_sound(sound: String) -> Result<(), String> {
+    validate(&sound)?;
+    play(&sound);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_files::temp_path;
+
+    #[test]
+    fn builtin_names_are_valid() {
+        for name in BUILTIN_SOUNDS {
+            assert!(validate(name).is_ok());
+            assert!(!builtin(name, 48_000).unwrap().is_empty());
+        }
+        assert!(validate("gong").is_err());
+    }
+
+    #[test]
+    fn wav_files_must_decode() {
+        let good = temp_path("chime.wav");
+        audio::write_wav(&good, &builtin("chime", 44_100).unwrap(), 44_100).unwrap();
+        assert!(validate(good.to_str().unwrap()).is_ok());
+        // Kept at the file's own rate rather than the voice pipeline's 16 kHz
+        let (samples, rate) = audio::decode_wav(&good).unwrap();
+        assert_eq!(rate, 44_100);
+        assert_eq!(samples.len(), builtin("chime", 44_100).unwrap().len());
+
+        let bad = temp_path("garbage.wav");
+        std::fs::write(&bad, b"not a wav file").unwrap();
+        assert!(validate(bad.to_str().unwrap()).is_err());
+
+        let _ = std::fs::remove_file(good);
+        let _ = std::fs::remove_file(bad);
+    }
+}
@@ -0,0 +1,478 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+
+use super::activity_tracker::ActivityTracker;
+use super::storage::{self, AppConfig, Persona};
+
+/// Folder in the brain directory holding the editable templates
+const PROMPTS_DIR: &str = "prompts";
+
+/// Built-in templates, written to the prompts folder on first use so they can be edited
+const DEFAULT_TEMPLATES: &[(&str, &str)] = &[
+    ("chat_retro.md", include_str!("chat_retro.md")),
+    ("chat_cozy.md", include_str!("chat_cozy.md")),
+    ("screen_retro.md", include_str!("screen_retro.md")),
+    ("screen_cozy.md", include_str!("screen_cozy.md")),
+];
+
+/// Variables a template may use, with a sample value for previews
+const VARIABLES: &[(&str, &str)] = &[
+    ("persona", "Joshua"),
+    ("persona_upper", "JOSHUA"),
+    ("user", "Falken"),
+    ("user_upper", "FALKEN"),
+    ("memory", "- Falken's favorite game is chess"),
+    ("screen_context", "A code editor with a Rust file open"),
+    ("recent_messages", "Falken: Shall we play a game?\nJoshua: HOW ABOUT A NICE GAME OF CHESS?"),
+    ("time", "Saturday 2026-10-17 14:05"),
+    ("active_app", "Visual Studio Code"),
+];
+
+/// Values supplied by the frontend; the rest come from config and the system
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PromptContext {
+    /// Overrides the persona name from config
+    #[serde(default)]
+    pub persona: Option<String>,
+    /// Overrides the user name from config
+    #[serde(default)]
+    pub user: Option<String>,
+    #[serde(default)]
+    pub memory: Option<String>,
+    #[serde(default)]
+    pub screen_context: Option<String>,
+    #[serde(default)]
+    pub recent_messages: Vec<PromptMessage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptMessage {
+    pub role: String,
+    pub content: String,
+}
+
+/// Name and wake word in effect: the active persona's, else the plain config settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EffectivePersona {
+    pub name: String,
+    pub wake_word: String,
+    /// Active persona, None when the plain config settings apply
+    pub persona: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptPreview {
+    pub rendered: String,
+    /// Syntax problems and unknown variables; empty when the template is valid
+    pub errors: Vec<String>,
+}
+
+/// Parsed template
+/// `{{name}}` inserts a variable, `{{#name}}...{{/name}}` is kept only when the variable is non-empty.
+#[derive(Debug)]
+enum Node {
+    Text(String),
+    Variable(String),
+    Section(String, Vec<Node>),
+}
+
+fn check_variable(name: &str, errors: &mut Vec<String>) {
+    if !VARIABLES.iter().any(|(known, _)| *known == name) {
+        errors.push(format!("Unknown variable: {{{{{}}}}}", name));
+    }
+}
+
+fn parse(template: &str) -> (Vec<Node>, Vec<String>) {
+    // Open sections, innermost last; the first entry is the top level
+    let mut stack: Vec<(String, Vec<Node>)> = vec![(String::new(), Vec::new())];
+    let mut errors = Vec::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        let nodes = &mut stack.last_mut().unwrap().1;
+        if start > 0 {
+            nodes.push(Node::Text(rest[..start].to_string()));
+        }
+
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            errors.push("'{{' is never closed".to_string());
+            nodes.push(Node::Text(rest[start..].to_string()));
+            rest = "";
+            break;
+        };
+        let tag = after[..end].trim();
+        rest = &after[end + 2..];
+
+        if let Some(name) = tag.strip_prefix('#') {
+            let name = name.trim();
+            check_variable(name, &mut errors);
+            stack.push((name.to_string(), Vec::new()));
+        } else if let Some(name) = tag.strip_prefix('/') {
+            let name = name.trim();
+            if stack.len() > 1 && stack.last().map(|(open, _)| open == name).unwrap_or(false) {
+                let (name, children) = stack.pop().unwrap();
+                stack.last_mut().unwrap().1.push(Node::Section(name, children));
+            } else {
+                errors.push(format!("{{{{/{}}}}} doesn't close an open section", name));
+            }
+        } else {
+            check_variable(tag, &mut errors);
+            nodes.push(Node::Variable(tag.to_string()));
+        }
+    }
+
+    if !rest.is_empty() {
+        stack.last_mut().unwrap().1.push(Node::Text(rest.to_string()));
+    }
+    while stack.len() > 1 {
+        let (name, children) = stack.pop().unwrap();
+        errors.push(format!("Section {{{{#{}}}}} is never closed", name));
+        stack.last_mut().unwrap().1.push(Node::Section(name, children));
+    }
+
+    (stack.pop().unwrap().1, errors)
+}
+
+fn render_nodes(nodes: &[Node], values: &HashMap<&str, String>, out: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Variable(name) => out.push_str(values.get(name.as_str()).map(String::as_str).unwrap_or("")),
+            Node::Section(name, children) => {
+                if values.get(name.as_str()).map(|v| !v.trim().is_empty()).unwrap_or(false) {
+                    render_nodes(children, values, out);
+                }
+            }
+        }
+    }
+}
+
+/// Render a template, returning the text and any problems found
+fn render(template: &str, values: &HashMap<&str, String>) -> (String, Vec<String>) {
+    let (nodes, errors) = parse(template);
+    let mut out = String::new();
+    render_nodes(&nodes, values, &mut out);
+    (out.trim().to_string(), errors)
+}
+
+fn prompts_dir(config: &AppConfig) -> PathBuf {
+    storage::get_brain_dir(config).join(PROMPTS_DIR)
+}
+
+/// Write any built-in template that isn't in the prompts folder yet
+fn ensure_default_templates(config: &AppConfig) {
+    let dir = prompts_dir(config);
+    if let Err(e) = fs::create_dir_all(&dir) {
+        eprintln!("Failed to create {}: {}", dir.display(), e);
+        return;
+    }
+    for (name, content) in DEFAULT_TEMPLATES {
+        let path = dir.join(name);
+        if !path.exists() {
+            if let Err(e) = fs::write(&path, content) {
+                eprintln!("Failed to write {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+/// Template names are plain file names inside the prompts folder
+fn validate_template_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        return Err(format!("Invalid template name: {}", name));
+    }
+    Ok(())
+}
+
+/// Load a template by file name, falling back to the built-in one of the same name
+fn load_template(config: &AppConfig, name: &str) -> Option<String> {
+    validate_template_name(name).ok()?;
+    fs::read_to_string(prompts_dir(config).join(name)).ok().or_else(|| {
+        DEFAULT_TEMPLATES
+            .iter()
+            .find(|(default, _)| *default == name)
+            .map(|(_, content)| content.to_string())
+    })
+}
+
+/// Template for "chat" or "screen" prompts: the active persona's, else the preset's default
+fn template_for(config: &AppConfig, kind: &str, preset: &str) -> Result<String, String> {
+    let preset = if preset == "cozy" { "cozy" } else { "retro" };
+    let default_name = format!("{}_{}.md", kind, preset);
+    let persona_template = config.active_persona().and_then(|persona| match kind {
+        "chat" => persona.chat_template.clone(),
+        _ => persona.screen_template.clone(),
+    });
+
+    let name = persona_template.unwrap_or(default_name.clone());
+    load_template(config, &name)
+        .or_else(|| load_template(config, &default_name))
+        .ok_or_else(|| format!("Template not found: {}", name))
+}
+
+fn values_for(app: &AppHandle, config: &AppConfig, context: PromptContext) -> HashMap<&'static str, String> {
+    let persona = context
+        .persona
+        .filter(|p| !p.trim().is_empty())
+        .unwrap_or_else(|| config.effective_persona_name().to_string());
+    let user = context
+        .user
+        .filter(|u| !u.trim().is_empty())
+        .unwrap_or_else(|| config.user_name.clone());
+
+    let recent_messages = context
+        .recent_messages
+        .iter()
+        .map(|m| {
+            let speaker = if m.role == "user" { &user } else { &persona };
+            format!("{}: {}", speaker, m.content)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let active_app = app
+        .try_state::<ActivityTracker>()
+        .and_then(|tracker| tracker.last_app.lock().ok().and_then(|app| app.clone()))
+        .unwrap_or_default();
+
+    HashMap::from([
+        ("persona_upper", persona.to_uppercase()),
+        ("persona", persona),
+        ("user_upper", user.to_uppercase()),
+        ("user", user),
+        ("memory", context.memory.unwrap_or_default()),
+        ("screen_context", context.screen_context.unwrap_or_default()),
+        ("recent_messages", recent_messages),
+        ("time", chrono::Local::now().format("%A %Y-%m-%d %H:%M").to_string()),
+        ("active_app", active_app),
+    ])
+}
+
+/// Render the system prompt for "chat" or "screen" analysis
+/// Uses the active persona's template, or the default for the UI preset ("retro" or "cozy").
+#[tauri::command]
+pub fn render_system_prompt(
+    app: AppHandle,
+    kind: String,
+    preset: String,
+    context: Option<PromptContext>,
+) -> Result<String, String> {
+    if kind != "chat" && kind != "screen" {
+        return Err(format!("Unknown prompt kind: {}", kind));
+    }
+    let config = storage::load_config(app.clone());
+    ensure_default_templates(&config);
+
+    let template = template_for(&config, &kind, &preset)?;
+    let values = values_for(&app, &config, context.unwrap_or_default());
+    let (rendered, errors) = render(&template, &values);
+    if !errors.is_empty() {
+        eprintln!("Prompt template problems: {}", errors.join("; "));
+    }
+    Ok(rendered)
+}
+
+/// Check a template and render it with sample values
+#[tauri::command]
+pub fn preview_prompt_template(template: String) -> PromptPreview {
+    let values: HashMap<&str, String> = VARIABLES
+        .iter()
+        .map(|(name, sample)| (*name, sample.to_string()))
+        .collect();
+    let (rendered, errors) = render(&template, &values);
+    PromptPreview { rendered, errors }
+}
+
+/// List template files in the brain's prompts folder
+#[tauri::command]
+pub fn list_prompt_templates(app: AppHandle) -> Result<Vec<String>, String> {
+    let config = storage::load_config(app);
+    ensure_default_templates(&config);
+
+    let mut names: Vec<String> = fs::read_dir(prompts_dir(&config))
+        .map_err(|e| e.to_string())?
+        .flatten()
+        .filter(|entry| entry.path().is_file())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| !name.starts_with('.'))
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+/// Read a template file
+#[tauri::command]
+pub fn load_prompt_template(app: AppHandle, name: String) -> Result<String, String> {
+    let config = storage::load_config(app);
+    load_template(&config, &name).ok_or_else(|| format!("Template not found: {}", name))
+}
+
+/// Write a template file, refusing templates with errors
+#[tauri::command]
+pub fn save_prompt_template(app: AppHandle, name: String, content: String) -> Result<(), String> {
+    validate_template_name(&name)?;
+    let (_, errors) = parse(&content);
+    if !errors.is_empty() {
+        return Err(errors.join("; "));
+    }
+
+    let dir = prompts_dir(&storage::load_config(app));
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    fs::write(dir.join(&name), content).map_err(|e| e.to_string())
+}
+
+/// List configured personas
+#[tauri::command]
+pub fn list_personas(app: AppHandle) -> Vec<Persona> {
+    storage::load_config(app).personas
+}
+
+/// Add or replace a persona
+#[tauri::command]
+pub fn save_persona(app: AppHandle, persona: Persona) -> Result<(), String> {
+    if persona.name.trim().is_empty() {
+        return Err("Persona name is required".to_string());
+    }
+    if persona.wake_word.trim().is_empty() {
+        return Err("Wake word is required".to_string());
+    }
+    for template in [&persona.chat_template, &persona.screen_template].into_iter().flatten() {
+        validate_template_name(template)?;
+    }
+
+    let mut config = storage::load_config(app.clone());
+    match config.personas.iter_mut().find(|p| p.name == persona.name) {
+        Some(existing) => *existing = persona,
+        None => config.personas.push(persona),
+    }
+    storage::save_config(app, config)
+}
+
+/// Delete a persona (the active one falls back to the plain config settings)
+#[tauri::command]
+pub fn delete_persona(app: AppHandle, name: String) -> Result<(), String> {
+    let mut config = storage::load_config(app.clone());
+    config.personas.retain(|p| p.name != name);
+    if config.active_persona.as_ref() == Some(&name) {
+        config.active_persona = None;
+    }
+    storage::save_config(app, config)
+}
+
+/// Switch persona; its name and wake word are used in place of the config settings, which are left as they are
+/// None goes back to those settings and the preset's default templates.
+#[tauri::command]
+pub fn set_active_persona(app: AppHandle, name: Option<String>) -> Result<(), String> {
+    let mut config = storage::load_config(app.clone());
+    if let Some(name) = &name {
+        if !config.personas.iter().any(|p| &p.name == name) {
+            return Err(format!("Unknown persona: {}", name));
+        }
+    }
+    config.active_persona = name;
+    storage::save_config(app, config)
+}
+
+/// Name and wake word in effect, for display and voice activation
+#[tauri::command]
+pub fn get_effective_persona(app: AppHandle) -> EffectivePersona {
+    let config = storage::load_config(app);
+    EffectivePersona {
+        name: config.effective_persona_name().to_string(),
+        wake_word: config.effective_wake_word().to_string(),
+        persona: config.active_persona().map(|p| p.name.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(pairs: &[(&'static str, &str)]) -> HashMap<&'static str, String> {
+        pairs.iter().map(|(name, value)| (*name, value.to_string())).collect()
+    }
+
+    #[test]
+    fn variables_are_filled_in() {
+        let (rendered, errors) = render(
+            "I am {{persona}}, talking to {{ user_upper }}.",
+            &values(&[("persona", "Joshua"), ("user_upper", "FALKEN")]),
+        );
+        assert_eq!(rendered, "I am Joshua, talking to FALKEN.");
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn sections_need_a_non_empty_variable() {
+        let template = "Hi.{{#memory}}\nYou know:\n{{memory}}{{/memory}}";
+        let (with, _) = render(template, &values(&[("memory", "- Likes chess")]));
+        assert_eq!(with, "Hi.\nYou know:\n- Likes chess");
+        let (blank, _) = render(template, &values(&[("memory", "  ")]));
+        assert_eq!(blank, "Hi.");
+        let (missing, _) = render(template, &values(&[]));
+        assert_eq!(missing, "Hi.");
+    }
+
+    #[test]
+    fn sections_nest() {
+        let template = "{{#user}}[{{#memory}}{{user}}: {{memory}}{{/memory}}]{{/user}}";
+        let (rendered, errors) = render(template, &values(&[("user", "Falken")]));
+        assert_eq!(rendered, "[]");
+        assert!(errors.is_empty());
+        let (rendered, _) = render(template, &values(&[("user", "Falken"), ("memory", "chess")]));
+        assert_eq!(rendered, "[Falken: chess]");
+    }
+
+    #[test]
+    fn problems_are_reported_and_text_is_kept() {
+        let (_, errors) = parse("{{nickname}}");
+        assert_eq!(errors, ["Unknown variable: {{nickname}}"]);
+
+        let (rendered, errors) = render("Hello {{persona", &values(&[("persona", "Joshua")]));
+        assert_eq!(rendered, "Hello {{persona");
+        assert_eq!(errors, ["'{{' is never closed"]);
+
+        let (_, errors) = parse("{{#memory}}open");
+        assert_eq!(errors, ["Section {{#memory}} is never closed"]);
+        let (_, errors) = parse("{{#memory}}{{/user}}{{/memory}}");
+        assert_eq!(errors, ["{{/user}} doesn't close an open section"]);
+    }
+
+    #[test]
+    fn sample_values_cover_every_variable() {
+        let template: String = VARIABLES.iter().map(|(name, _)| format!("{{{{{}}}}}\n", name)).collect();
+        let preview = preview_prompt_template(template);
+        assert!(preview.errors.is_empty());
+        assert!(preview.rendered.contains("JOSHUA"));
+    }
+
+    #[test]
+    fn active_persona_overlays_the_config_without_changing_it() {
+        let mut config = AppConfig {
+            persona_name: "Joshua".to_string(),
+            wake_word: "Joshua".to_string(),
+            personas: vec![Persona {
+                name: "Pixel".to_string(),
+                wake_word: "Hey Pixel".to_string(),
+                chat_template: None,
+                screen_template: None,
+                voice: None,
+            }],
+            ..Default::default()
+        };
+        config.active_persona = Some("Pixel".to_string());
+        assert_eq!(config.effective_persona_name(), "Pixel");
+        assert_eq!(config.effective_wake_word(), "Hey Pixel");
+
+        // Switching off, or deleting the persona, goes back to the settings
+        config.active_persona = None;
+        assert_eq!(config.effective_persona_name(), "Joshua");
+        config.active_persona = Some("Pixel".to_string());
+        config.personas.clear();
+        assert_eq!(config.effective_wake_word(), "Joshua");
+    }
+}
@@ -0,0 +1,428 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+use super::capture;
+use super::storage;
+use super::worker::Generation;
+
+/// Frames are downscaled to this width before being kept in memory
+const FRAME_MAX_WIDTH: u32 = 640;
+/// JPEG quality for buffered frames
+const FRAME_JPEG_QUALITY: u8 = 55;
+/// Hard cap on buffered frame data, regardless of fps/duration
+const MAX_BUFFER_BYTES: usize = 32 * 1024 * 1024;
+
+/// Replay buffer settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplaySettings {
+    /// Frames captured per second
+    pub fps: f32,
+    /// Seconds of history to keep
+    pub seconds: u32,
+    /// Monitor to sample (None = primary)
+    pub monitor_index: Option<usize>,
+}
+
+impl ReplaySettings {
+    /// Clamp settings to a low-rate, bounded range; a rate that isn't a number gets the default
+    fn clamped(mut self) -> Self {
+        if !self.fps.is_finite() {
+            self.fps = Self::default().fps;
+        }
+        self.fps = self.fps.clamp(0.2, 5.0);
+        self.seconds = self.seconds.clamp(5, 120);
+        self
+    }
+
+    fn max_frames(&self) -> usize {
+        ((self.fps * self.seconds as f32).ceil() as usize).max(1)
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs_f32(1.0 / self.fps)
+    }
+}
+
+impl Default for ReplaySettings {
+    fn default() -> Self {
+        Self {
+            fps: 1.0,
+            seconds: 30,
+            monitor_index: None,
+        }
+    }
+}
+
+/// A single buffered frame, kept JPEG-encoded to bound memory
+struct ReplayFrame {
+    timestamp_ms: u64,
+    jpeg: Vec<u8>,
+}
+
+/// Managed state for the replay ring buffer
+pub struct ReplayBuffer {
+    frames: Mutex<VecDeque<ReplayFrame>>,
+    settings: Mutex<ReplaySettings>,
+    running: AtomicBool,
+    sampler: Generation,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReplayStatus {
+    pub running: bool,
+    pub fps: f32,
+    pub seconds: u32,
+    pub monitor_index: Option<usize>,
+    pub frame_count: usize,
+    pub buffered_bytes: usize,
+    /// Seconds between the oldest and newest buffered frame
+    pub span_seconds: f32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReplayFrameData {
+    pub timestamp_ms: u64,
+    /// Base64 encoded JPEG
+    pub image: String,
+}
+
+impl ReplayBuffer {
+    pub fn new() -> Self {
+        Self {
+            frames: Mutex::new(VecDeque::new()),
+            settings: Mutex::new(ReplaySettings::default()),
+            running: AtomicBool::new(false),
+            sampler: Generation::default(),
+        }
+    }
+
+    fn push_frame(&self, frame: ReplayFrame) -> Result<(), String> {
+        let max_frames = self.settings.lock().map_err(|e| e.to_string())?.max_frames();
+        let mut frames = self.frames.lock().map_err(|e| e.to_string())?;
+
+        frames.push_back(frame);
+
+        // Evict oldest frames until both the frame and byte budgets are satisfied
+        let mut total: usize = frames.iter().map(|f| f.jpeg.len()).sum();
+        while frames.len() > max_frames || (total > MAX_BUFFER_BYTES && frames.len() > 1) {
+            if let Some(old) = frames.pop_front() {
+                total -= old.jpeg.len();
+            }
+        }
+
+        Ok(())
+    }
+
+    fn status(&self) -> Result<ReplayStatus, String> {
+        let settings = self.settings.lock().map_err(|e| e.to_string())?.clone();
+        let frames = self.frames.lock().map_err(|e| e.to_string())?;
+
+        let span_seconds = match (frames.front(), frames.back()) {
+            // The wall clock can step back between frames
+            (Some(first), Some(last)) => last.timestamp_ms.saturating_sub(first.timestamp_ms) as f32 / 1000.0,
+            _ => 0.0,
+        };
+
+        Ok(ReplayStatus {
+            running: self.running.load(Ordering::SeqCst),
+            fps: settings.fps,
+            seconds: settings.seconds,
+            monitor_index: settings.monitor_index,
+            frame_count: frames.len(),
+            buffered_bytes: frames.iter().map(|f| f.jpeg.len()).sum(),
+            span_seconds,
+        })
+    }
+}
+
+impl Default for ReplayBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Start sampling frames in the background, replacing any running sampler
+pub fn start(app: &AppHandle, settings: ReplaySettings) -> Result<(), String> {
+    let state = app.state::<ReplayBuffer>();
+    let settings = settings.clamped();
+    let interval = settings.interval();
+
+    *state.settings.lock().map_err(|e| e.to_string())? = settings.clone();
+    state.frames.lock().map_err(|e| e.to_string())?.clear();
+    let generation = state.sampler.next();
+    state.running.store(true, Ordering::SeqCst);
+
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let state = app.state::<ReplayBuffer>();
+        while state.sampler.is_current(generation) {
+            let frame = capture::capture_rgba(settings.monitor_index)
+                .map(|img| capture::resize_to_width(img, FRAME_MAX_WIDTH))
+                .and_then(|img| capture::encode_jpeg(img, FRAME_JPEG_QUALITY));
+
+            match frame {
+                Ok(jpeg) => {
+                    let _ = state.push_frame(ReplayFrame {
+                        timestamp_ms: now_ms(),
+                        jpeg,
+                    });
+                }
+                Err(e) => eprintln!("Replay buffer capture failed: {}", e),
+            }
+
+            std::thread::sleep(interval);
+        }
+    });
+
+    Ok(())
+}
+
+/// Start the replay buffer from the user's config
+pub fn start_from_config(app: &AppHandle, config: &storage::AppConfig) -> Result<(), String> {
+    start(
+        app,
+        ReplaySettings {
+            fps: config.replay_fps,
+            seconds: config.replay_seconds,
+            monitor_index: config.selected_monitor,
+        },
+    )
+}
+
+/// Start (or restart) the replay buffer
+#[tauri::command]
+pub fn start_replay_buffer(
+    app: AppHandle,
+    fps: Option<f32>,
+    seconds: Option<u32>,
+) -> Result<ReplayStatus, String> {
+    let config = storage::load_config(app.clone());
+    start(
+        &app,
+        ReplaySettings {
+            fps: fps.unwrap_or(config.replay_fps),
+            seconds: seconds.unwrap_or(config.replay_seconds),
+            monitor_index: config.selected_monitor,
+        },
+    )?;
+
+    app.state::<ReplayBuffer>().status()
+}
+
+/// Stop sampling and drop buffered frames
+#[tauri::command]
+pub fn stop_replay_buffer(state: tauri::State<ReplayBuffer>) -> Result<(), String> {
+    state.sampler.stop();
+    state.running.store(false, Ordering::SeqCst);
+    state.frames.lock().map_err(|e| e.to_string())?.clear();
+    Ok(())
+}
+
+/// Get replay buffer status
+#[tauri::command]
+pub fn get_replay_status(state: tauri::State<ReplayBuffer>) -> Result<ReplayStatus, String> {
+    state.status()
+}
+
+/// Save the buffered frames as an animated GIF in the captures folder
+/// Returns the path of the written file
+#[tauri::command]
+pub async fn save_replay_clip(app: AppHandle) -> Result<String, String> {
+    let (fps, jpegs) = {
+        let state = app.state::<ReplayBuffer>();
+        let fps = state.settings.lock().map_err(|e| e.to_string())?.fps;
+        // Decode outside of the frames lock so sampling isn't blocked for long
+        let frames = state.frames.lock().map_err(|e| e.to_string())?;
+        let jpegs: Vec<Vec<u8>> = frames.iter().map(|f| f.jpeg.clone()).collect();
+        (fps, jpegs)
+    };
+
+    if jpegs.is_empty() {
+        return Err("Replay buffer is empty".to_string());
+    }
+
+    // Encoding a full buffer takes seconds, keep it off the IPC thread
+    tauri::async_runtime::spawn_blocking(move || {
+        let config = storage::load_config(app);
+        storage::ensure_data_directories(&config)?;
+        let path = storage::get_captures_dir(&config).join(format!("replay_{}.gif", now_ms()));
+
+        let file = fs::File::create(&path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        let mut encoder = GifEncoder::new_with_speed(file, 10);
+        encoder
+            .set_repeat(Repeat::Infinite)
+            .map_err(|e| format!("Failed to encode GIF: {}", e))?;
+
+        let delay = Delay::from_saturating_duration(Duration::from_secs_f32(1.0 / fps));
+        for jpeg in jpegs {
+            let img = image::load_from_memory(&jpeg)
+                .map_err(|e| format!("Failed to decode frame: {}", e))?
+                .to_rgba8();
+            encoder
+                .encode_frame(Frame::from_parts(img, 0, 0, delay))
+                .map_err(|e| format!("Failed to encode GIF: {}", e))?;
+        }
+
+        Ok(path.to_string_lossy().to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Get an evenly spaced strip of buffered frames for multi-image AI analysis
+#[tauri::command]
+pub fn get_replay_frames(
+    state: tauri::State<ReplayBuffer>,
+    max_frames: Option<usize>,
+) -> Result<Vec<ReplayFrameData>, String> {
+    let frames = state.frames.lock().map_err(|e| e.to_string())?;
+    Ok(sample_frames(&frames, max_frames.unwrap_or(8)))
+}
+
+/// Pick up to `max_frames` frames spread evenly across the buffer, always including the latest
+fn sample_frames(frames: &VecDeque<ReplayFrame>, max_frames: usize) -> Vec<ReplayFrameData> {
+    let count = frames.len();
+    if count == 0 || max_frames == 0 {
+        return vec![];
+    }
+
+    let take = max_frames.min(count);
+    (0..take)
+        .map(|i| {
+            let index = if take == 1 {
+                count - 1
+            } else {
+                i * (count - 1) / (take - 1)
+            };
+            let frame = &frames[index];
+            ReplayFrameData {
+                timestamp_ms: frame.timestamp_ms,
+                image: STANDARD.encode(&frame.jpeg),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(timestamp_ms: u64, bytes: usize) -> ReplayFrame {
+        ReplayFrame {
+            timestamp_ms,
+            jpeg: vec![0; bytes],
+        }
+    }
+
+    fn buffer(fps: f32, seconds: u32) -> ReplayBuffer {
+        let buffer = ReplayBuffer::new();
+        *buffer.settings.lock().unwrap() = ReplaySettings {
+            fps,
+            seconds,
+            monitor_index: None,
+        }
+        .clamped();
+        buffer
+    }
+
+    #[test]
+    fn settings_are_clamped_and_nan_falls_back() {
+        let settings = ReplaySettings {
+            fps: f32::NAN,
+            seconds: 1000,
+            monitor_index: None,
+        }
+        .clamped();
+        assert_eq!(settings.fps, 1.0);
+        assert_eq!(settings.seconds, 120);
+        assert_eq!(settings.interval(), Duration::from_secs(1));
+
+        let fast = ReplaySettings {
+            fps: f32::INFINITY,
+            seconds: 0,
+            monitor_index: None,
+        }
+        .clamped();
+        assert_eq!((fast.fps, fast.seconds), (1.0, 5));
+        assert_eq!(ReplaySettings { fps: 0.01, ..fast }.clamped().fps, 0.2);
+    }
+
+    #[test]
+    fn max_frames_covers_the_whole_window() {
+        let settings = |fps, seconds| ReplaySettings {
+            fps,
+            seconds,
+            monitor_index: None,
+        };
+        assert_eq!(settings(1.0, 30).max_frames(), 30);
+        assert_eq!(settings(0.2, 7).max_frames(), 2);
+        assert_eq!(settings(0.0, 0).max_frames(), 1);
+    }
+
+    #[test]
+    fn oldest_frames_are_evicted_past_the_frame_count() {
+        let buffer = buffer(1.0, 5);
+        for i in 0..8 {
+            buffer.push_frame(frame(i * 1000, 10)).unwrap();
+        }
+
+        let status = buffer.status().unwrap();
+        assert_eq!(status.frame_count, 5);
+        assert_eq!(status.buffered_bytes, 50);
+        assert_eq!(status.span_seconds, 4.0);
+        assert_eq!(buffer.frames.lock().unwrap()[0].timestamp_ms, 3000);
+    }
+
+    #[test]
+    fn oldest_frames_are_evicted_past_the_byte_budget() {
+        let buffer = buffer(5.0, 120);
+        let big = MAX_BUFFER_BYTES / 3 + 1;
+        for i in 0..4 {
+            buffer.push_frame(frame(i, big)).unwrap();
+        }
+        assert_eq!(buffer.status().unwrap().frame_count, 2);
+
+        // A single frame over the budget is still kept
+        buffer.push_frame(frame(9, MAX_BUFFER_BYTES + 1)).unwrap();
+        assert_eq!(buffer.status().unwrap().frame_count, 1);
+    }
+
+    #[test]
+    fn clock_stepping_back_gives_no_span() {
+        let buffer = buffer(1.0, 30);
+        buffer.push_frame(frame(5000, 1)).unwrap();
+        buffer.push_frame(frame(1000, 1)).unwrap();
+        assert_eq!(buffer.status().unwrap().span_seconds, 0.0);
+    }
+
+    #[test]
+    fn sampled_frames_are_spread_out_and_end_with_the_latest() {
+        let frames: VecDeque<ReplayFrame> = (0..10).map(|i| frame(i, 1)).collect();
+        let times = |max| -> Vec<u64> {
+            sample_frames(&frames, max)
+                .iter()
+                .map(|f| f.timestamp_ms)
+                .collect()
+        };
+
+        assert_eq!(times(4), [0, 3, 6, 9]);
+        assert_eq!(times(1), [9]);
+        assert_eq!(times(20).len(), 10);
+        assert!(times(0).is_empty());
+    }
+}
@@ -0,0 +1,489 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use rand_core::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::AppHandle;
+
+use super::storage;
+
+/// Service name used for OS keyring entries
+const KEYRING_SERVICE: &str = "wopr";
+/// Environment variable holding an optional passphrase for the encrypted file store
+const PASSPHRASE_ENV: &str = "WOPR_SECRETS_PASSPHRASE";
+const PBKDF2_ROUNDS: u32 = 100_000;
+const NONCE_LEN: usize = 12;
+/// Generated key used when there is no passphrase and no machine ID
+const LOCAL_KEY_FILE: &str = "secrets.key";
+/// Encrypted into the store so a different master secret is noticed before any key is used
+const KEY_CHECK: &str = "wopr-secrets";
+
+/// Where a secret is stored
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretBackend {
+    /// macOS Keychain, Windows Credential Manager or Secret Service on Linux
+    Keyring,
+    /// AES-GCM encrypted file in the app data directory
+    File,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKeyStatus {
+    pub provider: String,
+    pub is_set: bool,
+    pub backend: Option<SecretBackend>,
+    /// Why the file store couldn't be checked for this key
+    pub error: Option<String>,
+}
+
+/// On-disk format of the encrypted fallback store
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SecretFile {
+    /// Base64 salt for key derivation
+    salt: String,
+    /// Provider -> base64(nonce || ciphertext)
+    entries: HashMap<String, String>,
+    /// `KEY_CHECK` encrypted like an entry; missing in stores written before it was added
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    check: Option<String>,
+}
+
+fn secrets_path(app: &AppHandle) -> PathBuf {
+    storage::get_internal_app_dir(app).join("secrets.json")
+}
+
+fn keyring_entry(provider: &str) -> Option<keyring::Entry> {
+    keyring::Entry::new(KEYRING_SERVICE, provider).ok()
+}
+
+/// Passphrase from the environment, else an ID tied to this machine, else a random key
+/// generated on first use and kept next to the store, readable only by the user
+fn master_secret(app: &AppHandle) -> Result<String, String> {
+    match std::env::var(PASSPHRASE_ENV) {
+        Ok(passphrase) if !passphrase.is_empty() => Ok(passphrase),
+        _ => match machine_uid::get() {
+            Ok(id) if !id.trim().is_empty() => Ok(id),
+            _ => local_key(app),
+        },
+    }
+}
+
+fn local_key(app: &AppHandle) -> Result<String, String> {
+    let path = storage::get_internal_app_dir(app).join(LOCAL_KEY_FILE);
+    if let Ok(key) = fs::read_to_string(&path) {
+        if !key.trim().is_empty() {
+            return Ok(key.trim().to_string());
+        }
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    let key = STANDARD.encode(bytes);
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(&path)
+        .map_err(|e| format!("Failed to create secret key {}: {}", path.display(), e))?;
+    file.write_all(key.as_bytes())
+        .map_err(|e| format!("Failed to write secret key {}: {}", path.display(), e))?;
+    Ok(key)
+}
+
+/// Key derived for the store's salt, kept so the master secret is only looked up
+/// and PBKDF2 only runs once per session
+struct DerivedKey {
+    salt: Vec<u8>,
+    key: [u8; 32],
+}
+
+static DERIVED_KEY: Mutex<Option<DerivedKey>> = Mutex::new(None);
+
+fn derive_cipher(app: &AppHandle, salt: &[u8]) -> Result<Aes256Gcm, String> {
+    let mut cached = DERIVED_KEY.lock().map_err(|e| e.to_string())?;
+    let key = match cached.as_ref() {
+        Some(derived) if derived.salt == salt => derived.key,
+        _ => {
+            let key = derive_key(&master_secret(app)?, salt);
+            *cached = Some(DerivedKey {
+                salt: salt.to_vec(),
+                key,
+            });
+            key
+        }
+    };
+    Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)))
+}
+
+fn derive_key(master_secret: &str, salt: &[u8]) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(master_secret.as_bytes(), salt, PBKDF2_ROUNDS, &mut key);
+    key
+}
+
+/// base64(nonce || ciphertext)
+fn encrypt(cipher: &Aes256Gcm, plaintext: &str) -> Result<String, String> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext.as_bytes())
+        .map_err(|e| format!("Failed to encrypt secret: {}", e))?;
+
+    let mut data = nonce.to_vec();
+    data.extend_from_slice(&ciphertext);
+    Ok(STANDARD.encode(data))
+}
+
+/// None when the data is malformed or was encrypted with another key
+fn decrypt(cipher: &Aes256Gcm, encoded: &str) -> Option<String> {
+    let data = STANDARD.decode(encoded).ok()?;
+    if data.len() <= NONCE_LEN {
+        return None;
+    }
+
+    let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+    let plaintext = cipher.decrypt(Nonce::from_slice(nonce), ciphertext).ok()?;
+    String::from_utf8(plaintext).ok()
+}
+
+/// Whether `cipher` is the key the store was written with
+/// Stores without a check value pass if any entry decrypts, or if they are empty.
+fn key_matches(cipher: &Aes256Gcm, file: &SecretFile) -> bool {
+    match &file.check {
+        Some(check) => decrypt(cipher, check).as_deref() == Some(KEY_CHECK),
+        None => file.entries.is_empty() || file.entries.values().any(|entry| decrypt(cipher, entry).is_some()),
+    }
+}
+
+fn key_changed(path: &Path) -> String {
+    format!(
+        "The master secret for {} has changed ({} or the machine ID), so stored API keys can't be decrypted. \
+         Restore it, or remove the file and enter the keys again",
+        path.display(),
+        PASSPHRASE_ENV
+    )
+}
+
+/// A store that exists but can't be read is an error rather than an empty one: saving over it
+/// would re-salt it and lose every key in it.
+fn read_file(path: &Path) -> Result<SecretFile, String> {
+    if !path.exists() {
+        return Ok(SecretFile::default());
+    }
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_str(&content).map_err(|e| {
+        format!(
+            "{} is damaged ({}); API keys in it can't be read or changed until it is fixed or removed",
+            path.display(),
+            e
+        )
+    })
+}
+
+/// Written readable only by the user, also when the file already existed with wider permissions
+fn write_file(path: &Path, file: &SecretFile) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_string_pretty(file).map_err(|e| e.to_string())?;
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        if path.exists() {
+            fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+                .map_err(|e| format!("Failed to restrict {}: {}", path.display(), e))?;
+        }
+    }
+    let mut out = options
+        .open(path)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    out.write_all(json.as_bytes())
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Salt of a store that has one; an empty store gets a new salt
+fn store_salt(path: &Path, file: &mut SecretFile) -> Result<Vec<u8>, String> {
+    match STANDARD.decode(&file.salt) {
+        Ok(salt) if !salt.is_empty() => Ok(salt),
+        _ if file.entries.is_empty() => {
+            let mut salt = vec![0u8; 16];
+            OsRng.fill_bytes(&mut salt);
+            file.salt = STANDARD.encode(&salt);
+            file.check = None;
+            Ok(salt)
+        }
+        _ => Err(format!("{} is damaged (invalid salt); API keys in it can't be read", path.display())),
+    }
+}
+
+/// Add or replace an entry; refused when the store was written with another master secret
+fn set_entry(
+    path: &Path,
+    file: &mut SecretFile,
+    cipher: &Aes256Gcm,
+    provider: &str,
+    secret: &str,
+) -> Result<(), String> {
+    if !key_matches(cipher, file) {
+        return Err(key_changed(path));
+    }
+    if file.check.is_none() {
+        file.check = Some(encrypt(cipher, KEY_CHECK)?);
+    }
+    file.entries.insert(provider.to_string(), encrypt(cipher, secret)?);
+    Ok(())
+}
+
+fn file_get(app: &AppHandle, provider: &str) -> Result<Option<String>, String> {
+    let path = secrets_path(app);
+    let mut file = read_file(&path)?;
+    if !file.entries.contains_key(provider) {
+        return Ok(None);
+    }
+
+    let salt = store_salt(&path, &mut file)?;
+    let cipher = derive_cipher(app, &salt)?;
+    if !key_matches(&cipher, &file) {
+        return Err(key_changed(&path));
+    }
+    Ok(file.entries.get(provider).and_then(|entry| decrypt(&cipher, entry)))
+}
+
+fn file_set(app: &AppHandle, provider: &str, secret: &str) -> Result<(), String> {
+    let path = secrets_path(app);
+    let mut file = read_file(&path)?;
+    let salt = store_salt(&path, &mut file)?;
+    let cipher = derive_cipher(app, &salt)?;
+    set_entry(&path, &mut file, &cipher, provider, secret)?;
+    write_file(&path, &file)
+}
+
+fn file_delete(app: &AppHandle, provider: &str) -> Result<(), String> {
+    let path = secrets_path(app);
+    let mut file = read_file(&path)?;
+    if file.entries.remove(provider).is_some() {
+        write_file(&path, &file)?;
+    }
+    Ok(())
+}
+
+/// Read the API key for a provider, returning where it was found
+fn lookup(app: &AppHandle, provider: &str) -> Result<Option<(String, SecretBackend)>, String> {
+    if let Some(secret) = keyring_entry(provider).and_then(|entry| entry.get_password().ok()) {
+        return Ok(Some((secret, SecretBackend::Keyring)));
+    }
+    Ok(file_get(app, provider)?.map(|secret| (secret, SecretBackend::File)))
+}
+
+/// Get the stored API key for a provider
+pub fn get_api_key(app: &AppHandle, provider: &str) -> Option<String> {
+    match lookup(app, provider) {
+        Ok(found) => found.map(|(secret, _)| secret),
+        Err(e) => {
+            eprintln!("Failed to read the {} API key: {}", provider, e);
+            None
+        }
+    }
+}
+
+/// Store an API key, preferring the OS keyring and falling back to the encrypted file
+pub fn store_api_key(app: &AppHandle, provider: &str, secret: &str) -> Result<SecretBackend, String> {
+    let in_keyring = keyring_entry(provider)
+        .map(|entry| entry.set_password(secret).is_ok())
+        .unwrap_or(false);
+
+    if in_keyring {
+        // Don't leave an older copy behind in the file store
+        file_delete(app, provider)?;
+        Ok(SecretBackend::Keyring)
+    } else {
+        file_set(app, provider, secret)?;
+        Ok(SecretBackend::File)
+    }
+}
+
+/// Remove an API key from every backend
+pub fn remove_api_key(app: &AppHandle, provider: &str) -> Result<(), String> {
+    if let Some(entry) = keyring_entry(provider) {
+        let _ = entry.delete_credential();
+    }
+    file_delete(app, provider)
+}
+
+/// Move a plaintext `api_key` from config.yaml into the secret store
+pub fn migrate_plaintext_key(app: &AppHandle) -> Result<(), String> {
+    let mut config = storage::load_config(app.clone());
+    let Some(secret) = config.api_key.take().filter(|k| !k.is_empty()) else {
+        return Ok(());
+    };
+
+    let provider = config.ai_provider.clone();
+    store_api_key(app, &provider, &secret)?;
+    if !config.api_key_providers.contains(&provider) {
+        config.api_key_providers.push(provider);
+    }
+
+    // api_key is never serialized, so this rewrites config.yaml without it
+    storage::save_config(app.clone(), config)
+}
+
+/// Store the API key for a provider
+#[tauri::command]
+pub fn set_api_key(app: AppHandle, provider: String, key: String) -> Result<ApiKeyStatus, String> {
+    if key.is_empty() {
+        return Err("API key is empty".to_string());
+    }
+
+    let backend = store_api_key(&app, &provider, &key)?;
+
+    let mut config = storage::load_config(app.clone());
+    if !config.api_key_providers.contains(&provider) {
+        config.api_key_providers.push(provider.clone());
+        storage::save_config(app, config)?;
+    }
+
+    Ok(ApiKeyStatus {
+        provider,
+        is_set: true,
+        backend: Some(backend),
+        error: None,
+    })
+}
+
+/// Remove the API key for a provider
+#[tauri::command]
+pub fn delete_api_key(app: AppHandle, provider: String) -> Result<(), String> {
+    remove_api_key(&app, &provider)?;
+
+    let mut config = storage::load_config(app.clone());
+    config.api_key_providers.retain(|p| p != &provider);
+    storage::save_config(app, config)
+}
+
+/// Report which providers have a stored key (never the keys themselves)
+#[tauri::command]
+pub fn get_api_key_status(app: AppHandle, providers: Vec<String>) -> Vec<ApiKeyStatus> {
+    providers
+        .into_iter()
+        .map(|provider| {
+            let (backend, error) = match lookup(&app, &provider) {
+                Ok(found) => (found.map(|(_, backend)| backend), None),
+                Err(e) => (None, Some(e)),
+            };
+            ApiKeyStatus {
+                provider,
+                is_set: backend.is_some(),
+                backend,
+                error,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_files::{temp_file, temp_path};
+
+    fn cipher(master_secret: &str) -> Aes256Gcm {
+        Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&derive_key(master_secret, b"test-salt")))
+    }
+
+    #[test]
+    fn secrets_round_trip() {
+        let cipher = cipher("passphrase");
+        let encrypted = encrypt(&cipher, "sk-test-123").unwrap();
+        assert!(!encrypted.contains("sk-test"));
+        assert_eq!(decrypt(&cipher, &encrypted).as_deref(), Some("sk-test-123"));
+        // A fresh nonce every time
+        assert_ne!(encrypt(&cipher, "sk-test-123").unwrap(), encrypted);
+    }
+
+    #[test]
+    fn wrong_key_or_bad_data_doesnt_decrypt() {
+        let encrypted = encrypt(&cipher("passphrase"), "sk-test-123").unwrap();
+        assert_eq!(decrypt(&cipher("other"), &encrypted), None);
+        assert_eq!(decrypt(&cipher("passphrase"), "not base64!"), None);
+        assert_eq!(decrypt(&cipher("passphrase"), &STANDARD.encode([0u8; NONCE_LEN])), None);
+    }
+
+    #[test]
+    fn changed_master_secret_is_detected() {
+        let path = temp_path("secrets-changed.json");
+        let mut file = SecretFile::default();
+        set_entry(&path, &mut file, &cipher("passphrase"), "openai", "sk-1").unwrap();
+        assert!(file.check.is_some());
+        assert!(key_matches(&cipher("passphrase"), &file));
+        assert!(!key_matches(&cipher("other"), &file));
+
+        let err = set_entry(&path, &mut file, &cipher("other"), "anthropic", "sk-2").unwrap_err();
+        assert!(err.contains("master secret"));
+        assert_eq!(file.entries.len(), 1);
+    }
+
+    #[test]
+    fn stores_without_a_check_value_are_checked_against_their_entries() {
+        let path = temp_path("secrets-legacy.json");
+        let mut file = SecretFile::default();
+        file.entries.insert("openai".to_string(), encrypt(&cipher("passphrase"), "sk-1").unwrap());
+        assert!(!key_matches(&cipher("other"), &file));
+        assert!(key_matches(&cipher("passphrase"), &file));
+
+        set_entry(&path, &mut file, &cipher("passphrase"), "gemini", "g-1").unwrap();
+        assert!(file.check.is_some());
+    }
+
+    #[test]
+    fn damaged_store_is_an_error() {
+        let path = temp_file("secrets-damaged.json", "{\"salt\": ");
+        assert!(read_file(&path).unwrap_err().contains("damaged"));
+        assert!(read_file(&temp_path("secrets-missing.json")).unwrap().entries.is_empty());
+
+        let mut file = SecretFile {
+            salt: "%%%".to_string(),
+            ..Default::default()
+        };
+        file.entries.insert("openai".to_string(), "x".to_string());
+        assert!(store_salt(&path, &mut file).is_err());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn empty_store_gets_a_salt() {
+        let mut file = SecretFile::default();
+        let salt = store_salt(&temp_path("secrets-new.json"), &mut file).unwrap();
+        assert_eq!(salt.len(), 16);
+        assert_eq!(STANDARD.decode(&file.salt).unwrap(), salt);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn store_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = temp_file("secrets-private.json", "{}");
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        write_file(&path, &SecretFile::default()).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        assert!(read_file(&path).unwrap().entries.is_empty());
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -1,9 +1,13 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 use tauri::{AppHandle, Manager};
 use dirs;
 
+use super::secrets;
+use super::voice;
+
 /// Main application configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppConfig {
@@ -12,12 +16,29 @@ pub struct AppConfig {
     /// User's name for personalized responses
     #[serde(default = "default_user_name")]
     pub user_name: String,
-    /// AI provider (openai, anthropic, gemini)
+    /// AI provider (openai, anthropic, gemini, local)
     pub ai_provider: String,
-    /// AI API key
+    /// Legacy plaintext API key. Only read so it can be moved into the secret store,
+    /// never written back to config.yaml or returned to the webview
+    #[serde(default, skip_serializing)]
     pub api_key: Option<String>,
+    /// Providers that have a key in the secret store
+    #[serde(default)]
+    pub api_key_providers: Vec<String>,
+    /// Whether the current provider has a stored key (computed on load)
+    #[serde(default, skip_deserializing)]
+    pub has_api_key: bool,
     /// AI model to use
     pub ai_model: String,
+    /// Base URL of the local model server (Ollama, llama.cpp, LM Studio)
+    #[serde(default = "default_local_base_url")]
+    pub local_base_url: String,
+    /// Named provider profiles
+    #[serde(default)]
+    pub provider_profiles: Vec<ProviderProfile>,
+    /// Profile names tried in order; empty = use the single provider settings above
+    #[serde(default)]
+    pub fallback_chain: Vec<String>,
     /// Screen capture interval in milliseconds
     pub capture_interval_ms: u32,
     /// Whether vision/screen observation is enabled
@@ -55,12 +76,167 @@ pub struct AppConfig {
     /// Whether AI web search is enabled
     #[serde(default = "default_web_search_enabled")]
     pub web_search_enabled: bool,
+
+    /// Whether the screen replay buffer is running
+    #[serde(default)]
+    pub replay_enabled: bool,
+    /// Replay buffer capture rate (frames per second)
+    #[serde(default = "default_replay_fps")]
+    pub replay_fps: f32,
+    /// Seconds of screen history kept in the replay buffer
+    #[serde(default = "default_replay_seconds")]
+    pub replay_seconds: u32,
+
+    /// Daily AI spend cap in USD; screen analysis pauses once reached (None = no cap)
+    #[serde(default)]
+    pub daily_spend_cap: Option<f64>,
+    /// Monthly AI spend cap in USD (None = no cap)
+    #[serde(default)]
+    pub monthly_spend_cap: Option<f64>,
+
+    /// MCP servers whose tools and resources are offered to the assistant
+    #[serde(default)]
+    pub mcp_servers: Vec<McpServerConfig>,
+    /// Embedder for brain search: "builtin" (offline keyword matching, no model) or "local"
+    /// (OpenAI-compatible embeddings server, matches by meaning)
+    #[serde(default = "default_embedding_provider")]
+    pub embedding_provider: String,
+    /// Embeddings server (None = local_base_url)
+    #[serde(default)]
+    pub embedding_base_url: Option<String>,
+    /// Embedding model on the local server
+    #[serde(default = "default_embedding_model")]
+    pub embedding_model: String,
+    /// Days to keep conversations after their last message (None = forever)
+    #[serde(default)]
+    pub conversation_retention_days: Option<u32>,
+    /// Selectable personas
+    #[serde(default)]
+    pub personas: Vec<Persona>,
+    /// Name of the persona in use (None = persona_name, wake_word and the preset's templates)
+    #[serde(default)]
+    pub active_persona: Option<String>,
+    /// Web search backend: "duckduckgo", "searxng" or "vendor" (the AI provider's built-in search)
+    #[serde(default = "default_web_search_provider")]
+    pub web_search_provider: String,
+    /// SearXNG instance for the "searxng" provider
+    #[serde(default)]
+    pub searxng_url: Option<String>,
+    /// Speech recognition: "browser" (webview Web Speech API) or "native" (offline pipeline in the backend)
+    /// Offline needs the built-in Whisper, which is only in builds with the `whisper` feature, or a local
+    /// transcription server in `stt_base_url`; the default build has no built-in Whisper.
+    #[serde(default = "default_voice_engine")]
+    pub voice_engine: String,
+    /// Transcriber for the native pipeline: "whisper" (built-in whisper.cpp, only in builds with the
+    /// `whisper` feature) or "server" (OpenAI-compatible)
+    #[serde(default = "default_stt_engine")]
+    pub stt_engine: String,
+    /// ggml Whisper model file (None = models/ggml-base.en.bin in the app data folder)
+    #[serde(default)]
+    pub stt_model_path: Option<String>,
+    /// Transcription server, required by the "server" engine (chat servers like Ollama don't transcribe)
+    #[serde(default)]
+    pub stt_base_url: Option<String>,
+    /// Model name sent to the transcription server
+    #[serde(default = "default_stt_model")]
+    pub stt_model: String,
+    /// Spoken language code, or "auto" to detect it
+    #[serde(default = "default_stt_language")]
+    pub stt_language: String,
+    /// Whether assistant responses are read aloud
+    #[serde(default)]
+    pub tts_enabled: bool,
+    /// Speech is silenced without turning it off
+    #[serde(default)]
+    pub tts_muted: bool,
+    /// Speech synthesizer: "espeak" (eSpeak NG) or "piper"
+    #[serde(default = "default_tts_engine")]
+    pub tts_engine: String,
+    /// Synthesizer executable (None = espeak-ng or piper on PATH)
+    #[serde(default)]
+    pub tts_command: Option<String>,
+    /// eSpeak voice name, or Piper model name/path; personas can override it
+    #[serde(default = "default_tts_voice")]
+    pub tts_voice: String,
+    /// Run speech through the ring-modulated "robot" filter
+    #[serde(default = "default_tts_robot_filter")]
+    pub tts_robot_filter: bool,
+    /// Apps that count as distractions during pomodoro focus (matched case-insensitively by name)
+    #[serde(default)]
+    pub focus_distractions: Vec<String>,
+    /// Interfaces counted in the network totals (empty = every physical one); "en*" matches a prefix
+    #[serde(default)]
+    pub network_include: Vec<String>,
+    /// Interfaces never counted in the network totals, same patterns as `network_include`
+    #[serde(default)]
+    pub network_exclude: Vec<String>,
+    /// Count loopback and virtual interfaces (bridges, tunnels, containers) in the network totals
+    #[serde(default)]
+    pub network_include_virtual: bool,
+    /// Mount point of the disk in the headline gauge (None = the system disk)
+    #[serde(default)]
+    pub primary_disk: Option<String>,
+    /// Keep the system metrics history across restarts
+    #[serde(default)]
+    pub system_history_persist: bool,
+}
+
+fn default_local_base_url() -> String {
+    "http://localhost:11434".to_string()
+}
+
+fn default_embedding_provider() -> String {
+    "builtin".to_string()
+}
+
+fn default_embedding_model() -> String {
+    "nomic-embed-text".to_string()
+}
+
+fn default_web_search_provider() -> String {
+    "duckduckgo".to_string()
+}
+
+fn default_voice_engine() -> String {
+    "browser".to_string()
+}
+
+fn default_stt_engine() -> String {
+    if cfg!(feature = "whisper") { "whisper" } else { "server" }.to_string()
+}
+
+fn default_stt_model() -> String {
+    "whisper-1".to_string()
+}
+
+fn default_stt_language() -> String {
+    "en".to_string()
+}
+
+fn default_tts_engine() -> String {
+    "espeak".to_string()
+}
+
+fn default_tts_voice() -> String {
+    "en-us".to_string()
+}
+
+fn default_tts_robot_filter() -> bool {
+    true
 }
 
 fn default_web_search_enabled() -> bool {
     true
 }
 
+fn default_replay_fps() -> f32 {
+    1.0
+}
+
+fn default_replay_seconds() -> u32 {
+    30
+}
+
 fn default_screentime_enabled() -> bool {
     true
 }
@@ -83,6 +259,115 @@ pub struct ThemeConfig {
     pub glow_intensity: f32,
 }
 
+/// A named provider configuration that can take part in the fallback chain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderProfile {
+    /// Unique profile name
+    pub name: String,
+    /// AI provider (openai, anthropic, gemini, local)
+    pub provider: String,
+    /// Model to use
+    pub model: String,
+    /// Secret store entry holding the key (None = the provider name)
+    #[serde(default)]
+    pub key_ref: Option<String>,
+    /// Base URL override (local provider)
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Retries on rate limit, server error or timeout before moving to the next profile
+    #[serde(default = "default_profile_max_retries")]
+    pub max_retries: u32,
+    /// Seconds to wait for the first byte of an answer, and then between streamed chunks
+    #[serde(default = "default_profile_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Upper bound on generated tokens (None = whatever the request asks for)
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+}
+
+/// A selectable assistant personality
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Persona {
+    /// Unique name, also the persona name used in prompts
+    pub name: String,
+    /// Wake word for voice activation
+    pub wake_word: String,
+    /// Chat prompt template in brain/prompts (None = the UI preset's default)
+    #[serde(default)]
+    pub chat_template: Option<String>,
+    /// Screen analysis prompt template in brain/prompts (None = the UI preset's default)
+    #[serde(default)]
+    pub screen_template: Option<String>,
+    /// Speech voice (None = tts_voice)
+    #[serde(default)]
+    pub voice: Option<String>,
+}
+
+/// A user-configured MCP server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpServerConfig {
+    /// Unique server name
+    pub name: String,
+    /// "stdio" (spawned process) or "http" (streamable HTTP on localhost)
+    #[serde(default = "default_mcp_transport")]
+    pub transport: String,
+    /// Program to run (stdio)
+    #[serde(default)]
+    pub command: Option<String>,
+    /// Program arguments (stdio)
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Extra environment variables (stdio)
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Endpoint URL (http)
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Whether the server is connected and its tools offered
+    #[serde(default = "default_mcp_enabled")]
+    pub enabled: bool,
+    /// Tools the user allowed to run without asking; "*" allows every tool on the server
+    #[serde(default)]
+    pub auto_approve: Vec<String>,
+}
+
+fn default_mcp_transport() -> String {
+    "stdio".to_string()
+}
+
+fn default_mcp_enabled() -> bool {
+    true
+}
+
+fn default_profile_max_retries() -> u32 {
+    2
+}
+
+fn default_profile_timeout_secs() -> u64 {
+    120
+}
+
+impl ProviderProfile {
+    /// Profile built from the top-level provider settings
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self {
+            name: "default".to_string(),
+            provider: config.ai_provider.clone(),
+            model: config.ai_model.clone(),
+            key_ref: None,
+            base_url: Some(config.local_base_url.clone()),
+            max_retries: default_profile_max_retries(),
+            timeout_secs: default_profile_timeout_secs(),
+            max_tokens: None,
+        }
+    }
+
+    /// Secret store entry for this profile's key
+    pub fn key_name(&self) -> &str {
+        self.key_ref.as_deref().unwrap_or(&self.provider)
+    }
+}
+
 impl Default for ThemeConfig {
     fn default() -> Self {
         Self {
@@ -93,6 +378,24 @@ impl Default for ThemeConfig {
     }
 }
 
+impl AppConfig {
+    /// The persona in use, if it still exists
+    pub fn active_persona(&self) -> Option<&Persona> {
+        let name = self.active_persona.as_ref()?;
+        self.personas.iter().find(|p| &p.name == name)
+    }
+
+    /// Persona name in effect: the active persona's, else `persona_name`
+    pub fn effective_persona_name(&self) -> &str {
+        self.active_persona().map(|p| p.name.as_str()).unwrap_or(&self.persona_name)
+    }
+
+    /// Wake word in effect: the active persona's, else `wake_word`
+    pub fn effective_wake_word(&self) -> &str {
+        self.active_persona().map(|p| p.wake_word.as_str()).unwrap_or(&self.wake_word)
+    }
+}
+
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
@@ -100,7 +403,12 @@ impl Default for AppConfig {
             user_name: "Falken".to_string(),
             ai_provider: "openai".to_string(),
             api_key: None,
+            api_key_providers: Vec::new(),
+            has_api_key: false,
             ai_model: "gpt-4o-mini".to_string(),
+            local_base_url: default_local_base_url(),
+            provider_profiles: Vec::new(),
+            fallback_chain: Vec::new(),
             capture_interval_ms: 300000, // 5 minutes
             vision_enabled: false,  // Default OFF - requires permission
             voice_enabled: false,   // Default OFF - requires permission
@@ -115,6 +423,38 @@ impl Default for AppConfig {
             screentime_enabled: true,    // Enable screen time tracking by default
             preset: "preset1".to_string(), // Default UI preset
             web_search_enabled: true,     // Enable web search by default
+            replay_enabled: false,        // Replay buffer is opt-in
+            replay_fps: 1.0,
+            replay_seconds: 30,
+            daily_spend_cap: None,
+            monthly_spend_cap: None,
+            mcp_servers: Vec::new(),
+            embedding_provider: default_embedding_provider(),
+            embedding_base_url: None,
+            embedding_model: default_embedding_model(),
+            conversation_retention_days: None,
+            personas: Vec::new(),
+            active_persona: None,
+            web_search_provider: default_web_search_provider(),
+            searxng_url: None,
+            voice_engine: default_voice_engine(),
+            stt_engine: default_stt_engine(),
+            stt_model_path: None,
+            stt_base_url: None,
+            stt_model: default_stt_model(),
+            stt_language: default_stt_language(),
+            tts_enabled: false,
+            tts_muted: false,
+            tts_engine: default_tts_engine(),
+            tts_command: None,
+            tts_voice: default_tts_voice(),
+            tts_robot_filter: true,
+            focus_distractions: Vec::new(),
+            network_include: Vec::new(),
+            network_exclude: Vec::new(),
+            network_include_virtual: false,
+            primary_disk: None,
+            system_history_persist: false,
         }
     }
 }
@@ -134,8 +474,8 @@ fn get_data_folder(config: &AppConfig) -> PathBuf {
     }
 }
 
-/// Get the internal app data directory (for config only)
-fn get_internal_app_dir(app: &AppHandle) -> PathBuf {
+/// Get the internal app data directory (for config and secrets)
+pub fn get_internal_app_dir(app: &AppHandle) -> PathBuf {
     app.path()
         .app_data_dir()
         .unwrap_or_else(|_| PathBuf::from("."))
@@ -147,7 +487,7 @@ fn get_config_path(app: &AppHandle) -> PathBuf {
 }
 
 /// Get the brain directory path (in user's data folder)
-fn get_brain_dir(config: &AppConfig) -> PathBuf {
+pub fn get_brain_dir(config: &AppConfig) -> PathBuf {
     get_data_folder(config).join("brain")
 }
 
@@ -156,6 +496,11 @@ pub fn get_captures_dir(config: &AppConfig) -> PathBuf {
     get_data_folder(config).join("captures")
 }
 
+/// Get the saved conversations directory path (in user's data folder)
+pub fn get_conversations_dir(config: &AppConfig) -> PathBuf {
+    get_data_folder(config).join("conversations")
+}
+
 /// Ensure internal app directory exists
 fn ensure_internal_dir(app: &AppHandle) -> Result<(), String> {
     let dir = get_internal_app_dir(app);
@@ -169,6 +514,7 @@ pub fn ensure_data_directories(config: &AppConfig) -> Result<(), String> {
         get_data_folder(config),
         get_brain_dir(config),
         get_captures_dir(config),
+        get_conversations_dir(config),
     ];
 
     for dir in dirs {
@@ -185,7 +531,8 @@ pub fn load_config(app: AppHandle) -> AppConfig {
 
     if path.exists() {
         if let Ok(content) = fs::read_to_string(&path) {
-            if let Ok(config) = serde_yaml::from_str(&content) {
+            if let Ok(mut config) = serde_yaml::from_str::<AppConfig>(&content) {
+                config.has_api_key = config.api_key_providers.contains(&config.ai_provider);
                 return config;
             }
         }
@@ -196,11 +543,19 @@ pub fn load_config(app: AppHandle) -> AppConfig {
 }
 
 /// Save application configuration
+/// A non-empty `api_key` is moved into the secret store instead of config.yaml
 #[tauri::command]
-pub fn save_config(app: AppHandle, config: AppConfig) -> Result<(), String> {
+pub fn save_config(app: AppHandle, mut config: AppConfig) -> Result<(), String> {
     ensure_internal_dir(&app)?;
     ensure_data_directories(&config)?;
 
+    if let Some(key) = config.api_key.take().filter(|k| !k.is_empty()) {
+        secrets::store_api_key(&app, &config.ai_provider, &key)?;
+        if !config.api_key_providers.contains(&config.ai_provider) {
+            config.api_key_providers.push(config.ai_provider.clone());
+        }
+    }
+
     let path = get_config_path(&app);
     let yaml = serde_yaml::to_string(&config).map_err(|e| e.to_string())?;
     fs::write(&path, yaml).map_err(|e| e.to_string())?;
@@ -208,6 +563,18 @@ pub fn save_config(app: AppHandle, config: AppConfig) -> Result<(), String> {
     Ok(())
 }
 
+/// Parse a spend cap; an empty value removes the cap
+fn parse_spend_cap(value: &str) -> Result<Option<f64>, String> {
+    if value.trim().is_empty() {
+        return Ok(None);
+    }
+    let cap: f64 = value.trim().parse().map_err(|_| "Invalid number")?;
+    if cap < 0.0 {
+        return Err("Spend cap can't be negative".to_string());
+    }
+    Ok(Some(cap))
+}
+
 /// Update a single config value
 #[tauri::command]
 pub fn update_config_value(app: AppHandle, key: String, value: String) -> Result<(), String> {
@@ -216,7 +583,16 @@ pub fn update_config_value(app: AppHandle, key: String, value: String) -> Result
     match key.as_str() {
         "persona_name" => config.persona_name = value,
         "user_name" => config.user_name = value,
-        "api_key" => config.api_key = if value.is_empty() { None } else { Some(value) },
+        "api_key" => {
+            if value.is_empty() {
+                secrets::remove_api_key(&app, &config.ai_provider)?;
+                let provider = config.ai_provider.clone();
+                config.api_key_providers.retain(|p| p != &provider);
+            } else {
+                config.api_key = Some(value);
+            }
+        }
+        "local_base_url" => config.local_base_url = value,
         "capture_interval_ms" => {
             config.capture_interval_ms = value.parse().map_err(|_| "Invalid number")?
         }
@@ -235,6 +611,100 @@ pub fn update_config_value(app: AppHandle, key: String, value: String) -> Result
             config.always_on_top = value.parse().map_err(|_| "Invalid boolean")?
         }
         "preset" => config.preset = value,
+        "replay_enabled" => {
+            config.replay_enabled = value.parse().map_err(|_| "Invalid boolean")?
+        }
+        "replay_fps" => {
+            config.replay_fps = value.parse::<f32>().ok().filter(|fps| fps.is_finite()).ok_or("Invalid number")?
+        }
+        "replay_seconds" => {
+            config.replay_seconds = value.parse().map_err(|_| "Invalid number")?
+        }
+        "daily_spend_cap" => config.daily_spend_cap = parse_spend_cap(&value)?,
+        "monthly_spend_cap" => config.monthly_spend_cap = parse_spend_cap(&value)?,
+        "embedding_provider" => match value.as_str() {
+            "builtin" | "local" => config.embedding_provider = value,
+            _ => return Err(format!("Unknown embedding provider: {}", value)),
+        },
+        "embedding_base_url" => {
+            config.embedding_base_url = if value.trim().is_empty() { None } else { Some(value) }
+        }
+        "embedding_model" => config.embedding_model = value,
+        "web_search_provider" => match value.as_str() {
+            "duckduckgo" | "searxng" | "vendor" => config.web_search_provider = value,
+            _ => return Err(format!("Unknown web search provider: {}", value)),
+        },
+        "searxng_url" => {
+            config.searxng_url = if value.trim().is_empty() { None } else { Some(value) }
+        }
+        "voice_engine" => match value.as_str() {
+            "browser" | "native" => config.voice_engine = value,
+            _ => return Err(format!("Unknown voice engine: {}", value)),
+        },
+        "stt_engine" => match value.as_str() {
+            "whisper" if !cfg!(feature = "whisper") => return Err(voice::NO_BUILTIN_WHISPER.to_string()),
+            "whisper" | "server" => config.stt_engine = value,
+            _ => return Err(format!("Unknown transcription engine: {}", value)),
+        },
+        "stt_model_path" => {
+            config.stt_model_path = if value.trim().is_empty() { None } else { Some(value) }
+        }
+        "stt_base_url" => {
+            config.stt_base_url = if value.trim().is_empty() { None } else { Some(value) }
+        }
+        "stt_model" => config.stt_model = value,
+        "stt_language" => config.stt_language = value,
+        "tts_enabled" => config.tts_enabled = value.parse().map_err(|_| "Invalid boolean")?,
+        "tts_engine" => match value.as_str() {
+            "espeak" | "piper" => config.tts_engine = value,
+            _ => return Err(format!("Unknown speech engine: {}", value)),
+        },
+        "tts_command" => {
+            config.tts_command = if value.trim().is_empty() { None } else { Some(value) }
+        }
+        "tts_voice" => config.tts_voice = value,
+        "tts_robot_filter" => {
+            config.tts_robot_filter = value.parse().map_err(|_| "Invalid boolean")?
+        }
+        "focus_distractions" => {
+            config.focus_distractions = value
+                .split([',', '\n'])
+                .map(|app| app.trim().to_string())
+                .filter(|app| !app.is_empty())
+                .collect()
+        }
+        "network_include" | "network_exclude" => {
+            let interfaces = value
+                .split([',', '\n'])
+                .map(|name| name.trim().to_string())
+                .filter(|name| !name.is_empty())
+                .collect();
+            if key == "network_include" {
+                config.network_include = interfaces;
+            } else {
+                config.network_exclude = interfaces;
+            }
+        }
+        "network_include_virtual" => {
+            config.network_include_virtual = value.parse().map_err(|_| "Invalid boolean")?
+        }
+        "system_history_persist" => {
+            config.system_history_persist = value.parse().map_err(|_| "Invalid boolean")?
+        }
+        "primary_disk" => {
+            config.primary_disk = if value.trim().is_empty() {
+                None
+            } else {
+                Some(value.trim().to_string())
+            }
+        }
+        "conversation_retention_days" => {
+            config.conversation_retention_days = if value.trim().is_empty() {
+                None
+            } else {
+                Some(value.trim().parse().map_err(|_| "Invalid number")?)
+            }
+        }
         _ => return Err(format!("Unknown config key: {}", key)),
     }
 
@@ -325,6 +795,11 @@ pub fn init_storage(app: &AppHandle) -> Result<(), String> {
         fs::write(&config_path, yaml).map_err(|e| e.to_string())?;
     }
 
+    // Move any plaintext API key out of config.yaml
+    if let Err(e) = secrets::migrate_plaintext_key(app) {
+        eprintln!("Failed to migrate API key to secret store: {}", e);
+    }
+
     // Load config and ensure data directories
     let config = load_config(app.clone());
     ensure_data_directories(&config)?;
@@ -375,13 +850,30 @@ fn copy_dir_recursive(src: &PathBuf, dst: &PathBuf) -> Result<(), String> {
     Ok(())
 }
 
-/// Pomodoro settings
+/// Pomodoro settings, also the "Default" timer profile
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PomodoroSettings {
     pub work_minutes: u32,
     pub break_minutes: u32,
     pub long_break_minutes: u32,
     pub sessions_until_long_break: u32,
+    #[serde(default)]
+    pub sounds: PomodoroSounds,
+    /// Start a break as soon as focus ends
+    #[serde(default)]
+    pub auto_start_breaks: bool,
+    /// Start focus as soon as a break ends
+    #[serde(default)]
+    pub auto_start_work: bool,
+}
+
+/// Sounds for the end of a pomodoro phase: "chime", "bell", "beep" or a WAV file path (None = silent)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PomodoroSounds {
+    #[serde(default)]
+    pub work_end: Option<String>,
+    #[serde(default)]
+    pub break_end: Option<String>,
 }
 
 impl Default for PomodoroSettings {
@@ -391,6 +883,9 @@ impl Default for PomodoroSettings {
             break_minutes: 5,
             long_break_minutes: 15,
             sessions_until_long_break: 4,
+            sounds: PomodoroSounds::default(),
+            auto_start_breaks: false,
+            auto_start_work: false,
         }
     }
 }
@@ -415,13 +910,18 @@ pub fn load_pomodoro_settings(app: AppHandle) -> PomodoroSettings {
 /// Save pomodoro settings to captures folder
 #[tauri::command]
 pub fn save_pomodoro_settings(app: AppHandle, settings: PomodoroSettings) -> Result<(), String> {
-    let config = load_config(app);
+    for sound in [&settings.sounds.work_end, &settings.sounds.break_end].into_iter().flatten() {
+        super::pomodoro::sounds::validate(sound)?;
+    }
+    let config = load_config(app.clone());
     ensure_data_directories(&config)?;
 
     let path = get_captures_dir(&config).join("pomodoro_settings.json");
     let json = serde_json::to_string_pretty(&settings).map_err(|e| e.to_string())?;
     fs::write(&path, json).map_err(|e| e.to_string())?;
 
+    // A phase that hasn't started yet picks up the new length
+    super::pomodoro::profile_changed(&app);
     Ok(())
 }
 
@@ -0,0 +1,686 @@
+//! Threshold alerts on system metrics
+//! Rules are checked against every reading the history sampler takes. A rule fires once its
+//! condition has held for `sustained_secs`, then stays quiet for `cooldown_secs`. Firing emits
+//! `system://alert`, shows a desktop notification when the rule asks for one and is logged.
+//! Rules are kept in `system_alert_rules.json`, the log in `system_alert_history.json`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+use super::history::MetricSample;
+use super::SystemMonitor;
+use crate::services::storage::{self, AppConfig};
+
+/// Alerts kept in the log
+const MAX_HISTORY: usize = 500;
+/// How often disk space is checked, it changes slowly and is slower to read
+const DISK_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+/// Longest a condition can be required to hold, and longest cooldown
+const MAX_SUSTAINED_SECS: u64 = 24 * 3600;
+const MAX_COOLDOWN_SECS: u64 = 7 * 24 * 3600;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertMetric {
+    /// Percent, averaged over all cores
+    Cpu,
+    /// Percent of RAM in use
+    Memory,
+    /// Percent of swap in use
+    Swap,
+    /// °C
+    CpuTemperature,
+    GpuTemperature,
+    /// Percent used, checked on every disk separately
+    Disk,
+    /// Bytes per second
+    NetworkIn,
+    NetworkOut,
+}
+
+impl AlertMetric {
+    fn label(self) -> &'static str {
+        match self {
+            AlertMetric::Cpu => "CPU usage",
+            AlertMetric::Memory => "Memory usage",
+            AlertMetric::Swap => "Swap usage",
+            AlertMetric::CpuTemperature => "CPU temperature",
+            AlertMetric::GpuTemperature => "GPU temperature",
+            AlertMetric::Disk => "Disk usage",
+            AlertMetric::NetworkIn => "Download rate",
+            AlertMetric::NetworkOut => "Upload rate",
+        }
+    }
+
+    fn is_percent(self) -> bool {
+        matches!(
+            self,
+            AlertMetric::Cpu | AlertMetric::Memory | AlertMetric::Swap | AlertMetric::Disk
+        )
+    }
+
+    fn format(self, value: f32) -> String {
+        match self {
+            AlertMetric::CpuTemperature | AlertMetric::GpuTemperature => {
+                format!("{:.0}°C", value)
+            }
+            AlertMetric::NetworkIn | AlertMetric::NetworkOut => {
+                format!("{:.1} MB/s", value / 1_000_000.0)
+            }
+            _ => format!("{:.0}%", value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Comparison {
+    Above,
+    Below,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertRule {
+    /// Generated when empty
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub metric: AlertMetric,
+    pub comparison: Comparison,
+    pub threshold: f32,
+    /// How long the condition has to hold before the alert fires
+    #[serde(default)]
+    pub sustained_secs: u64,
+    /// Quiet time after firing
+    #[serde(default)]
+    pub cooldown_secs: u64,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Show a desktop notification
+    #[serde(default = "default_true")]
+    pub notify: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl AlertRule {
+    fn new(
+        id: &str,
+        name: &str,
+        metric: AlertMetric,
+        threshold: f32,
+        sustained_secs: u64,
+        cooldown_secs: u64,
+    ) -> Self {
+        Self {
+            id: id.to_string(),
+            name: name.to_string(),
+            metric,
+            comparison: Comparison::Above,
+            threshold,
+            sustained_secs,
+            cooldown_secs,
+            enabled: true,
+            notify: true,
+        }
+    }
+
+    fn breached(&self, value: f32) -> bool {
+        match self.comparison {
+            Comparison::Above => value > self.threshold,
+            Comparison::Below => value < self.threshold,
+        }
+    }
+}
+
+/// Rules used until the user changes them
+fn default_rules() -> Vec<AlertRule> {
+    vec![
+        AlertRule::new(
+            "cpu-temperature",
+            "CPU running hot",
+            AlertMetric::CpuTemperature,
+            90.0,
+            30,
+            10 * 60,
+        ),
+        AlertRule::new(
+            "memory",
+            "Memory almost full",
+            AlertMetric::Memory,
+            90.0,
+            60,
+            15 * 60,
+        ),
+        AlertRule::new(
+            "swap",
+            "Heavy swapping",
+            AlertMetric::Swap,
+            50.0,
+            60,
+            30 * 60,
+        ),
+        AlertRule::new(
+            "disk-full",
+            "Disk almost full",
+            AlertMetric::Disk,
+            95.0,
+            0,
+            6 * 3600,
+        ),
+    ]
+}
+
+/// A fired alert, payload of `system://alert`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SystemAlert {
+    pub id: String,
+    pub rule_id: String,
+    pub rule_name: String,
+    pub metric: AlertMetric,
+    pub comparison: Comparison,
+    pub threshold: f32,
+    pub value: f32,
+    /// Mount point of the disk for disk alerts
+    pub subject: Option<String>,
+    pub message: String,
+    /// Unix ms
+    pub time: u64,
+}
+
+/// Managed state: the rules in force
+pub struct SystemAlerts {
+    rules: Mutex<Vec<AlertRule>>,
+    /// Why the saved rules couldn't be loaded; the defaults run meanwhile and the file is only
+    /// written again by a reset
+    load_error: Mutex<Option<String>>,
+}
+
+fn rules_path(config: &AppConfig) -> PathBuf {
+    storage::get_captures_dir(config).join("system_alert_rules.json")
+}
+
+fn history_path(config: &AppConfig) -> PathBuf {
+    storage::get_captures_dir(config).join("system_alert_history.json")
+}
+
+impl SystemAlerts {
+    /// Saved rules, or the defaults when none were saved or they can't be read
+    pub fn load(app: &AppHandle) -> Self {
+        let config = storage::load_config(app.clone());
+        let (rules, load_error) = match read_rules(&rules_path(&config)) {
+            Ok(rules) => (rules, None),
+            Err(e) => {
+                eprintln!("{}", e);
+                (default_rules(), Some(e))
+            }
+        };
+        Self {
+            rules: Mutex::new(rules),
+            load_error: Mutex::new(load_error),
+        }
+    }
+
+    /// Refuse changes that would be saved over rules that couldn't be loaded
+    fn check_writable(&self) -> Result<(), String> {
+        match &*self.load_error.lock().map_err(|e| e.to_string())? {
+            Some(e) => Err(e.clone()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Defaults when nothing was saved; a file that exists but doesn't parse is an error
+fn read_rules(path: &Path) -> Result<Vec<AlertRule>, String> {
+    if !path.exists() {
+        return Ok(default_rules());
+    }
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_str(&content).map_err(|e| {
+        format!(
+            "{} is damaged ({}); the default alert rules are used and can't be changed until it is fixed, removed or the rules are reset",
+            path.display(),
+            e
+        )
+    })
+}
+
+fn save_rules(app: &AppHandle, rules: &[AlertRule]) -> Result<(), String> {
+    let config = storage::load_config(app.clone());
+    storage::ensure_data_directories(&config)?;
+    let json = serde_json::to_string_pretty(rules).map_err(|e| e.to_string())?;
+    fs::write(rules_path(&config), json).map_err(|e| e.to_string())
+}
+
+fn load_history(config: &AppConfig) -> Vec<SystemAlert> {
+    fs::read_to_string(history_path(config))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn record(config: &AppConfig, alert: &SystemAlert) -> Result<(), String> {
+    storage::ensure_data_directories(config)?;
+    let mut alerts = load_history(config);
+    alerts.push(alert.clone());
+    if alerts.len() > MAX_HISTORY {
+        alerts.drain(..alerts.len() - MAX_HISTORY);
+    }
+    let json = serde_json::to_string_pretty(&alerts).map_err(|e| e.to_string())?;
+    fs::write(history_path(config), json).map_err(|e| e.to_string())
+}
+
+/// Where a rule stands between readings, for one subject
+#[derive(Default)]
+struct RuleState {
+    /// Unix ms the condition started holding
+    breached_since: Option<u64>,
+    last_fired: Option<u64>,
+}
+
+/// A rule whose condition held long enough, with the reading that set it off
+struct Firing<'a> {
+    rule: &'a AlertRule,
+    value: f32,
+    subject: Option<String>,
+}
+
+/// Checks readings against the rules; owned by the sampler thread
+#[derive(Default)]
+pub struct AlertEvaluator {
+    /// Keyed by rule id and subject, so each disk is timed and cooled down on its own
+    states: HashMap<(String, Option<String>), RuleState>,
+    /// Mount point and percent used of every writable disk
+    disks: Vec<(String, f32)>,
+    disk_checked: Option<Instant>,
+}
+
+impl AlertEvaluator {
+    fn refresh_disks(&mut self, app: &AppHandle, config: &AppConfig) {
+        if self
+            .disk_checked
+            .is_some_and(|at| at.elapsed() < DISK_CHECK_INTERVAL)
+        {
+            return;
+        }
+        self.disk_checked = Some(Instant::now());
+        let monitor = app.state::<SystemMonitor>();
+        let Ok(mut rates) = monitor.disk_rates.lock() else {
+            return;
+        };
+        self.disks = rates
+            .sample(config)
+            .into_iter()
+            // Read-only images (snaps, install media) are always full
+            .filter(|disk| !disk.is_read_only)
+            .map(|disk| (disk.mount_point, disk.percent))
+            .collect();
+    }
+
+    /// A metric's readings by subject; only disks have one, and one reading each
+    fn values(&self, metric: AlertMetric, sample: &MetricSample) -> Vec<(Option<String>, f32)> {
+        let value = match metric {
+            AlertMetric::Cpu => Some(sample.cpu),
+            AlertMetric::Memory => Some(sample.memory),
+            AlertMetric::Swap => Some(sample.swap),
+            AlertMetric::CpuTemperature => sample.cpu_temperature,
+            AlertMetric::GpuTemperature => sample.gpu_temperature,
+            AlertMetric::NetworkIn => Some(sample.network_in),
+            AlertMetric::NetworkOut => Some(sample.network_out),
+            AlertMetric::Disk => {
+                return self
+                    .disks
+                    .iter()
+                    .map(|(mount, percent)| (Some(mount.clone()), *percent))
+                    .collect();
+            }
+        };
+        value.map(|value| (None, value)).into_iter().collect()
+    }
+
+    /// Advance every enabled rule and subject to a reading; returns those that are due to fire
+    fn check<'a>(&mut self, rules: &'a [AlertRule], sample: &MetricSample) -> Vec<Firing<'a>> {
+        // Forget rules that were deleted
+        self.states
+            .retain(|(id, _), _| rules.iter().any(|rule| &rule.id == id));
+
+        let now = sample.time;
+        let mut due = Vec::new();
+        for rule in rules.iter().filter(|rule| rule.enabled) {
+            let readings = self.values(rule.metric, sample);
+            // A subject without a reading (unreadable sensor, unmounted disk) isn't breaching;
+            // its cooldown is kept in case it comes back
+            for ((id, subject), state) in self.states.iter_mut() {
+                if id == &rule.id && !readings.iter().any(|(s, _)| s == subject) {
+                    state.breached_since = None;
+                }
+            }
+
+            for (subject, value) in readings {
+                let state = self
+                    .states
+                    .entry((rule.id.clone(), subject.clone()))
+                    .or_default();
+                if !rule.breached(value) {
+                    state.breached_since = None;
+                    continue;
+                }
+
+                let since = *state.breached_since.get_or_insert(now);
+                let sustained = now.saturating_sub(since) >= rule.sustained_secs * 1000;
+                let cooled = state
+                    .last_fired
+                    .is_none_or(|fired| now.saturating_sub(fired) >= rule.cooldown_secs * 1000);
+                if !(sustained && cooled) {
+                    continue;
+                }
+                state.last_fired = Some(now);
+                due.push(Firing {
+                    rule,
+                    value,
+                    subject,
+                });
+            }
+        }
+        due
+    }
+
+    /// Check a reading against every enabled rule and fire the ones that are due
+    pub fn evaluate(&mut self, app: &AppHandle, config: &AppConfig, sample: &MetricSample) {
+        let rules = match app.state::<SystemAlerts>().rules.lock() {
+            Ok(rules) => rules.clone(),
+            Err(_) => return,
+        };
+        if rules
+            .iter()
+            .any(|rule| rule.enabled && rule.metric == AlertMetric::Disk)
+        {
+            self.refresh_disks(app, config);
+        }
+        for firing in self.check(&rules, sample) {
+            fire(
+                app,
+                config,
+                firing.rule,
+                firing.value,
+                firing.subject,
+                sample.time,
+            );
+        }
+    }
+}
+
+fn fire(
+    app: &AppHandle,
+    config: &AppConfig,
+    rule: &AlertRule,
+    value: f32,
+    subject: Option<String>,
+    time: u64,
+) {
+    let comparison = match rule.comparison {
+        Comparison::Above => "above",
+        Comparison::Below => "below",
+    };
+    let mut message = format!(
+        "{} is {} ({} {})",
+        rule.metric.label(),
+        rule.metric.format(value),
+        comparison,
+        rule.metric.format(rule.threshold)
+    );
+    if let Some(subject) = &subject {
+        message.push_str(&format!(" on {}", subject));
+    }
+    if rule.sustained_secs > 0 {
+        message.push_str(&format!(" for {}s", rule.sustained_secs));
+    }
+
+    let alert = SystemAlert {
+        id: uuid::Uuid::new_v4().to_string(),
+        rule_id: rule.id.clone(),
+        rule_name: rule.name.clone(),
+        metric: rule.metric,
+        comparison: rule.comparison,
+        threshold: rule.threshold,
+        value,
+        subject,
+        message,
+        time,
+    };
+    let _ = app.emit("system://alert", &alert);
+    if rule.notify {
+        if let Err(e) = app
+            .notification()
+            .builder()
+            .title(&alert.rule_name)
+            .body(&alert.message)
+            .show()
+        {
+            eprintln!("Failed to show alert notification: {}", e);
+        }
+    }
+    if let Err(e) = record(config, &alert) {
+        eprintln!("Failed to log system alert: {}", e);
+    }
+}
+
+fn validate(rule: &AlertRule) -> Result<(), String> {
+    if rule.name.trim().is_empty() {
+        return Err("Alert name is required".to_string());
+    }
+    if !rule.threshold.is_finite() {
+        return Err("Threshold must be a number".to_string());
+    }
+    if rule.metric.is_percent() && !(0.0..=100.0).contains(&rule.threshold) {
+        return Err("Threshold is a percentage (0 – 100)".to_string());
+    }
+    if rule.sustained_secs > MAX_SUSTAINED_SECS {
+        return Err("Duration can be at most 24 hours".to_string());
+    }
+    if rule.cooldown_secs > MAX_COOLDOWN_SECS {
+        return Err("Cooldown can be at most 7 days".to_string());
+    }
+    Ok(())
+}
+
+/// Fails while the saved rules can't be loaded
+#[tauri::command]
+pub fn list_alert_rules(alerts: tauri::State<SystemAlerts>) -> Result<Vec<AlertRule>, String> {
+    alerts.check_writable()?;
+    Ok(alerts.rules.lock().map_err(|e| e.to_string())?.clone())
+}
+
+/// Add a rule (no id) or replace the one with the same id
+#[tauri::command]
+pub fn save_alert_rule(
+    app: AppHandle,
+    alerts: tauri::State<SystemAlerts>,
+    rule: AlertRule,
+) -> Result<Vec<AlertRule>, String> {
+    alerts.check_writable()?;
+    validate(&rule)?;
+    let mut rule = rule;
+    rule.name = rule.name.trim().to_string();
+    if rule.id.trim().is_empty() {
+        rule.id = uuid::Uuid::new_v4().to_string();
+    }
+
+    let mut rules = alerts.rules.lock().map_err(|e| e.to_string())?;
+    match rules.iter_mut().find(|r| r.id == rule.id) {
+        Some(existing) => *existing = rule,
+        None => rules.push(rule),
+    }
+    save_rules(&app, &rules)?;
+    Ok(rules.clone())
+}
+
+#[tauri::command]
+pub fn delete_alert_rule(
+    app: AppHandle,
+    alerts: tauri::State<SystemAlerts>,
+    id: String,
+) -> Result<Vec<AlertRule>, String> {
+    alerts.check_writable()?;
+    let mut rules = alerts.rules.lock().map_err(|e| e.to_string())?;
+    rules.retain(|r| r.id != id);
+    save_rules(&app, &rules)?;
+    Ok(rules.clone())
+}
+
+/// Put the shipped rules back, replacing a damaged rules file too
+#[tauri::command]
+pub fn reset_alert_rules(
+    app: AppHandle,
+    alerts: tauri::State<SystemAlerts>,
+) -> Result<Vec<AlertRule>, String> {
+    let mut rules = alerts.rules.lock().map_err(|e| e.to_string())?;
+    *rules = default_rules();
+    save_rules(&app, &rules)?;
+    *alerts.load_error.lock().map_err(|e| e.to_string())? = None;
+    Ok(rules.clone())
+}
+
+/// Fired alerts, newest first
+#[tauri::command]
+pub fn get_alert_history(app: AppHandle, limit: Option<usize>) -> Vec<SystemAlert> {
+    let config = storage::load_config(app);
+    let mut alerts = load_history(&config);
+    alerts.reverse();
+    if let Some(limit) = limit {
+        alerts.truncate(limit);
+    }
+    alerts
+}
+
+#[tauri::command]
+pub fn clear_alert_history(app: AppHandle) -> Result<(), String> {
+    let config = storage::load_config(app);
+    let path = history_path(&config);
+    if path.exists() {
+        fs::remove_file(path).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_files::{temp_file, temp_path};
+
+    fn disk_rule() -> AlertRule {
+        AlertRule::new("disk", "Disk almost full", AlertMetric::Disk, 90.0, 0, 3600)
+    }
+
+    fn sample(time: u64) -> MetricSample {
+        MetricSample {
+            time,
+            ..Default::default()
+        }
+    }
+
+    fn fired(evaluator: &mut AlertEvaluator, rules: &[AlertRule], time: u64) -> Vec<String> {
+        evaluator
+            .check(rules, &sample(time))
+            .into_iter()
+            .filter_map(|firing| firing.subject)
+            .collect()
+    }
+
+    #[test]
+    fn every_full_disk_fires_not_just_the_fullest() {
+        let rules = [disk_rule()];
+        let mut evaluator = AlertEvaluator {
+            disks: vec![
+                ("/".to_string(), 97.0),
+                ("/data".to_string(), 93.0),
+                ("/home".to_string(), 40.0),
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(fired(&mut evaluator, &rules, 1_000), ["/", "/data"]);
+    }
+
+    #[test]
+    fn disks_cool_down_separately() {
+        let rules = [disk_rule()];
+        let mut evaluator = AlertEvaluator {
+            disks: vec![("/".to_string(), 97.0), ("/data".to_string(), 50.0)],
+            ..Default::default()
+        };
+        assert_eq!(fired(&mut evaluator, &rules, 1_000), ["/"]);
+
+        // A second disk filling up isn't held back by the first one's cooldown
+        evaluator.disks[1].1 = 95.0;
+        assert_eq!(fired(&mut evaluator, &rules, 2_000), ["/data"]);
+        assert!(fired(&mut evaluator, &rules, 3_000).is_empty());
+
+        // Each fires again once its own cooldown is over
+        assert_eq!(fired(&mut evaluator, &rules, 1_000 + 3_600_000), ["/"]);
+    }
+
+    #[test]
+    fn sustained_rules_wait_per_subject() {
+        let rules = [AlertRule::new("cpu", "Busy", AlertMetric::Cpu, 80.0, 10, 0)];
+        let mut evaluator = AlertEvaluator::default();
+        let busy = |time| MetricSample {
+            time,
+            cpu: 95.0,
+            ..Default::default()
+        };
+
+        assert!(evaluator.check(&rules, &busy(0)).is_empty());
+        assert!(evaluator.check(&rules, &busy(5_000)).is_empty());
+        let due = evaluator.check(&rules, &busy(10_000));
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].subject, None);
+        assert_eq!(due[0].value, 95.0);
+
+        // Dropping below the threshold starts the wait over
+        assert!(evaluator.check(&rules, &sample(11_000)).is_empty());
+        assert!(evaluator.check(&rules, &busy(12_000)).is_empty());
+    }
+
+    #[test]
+    fn saved_rules_are_read_back() {
+        let rules = vec![disk_rule()];
+        let path = temp_file("alert-rules.json", &serde_json::to_string(&rules).unwrap());
+        let read = read_rules(&path).unwrap();
+        assert_eq!(read.len(), 1);
+        assert_eq!(read[0].id, "disk");
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(
+            read_rules(&temp_path("alert-rules-missing.json"))
+                .unwrap()
+                .len(),
+            default_rules().len()
+        );
+    }
+
+    #[test]
+    fn damaged_rules_block_changes() {
+        let path = temp_file("alert-rules-damaged.json", "[{\"id\": ");
+        let error = read_rules(&path).unwrap_err();
+        assert!(error.contains("damaged"));
+        fs::remove_file(path).unwrap();
+
+        let alerts = SystemAlerts {
+            rules: Mutex::new(default_rules()),
+            load_error: Mutex::new(Some(error)),
+        };
+        assert!(alerts.check_writable().is_err());
+        *alerts.load_error.lock().unwrap() = None;
+        assert!(alerts.check_writable().is_ok());
+    }
+}
@@ -0,0 +1,304 @@
+//! Per-disk usage and I/O throughput
+//! Mounts of the same device (bind mounts, APFS volumes sharing a container) are folded into
+//! one entry so space isn't counted twice. Read/write rates come from `/proc/diskstats` and
+//! are only available on Linux; elsewhere they are None.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use sysinfo::{Disk, Disks};
+
+use super::rates::Rates;
+use crate::services::storage::AppConfig;
+
+/// Layered or pseudo filesystems whose space belongs to another disk
+const SKIPPED_FILE_SYSTEMS: [&str; 8] = [
+    "overlay",
+    "squashfs",
+    "fuse.snapfuse",
+    "tmpfs",
+    "devfs",
+    "autofs",
+    "nullfs",
+    "ramfs",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiskInfo {
+    /// Device (e.g. /dev/nvme0n1p2), volume name on macOS and Windows
+    pub name: String,
+    /// Where the device is mounted first (the shortest path)
+    pub mount_point: String,
+    /// Other places the same device is mounted
+    pub other_mount_points: Vec<String>,
+    pub file_system: String,
+    /// "SSD", "HDD" or "Unknown"
+    pub kind: String,
+    pub is_removable: bool,
+    pub is_read_only: bool,
+    pub total_space: u64,
+    pub available_space: u64,
+    pub used_space: u64,
+    pub percent: f32,
+    /// Bytes per second, None where the OS doesn't report it
+    pub read_per_sec: Option<u64>,
+    pub written_per_sec: Option<u64>,
+    /// Shown in the headline disk gauge
+    pub is_primary: bool,
+}
+
+/// Disk I/O counters at the previous sample, to work out rates from
+#[derive(Default)]
+pub struct DiskRates {
+    rates: Rates,
+}
+
+impl DiskRates {
+    /// Every disk once, primary first
+    pub fn sample(&mut self, config: &AppConfig) -> Vec<DiskInfo> {
+        let disks = Disks::new_with_refreshed_list();
+        let io = platform::io_counters();
+        self.rates.update(io.clone());
+
+        let mut by_device: HashMap<String, DiskInfo> = HashMap::new();
+        let mut order = Vec::new();
+        for disk in disks.list() {
+            let file_system = disk.file_system().to_string_lossy().to_string();
+            if SKIPPED_FILE_SYSTEMS.contains(&file_system.as_str()) || disk.total_space() == 0 {
+                continue;
+            }
+            let mount_point = disk.mount_point().to_string_lossy().to_string();
+            let key = device_key(disk, &file_system, &mount_point);
+
+            if let Some(existing) = by_device.get_mut(&key) {
+                // Keep the shortest mount point as the main one ("/" over "/var/lib/docker")
+                if mount_point.len() < existing.mount_point.len() {
+                    let previous = std::mem::replace(&mut existing.mount_point, mount_point);
+                    existing.other_mount_points.push(previous);
+                } else {
+                    existing.other_mount_points.push(mount_point);
+                }
+                continue;
+            }
+
+            let name = disk.name().to_string_lossy().to_string();
+            let io_name = platform::io_name(&name);
+            let rates = io_name
+                .filter(|io_name| io.contains_key(io_name))
+                .map(|io_name| self.rates.get(&io_name));
+            let used_space = disk.total_space().saturating_sub(disk.available_space());
+
+            order.push(key.clone());
+            by_device.insert(
+                key,
+                DiskInfo {
+                    name,
+                    mount_point,
+                    other_mount_points: Vec::new(),
+                    file_system,
+                    kind: disk.kind().to_string(),
+                    is_removable: disk.is_removable(),
+                    is_read_only: disk.is_read_only(),
+                    total_space: disk.total_space(),
+                    available_space: disk.available_space(),
+                    used_space,
+                    percent: (used_space as f64 / disk.total_space() as f64 * 100.0) as f32,
+                    read_per_sec: rates.map(|(read, _)| read),
+                    written_per_sec: rates.map(|(_, written)| written),
+                    is_primary: false,
+                },
+            );
+        }
+
+        let mut disks: Vec<DiskInfo> = order
+            .into_iter()
+            .filter_map(|key| by_device.remove(&key))
+            .collect();
+        let primary = primary_index(&disks, config.primary_disk.as_deref());
+        if let Some(index) = primary {
+            disks[index].is_primary = true;
+            let disk = disks.remove(index);
+            disks.insert(0, disk);
+        }
+        disks
+    }
+
+    /// Bytes read and written per second over the physical disks, None where the OS doesn't report it
+    pub fn total_io(&mut self) -> Option<(u64, u64)> {
+        let io = platform::io_counters();
+        if io.is_empty() {
+            return None;
+        }
+        // Partitions and mapped devices are left out, their I/O is already in their disk's
+        let physical: Vec<String> = io
+            .keys()
+            .filter(|name| platform::is_physical(name))
+            .cloned()
+            .collect();
+        self.rates.update(io);
+        let total = physical
+            .iter()
+            .map(|name| self.rates.get(name))
+            .fold((0, 0), |(read, written), (r, w)| (read + r, written + w));
+        Some(total)
+    }
+}
+
+/// What identifies the device behind a mount
+fn device_key(disk: &Disk, file_system: &str, mount_point: &str) -> String {
+    // APFS volumes of one container share its space; volumes that can't be traced to a
+    // container stay apart rather than risk merging different drives
+    if file_system.eq_ignore_ascii_case("apfs") {
+        let container = mounted_from(mount_point).and_then(|device| apfs_container(&device));
+        return match container {
+            Some(container) => format!("apfs:{}", container),
+            None => mount_point.to_string(),
+        };
+    }
+    let name = disk.name().to_string_lossy();
+    // Windows gives volume labels, which can repeat or be empty
+    if cfg!(windows) || name.is_empty() {
+        return mount_point.to_string();
+    }
+    name.to_string()
+}
+
+/// Container disk of an APFS volume's device ("/dev/disk3s1s1" is in "disk3")
+fn apfs_container(device: &str) -> Option<String> {
+    let name = device.strip_prefix("/dev/").unwrap_or(device);
+    let number = name.strip_prefix("disk")?;
+    let digits = number
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(number.len());
+    (digits > 0).then(|| format!("disk{}", &number[..digits]))
+}
+
+/// Device a path is mounted from; sysinfo only gives volume names on macOS
+#[cfg(target_os = "macos")]
+fn mounted_from(mount_point: &str) -> Option<String> {
+    let path = std::ffi::CString::new(mount_point).ok()?;
+    let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    let from = unsafe { std::ffi::CStr::from_ptr(stat.f_mntfromname.as_ptr()) };
+    Some(from.to_string_lossy().to_string())
+}
+
+#[cfg(not(target_os = "macos"))]
+fn mounted_from(_mount_point: &str) -> Option<String> {
+    None
+}
+
+/// The configured disk if it is mounted, else the system disk, else the largest
+fn primary_index(disks: &[DiskInfo], configured: Option<&str>) -> Option<usize> {
+    let mounted_at = |path: &str| {
+        disks.iter().position(|disk| {
+            disk.mount_point == path || disk.other_mount_points.iter().any(|m| m == path)
+        })
+    };
+    configured
+        .and_then(mounted_at)
+        .or_else(|| mounted_at(&system_mount_point()))
+        .or_else(|| {
+            disks
+                .iter()
+                .enumerate()
+                .max_by_key(|(_, disk)| disk.total_space)
+                .map(|(index, _)| index)
+        })
+}
+
+fn system_mount_point() -> String {
+    if cfg!(windows) {
+        format!(
+            "{}\\",
+            std::env::var("SystemDrive").unwrap_or_else(|_| "C:".to_string())
+        )
+    } else {
+        "/".to_string()
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::collections::HashMap;
+    use std::fs;
+
+    /// /proc/diskstats counts in 512-byte sectors whatever the device's sector size
+    const SECTOR_SIZE: u64 = 512;
+
+    /// Bytes read and written since boot per block device ("nvme0n1p2", "dm-0")
+    pub fn io_counters() -> HashMap<String, (u64, u64)> {
+        let Ok(content) = fs::read_to_string("/proc/diskstats") else {
+            return HashMap::new();
+        };
+        content
+            .lines()
+            .filter_map(|line| {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                let sectors_read: u64 = fields.get(5)?.parse().ok()?;
+                let sectors_written: u64 = fields.get(9)?.parse().ok()?;
+                Some((
+                    fields.get(2)?.to_string(),
+                    (sectors_read * SECTOR_SIZE, sectors_written * SECTOR_SIZE),
+                ))
+            })
+            .collect()
+    }
+
+    /// Whole disks backed by hardware have a `device` link in sysfs, partitions and loop,
+    /// dm and zram devices don't
+    pub fn is_physical(name: &str) -> bool {
+        std::path::Path::new("/sys/block")
+            .join(name)
+            .join("device")
+            .exists()
+    }
+
+    /// Block device behind a device path; /dev/mapper names are links to /dev/dm-N
+    pub fn io_name(device: &str) -> Option<String> {
+        let path = fs::canonicalize(device).unwrap_or_else(|_| device.into());
+        Some(
+            path.strip_prefix("/dev")
+                .ok()?
+                .to_string_lossy()
+                .to_string(),
+        )
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod platform {
+    use std::collections::HashMap;
+
+    pub fn io_counters() -> HashMap<String, (u64, u64)> {
+        HashMap::new()
+    }
+
+    pub fn io_name(_device: &str) -> Option<String> {
+        None
+    }
+
+    pub fn is_physical(_name: &str) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apfs_volumes_resolve_to_their_container() {
+        assert_eq!(apfs_container("/dev/disk3s1s1").as_deref(), Some("disk3"));
+        assert_eq!(apfs_container("/dev/disk3s5").as_deref(), Some("disk3"));
+        assert_eq!(apfs_container("disk12s2").as_deref(), Some("disk12"));
+        // A different container of the same size stays apart
+        assert_ne!(
+            apfs_container("/dev/disk5s1"),
+            apfs_container("/dev/disk3s1")
+        );
+        assert_eq!(apfs_container("/dev/diskX"), None);
+        assert_eq!(apfs_container("map auto_home"), None);
+    }
+}
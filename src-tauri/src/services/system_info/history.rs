@@ -0,0 +1,622 @@
+//! Rolling history of system metrics at three resolutions
+//! A background sampler takes a reading every second. Readings are kept as they are for
+//! 5 minutes, averaged into 10 s points for an hour and into 1 minute points for a day.
+//! With `system_history_persist` on, the history is saved to `system_history.json` once a
+//! minute and picked up again at start.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use sysinfo::{Components, CpuRefreshKind, MemoryRefreshKind, RefreshKind, System};
+use tauri::{AppHandle, Manager};
+
+use super::alerts::AlertEvaluator;
+use super::{average_cpu_usage, cpu_temperature, gpu_temperature, SystemMonitor};
+use crate::services::storage::{self, AppConfig};
+
+/// Time between readings
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+/// Readings between reloads of the config (network filters, persistence)
+const CONFIG_RELOAD_SAMPLES: u64 = 60;
+/// (seconds per point, points kept): 5 minutes, 1 hour, 24 hours
+const TIERS: [(u64, usize); 3] = [(1, 300), (10, 360), (60, 1440)];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryMetric {
+    /// Percent, averaged over all cores
+    Cpu,
+    /// Percent, one series per core
+    CpuCores,
+    /// Percent of RAM in use
+    Memory,
+    /// Percent of swap in use
+    Swap,
+    /// Bytes per second in and out over the counted interfaces
+    Network,
+    /// Bytes per second read and written over the physical disks
+    DiskIo,
+    /// °C, CPU and GPU
+    Temperature,
+}
+
+/// One reading of every metric
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MetricSample {
+    /// Unix ms
+    pub time: u64,
+    pub cpu: f32,
+    pub cpu_cores: Vec<f32>,
+    pub memory: f32,
+    pub swap: f32,
+    pub network_in: f32,
+    pub network_out: f32,
+    /// None where the OS doesn't report disk I/O
+    pub disk_read: Option<f32>,
+    pub disk_write: Option<f32>,
+    pub cpu_temperature: Option<f32>,
+    pub gpu_temperature: Option<f32>,
+}
+
+fn mean(values: impl Iterator<Item = f32>) -> Option<f32> {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), v| (sum + v, count + 1));
+    (count > 0).then(|| sum / count as f32)
+}
+
+impl MetricSample {
+    /// Series names of a metric and this reading's values for them
+    fn series(&self, metric: HistoryMetric) -> (Vec<String>, Vec<Option<f32>>) {
+        let named = |pairs: &[(&str, Option<f32>)]| {
+            (
+                pairs.iter().map(|(name, _)| name.to_string()).collect(),
+                pairs.iter().map(|(_, value)| *value).collect(),
+            )
+        };
+        match metric {
+            HistoryMetric::Cpu => named(&[("usage", Some(self.cpu))]),
+            HistoryMetric::CpuCores => (
+                (0..self.cpu_cores.len()).map(|i| i.to_string()).collect(),
+                self.cpu_cores.iter().map(|&v| Some(v)).collect(),
+            ),
+            HistoryMetric::Memory => named(&[("used", Some(self.memory))]),
+            HistoryMetric::Swap => named(&[("used", Some(self.swap))]),
+            HistoryMetric::Network => named(&[
+                ("in", Some(self.network_in)),
+                ("out", Some(self.network_out)),
+            ]),
+            HistoryMetric::DiskIo => named(&[("read", self.disk_read), ("write", self.disk_write)]),
+            HistoryMetric::Temperature => {
+                named(&[("cpu", self.cpu_temperature), ("gpu", self.gpu_temperature)])
+            }
+        }
+    }
+
+    /// Average of a bucket of readings, stamped with the bucket's start
+    fn average(samples: &[MetricSample], time: u64) -> Self {
+        let avg = |f: fn(&MetricSample) -> f32| mean(samples.iter().map(f)).unwrap_or(0.0);
+        let avg_opt = |f: fn(&MetricSample) -> Option<f32>| mean(samples.iter().filter_map(f));
+        let cores = samples.iter().map(|s| s.cpu_cores.len()).max().unwrap_or(0);
+        Self {
+            time,
+            cpu: avg(|s| s.cpu),
+            cpu_cores: (0..cores)
+                .map(|i| {
+                    mean(samples.iter().filter_map(|s| s.cpu_cores.get(i).copied())).unwrap_or(0.0)
+                })
+                .collect(),
+            memory: avg(|s| s.memory),
+            swap: avg(|s| s.swap),
+            network_in: avg(|s| s.network_in),
+            network_out: avg(|s| s.network_out),
+            disk_read: avg_opt(|s| s.disk_read),
+            disk_write: avg_opt(|s| s.disk_write),
+            cpu_temperature: avg_opt(|s| s.cpu_temperature),
+            gpu_temperature: avg_opt(|s| s.gpu_temperature),
+        }
+    }
+}
+
+/// Points at one resolution, oldest first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Tier {
+    resolution_secs: u64,
+    capacity: usize,
+    points: VecDeque<MetricSample>,
+    /// Readings of the bucket being filled
+    #[serde(skip)]
+    pending: Vec<MetricSample>,
+}
+
+impl Tier {
+    fn new((resolution_secs, capacity): (u64, usize)) -> Self {
+        Self {
+            resolution_secs,
+            capacity,
+            points: VecDeque::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    fn span_ms(&self) -> u64 {
+        self.resolution_secs * self.capacity as u64 * 1000
+    }
+
+    fn bucket(&self, time: u64) -> u64 {
+        time / (self.resolution_secs * 1000)
+    }
+
+    /// Add a reading; true when it completed a point
+    fn push(&mut self, sample: &MetricSample) -> bool {
+        let mut added = false;
+        if self.resolution_secs <= 1 {
+            self.points.push_back(sample.clone());
+            added = true;
+        } else {
+            if let Some(first) = self.pending.first() {
+                let bucket = self.bucket(first.time);
+                if bucket != self.bucket(sample.time) {
+                    let start = bucket * self.resolution_secs * 1000;
+                    self.points
+                        .push_back(MetricSample::average(&self.pending, start));
+                    self.pending.clear();
+                    added = true;
+                }
+            }
+            self.pending.push(sample.clone());
+        }
+        while self.points.len() > self.capacity {
+            self.points.pop_front();
+        }
+        added
+    }
+
+    /// Drop points older than the tier covers
+    fn expire(&mut self, now: u64) {
+        let oldest = now.saturating_sub(self.span_ms());
+        while self.points.front().is_some_and(|p| p.time < oldest) {
+            self.points.pop_front();
+        }
+    }
+}
+
+/// Managed state: every tier of the history
+pub struct MetricsHistory {
+    tiers: Mutex<Vec<Tier>>,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn history_path(config: &AppConfig) -> PathBuf {
+    storage::get_captures_dir(config).join("system_history.json")
+}
+
+impl MetricsHistory {
+    /// Empty, or the saved history when persistence is on
+    pub fn load(app: &AppHandle) -> Self {
+        let config = storage::load_config(app.clone());
+        let saved = config
+            .system_history_persist
+            .then(|| fs::read_to_string(history_path(&config)).ok())
+            .flatten();
+        Self {
+            tiers: Mutex::new(restore_tiers(saved.as_deref(), now_ms())),
+        }
+    }
+
+    /// Add a reading to every tier; true when the coarsest tier got a new point
+    fn record(&self, sample: &MetricSample) -> bool {
+        let Ok(mut tiers) = self.tiers.lock() else {
+            return false;
+        };
+        let mut added = false;
+        // Coarsest last, so this ends up telling about it
+        for tier in tiers.iter_mut() {
+            added = tier.push(sample);
+        }
+        added
+    }
+
+    fn save(&self, config: &AppConfig) -> Result<(), String> {
+        storage::ensure_data_directories(config)?;
+        let json = {
+            let tiers = self.tiers.lock().map_err(|e| e.to_string())?;
+            serde_json::to_string(&*tiers).map_err(|e| e.to_string())?
+        };
+        fs::write(history_path(config), json).map_err(|e| e.to_string())
+    }
+}
+
+/// Saved tiers with expired points dropped, or empty ones when there is nothing saved with
+/// the current layout
+fn restore_tiers(saved: Option<&str>, now: u64) -> Vec<Tier> {
+    let saved = saved
+        .and_then(|content| serde_json::from_str::<Vec<Tier>>(content).ok())
+        // Only if it was saved with the same layout
+        .filter(|tiers| {
+            tiers.len() == TIERS.len()
+                && tiers
+                    .iter()
+                    .zip(TIERS)
+                    .all(|(tier, (res, cap))| tier.resolution_secs == res && tier.capacity == cap)
+        });
+
+    let mut tiers = saved.unwrap_or_else(|| TIERS.into_iter().map(Tier::new).collect());
+    for tier in &mut tiers {
+        tier.expire(now);
+    }
+    tiers
+}
+
+fn percent(used: u64, total: u64) -> f32 {
+    if total > 0 {
+        (used as f64 / total as f64 * 100.0) as f32
+    } else {
+        0.0
+    }
+}
+
+/// Read every metric, taking each lock only as long as needed
+/// `sys` is the sampler's own: CPU usage is measured since the previous refresh, so sharing
+/// `SystemMonitor.system` with `get_system_stats` would shorten both intervals and skew both readings
+fn take_sample(
+    monitor: &SystemMonitor,
+    sys: &mut System,
+    components: &mut Components,
+    config: &AppConfig,
+) -> Result<MetricSample, String> {
+    sys.refresh_cpu_usage();
+    sys.refresh_memory();
+    let cpu = average_cpu_usage(sys);
+    let cpu_cores = sys.cpus().iter().map(|cpu| cpu.cpu_usage()).collect();
+    let memory = percent(sys.used_memory(), sys.total_memory());
+    let swap = percent(sys.used_swap(), sys.total_swap());
+    let network = {
+        let mut networks = monitor.networks.lock().map_err(|e| e.to_string())?;
+        let mut rates = monitor.network_rates.lock().map_err(|e| e.to_string())?;
+        rates.sample(&mut networks, config)
+    };
+    let disk_io = monitor
+        .disk_rates
+        .lock()
+        .map_err(|e| e.to_string())?
+        .total_io();
+    components.refresh();
+
+    Ok(MetricSample {
+        time: now_ms(),
+        cpu,
+        cpu_cores,
+        memory,
+        swap,
+        network_in: network.received_per_sec as f32,
+        network_out: network.transmitted_per_sec as f32,
+        disk_read: disk_io.map(|(read, _)| read as f32),
+        disk_write: disk_io.map(|(_, written)| written as f32),
+        cpu_temperature: cpu_temperature(components),
+        gpu_temperature: gpu_temperature(components),
+    })
+}
+
+/// Take a reading every second, add it to the history and check it against the alert rules
+pub fn start_sampler(app: AppHandle) {
+    std::thread::spawn(move || {
+        let mut sys = System::new_with_specifics(
+            RefreshKind::new()
+                .with_cpu(CpuRefreshKind::new().with_cpu_usage())
+                .with_memory(MemoryRefreshKind::everything()),
+        );
+        let mut components = Components::new_with_refreshed_list();
+        let mut alerts = AlertEvaluator::default();
+        let mut config = storage::load_config(app.clone());
+        let mut count: u64 = 0;
+        loop {
+            std::thread::sleep(SAMPLE_INTERVAL);
+            count += 1;
+            if count >= CONFIG_RELOAD_SAMPLES {
+                config = storage::load_config(app.clone());
+                count = 0;
+            }
+
+            let monitor = app.state::<SystemMonitor>();
+            let sample = match take_sample(&monitor, &mut sys, &mut components, &config) {
+                Ok(sample) => sample,
+                Err(e) => {
+                    eprintln!("Failed to sample system metrics: {}", e);
+                    continue;
+                }
+            };
+            let history = app.state::<MetricsHistory>();
+            if history.record(&sample) && config.system_history_persist {
+                if let Err(e) = history.save(&config) {
+                    eprintln!("Failed to save system history: {}", e);
+                }
+            }
+            alerts.evaluate(&app, &config, &sample);
+        }
+    });
+}
+
+/// Points of one metric over a time range
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SystemHistory {
+    pub metric: HistoryMetric,
+    /// Seconds between points
+    pub resolution_secs: u64,
+    /// What each of a point's values is ("in"/"out", core numbers, ...)
+    pub series: Vec<String>,
+    /// Oldest first
+    pub points: Vec<HistoryPoint>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryPoint {
+    /// Unix ms
+    pub time: u64,
+    /// One per series, None when it couldn't be read
+    pub values: Vec<Option<f32>>,
+}
+
+/// "90s", "5m", "1h", "24h" or "1d" in milliseconds
+fn parse_range_ms(range: &str) -> Result<u64, String> {
+    let range = range.trim();
+    let invalid = || format!("Invalid range '{}', use e.g. 5m, 1h or 24h", range);
+    let split = range
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(range.len());
+    let (amount, unit) = range.split_at(split);
+    let amount: u64 = amount.parse().map_err(|_| invalid())?;
+    let unit_secs = match unit.trim() {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        _ => return Err(invalid()),
+    };
+    if amount == 0 {
+        return Err(invalid());
+    }
+    amount
+        .checked_mul(unit_secs)
+        .and_then(|secs| secs.checked_mul(1000))
+        .ok_or_else(invalid)
+}
+
+/// History of a metric over the last `range` ("5m", "1h", "24h"), at the finest
+/// resolution that covers it
+#[tauri::command]
+pub fn get_system_history(
+    history: tauri::State<MetricsHistory>,
+    metric: HistoryMetric,
+    range: String,
+) -> Result<SystemHistory, String> {
+    let range_ms = parse_range_ms(&range)?;
+    let tiers = history.tiers.lock().map_err(|e| e.to_string())?;
+    select_history(&tiers, metric, range_ms, now_ms())
+}
+
+fn select_history(
+    tiers: &[Tier],
+    metric: HistoryMetric,
+    range_ms: u64,
+    now: u64,
+) -> Result<SystemHistory, String> {
+    let tier = tiers
+        .iter()
+        .find(|tier| tier.span_ms() >= range_ms)
+        .or(tiers.last())
+        .ok_or("No system history")?;
+
+    let since = now.saturating_sub(range_ms);
+    let (mut series, _) = MetricSample::default().series(metric);
+    let points = tier
+        .points
+        .iter()
+        .filter(|sample| sample.time >= since)
+        .map(|sample| {
+            let (names, values) = sample.series(metric);
+            // Core counts don't change while running, the widest reading names them
+            if names.len() > series.len() {
+                series = names;
+            }
+            HistoryPoint {
+                time: sample.time,
+                values,
+            }
+        })
+        .collect();
+
+    Ok(SystemHistory {
+        metric,
+        resolution_secs: tier.resolution_secs,
+        series,
+        points,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(time: u64, cpu: f32) -> MetricSample {
+        MetricSample {
+            time,
+            cpu,
+            ..Default::default()
+        }
+    }
+
+    fn filled_tiers(now: u64) -> Vec<Tier> {
+        let mut tiers: Vec<Tier> = TIERS.into_iter().map(Tier::new).collect();
+        for tier in &mut tiers {
+            let step = tier.resolution_secs * 1000;
+            for i in (0..tier.capacity as u64).rev() {
+                tier.points.push_back(sample(now - i * step, 1.0));
+            }
+        }
+        tiers
+    }
+
+    #[test]
+    fn finest_tier_keeps_every_reading() {
+        let mut tier = Tier::new((1, 3));
+        for i in 0..5 {
+            assert!(tier.push(&sample(i * 1000, i as f32)));
+        }
+        let times: Vec<u64> = tier.points.iter().map(|p| p.time).collect();
+        assert_eq!(times, [2000, 3000, 4000]);
+    }
+
+    #[test]
+    fn coarse_tiers_average_each_bucket() {
+        let mut tier = Tier::new((10, 10));
+        // 12 s to 21 s: the 10 s bucket is done when the first reading of the next one arrives
+        for (i, time) in (12_000..=19_000).step_by(1000).enumerate() {
+            assert!(!tier.push(&sample(time, i as f32)));
+        }
+        assert!(tier.points.is_empty());
+        assert!(tier.push(&sample(21_000, 100.0)));
+
+        let point = &tier.points[0];
+        assert_eq!(point.time, 10_000);
+        assert_eq!(point.cpu, 3.5);
+        assert_eq!(tier.pending.len(), 1);
+    }
+
+    #[test]
+    fn coarse_tiers_keep_their_capacity() {
+        let mut tier = Tier::new((10, 2));
+        for bucket in 0..5 {
+            tier.push(&sample(bucket * 10_000, bucket as f32));
+        }
+        let times: Vec<u64> = tier.points.iter().map(|p| p.time).collect();
+        assert_eq!(times, [20_000, 30_000]);
+    }
+
+    #[test]
+    fn expire_drops_points_older_than_the_span() {
+        let mut tier = Tier::new((10, 3));
+        for time in [0, 10_000, 20_000, 30_000, 40_000] {
+            tier.points.push_back(sample(time, 0.0));
+        }
+        tier.expire(50_000);
+        let times: Vec<u64> = tier.points.iter().map(|p| p.time).collect();
+        assert_eq!(times, [20_000, 30_000, 40_000]);
+
+        tier.expire(1_000_000);
+        assert!(tier.points.is_empty());
+    }
+
+    #[test]
+    fn average_handles_missing_cores_and_values() {
+        let readings = [
+            MetricSample {
+                cpu: 10.0,
+                cpu_cores: vec![10.0, 20.0],
+                disk_read: Some(100.0),
+                cpu_temperature: None,
+                ..Default::default()
+            },
+            MetricSample {
+                cpu: 30.0,
+                cpu_cores: vec![30.0],
+                disk_read: None,
+                cpu_temperature: None,
+                ..Default::default()
+            },
+        ];
+        let point = MetricSample::average(&readings, 5);
+        assert_eq!(point.time, 5);
+        assert_eq!(point.cpu, 20.0);
+        // A core only one reading has is averaged over that reading
+        assert_eq!(point.cpu_cores, [20.0, 20.0]);
+        assert_eq!(point.disk_read, Some(100.0));
+        assert_eq!(point.cpu_temperature, None);
+    }
+
+    #[test]
+    fn ranges_parse_to_milliseconds() {
+        assert_eq!(parse_range_ms("90s"), Ok(90_000));
+        assert_eq!(parse_range_ms(" 5m "), Ok(300_000));
+        assert_eq!(parse_range_ms("24h"), Ok(86_400_000));
+        assert_eq!(parse_range_ms("1d"), Ok(86_400_000));
+        assert_eq!(parse_range_ms("30"), Ok(30_000));
+        for bad in [
+            "",
+            "0m",
+            "5w",
+            "h",
+            "-5m",
+            "99999999999999999d",
+            "18446744073709551615",
+        ] {
+            assert!(parse_range_ms(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn history_uses_the_finest_tier_covering_the_range() {
+        let now = 100_000_000;
+        let tiers = filled_tiers(now);
+
+        let history = select_history(&tiers, HistoryMetric::Cpu, 300_000, now).unwrap();
+        assert_eq!(history.resolution_secs, 1);
+        assert_eq!(history.series, ["usage"]);
+        assert_eq!(history.points.len(), 300);
+
+        let history = select_history(&tiers, HistoryMetric::Cpu, 301_000, now).unwrap();
+        assert_eq!(history.resolution_secs, 10);
+
+        let history = select_history(&tiers, HistoryMetric::Network, 3_600_000, now).unwrap();
+        assert_eq!(history.resolution_secs, 10);
+        assert_eq!(history.series, ["in", "out"]);
+
+        // Longer than anything kept: the coarsest tier
+        let history = select_history(&tiers, HistoryMetric::Cpu, 7 * 86_400_000, now).unwrap();
+        assert_eq!(history.resolution_secs, 60);
+        assert_eq!(history.points.len(), 1440);
+    }
+
+    #[test]
+    fn history_points_are_limited_to_the_range() {
+        let now = 100_000_000;
+        let history =
+            select_history(&filled_tiers(now), HistoryMetric::Memory, 60_000, now).unwrap();
+        assert_eq!(history.points.len(), 61);
+        assert!(history.points.iter().all(|p| p.time >= now - 60_000));
+    }
+
+    #[test]
+    fn saved_history_needs_the_same_layout() {
+        let now = 100_000_000;
+        let saved = serde_json::to_string(&filled_tiers(now)).unwrap();
+        let tiers = restore_tiers(Some(&saved), now);
+        assert_eq!(tiers[0].points.len(), 300);
+
+        // Expired points are dropped
+        let tiers = restore_tiers(Some(&saved), now + 60_000);
+        assert_eq!(tiers[0].points.len(), 241);
+
+        let mut other: Vec<Tier> = filled_tiers(now);
+        other[1].capacity = 100;
+        let other = serde_json::to_string(&other).unwrap();
+        let two = serde_json::to_string(&filled_tiers(now)[..2]).unwrap();
+        for saved in [
+            Some(other.as_str()),
+            Some(two.as_str()),
+            Some("not json"),
+            None,
+        ] {
+            let tiers = restore_tiers(saved, now);
+            assert_eq!(tiers.len(), TIERS.len());
+            assert!(tiers.iter().all(|tier| tier.points.is_empty()));
+        }
+    }
+}
@@ -0,0 +1,46 @@
+//! Memory sysinfo doesn't break down
+//! Page cache and buffer sizes come from `/proc/meminfo` and are only available on Linux;
+//! elsewhere they are None.
+
+/// Bytes the kernel holds for file caching, reclaimable when programs need it
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PageCache {
+    pub cached: u64,
+    pub buffers: u64,
+}
+
+pub fn page_cache() -> Option<PageCache> {
+    platform::page_cache()
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::fs;
+
+    use super::PageCache;
+
+    /// "Cached:     1234567 kB" lines; the kernel always means KiB
+    pub fn page_cache() -> Option<PageCache> {
+        let content = fs::read_to_string("/proc/meminfo").ok()?;
+        let field = |name: &str| {
+            content.lines().find_map(|line| {
+                let value = line.strip_prefix(name)?.strip_prefix(':')?;
+                let kib: u64 = value.split_whitespace().next()?.parse().ok()?;
+                Some(kib * 1024)
+            })
+        };
+        Some(PageCache {
+            cached: field("Cached")?,
+            buffers: field("Buffers")?,
+        })
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod platform {
+    use super::PageCache;
+
+    pub fn page_cache() -> Option<PageCache> {
+        None
+    }
+}
@@ -0,0 +1,330 @@
+pub mod alerts;
+pub mod disks;
+pub mod history;
+mod memory;
+pub mod network;
+mod rates;
+
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use sysinfo::{Components, CpuRefreshKind, Networks, System, ThreadKind};
+use tauri::AppHandle;
+
+use crate::services::storage;
+use disks::{DiskInfo, DiskRates};
+use network::{NetworkInterface, NetworkRates};
+
+/// Managed state for system monitoring
+pub struct SystemMonitor {
+    pub system: Mutex<System>,
+    pub networks: Mutex<Networks>,
+    pub network_rates: Mutex<NetworkRates>,
+    pub disk_rates: Mutex<DiskRates>,
+}
+
+impl SystemMonitor {
+    pub fn new() -> Self {
+        let mut sys = System::new_all();
+        // Initial refresh to populate data
+        sys.refresh_all();
+
+        Self {
+            system: Mutex::new(sys),
+            networks: Mutex::new(Networks::new_with_refreshed_list()),
+            network_rates: Mutex::new(NetworkRates::default()),
+            disk_rates: Mutex::new(DiskRates::default()),
+        }
+    }
+}
+
+impl Default for SystemMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SystemStats {
+    pub cpu_usage: f32,
+    pub cpu_count: usize,
+    pub cpu_name: String,
+    pub cpu_brand: String,
+    pub cpu_vendor: String,
+    /// In the order the OS lists them
+    pub cpu_cores: Vec<CpuCore>,
+    /// None on Windows, which has no load average
+    pub load_average: Option<LoadAverage>,
+    pub memory_used: u64,
+    pub memory_total: u64,
+    pub memory_percent: f32,
+    /// Free plus what can be reclaimed without swapping
+    pub memory_available: u64,
+    pub memory_free: u64,
+    /// Page cache and buffers, None outside Linux
+    pub memory_cached: Option<u64>,
+    pub memory_buffers: Option<u64>,
+    pub swap_used: u64,
+    pub swap_total: u64,
+    pub swap_percent: f32,
+    /// Primary disk
+    pub disk_used: u64,
+    pub disk_total: u64,
+    pub disk_percent: f32,
+    /// Every disk once, primary first
+    pub disks: Vec<DiskInfo>,
+    pub cpu_temperature: Option<f32>,
+    pub gpu_temperature: Option<f32>,
+    pub gpu_name: Option<String>,
+    pub gpu_usage: Option<f32>,
+    pub gpu_memory_used: Option<u64>,
+    pub gpu_memory_total: Option<u64>,
+    /// Bytes per second over the counted interfaces
+    pub network_in: u64,
+    pub network_out: u64,
+    /// Bytes since boot over the counted interfaces
+    pub network_received_total: u64,
+    pub network_transmitted_total: u64,
+    pub networks: Vec<NetworkInterface>,
+    pub uptime_seconds: u64,
+    pub process_count: usize,
+    /// Threads across all processes, None outside Linux
+    pub thread_count: Option<usize>,
+    pub processes: Vec<ProcessInfo>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CpuCore {
+    pub name: String,
+    pub usage: f32,
+    /// Current clock speed in MHz
+    pub frequency: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoadAverage {
+    pub one: f64,
+    pub five: f64,
+    pub fifteen: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub name: String,
+    pub cpu_usage: f32,
+    pub memory_mb: f64,
+    pub status: String,
+}
+
+/// CPU temperature - look for CPU/core/die/package labels
+fn cpu_temperature(components: &Components) -> Option<f32> {
+    components
+        .iter()
+        .find(|c| {
+            let label = c.label().to_lowercase();
+            label.contains("cpu") || label.contains("core") || label.contains("die") || label.contains("package")
+        })
+        .map(|c| c.temperature())
+}
+
+/// GPU temperature - look for GPU labels
+/// On macOS: TG0P, IGPU are common GPU/graphics indicators
+/// On Linux: usually labeled as "gpu" or "Graphics"
+fn gpu_temperature(components: &Components) -> Option<f32> {
+    components
+        .iter()
+        .find(|c| {
+            let label = c.label().to_lowercase();
+            label.contains("gpu") || label.contains("graphics") ||
+            label.contains("tg0p") || label.contains("tg0d") ||
+            label.contains("igpu")
+        })
+        .map(|c| c.temperature())
+}
+
+/// Usage averaged over all cores
+fn average_cpu_usage(sys: &System) -> f32 {
+    if sys.cpus().is_empty() {
+        0.0
+    } else {
+        sys.cpus().iter().map(|cpu| cpu.cpu_usage()).sum::<f32>() / sys.cpus().len() as f32
+    }
+}
+
+#[tauri::command]
+pub fn get_system_stats(
+    app: AppHandle,
+    state: tauri::State<SystemMonitor>,
+) -> Result<SystemStats, String> {
+    let config = storage::load_config(app);
+    let mut sys = state.system.lock().map_err(|e| e.to_string())?;
+    let mut networks = state.networks.lock().map_err(|e| e.to_string())?;
+    let mut network_rates = state.network_rates.lock().map_err(|e| e.to_string())?;
+    let mut disk_rates = state.disk_rates.lock().map_err(|e| e.to_string())?;
+
+    // Refresh all metrics
+    sys.refresh_cpu_specifics(CpuRefreshKind::everything());
+    sys.refresh_memory();
+    sys.refresh_processes(sysinfo::ProcessesToUpdate::All, true);
+
+    // CPU usage (average across all cores)
+    let cpu_usage = average_cpu_usage(&sys);
+
+    // CPU info (get from first CPU as they're all the same model)
+    let (cpu_name, cpu_brand, cpu_vendor) = if let Some(cpu) = sys.cpus().first() {
+        (
+            cpu.name().to_string(),
+            cpu.brand().to_string(),
+            cpu.vendor_id().to_string(),
+        )
+    } else {
+        (String::from("Unknown"), String::from("Unknown"), String::from("Unknown"))
+    };
+
+    // Per-core usage and clock speed
+    let cpu_cores = sys
+        .cpus()
+        .iter()
+        .map(|cpu| CpuCore {
+            name: cpu.name().to_string(),
+            usage: cpu.cpu_usage(),
+            frequency: cpu.frequency(),
+        })
+        .collect();
+
+    // Load average (sysinfo reports zeros on Windows)
+    let load_average = if cfg!(windows) {
+        None
+    } else {
+        let load = System::load_average();
+        Some(LoadAverage {
+            one: load.one,
+            five: load.five,
+            fifteen: load.fifteen,
+        })
+    };
+
+    // Memory
+    let memory_used = sys.used_memory();
+    let memory_total = sys.total_memory();
+    let memory_percent = if memory_total > 0 {
+        (memory_used as f64 / memory_total as f64 * 100.0) as f32
+    } else {
+        0.0
+    };
+    let page_cache = memory::page_cache();
+
+    // Swap
+    let swap_used = sys.used_swap();
+    let swap_total = sys.total_swap();
+    let swap_percent = if swap_total > 0 {
+        (swap_used as f64 / swap_total as f64 * 100.0) as f32
+    } else {
+        0.0
+    };
+
+    // Disks, one entry per device; the headline gauge shows the primary one
+    let disks = disk_rates.sample(&config);
+    let (disk_used, disk_total, disk_percent) = disks
+        .iter()
+        .find(|disk| disk.is_primary)
+        .map(|disk| (disk.used_space, disk.total_space, disk.percent))
+        .unwrap_or((0, 0, 0.0));
+
+    // Temperature (separate CPU and GPU temps)
+    let components = Components::new_with_refreshed_list();
+    let cpu_temperature = cpu_temperature(&components);
+    let gpu_temperature = gpu_temperature(&components);
+
+    // GPU info - sysinfo doesn't provide GPU stats natively
+    // These would require platform-specific APIs (Metal for macOS, NVML for NVIDIA, etc.)
+    // For now, we'll leave them as None, but we can detect if a GPU temp is available
+    let gpu_name = if gpu_temperature.is_some() {
+        Some(String::from("GPU")) // Generic name, could be enhanced with platform-specific detection
+    } else {
+        None
+    };
+    let gpu_usage: Option<f32> = None;
+    let gpu_memory_used: Option<u64> = None;
+    let gpu_memory_total: Option<u64> = None;
+
+    // Network I/O per interface, with the counted ones summed
+    let network = network_rates.sample(&mut networks, &config);
+
+    // System uptime
+    let uptime_seconds = System::uptime();
+
+    // On Linux every thread is listed too, as an entry of its own
+    let real_processes: Vec<_> = sys
+        .processes()
+        .iter()
+        .filter(|(_, process)| process.thread_kind() != Some(ThreadKind::Userland))
+        .collect();
+    let process_count = real_processes.len();
+    // Tasks are the threads besides the main one, and are only known on Linux
+    let thread_count = cfg!(target_os = "linux").then(|| {
+        real_processes
+            .iter()
+            .map(|(_, process)| 1 + process.tasks().map_or(0, |tasks| tasks.len()))
+            .sum()
+    });
+
+    // Top processes by memory usage
+    let mut processes: Vec<ProcessInfo> = real_processes
+        .into_iter()
+        .map(|(pid, process)| ProcessInfo {
+            pid: pid.as_u32(),
+            name: process.name().to_string_lossy().to_string(),
+            cpu_usage: process.cpu_usage(),
+            memory_mb: process.memory() as f64 / 1024.0 / 1024.0,
+            status: format!("{:?}", process.status()),
+        })
+        .collect();
+
+    // Sort by memory usage descending (more reliable than CPU for single snapshots)
+    processes.sort_by(|a, b| {
+        b.memory_mb
+            .partial_cmp(&a.memory_mb)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    processes.truncate(30);
+
+    Ok(SystemStats {
+        cpu_usage,
+        cpu_count: sys.cpus().len(),
+        cpu_name,
+        cpu_brand,
+        cpu_vendor,
+        cpu_cores,
+        load_average,
+        memory_used,
+        memory_total,
+        memory_percent,
+        memory_available: sys.available_memory(),
+        memory_free: sys.free_memory(),
+        memory_cached: page_cache.map(|cache| cache.cached),
+        memory_buffers: page_cache.map(|cache| cache.buffers),
+        swap_used,
+        swap_total,
+        swap_percent,
+        disk_used,
+        disk_total,
+        disk_percent,
+        disks,
+        cpu_temperature,
+        gpu_temperature,
+        gpu_name,
+        gpu_usage,
+        gpu_memory_used,
+        gpu_memory_total,
+        network_in: network.received_per_sec,
+        network_out: network.transmitted_per_sec,
+        network_received_total: network.total_received,
+        network_transmitted_total: network.total_transmitted,
+        networks: network.interfaces,
+        uptime_seconds,
+        process_count,
+        thread_count,
+        processes,
+    })
+}
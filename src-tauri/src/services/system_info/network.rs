@@ -0,0 +1,248 @@
+//! Per-interface network throughput
+//! sysinfo's `received()`/`transmitted()` are deltas since the previous refresh, so they
+//! depend on how often stats are polled. Rates here come from the totals since boot and the
+//! time between samples. Loopback and virtual interfaces are left out of the totals unless
+//! `network_include_virtual` is set or they are listed in `network_include`.
+
+use serde::{Deserialize, Serialize};
+use sysinfo::Networks;
+
+use super::rates::Rates;
+use crate::services::storage::AppConfig;
+
+/// Name prefixes of virtual interfaces, used where the OS can't tell
+const VIRTUAL_PREFIXES: [&str; 13] = [
+    "docker", "veth", "br-", "virbr", "vmnet", "vboxnet", "utun", "awdl", "llw", "tun", "tap",
+    "bridge", "anpi",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkInterface {
+    pub name: String,
+    pub is_up: bool,
+    pub is_loopback: bool,
+    /// Bridges, tunnels, container and VM interfaces
+    pub is_virtual: bool,
+    /// Counted in the network totals
+    pub included: bool,
+    /// None when the interface has no hardware address
+    pub mac_address: Option<String>,
+    /// "address/prefix"
+    pub ip_addresses: Vec<String>,
+    /// Bytes per second
+    pub received_per_sec: u64,
+    pub transmitted_per_sec: u64,
+    /// Bytes since boot
+    pub total_received: u64,
+    pub total_transmitted: u64,
+}
+
+/// Included interfaces added up, plus every interface on its own
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NetworkSummary {
+    pub received_per_sec: u64,
+    pub transmitted_per_sec: u64,
+    pub total_received: u64,
+    pub total_transmitted: u64,
+    pub interfaces: Vec<NetworkInterface>,
+}
+
+/// Totals at the previous sample, to work out rates from
+#[derive(Default)]
+pub struct NetworkRates {
+    rates: Rates,
+}
+
+impl NetworkRates {
+    /// Refresh the interface list (interfaces come and go) and report on them
+    pub fn sample(&mut self, networks: &mut Networks, config: &AppConfig) -> NetworkSummary {
+        networks.refresh_list();
+        self.rates.update(
+            networks
+                .iter()
+                .map(|(name, data)| {
+                    (
+                        name.clone(),
+                        (data.total_received(), data.total_transmitted()),
+                    )
+                })
+                .collect(),
+        );
+
+        let mut summary = NetworkSummary::default();
+        for (name, data) in networks.iter() {
+            let ips: Vec<_> = data.ip_networks().iter().map(|ip| ip.addr).collect();
+            let is_loopback = name == "lo"
+                || name.starts_with("lo0")
+                || (!ips.is_empty() && ips.iter().all(|ip| ip.is_loopback()));
+            let is_virtual = !is_loopback
+                && platform::is_virtual(name).unwrap_or_else(|| {
+                    VIRTUAL_PREFIXES
+                        .iter()
+                        .any(|prefix| name.starts_with(prefix))
+                });
+            let is_up = platform::is_up(name).unwrap_or(!ips.is_empty());
+            let mac = data.mac_address();
+            let (received_per_sec, transmitted_per_sec) = self.rates.get(name);
+
+            let mut interface = NetworkInterface {
+                name: name.clone(),
+                is_up,
+                is_loopback,
+                is_virtual,
+                included: false,
+                mac_address: (!mac.is_unspecified()).then(|| mac.to_string()),
+                ip_addresses: data.ip_networks().iter().map(|ip| ip.to_string()).collect(),
+                received_per_sec,
+                transmitted_per_sec,
+                total_received: data.total_received(),
+                total_transmitted: data.total_transmitted(),
+            };
+            interface.included = is_included(&interface, config);
+            if interface.included {
+                summary.received_per_sec += interface.received_per_sec;
+                summary.transmitted_per_sec += interface.transmitted_per_sec;
+                summary.total_received += interface.total_received;
+                summary.total_transmitted += interface.total_transmitted;
+            }
+            summary.interfaces.push(interface);
+        }
+
+        // Counted ones first, then the busiest
+        summary.interfaces.sort_by(|a, b| {
+            b.included
+                .cmp(&a.included)
+                .then_with(|| b.total_received.cmp(&a.total_received))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        summary
+    }
+}
+
+/// An exact name, or a prefix when the pattern ends with `*` ("en*")
+fn matches(name: &str, pattern: &str) -> bool {
+    let pattern = pattern.trim();
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => name == pattern,
+    }
+}
+
+/// Excludes win; a non-empty include list picks the interfaces outright,
+/// otherwise every physical interface counts
+fn is_included(interface: &NetworkInterface, config: &AppConfig) -> bool {
+    let name = interface.name.as_str();
+    if config.network_exclude.iter().any(|p| matches(name, p)) {
+        return false;
+    }
+    if !config.network_include.is_empty() {
+        return config.network_include.iter().any(|p| matches(name, p));
+    }
+    config.network_include_virtual || !(interface.is_loopback || interface.is_virtual)
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::fs;
+
+    /// Link state from sysfs; None for "unknown", which loopback and some tunnels report
+    pub fn is_up(name: &str) -> Option<bool> {
+        let state = fs::read_to_string(format!("/sys/class/net/{}/operstate", name)).ok()?;
+        match state.trim() {
+            "up" => Some(true),
+            "unknown" => None,
+            _ => Some(false),
+        }
+    }
+
+    /// Interfaces without a backing device live under /sys/devices/virtual
+    pub fn is_virtual(name: &str) -> Option<bool> {
+        fs::canonicalize(format!("/sys/class/net/{}", name))
+            .ok()
+            .map(|path| path.to_string_lossy().contains("/virtual/"))
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod platform {
+    pub fn is_up(_name: &str) -> Option<bool> {
+        None
+    }
+
+    pub fn is_virtual(_name: &str) -> Option<bool> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interface(name: &str, is_loopback: bool, is_virtual: bool) -> NetworkInterface {
+        NetworkInterface {
+            name: name.to_string(),
+            is_up: true,
+            is_loopback,
+            is_virtual,
+            included: false,
+            mac_address: None,
+            ip_addresses: Vec::new(),
+            received_per_sec: 0,
+            transmitted_per_sec: 0,
+            total_received: 0,
+            total_transmitted: 0,
+        }
+    }
+
+    fn filters(include: &[&str], exclude: &[&str], include_virtual: bool) -> AppConfig {
+        AppConfig {
+            network_include: include.iter().map(|s| s.to_string()).collect(),
+            network_exclude: exclude.iter().map(|s| s.to_string()).collect(),
+            network_include_virtual: include_virtual,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn patterns_match_exactly_or_by_prefix() {
+        assert!(matches("en0", "en0"));
+        assert!(matches("en0", " en0 "));
+        assert!(!matches("en01", "en0"));
+        assert!(matches("en0", "en*"));
+        assert!(matches("enp3s0", "en*"));
+        assert!(!matches("wlan0", "en*"));
+        assert!(matches("anything", "*"));
+    }
+
+    #[test]
+    fn physical_interfaces_count_by_default() {
+        let config = filters(&[], &[], false);
+        assert!(is_included(&interface("eth0", false, false), &config));
+        assert!(!is_included(&interface("lo", true, false), &config));
+        assert!(!is_included(&interface("docker0", false, true), &config));
+
+        let config = filters(&[], &[], true);
+        assert!(is_included(&interface("lo", true, false), &config));
+        assert!(is_included(&interface("docker0", false, true), &config));
+    }
+
+    #[test]
+    fn include_list_picks_interfaces_outright() {
+        let config = filters(&["en*", "utun3"], &[], false);
+        assert!(is_included(&interface("en0", false, false), &config));
+        assert!(is_included(&interface("utun3", false, true), &config));
+        assert!(!is_included(&interface("utun4", false, true), &config));
+        assert!(!is_included(&interface("wlan0", false, false), &config));
+    }
+
+    #[test]
+    fn exclude_wins_over_include() {
+        let config = filters(&["en*"], &["en1"], true);
+        assert!(is_included(&interface("en0", false, false), &config));
+        assert!(!is_included(&interface("en1", false, false), &config));
+
+        let config = filters(&[], &["eth*"], true);
+        assert!(!is_included(&interface("eth0", false, false), &config));
+        assert!(is_included(&interface("lo", true, false), &config));
+    }
+}
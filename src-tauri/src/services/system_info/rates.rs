@@ -0,0 +1,141 @@
+//! Per-second rates from counters that only ever go up (bytes since boot and the like)
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Samples closer together than this reuse the previous rates (e.g. two windows polling at once)
+const MIN_SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Pairs of counters per key at the previous sample, and the rates worked out from them
+#[derive(Default)]
+pub struct Rates {
+    sampled_at: Option<Instant>,
+    totals: HashMap<String, (u64, u64)>,
+    rates: HashMap<String, (u64, u64)>,
+}
+
+impl Rates {
+    /// Take new counter values; keys that are gone are dropped
+    pub fn update(&mut self, totals: HashMap<String, (u64, u64)>) {
+        self.update_at(totals, Instant::now());
+    }
+
+    fn update_at(&mut self, totals: HashMap<String, (u64, u64)>, now: Instant) {
+        let elapsed = self.sampled_at.map(|at| now.duration_since(at));
+        if elapsed.is_some_and(|elapsed| elapsed < MIN_SAMPLE_INTERVAL) {
+            return;
+        }
+
+        let rates = totals
+            .iter()
+            .map(|(key, &(a, b))| {
+                // Nothing to compare with on the first sample or for a new key
+                let rate = match (elapsed, self.totals.get(key)) {
+                    (Some(elapsed), Some(&(prev_a, prev_b))) => {
+                        let secs = elapsed.as_secs_f64();
+                        (
+                            // A counter that went down was reset, not negative
+                            (a.saturating_sub(prev_a) as f64 / secs).round() as u64,
+                            (b.saturating_sub(prev_b) as f64 / secs).round() as u64,
+                        )
+                    }
+                    _ => (0, 0),
+                };
+                (key.clone(), rate)
+            })
+            .collect();
+
+        self.sampled_at = Some(now);
+        self.totals = totals;
+        self.rates = rates;
+    }
+
+    /// Per-second rates of a key's counters, (0, 0) until it has been seen twice
+    pub fn get(&self, key: &str) -> (u64, u64) {
+        self.rates.get(key).copied().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn totals(pairs: &[(&str, u64, u64)]) -> HashMap<String, (u64, u64)> {
+        pairs
+            .iter()
+            .map(|&(key, a, b)| (key.to_string(), (a, b)))
+            .collect()
+    }
+
+    #[test]
+    fn first_sample_has_no_rates() {
+        let mut rates = Rates::default();
+        rates.update_at(totals(&[("en0", 1000, 2000)]), Instant::now());
+        assert_eq!(rates.get("en0"), (0, 0));
+        assert_eq!(rates.get("missing"), (0, 0));
+    }
+
+    #[test]
+    fn rates_are_per_second() {
+        let start = Instant::now();
+        let mut rates = Rates::default();
+        rates.update_at(totals(&[("en0", 1000, 2000)]), start);
+        rates.update_at(
+            totals(&[("en0", 3000, 2500), ("new", 50, 50)]),
+            start + Duration::from_secs(2),
+        );
+        assert_eq!(rates.get("en0"), (1000, 250));
+        // Seen once so far
+        assert_eq!(rates.get("new"), (0, 0));
+    }
+
+    #[test]
+    fn reset_counter_is_not_negative() {
+        let start = Instant::now();
+        let mut rates = Rates::default();
+        rates.update_at(totals(&[("en0", 5000, 5000)]), start);
+        rates.update_at(
+            totals(&[("en0", 100, 6000)]),
+            start + Duration::from_secs(1),
+        );
+        assert_eq!(rates.get("en0"), (0, 1000));
+    }
+
+    #[test]
+    fn samples_too_close_together_keep_the_previous_rates() {
+        let start = Instant::now();
+        let mut rates = Rates::default();
+        rates.update_at(totals(&[("en0", 0, 0)]), start);
+        rates.update_at(totals(&[("en0", 1000, 0)]), start + Duration::from_secs(1));
+        rates.update_at(
+            totals(&[("en0", 9000, 0)]),
+            start + Duration::from_millis(1100),
+        );
+        assert_eq!(rates.get("en0"), (1000, 0));
+
+        // The skipped sample didn't move the baseline either
+        rates.update_at(totals(&[("en0", 3000, 0)]), start + Duration::from_secs(2));
+        assert_eq!(rates.get("en0"), (2000, 0));
+    }
+
+    #[test]
+    fn keys_that_are_gone_are_dropped() {
+        let start = Instant::now();
+        let mut rates = Rates::default();
+        rates.update_at(totals(&[("en0", 0, 0), ("utun0", 0, 0)]), start);
+        rates.update_at(
+            totals(&[("en0", 100, 0), ("utun0", 100, 0)]),
+            start + Duration::from_secs(1),
+        );
+        rates.update_at(totals(&[("en0", 200, 0)]), start + Duration::from_secs(2));
+        assert_eq!(rates.get("utun0"), (0, 0));
+
+        // Back again: a fresh start, not a jump from the old total
+        rates.update_at(
+            totals(&[("en0", 300, 0), ("utun0", 900, 0)]),
+            start + Duration::from_secs(3),
+        );
+        assert_eq!(rates.get("utun0"), (0, 0));
+        assert_eq!(rates.get("en0"), (100, 0));
+    }
+}
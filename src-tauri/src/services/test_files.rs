@@ -0,0 +1,27 @@
+//! Scratch files for tests, in the system temp directory
+//! Names are prefixed with the process id so parallel test runs don't collide.
+
+use std::fs;
+use std::path::PathBuf;
+
+/// A path in the temp directory with nothing at it
+pub fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("wopr-{}-{}", std::process::id(), name));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+/// A temp file holding `content`
+pub fn temp_file(name: &str, content: &str) -> PathBuf {
+    let path = temp_path(name);
+    fs::write(&path, content).unwrap();
+    path
+}
+
+/// An empty temp directory
+pub fn temp_dir(name: &str) -> PathBuf {
+    let path = temp_path(name);
+    let _ = fs::remove_dir_all(&path);
+    fs::create_dir_all(&path).unwrap();
+    path
+}
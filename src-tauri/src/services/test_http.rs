@@ -0,0 +1,202 @@
+//! Local stand-in HTTP server for tests of code that talks to web services
+//! Runs on a background thread, answers every request through a handler and
+//! records what it was sent.
+
+use serde_json::Value;
+use std::future::Future;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// A request as the server saw it
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    /// Path and query string
+    pub path: String,
+    headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn json(&self) -> Value {
+        serde_json::from_str(&self.body).unwrap_or_default()
+    }
+}
+
+/// A canned response
+#[derive(Debug, Clone)]
+pub struct Reply {
+    status: u16,
+    content_type: &'static str,
+    /// Written one at a time with a pause in between, so clients see separate reads
+    chunks: Vec<String>,
+    /// Pause before each chunk
+    delay: Duration,
+}
+
+impl Reply {
+    pub fn json(status: u16, body: Value) -> Self {
+        Self::text(status, "application/json", &body.to_string())
+    }
+
+    pub fn text(status: u16, content_type: &'static str, body: &str) -> Self {
+        Self {
+            status,
+            content_type,
+            chunks: vec![body.to_string()],
+            delay: Duration::ZERO,
+        }
+    }
+
+    /// Server-sent events, one `data:` line per event, each written separately
+    pub fn sse(events: &[Value]) -> Self {
+        let mut chunks: Vec<String> = events
+            .iter()
+            .map(|event| format!("data: {}\n\n", event))
+            .collect();
+        chunks.push("data: [DONE]\n\n".to_string());
+        Self {
+            status: 200,
+            content_type: "text/event-stream",
+            chunks,
+            delay: Duration::from_millis(10),
+        }
+    }
+
+    /// Wait this long before the head and before each chunk
+    pub fn delayed(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+}
+
+pub struct TestServer {
+    /// `http://127.0.0.1:<port>`, without a trailing slash
+    pub url: String,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl TestServer {
+    /// Answer every request with what `handler` returns for it
+    pub fn start(handler: impl Fn(&Request) -> Reply + Send + Sync + 'static) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind test server");
+        let url = format!(
+            "http://{}",
+            listener.local_addr().expect("test server address")
+        );
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let seen = requests.clone();
+        let handler = Arc::new(handler);
+
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let handler = handler.clone();
+                let seen = seen.clone();
+                // One thread per connection, so a slow reply doesn't hold up the next request
+                thread::spawn(move || {
+                    if let Some(request) = read_request(&stream) {
+                        let reply = handler(&request);
+                        seen.lock().unwrap().push(request);
+                        write_reply(stream, &reply);
+                    }
+                });
+            }
+        });
+
+        Self { url, requests }
+    }
+
+    /// Always answer with the same reply
+    pub fn replying(reply: Reply) -> Self {
+        Self::start(move |_| reply.clone())
+    }
+
+    /// Requests received so far, oldest first
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+/// An address nothing listens on, for connection failures
+pub fn closed_url() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+    format!("http://{}", listener.local_addr().expect("address"))
+}
+
+/// Run a future to completion on a fresh runtime
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("test runtime")
+        .block_on(future)
+}
+
+fn read_request(stream: &TcpStream) -> Option<Request> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let path = parts.next()?.to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        let (key, value) = line.split_once(':')?;
+        headers.push((key.trim().to_string(), value.trim().to_string()));
+    }
+
+    let length = headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.parse().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).ok()?;
+
+    Some(Request {
+        method,
+        path,
+        headers,
+        body: String::from_utf8_lossy(&body).to_string(),
+    })
+}
+
+fn write_reply(mut stream: TcpStream, reply: &Reply) {
+    let mut head = format!(
+        "HTTP/1.1 {} Test\r\nContent-Type: {}\r\nConnection: close\r\n",
+        reply.status, reply.content_type
+    );
+    // A single chunk gets a length; streamed replies end when the connection closes
+    if let [body] = reply.chunks.as_slice() {
+        head.push_str(&format!("Content-Length: {}\r\n", body.len()));
+    }
+    head.push_str("\r\n");
+    thread::sleep(reply.delay);
+    if stream.write_all(head.as_bytes()).is_err() {
+        return;
+    }
+
+    for chunk in &reply.chunks {
+        thread::sleep(reply.delay);
+        if stream.write_all(chunk.as_bytes()).is_err() || stream.flush().is_err() {
+            return;
+        }
+    }
+}
@@ -0,0 +1,299 @@
+//! Audio devices and WAV files
+//! Input is converted to the 16 kHz mono that speech models expect; output plays mono speech.
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use std::collections::VecDeque;
+use std::io::{Cursor, Seek, Write};
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+pub const SAMPLE_RATE: u32 = 16_000;
+
+/// Average interleaved channels into one
+fn downmix(samples: &[f32], channels: usize) -> Vec<f32> {
+    if channels <= 1 {
+        return samples.to_vec();
+    }
+    samples
+        .chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+        .collect()
+}
+
+/// Streaming linear resampler, plenty for speech
+pub struct Resampler {
+    /// Input samples per output sample
+    step: f64,
+    /// Read position relative to the current block; -1 is the last sample of the previous block
+    position: f64,
+    previous: f32,
+}
+
+impl Resampler {
+    pub fn new(input_rate: u32, output_rate: u32) -> Self {
+        Self {
+            step: input_rate as f64 / output_rate.max(1) as f64,
+            position: 0.0,
+            previous: 0.0,
+        }
+    }
+
+    pub fn process(&mut self, input: &[f32]) -> Vec<f32> {
+        if input.is_empty() || self.step == 1.0 {
+            return input.to_vec();
+        }
+
+        let mut output = Vec::with_capacity((input.len() as f64 / self.step) as usize + 1);
+        let last = (input.len() - 1) as f64;
+        while self.position < last {
+            let index = self.position.floor();
+            let fraction = (self.position - index) as f32;
+            let a = if index < 0.0 {
+                self.previous
+            } else {
+                input[index as usize]
+            };
+            let b = input[(index + 1.0) as usize];
+            output.push(a + (b - a) * fraction);
+            self.position += self.step;
+        }
+
+        self.position -= input.len() as f64;
+        self.previous = input[input.len() - 1];
+        output
+    }
+}
+
+/// The configured input device by ID (or legacy name), falling back to the system default
+fn input_device(id: Option<&str>) -> Result<cpal::Device, String> {
+    if let Some(id) = id.filter(|id| !id.trim().is_empty()) {
+        match super::microphones::find(id)? {
+            Some(device) => return Ok(device),
+            None => eprintln!("Microphone '{}' not found, using the default", id),
+        }
+    }
+    cpal::default_host()
+        .default_input_device()
+        .ok_or_else(|| "No microphone available".to_string())
+}
+
+/// Start capturing; 16 kHz mono blocks go to `sender` until the returned stream is dropped
+/// Returns the stream and the device name
+pub fn open_microphone(
+    id: Option<&str>,
+    sender: Sender<Vec<f32>>,
+) -> Result<(cpal::Stream, String), String> {
+    let device = input_device(id)?;
+    let device_name = device.name().unwrap_or_else(|_| "Microphone".to_string());
+    let supported = device
+        .default_input_config()
+        .map_err(|e| format!("Microphone unavailable: {}", e))?;
+    let sample_format = supported.sample_format();
+    let config: cpal::StreamConfig = supported.into();
+    let channels = config.channels as usize;
+
+    let mut resampler = Resampler::new(config.sample_rate.0, SAMPLE_RATE);
+    let mut deliver = move |samples: Vec<f32>| {
+        let _ = sender.send(resampler.process(&downmix(&samples, channels)));
+    };
+    let on_error = |e: cpal::StreamError| eprintln!("Microphone stream error: {}", e);
+
+    let stream = match sample_format {
+        cpal::SampleFormat::F32 => device.build_input_stream(
+            &config,
+            move |data: &[f32], _: &cpal::InputCallbackInfo| deliver(data.to_vec()),
+            on_error,
+            None,
+        ),
+        cpal::SampleFormat::I16 => device.build_input_stream(
+            &config,
+            move |data: &[i16], _: &cpal::InputCallbackInfo| {
+                deliver(data.iter().map(|&s| s as f32 / 32768.0).collect())
+            },
+            on_error,
+            None,
+        ),
+        cpal::SampleFormat::U16 => device.build_input_stream(
+            &config,
+            move |data: &[u16], _: &cpal::InputCallbackInfo| {
+                deliver(
+                    data.iter()
+                        .map(|&s| (s as f32 - 32768.0) / 32768.0)
+                        .collect(),
+                )
+            },
+            on_error,
+            None,
+        ),
+        other => return Err(format!("Unsupported microphone sample format: {:?}", other)),
+    }
+    .map_err(|e| format!("Failed to open microphone: {}", e))?;
+
+    stream
+        .play()
+        .map_err(|e| format!("Failed to start microphone: {}", e))?;
+    Ok((stream, device_name))
+}
+
+/// Open a microphone on a new thread and run `capture` there with its 16 kHz mono blocks
+/// A cpal stream stops when dropped and can't move to another thread, so it stays on this one
+/// until `capture` returns. Resolves to the device name once the stream is running.
+pub async fn spawn_capture(
+    id: Option<String>,
+    capture: impl FnOnce(Receiver<Vec<f32>>) + Send + 'static,
+) -> Result<String, String> {
+    let (ready_sender, ready) = mpsc::channel();
+    std::thread::spawn(move || {
+        let (sender, receiver) = mpsc::channel();
+        match open_microphone(id.as_deref(), sender) {
+            Ok((stream, name)) => {
+                let _ = ready_sender.send(Ok(name));
+                capture(receiver);
+                drop(stream);
+            }
+            Err(e) => {
+                let _ = ready_sender.send(Err(e));
+            }
+        }
+    });
+
+    tauri::async_runtime::spawn_blocking(move || {
+        ready
+            .recv()
+            .unwrap_or_else(|_| Err("Microphone thread exited".to_string()))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Read a WAV file as 16 kHz mono
+pub fn read_wav(path: &Path) -> Result<Vec<f32>, String> {
+    let (samples, rate) = decode_wav(path)?;
+    Ok(Resampler::new(rate, SAMPLE_RATE).process(&samples))
+}
+
+/// Read a WAV file as mono at its own sample rate
+/// Returns the samples and that rate.
+pub fn decode_wav(path: &Path) -> Result<(Vec<f32>, u32), String> {
+    let mut reader = hound::WavReader::open(path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let spec = reader.spec();
+    let samples: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>(),
+        hound::SampleFormat::Int => {
+            let scale = (1i64 << (spec.bits_per_sample.clamp(1, 32) - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|s| s.map(|s| s as f32 / scale))
+                .collect::<Result<_, _>>()
+        }
+    }
+    .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+    Ok((downmix(&samples, spec.channels as usize), spec.sample_rate))
+}
+
+/// Write mono samples as 16-bit PCM
+fn write_samples<W: Write + Seek>(
+    target: W,
+    samples: &[f32],
+    sample_rate: u32,
+) -> Result<(), String> {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::new(target, spec).map_err(|e| e.to_string())?;
+    for sample in samples {
+        writer
+            .write_sample((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)
+            .map_err(|e| e.to_string())?;
+    }
+    writer.finalize().map_err(|e| e.to_string())
+}
+
+/// Encode 16 kHz mono samples as a 16-bit WAV file
+pub fn encode_wav(samples: &[f32]) -> Result<Vec<u8>, String> {
+    let mut cursor = Cursor::new(Vec::new());
+    write_samples(&mut cursor, samples, SAMPLE_RATE)?;
+    Ok(cursor.into_inner())
+}
+
+/// Save mono samples as a 16-bit WAV file
+pub fn write_wav(path: &Path, samples: &[f32], sample_rate: u32) -> Result<(), String> {
+    let file = std::fs::File::create(path)
+        .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    write_samples(std::io::BufWriter::new(file), samples, sample_rate)
+}
+
+/// Start the default output device, playing mono samples taken from `buffer`
+/// Returns the stream and the rate `buffer` has to be filled at; silence is played while it is empty
+pub fn open_speaker(buffer: Arc<Mutex<VecDeque<f32>>>) -> Result<(cpal::Stream, u32), String> {
+    let device = cpal::default_host()
+        .default_output_device()
+        .ok_or("No audio output device available")?;
+    let supported = device
+        .default_output_config()
+        .map_err(|e| format!("Audio output unavailable: {}", e))?;
+    let sample_format = supported.sample_format();
+    let config: cpal::StreamConfig = supported.into();
+    let channels = config.channels as usize;
+    let sample_rate = config.sample_rate.0;
+
+    // One lock per callback, every channel gets the same sample
+    let next_frames = move |len: usize| -> Vec<f32> {
+        let Ok(mut buffer) = buffer.lock() else {
+            return vec![0.0; len / channels];
+        };
+        (0..len / channels)
+            .map(|_| buffer.pop_front().unwrap_or(0.0))
+            .collect()
+    };
+    let on_error = |e: cpal::StreamError| eprintln!("Audio output stream error: {}", e);
+
+    let stream = match sample_format {
+        cpal::SampleFormat::F32 => device.build_output_stream(
+            &config,
+            move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+                let samples = next_frames(data.len());
+                for (frame, sample) in data.chunks_mut(channels).zip(samples) {
+                    frame.fill(sample);
+                }
+            },
+            on_error,
+            None,
+        ),
+        cpal::SampleFormat::I16 => device.build_output_stream(
+            &config,
+            move |data: &mut [i16], _: &cpal::OutputCallbackInfo| {
+                let samples = next_frames(data.len());
+                for (frame, sample) in data.chunks_mut(channels).zip(samples) {
+                    frame.fill((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16);
+                }
+            },
+            on_error,
+            None,
+        ),
+        cpal::SampleFormat::U16 => device.build_output_stream(
+            &config,
+            move |data: &mut [u16], _: &cpal::OutputCallbackInfo| {
+                let samples = next_frames(data.len());
+                for (frame, sample) in data.chunks_mut(channels).zip(samples) {
+                    frame.fill((sample.clamp(-1.0, 1.0) * 32767.0 + 32768.0) as u16);
+                }
+            },
+            on_error,
+            None,
+        ),
+        other => return Err(format!("Unsupported audio output format: {:?}", other)),
+    }
+    .map_err(|e| format!("Failed to open audio output: {}", e))?;
+
+    stream
+        .play()
+        .map_err(|e| format!("Failed to start audio output: {}", e))?;
+    Ok((stream, sample_rate))
+}
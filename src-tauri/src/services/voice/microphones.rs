@@ -0,0 +1,280 @@
+//! Microphone selection, the "mic test" level meter and hot-plug detection
+//! Devices are identified as `{host}/{name}` (with `#2`, `#3`… for repeated names) so a saved
+//! choice survives restarts and replugging, unlike the position in the device list.
+
+use cpal::traits::{DeviceTrait, HostTrait};
+use serde::{Deserialize, Serialize};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::audio;
+use crate::services::storage;
+use crate::services::worker::Generation;
+
+/// How often the device list is checked for changes
+const HOT_PLUG_INTERVAL: Duration = Duration::from_secs(3);
+/// Samples per level report (50 ms at 16 kHz)
+const LEVEL_SAMPLES: usize = audio::SAMPLE_RATE as usize / 20;
+/// Level reported for digital silence
+const SILENCE_DB: f32 = -100.0;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MicrophoneInfo {
+    /// Stable ID, saved as `selected_microphone`
+    pub id: String,
+    pub name: String,
+    pub is_default: bool,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u16>,
+}
+
+/// One level reading from the mic test
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MicrophoneLevel {
+    /// 0.0 – 1.0
+    pub rms: f32,
+    pub peak: f32,
+    /// RMS in dBFS
+    pub db: f32,
+}
+
+/// Managed state for the mic test
+#[derive(Default)]
+pub struct MicrophoneTest {
+    runner: Generation,
+}
+
+/// Input devices of the default host with their IDs
+fn enumerate() -> Result<Vec<(MicrophoneInfo, cpal::Device)>, String> {
+    let host = cpal::default_host();
+    let default_name = host.default_input_device().and_then(|d| d.name().ok());
+    let devices: Vec<(String, cpal::Device)> = host
+        .input_devices()
+        .map_err(|e| format!("Failed to list microphones: {}", e))?
+        .filter_map(|device| device.name().ok().map(|name| (name, device)))
+        .collect();
+    let names: Vec<String> = devices.iter().map(|(name, _)| name.clone()).collect();
+    let ids = device_ids(host.id().name(), &names, default_name.as_deref());
+
+    Ok(devices
+        .into_iter()
+        .zip(ids)
+        .map(|((name, device), (id, is_default))| {
+            let config = device.default_input_config().ok();
+            let info = MicrophoneInfo {
+                id,
+                is_default,
+                sample_rate: config.as_ref().map(|c| c.sample_rate().0),
+                channels: config.as_ref().map(|c| c.channels()),
+                name,
+            };
+            (info, device)
+        })
+        .collect())
+}
+
+/// ID of each device in list order, and whether it is the default
+/// Repeated names get `#2`, `#3`…; the default is only known by name, so it is the first of them.
+fn device_ids(
+    host_name: &str,
+    names: &[String],
+    default_name: Option<&str>,
+) -> Vec<(String, bool)> {
+    names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let repeats = names[..i].iter().filter(|n| *n == name).count();
+            let id = if repeats == 0 {
+                format!("{}/{}", host_name, name)
+            } else {
+                format!("{}/{}#{}", host_name, name, repeats + 1)
+            };
+            (id, repeats == 0 && default_name == Some(name.as_str()))
+        })
+        .collect()
+}
+
+/// Find a device by ID, or by plain name as saved by older versions
+pub fn find(id: &str) -> Result<Option<cpal::Device>, String> {
+    let mut devices = enumerate()?;
+    let position = devices
+        .iter()
+        .position(|(info, _)| info.id == id)
+        .or_else(|| devices.iter().position(|(info, _)| info.name == id));
+    Ok(position.map(|i| devices.swap_remove(i).1))
+}
+
+/// List available microphones
+#[tauri::command]
+pub fn list_microphones() -> Result<Vec<MicrophoneInfo>, String> {
+    Ok(enumerate()?.into_iter().map(|(info, _)| info).collect())
+}
+
+/// Get the system default microphone
+#[tauri::command]
+pub fn get_default_microphone() -> Result<Option<MicrophoneInfo>, String> {
+    Ok(list_microphones()?.into_iter().find(|info| info.is_default))
+}
+
+/// Choose the microphone for voice input; None goes back to the system default
+/// Takes effect the next time listening starts
+#[tauri::command]
+pub fn select_microphone(app: AppHandle, id: Option<String>) -> Result<(), String> {
+    let id = id.filter(|id| !id.trim().is_empty() && id != "default");
+    if let Some(id) = &id {
+        if !list_microphones()?.iter().any(|info| &info.id == id) {
+            return Err(format!("Microphone not found: {}", id));
+        }
+    }
+    let mut config = storage::load_config(app.clone());
+    config.selected_microphone = id;
+    storage::save_config(app, config)
+}
+
+fn level(samples: &[f32]) -> MicrophoneLevel {
+    let rms = (samples.iter().map(|s| s * s).sum::<f32>() / samples.len().max(1) as f32).sqrt();
+    let peak = samples.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+    let db = if rms > 0.0 {
+        (20.0 * rms.log10()).max(SILENCE_DB)
+    } else {
+        SILENCE_DB
+    };
+    MicrophoneLevel { rms, peak, db }
+}
+
+/// Report levels until the test is stopped or the microphone goes away
+fn run_test(app: AppHandle, generation: u64, receiver: Receiver<Vec<f32>>) {
+    let state = app.state::<MicrophoneTest>();
+    let mut pending: Vec<f32> = Vec::with_capacity(LEVEL_SAMPLES * 2);
+    while state.runner.is_current(generation) {
+        match receiver.recv_timeout(Duration::from_millis(200)) {
+            Ok(block) => {
+                pending.extend(block);
+                while pending.len() >= LEVEL_SAMPLES {
+                    let _ = app.emit("microphone://level", level(&pending[..LEVEL_SAMPLES]));
+                    pending.drain(..LEVEL_SAMPLES);
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                let _ = app.emit("microphone://test-ended", "Microphone disconnected");
+                break;
+            }
+        }
+    }
+}
+
+/// Start the mic test on a device, or the system default; levels arrive as `microphone://level`
+/// Takes an ID rather than using the saved choice so a device can be tried before it is selected.
+/// Returns the name of the device being tested
+#[tauri::command]
+pub async fn start_microphone_test(app: AppHandle, id: Option<String>) -> Result<String, String> {
+    let device = id.filter(|id| !id.trim().is_empty() && id != "default");
+    let generation = app.state::<MicrophoneTest>().runner.next();
+    audio::spawn_capture(device, move |blocks| run_test(app, generation, blocks)).await
+}
+
+/// Stop the mic test and release the microphone
+#[tauri::command]
+pub fn stop_microphone_test(app: AppHandle) {
+    app.state::<MicrophoneTest>().runner.stop();
+}
+
+/// Names of the input devices and the default, without opening any of them
+/// Cheap enough to poll, unlike `enumerate`, which asks every device for its format.
+fn device_names() -> Option<(Vec<String>, Option<String>)> {
+    let host = cpal::default_host();
+    let names = host
+        .input_devices()
+        .ok()?
+        .filter_map(|d| d.name().ok())
+        .collect();
+    Some((
+        names,
+        host.default_input_device().and_then(|d| d.name().ok()),
+    ))
+}
+
+/// Watch for microphones being plugged in or removed, or the default changing
+/// Emits `microphone://devices-changed` with the new list
+pub fn watch_devices(app: AppHandle) {
+    std::thread::spawn(move || {
+        let mut known = device_names();
+        loop {
+            std::thread::sleep(HOT_PLUG_INTERVAL);
+            let current = device_names();
+            if current.is_none() || current == known {
+                continue;
+            }
+            known = current;
+            if let Ok(microphones) = list_microphones() {
+                let _ = app.emit("microphone://devices-changed", &microphones);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn repeated_names_are_numbered() {
+        let ids = device_ids(
+            "ALSA",
+            &names(&["USB Mic", "Webcam", "USB Mic", "USB Mic"]),
+            Some("USB Mic"),
+        );
+        assert_eq!(
+            ids,
+            [
+                ("ALSA/USB Mic".to_string(), true),
+                ("ALSA/Webcam".to_string(), false),
+                ("ALSA/USB Mic#2".to_string(), false),
+                ("ALSA/USB Mic#3".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn no_default_without_a_matching_name() {
+        let ids = device_ids("CoreAudio", &names(&["Built-in"]), None);
+        assert_eq!(ids, [("CoreAudio/Built-in".to_string(), false)]);
+        let ids = device_ids("CoreAudio", &names(&["Built-in"]), Some("AirPods"));
+        assert!(!ids[0].1);
+        assert!(device_ids("CoreAudio", &[], Some("AirPods")).is_empty());
+    }
+
+    #[test]
+    fn level_of_a_steady_signal() {
+        let reading = level(&[0.5, -0.5, 0.5, -0.5]);
+        assert_eq!(reading.rms, 0.5);
+        assert_eq!(reading.peak, 0.5);
+        assert!((reading.db - -6.0206).abs() < 0.001);
+
+        let reading = level(&[1.0; 8]);
+        assert_eq!(reading.db, 0.0);
+    }
+
+    #[test]
+    fn peak_is_the_largest_magnitude() {
+        let reading = level(&[0.1, -0.8, 0.3, 0.0]);
+        assert_eq!(reading.peak, 0.8);
+        assert!((reading.rms - (0.74f32 / 4.0).sqrt()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn quiet_and_empty_input_stop_at_the_floor() {
+        for samples in [&[][..], &[0.0; 16][..], &[1e-7; 16][..]] {
+            let reading = level(samples);
+            assert_eq!(reading.db, SILENCE_DB);
+            assert!(reading.rms < 1e-6);
+        }
+    }
+}
@@ -0,0 +1,496 @@
+//! Offline voice input and spoken output
+//! Audio from the microphone or a WAV file is split into utterances, transcribed locally
+//! and checked for the wake word. Results reach the UI as `voice://` events; speech output lives in `tts`.
+
+pub mod audio;
+pub mod microphones;
+mod synth;
+mod transcribe;
+pub mod tts;
+mod vad;
+mod wake;
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::storage;
+use super::worker::Generation;
+
+pub use transcribe::NO_BUILTIN_WHISPER;
+
+/// Time after a bare wake word in which the next utterance counts as the command
+const COMMAND_WINDOW_MS: u64 = 8000;
+
+/// A transcribed utterance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Transcript {
+    pub text: String,
+    /// Offsets from the start of the stream or file
+    pub start_ms: u64,
+    pub end_ms: u64,
+    /// Whether the wake word was heard in this utterance
+    pub wake_word: bool,
+    /// Command for the assistant, when this utterance carried one
+    pub command: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VoiceStatus {
+    pub listening: bool,
+    /// Waiting for a command after a bare wake word
+    pub command_mode: bool,
+    /// Transcription engine in use
+    pub engine: Option<String>,
+    /// Microphone in use
+    pub device: Option<String>,
+    /// Last error from the pipeline
+    pub error: Option<String>,
+}
+
+/// Managed state for the microphone pipeline
+#[derive(Default)]
+pub struct VoicePipeline {
+    status: Mutex<VoiceStatus>,
+    runner: Generation,
+}
+
+/// Apply a change to the pipeline status and tell the UI
+fn update_status(app: &AppHandle, change: impl FnOnce(&mut VoiceStatus)) -> VoiceStatus {
+    let state = app.state::<VoicePipeline>();
+    let status = match state.status.lock() {
+        Ok(mut status) => {
+            change(&mut status);
+            status.clone()
+        }
+        Err(_) => return VoiceStatus::default(),
+    };
+    let _ = app.emit("voice://state", &status);
+    status
+}
+
+/// Drop annotations like "[BLANK_AUDIO]" or "(music)" that Whisper emits for non-speech
+fn clean_transcript(text: &str) -> String {
+    let mut kept = String::new();
+    let mut depth = 0usize;
+    for c in text.chars() {
+        match c {
+            '[' | '(' => depth += 1,
+            ']' | ')' => depth = depth.saturating_sub(1),
+            _ if depth == 0 => kept.push(c),
+            _ => {}
+        }
+    }
+    kept.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Wake word and command handling, shared by the microphone and WAV paths
+struct WakeTracker {
+    wake_word: String,
+    /// Stream time until which the next utterance is taken as a command
+    command_until: Option<u64>,
+}
+
+impl WakeTracker {
+    fn new(wake_word: String) -> Self {
+        Self {
+            wake_word,
+            command_until: None,
+        }
+    }
+
+    fn command_mode(&self) -> bool {
+        self.command_until.is_some()
+    }
+
+    /// Leave command mode once the window has passed without speech
+    fn expire(&mut self, now_ms: u64) {
+        if matches!(self.command_until, Some(until) if now_ms > until) {
+            self.command_until = None;
+        }
+    }
+
+    fn end(&mut self) {
+        self.command_until = None;
+    }
+
+    fn handle(&mut self, utterance: &vad::Utterance, text: String) -> Transcript {
+        let awaiting_command =
+            matches!(self.command_until, Some(until) if utterance.start_ms <= until);
+        let mut transcript = Transcript {
+            start_ms: utterance.start_ms,
+            end_ms: utterance.end_ms,
+            wake_word: false,
+            command: None,
+            text,
+        };
+
+        self.command_until = None;
+        match wake::find(&transcript.text, &self.wake_word) {
+            Some(rest) => {
+                transcript.wake_word = true;
+                if rest.is_empty() {
+                    // Wake word on its own, the command follows in the next utterance
+                    self.command_until = Some(utterance.end_ms + COMMAND_WINDOW_MS);
+                } else {
+                    transcript.command = Some(rest);
+                }
+            }
+            None => {
+                if awaiting_command {
+                    transcript.command = Some(transcript.text.clone());
+                }
+            }
+        }
+        transcript
+    }
+}
+
+/// Sends what the wake tracker heard to the UI as `voice://` events
+struct Announcer {
+    app: AppHandle,
+    /// Whether status updates are sent (only for the live pipeline)
+    live: bool,
+    /// Command mode as last announced
+    command_mode: bool,
+}
+
+impl Announcer {
+    fn new(app: AppHandle, live: bool) -> Self {
+        Self {
+            app,
+            live,
+            command_mode: false,
+        }
+    }
+
+    /// Announce a transcript (if any) and a change of command mode
+    fn report(&mut self, tracker: &WakeTracker, transcript: Option<&Transcript>) {
+        if let Some(transcript) = transcript.filter(|t| t.wake_word) {
+            let _ = self.app.emit("voice://wake", transcript);
+        }
+
+        let command_mode = tracker.command_mode();
+        if command_mode != self.command_mode {
+            self.command_mode = command_mode;
+            let _ = self.app.emit("voice://command-mode", command_mode);
+            if self.live {
+                update_status(&self.app, |status| status.command_mode = command_mode);
+            }
+        }
+
+        if let Some(transcript) = transcript {
+            let _ = self.app.emit("voice://transcript", transcript);
+            if let Some(command) = &transcript.command {
+                let _ = self.app.emit("voice://command", command);
+            }
+        }
+    }
+}
+
+/// Transcribe an utterance; None when it held no words
+fn transcribe_utterance(
+    transcriber: &mut dyn transcribe::Transcriber,
+    utterance: &vad::Utterance,
+) -> Result<Option<String>, String> {
+    let text = clean_transcript(&transcriber.transcribe(&utterance.samples)?);
+    Ok(if text.is_empty() { None } else { Some(text) })
+}
+
+/// Capture loop, runs until listening is stopped or restarted or the microphone goes away
+fn run_pipeline(
+    app: AppHandle,
+    generation: u64,
+    receiver: Receiver<Vec<f32>>,
+    mut transcriber: Box<dyn transcribe::Transcriber>,
+    wake_word: String,
+) {
+    let state = app.state::<VoicePipeline>();
+    let mut segmenter = vad::Segmenter::new();
+    let mut tracker = WakeTracker::new(wake_word);
+    let mut announcer = Announcer::new(app.clone(), true);
+    let mut failure = None;
+    while state.runner.is_current(generation) {
+        match receiver.recv_timeout(Duration::from_millis(200)) {
+            Ok(block) => {
+                for utterance in segmenter.push(&block) {
+                    match transcribe_utterance(transcriber.as_mut(), &utterance) {
+                        Ok(Some(text)) => {
+                            let transcript = tracker.handle(&utterance, text);
+                            announcer.report(&tracker, Some(&transcript));
+                        }
+                        Ok(None) => {}
+                        Err(e) => {
+                            eprintln!("Voice transcription failed: {}", e);
+                            update_status(&app, |status| status.error = Some(e));
+                        }
+                    }
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                failure = Some("Microphone disconnected".to_string());
+                break;
+            }
+        }
+        tracker.expire(segmenter.elapsed_ms());
+        announcer.report(&tracker, None);
+    }
+
+    // Stopped on our own rather than by stop/restart
+    if state.runner.is_current(generation) {
+        update_status(&app, |status| {
+            status.listening = false;
+            status.command_mode = false;
+            status.error = failure;
+        });
+    }
+}
+
+/// Start listening on the configured microphone, replacing any running pipeline
+#[tauri::command]
+pub async fn start_voice_listening(app: AppHandle) -> Result<VoiceStatus, String> {
+    let config = storage::load_config(app.clone());
+    let generation = app.state::<VoicePipeline>().runner.next();
+
+    let loader_app = app.clone();
+    let loader_config = config.clone();
+    let transcriber = tauri::async_runtime::spawn_blocking(move || {
+        transcribe::for_config(&loader_app, &loader_config)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    let pipeline_app = app.clone();
+    let wake_word = config.effective_wake_word().to_string();
+    let device = audio::spawn_capture(config.selected_microphone.clone(), move |blocks| {
+        run_pipeline(pipeline_app, generation, blocks, transcriber, wake_word)
+    })
+    .await;
+
+    match device {
+        Ok(device) => Ok(update_status(&app, |status| {
+            *status = VoiceStatus {
+                listening: true,
+                command_mode: false,
+                engine: Some(config.stt_engine.clone()),
+                device: Some(device),
+                error: None,
+            }
+        })),
+        Err(e) => {
+            update_status(&app, |status| {
+                status.listening = false;
+                status.error = Some(e.clone());
+            });
+            Err(e)
+        }
+    }
+}
+
+/// Stop listening and release the microphone
+#[tauri::command]
+pub fn stop_voice_listening(app: AppHandle) -> VoiceStatus {
+    app.state::<VoicePipeline>().runner.stop();
+    update_status(&app, |status| {
+        status.listening = false;
+        status.command_mode = false;
+        status.device = None;
+    })
+}
+
+/// Get the voice pipeline status
+#[tauri::command]
+pub fn get_voice_status(state: tauri::State<VoicePipeline>) -> Result<VoiceStatus, String> {
+    Ok(state.status.lock().map_err(|e| e.to_string())?.clone())
+}
+
+/// Run a WAV file through the pipeline as if it were spoken into the microphone
+/// Emits the same events as live listening and returns every transcript
+#[tauri::command]
+pub async fn process_voice_file(app: AppHandle, path: String) -> Result<Vec<Transcript>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let config = storage::load_config(app.clone());
+        let samples = audio::read_wav(&PathBuf::from(&path))?;
+        let mut transcriber = transcribe::for_config(&app, &config)?;
+
+        let mut announcer = Announcer::new(app.clone(), false);
+        transcribe_samples(
+            &samples,
+            transcriber.as_mut(),
+            config.effective_wake_word().to_string(),
+            |tracker, transcript| announcer.report(tracker, transcript),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Segment and transcribe a whole recording, handling wake words along the way
+/// `heard` is called after every step with the tracker and the new transcript, if any.
+fn transcribe_samples(
+    samples: &[f32],
+    transcriber: &mut dyn transcribe::Transcriber,
+    wake_word: String,
+    mut heard: impl FnMut(&WakeTracker, Option<&Transcript>),
+) -> Result<Vec<Transcript>, String> {
+    let mut segmenter = vad::Segmenter::new();
+    let mut utterances = segmenter.push(samples);
+    utterances.extend(segmenter.flush());
+
+    let mut tracker = WakeTracker::new(wake_word);
+    let mut transcripts = Vec::new();
+    for utterance in utterances {
+        tracker.expire(utterance.start_ms);
+        heard(&tracker, None);
+        if let Some(text) = transcribe_utterance(transcriber, &utterance)? {
+            let transcript = tracker.handle(&utterance, text);
+            heard(&tracker, Some(&transcript));
+            transcripts.push(transcript);
+        }
+    }
+    tracker.end();
+    heard(&tracker, None);
+    Ok(transcripts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    /// Answers each utterance with the next canned text
+    struct Scripted(VecDeque<&'static str>);
+
+    impl transcribe::Transcriber for Scripted {
+        fn transcribe(&mut self, _samples: &[f32]) -> Result<String, String> {
+            Ok(self.0.pop_front().unwrap_or_default().to_string())
+        }
+    }
+
+    fn recording(rate: u32, parts: &[(bool, usize)]) -> Vec<f32> {
+        parts
+            .iter()
+            .flat_map(|&(voiced, ms)| {
+                (0..rate as usize * ms / 1000).map(move |i| {
+                    let phase = i as f32 * 220.0 * std::f32::consts::TAU / rate as f32;
+                    if voiced {
+                        0.3 * phase.sin()
+                    } else {
+                        0.0
+                    }
+                })
+            })
+            .collect()
+    }
+
+    fn run(samples: &[f32], texts: &[&'static str]) -> (Vec<Transcript>, Vec<bool>) {
+        let mut modes = vec![false];
+        let transcripts = transcribe_samples(
+            samples,
+            &mut Scripted(texts.iter().copied().collect()),
+            "Joshua".to_string(),
+            |tracker, _| {
+                if modes.last() != Some(&tracker.command_mode()) {
+                    modes.push(tracker.command_mode());
+                }
+            },
+        )
+        .unwrap();
+        (transcripts, modes)
+    }
+
+    fn summary(transcripts: &[Transcript]) -> Vec<(&str, bool, Option<&str>)> {
+        transcripts
+            .iter()
+            .map(|t| (t.text.as_str(), t.wake_word, t.command.as_deref()))
+            .collect()
+    }
+
+    #[test]
+    fn bare_wake_word_takes_the_next_utterance_as_the_command() {
+        let samples = recording(
+            audio::SAMPLE_RATE,
+            &[
+                (false, 500),
+                (true, 600),
+                (false, 1500),
+                (true, 900),
+                (false, 1500),
+                (true, 600),
+                (false, 1500),
+            ],
+        );
+        let (transcripts, modes) = run(&samples, &["Joshua.", "open the doors", "never mind"]);
+
+        assert_eq!(
+            summary(&transcripts),
+            [
+                ("Joshua.", true, None),
+                ("open the doors", false, Some("open the doors")),
+                ("never mind", false, None),
+            ]
+        );
+        assert_eq!(modes, [false, true, false]);
+        assert!(transcripts[0].end_ms <= transcripts[1].start_ms);
+    }
+
+    #[test]
+    fn command_window_expires() {
+        let samples = recording(
+            audio::SAMPLE_RATE,
+            &[
+                (true, 600),
+                (false, (COMMAND_WINDOW_MS + 2000) as usize),
+                (true, 600),
+                (false, 1500),
+            ],
+        );
+        let (transcripts, modes) = run(&samples, &["Joshua", "open the doors"]);
+
+        assert_eq!(
+            summary(&transcripts),
+            [("Joshua", true, None), ("open the doors", false, None)]
+        );
+        assert_eq!(modes, [false, true, false]);
+    }
+
+    #[test]
+    fn wake_word_with_command_and_non_speech_annotations() {
+        let samples = recording(
+            audio::SAMPLE_RATE,
+            &[(true, 600), (false, 1500), (true, 900), (false, 1500)],
+        );
+        let (transcripts, modes) = run(
+            &samples,
+            &["[BLANK_AUDIO]", "(music) Joshua, what time is it?"],
+        );
+
+        assert_eq!(
+            summary(&transcripts),
+            [("Joshua, what time is it?", true, Some("what time is it?"))]
+        );
+        assert_eq!(modes, [false]);
+    }
+
+    #[test]
+    fn voice_file_is_read_at_any_rate() {
+        let path = crate::services::test_files::temp_path("voice.wav");
+        let samples = recording(44_100, &[(false, 300), (true, 900), (false, 1500)]);
+        audio::write_wav(&path, &samples, 44_100).unwrap();
+
+        let decoded = audio::read_wav(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let expected = (samples.len() as u64 * audio::SAMPLE_RATE as u64 / 44_100) as usize;
+        assert!(decoded.len().abs_diff(expected) <= 2);
+
+        let (transcripts, _) = run(&decoded, &["Joshua lights on"]);
+        assert_eq!(
+            summary(&transcripts),
+            [("Joshua lights on", true, Some("lights on"))]
+        );
+    }
+}
@@ -0,0 +1,349 @@
+//! Local speech synthesis through eSpeak NG or Piper, plus the "robot" voice filter
+
+use serde_json::Value;
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use tauri::AppHandle;
+
+use crate::services::storage::{self, AppConfig};
+
+/// Piper's output rate when a voice has no config file
+const PIPER_DEFAULT_RATE: u32 = 22_050;
+/// Sentences shorter than this wait for more text, so "Dr." or "1." don't become their own clip
+const MIN_SENTENCE_CHARS: usize = 12;
+
+/// Synthesized mono audio
+pub struct Speech {
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+}
+
+/// Everything synthesis needs, resolved from config up front
+#[derive(Debug, Clone)]
+pub struct SpeechSettings {
+    /// "espeak" or "piper"
+    pub engine: String,
+    pub command: String,
+    /// eSpeak voice name or Piper model
+    pub voice: String,
+    pub robot: bool,
+    /// Where Piper models are looked up by name
+    pub voices_dir: PathBuf,
+}
+
+impl SpeechSettings {
+    /// Settings for the active persona, whose voice overrides `tts_voice`
+    pub fn from_config(app: &AppHandle, config: &AppConfig) -> Self {
+        let persona_voice = config
+            .active_persona
+            .as_ref()
+            .and_then(|name| config.personas.iter().find(|p| &p.name == name))
+            .and_then(|persona| persona.voice.clone())
+            .filter(|voice| !voice.trim().is_empty());
+        let command = config
+            .tts_command
+            .clone()
+            .filter(|c| !c.trim().is_empty())
+            .unwrap_or_else(|| default_command(&config.tts_engine).to_string());
+
+        Self {
+            engine: config.tts_engine.clone(),
+            command,
+            voice: persona_voice.unwrap_or_else(|| config.tts_voice.clone()),
+            robot: config.tts_robot_filter,
+            voices_dir: voices_dir(app),
+        }
+    }
+}
+
+fn default_command(engine: &str) -> &'static str {
+    match engine {
+        "piper" => "piper",
+        _ => "espeak-ng",
+    }
+}
+
+/// Folder for Piper voice models
+pub fn voices_dir(app: &AppHandle) -> PathBuf {
+    storage::get_internal_app_dir(app).join("voices")
+}
+
+/// Run an engine with `text` on stdin and collect stdout
+fn run_engine(command: &mut Command, text: &str) -> Result<Vec<u8>, String> {
+    command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    // Don't flash a console window for every sentence on Windows
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        command.creation_flags(0x0800_0000);
+    }
+
+    let mut child = command
+        .spawn()
+        .map_err(|e| format!("Failed to start speech engine: {}", e))?;
+    let mut stdin = child.stdin.take().ok_or("Speech engine has no stdin")?;
+    // Write from another thread so a full stdout pipe can't deadlock us
+    let text = text.to_string();
+    let writer = std::thread::spawn(move || {
+        let _ = stdin.write_all(text.as_bytes());
+    });
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Speech engine failed: {}", e))?;
+    let _ = writer.join();
+
+    if !output.status.success() {
+        return Err(format!(
+            "Speech engine failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output.stdout)
+}
+
+fn espeak(settings: &SpeechSettings, text: &str) -> Result<Speech, String> {
+    let wav = run_engine(
+        Command::new(&settings.command).args(["--stdout", "--stdin", "-v", &settings.voice]),
+        text,
+    )?;
+
+    // eSpeak streams the WAV with a placeholder length, so read until the data runs out
+    let mut reader = hound::WavReader::new(Cursor::new(wav))
+        .map_err(|e| format!("Invalid audio from eSpeak: {}", e))?;
+    let spec = reader.spec();
+    let channels = spec.channels.max(1) as usize;
+    let samples: Vec<f32> = reader
+        .samples::<i16>()
+        .map_while(Result::ok)
+        .step_by(channels)
+        .map(|s| s as f32 / 32768.0)
+        .collect();
+
+    Ok(Speech {
+        samples,
+        sample_rate: spec.sample_rate,
+    })
+}
+
+/// A Piper model given as a path, or by name from the voices folder
+fn piper_model(settings: &SpeechSettings) -> Result<PathBuf, String> {
+    let direct = PathBuf::from(&settings.voice);
+    if direct.is_file() {
+        return Ok(direct);
+    }
+    let name = settings.voice.trim_end_matches(".onnx");
+    let model = settings.voices_dir.join(format!("{}.onnx", name));
+    if model.is_file() {
+        Ok(model)
+    } else {
+        Err(format!(
+            "Piper voice '{}' not found in {}",
+            settings.voice,
+            settings.voices_dir.display()
+        ))
+    }
+}
+
+/// Output rate from the model's `.onnx.json` config
+fn piper_rate(model: &Path) -> u32 {
+    let config = PathBuf::from(format!("{}.json", model.display()));
+    std::fs::read_to_string(config)
+        .ok()
+        .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+        .and_then(|data| data["audio"]["sample_rate"].as_u64())
+        .map(|rate| rate as u32)
+        .unwrap_or(PIPER_DEFAULT_RATE)
+}
+
+fn piper(settings: &SpeechSettings, text: &str) -> Result<Speech, String> {
+    let model = piper_model(settings)?;
+    // Piper speaks one utterance per line
+    let line = format!("{}\n", text.replace(['\r', '\n'], " "));
+    let raw = run_engine(
+        Command::new(&settings.command)
+            .arg("--model")
+            .arg(&model)
+            .arg("--output_raw"),
+        &line,
+    )?;
+
+    Ok(Speech {
+        samples: raw
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0)
+            .collect(),
+        sample_rate: piper_rate(&model),
+    })
+}
+
+/// Synthesize text, applying the robot filter when it is on
+pub fn synthesize(settings: &SpeechSettings, text: &str) -> Result<Speech, String> {
+    let mut speech = match settings.engine.as_str() {
+        "piper" => piper(settings, text)?,
+        "espeak" => espeak(settings, text)?,
+        other => return Err(format!("Unknown speech engine: {}", other)),
+    };
+    if settings.robot {
+        robot_filter(&mut speech);
+    }
+    Ok(speech)
+}
+
+/// Voices the engine offers: eSpeak language voices, or Piper models in the voices folder
+pub fn list_voices(settings: &SpeechSettings) -> Result<Vec<String>, String> {
+    if settings.engine == "piper" {
+        let mut voices: Vec<String> = std::fs::read_dir(&settings.voices_dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .filter_map(|entry| {
+                        let name = entry.file_name().to_string_lossy().to_string();
+                        name.strip_suffix(".onnx").map(str::to_string)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        voices.sort();
+        return Ok(voices);
+    }
+
+    let listing = run_engine(Command::new(&settings.command).arg("--voices"), "")?;
+    // Columns: Pty Language Age/Gender VoiceName File Other Languages
+    Ok(String::from_utf8_lossy(&listing)
+        .lines()
+        .skip(1)
+        .filter_map(|line| line.split_whitespace().nth(1).map(str::to_string))
+        .collect())
+}
+
+/// Ring modulation with a short metallic echo and coarse quantization, for a 1983 mainframe voice
+pub fn robot_filter(speech: &mut Speech) {
+    const CARRIER_HZ: f32 = 55.0;
+    const RING_MIX: f32 = 0.6;
+    const ECHO_MS: f32 = 6.0;
+    const ECHO_FEEDBACK: f32 = 0.45;
+    /// Amplitude steps per polarity
+    const LEVELS: f32 = 48.0;
+
+    let rate = speech.sample_rate.max(1) as f32;
+    let delay = ((rate * ECHO_MS / 1000.0) as usize).max(1);
+    let step = std::f32::consts::TAU * CARRIER_HZ / rate;
+
+    let mut output: Vec<f32> = Vec::with_capacity(speech.samples.len());
+    for (i, &sample) in speech.samples.iter().enumerate() {
+        let ring = sample * (step * i as f32).sin();
+        let mixed = RING_MIX * ring + (1.0 - RING_MIX) * sample;
+        let echo = if i >= delay {
+            output[i - delay] * ECHO_FEEDBACK
+        } else {
+            0.0
+        };
+        output.push(mixed + echo);
+    }
+
+    let peak = output.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+    let gain = if peak > 0.0 { 0.9 / peak } else { 1.0 };
+    speech.samples = output
+        .into_iter()
+        .map(|s| (s * gain * LEVELS).round() / LEVELS)
+        .collect();
+}
+
+/// Whether `text` opens with a knowledge command, which is never read aloud
+fn is_knowledge_command(text: &str) -> bool {
+    let upper = text.get(..10).unwrap_or(text).to_uppercase();
+    upper.starts_with("[REMEMBER:") || upper.starts_with("[FORGET:")
+}
+
+/// Reduce markdown to what should be spoken: no code blocks, link targets, markup or knowledge commands
+pub fn speakable(text: &str) -> String {
+    let mut spoken = String::new();
+    // Fenced code alternates with prose
+    for prose in text.split("```").step_by(2) {
+        let mut rest = prose;
+        while let Some(c) = rest.chars().next() {
+            if c == '[' {
+                if let Some(close) = rest.find(']') {
+                    let inner = &rest[1..close];
+                    let after = &rest[close + 1..];
+                    if is_knowledge_command(rest) {
+                        rest = after;
+                        continue;
+                    }
+                    // [label](url) reads as the label
+                    if after.starts_with('(') {
+                        if let Some(end) = after.find(')') {
+                            spoken.push_str(inner);
+                            rest = &after[end + 1..];
+                            continue;
+                        }
+                    }
+                }
+            }
+            if rest.starts_with("http://") || rest.starts_with("https://") {
+                spoken.push_str("link");
+                let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                rest = &rest[end..];
+                continue;
+            }
+            if !matches!(c, '*' | '_' | '`' | '#' | '>' | '|' | '~') {
+                spoken.push(c);
+            }
+            rest = &rest[c.len_utf8()..];
+        }
+        spoken.push(' ');
+    }
+    spoken.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Cut complete sentences off the front of streamed text
+/// Boundaries inside code fences and brackets are ignored; with `flush` the remainder is returned too.
+pub fn take_sentences(buffer: &mut String, flush: bool) -> Vec<String> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut in_code = false;
+    let mut brackets = 0usize;
+    let chars: Vec<(usize, char)> = buffer.char_indices().collect();
+
+    for (n, &(i, c)) in chars.iter().enumerate() {
+        if buffer[i..].starts_with("```") {
+            in_code = !in_code;
+        }
+        match c {
+            '[' => brackets += 1,
+            ']' => brackets = brackets.saturating_sub(1),
+            _ => {}
+        }
+        if in_code || brackets > 0 || !matches!(c, '.' | '!' | '?' | '\n') {
+            continue;
+        }
+        // Only a boundary once we can see what follows it
+        let Some(&(next_index, next)) = chars.get(n + 1) else {
+            continue;
+        };
+        if !next.is_whitespace() {
+            continue;
+        }
+        let sentence = buffer[start..next_index].trim();
+        if sentence.chars().count() >= MIN_SENTENCE_CHARS {
+            sentences.push(sentence.to_string());
+            start = next_index;
+        }
+    }
+
+    if flush {
+        let rest = buffer[start..].trim();
+        if !rest.is_empty() {
+            sentences.push(rest.to_string());
+        }
+        buffer.clear();
+    } else {
+        buffer.drain(..start);
+    }
+    sentences
+}
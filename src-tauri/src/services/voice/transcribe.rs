@@ -0,0 +1,236 @@
+//! Speech to text, with whisper.cpp in-process or a local OpenAI-compatible server
+
+use serde_json::Value;
+use std::path::PathBuf;
+use std::time::Duration;
+use tauri::AppHandle;
+
+use super::audio;
+use crate::services::ai::local;
+use crate::services::storage::{self, AppConfig};
+
+/// Default model file, looked up in the app data folder
+const DEFAULT_MODEL_FILE: &str = "ggml-base.en.bin";
+/// Why offline recognition can't run in a build without the `whisper` feature, and what to do
+pub const NO_BUILTIN_WHISPER: &str = "This build has no built-in Whisper (it needs the `whisper` feature), \
+    so offline speech recognition needs a local Whisper-compatible server such as whisper.cpp's server or \
+    faster-whisper-server. Start one and enter its URL in the voice settings";
+
+/// Turns an utterance (16 kHz mono) into text
+pub trait Transcriber: Send {
+    fn transcribe(&mut self, samples: &[f32]) -> Result<String, String>;
+}
+
+/// Where the built-in engine looks for its model
+pub fn model_path(app: &AppHandle, config: &AppConfig) -> PathBuf {
+    match config
+        .stt_model_path
+        .as_deref()
+        .filter(|p| !p.trim().is_empty())
+    {
+        Some(path) => PathBuf::from(path),
+        None => storage::get_internal_app_dir(app)
+            .join("models")
+            .join(DEFAULT_MODEL_FILE),
+    }
+}
+
+/// Language for the transcriber, None = detect
+fn language(config: &AppConfig) -> Option<String> {
+    let language = config.stt_language.trim();
+    if language.is_empty() || language.eq_ignore_ascii_case("auto") {
+        None
+    } else {
+        Some(language.to_lowercase())
+    }
+}
+
+/// Transcription endpoint of the configured server
+/// There is no fallback to `local_base_url`: chat servers such as Ollama don't transcribe,
+/// and pointing at one would only fail on the first utterance with a bare 404.
+fn server_url(config: &AppConfig) -> Result<String, String> {
+    let base_url = config
+        .stt_base_url
+        .as_deref()
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+        .ok_or(if cfg!(feature = "whisper") {
+            "No transcription server is set. Enter the URL of a Whisper-compatible server in the voice settings, \
+             or use the built-in Whisper"
+        } else {
+            NO_BUILTIN_WHISPER
+        })?;
+    Ok(format!(
+        "{}/v1/audio/transcriptions",
+        local::server_root(base_url)
+    ))
+}
+
+/// Transcriber from config; loading a Whisper model takes a moment, so call this off the main thread
+pub fn for_config(app: &AppHandle, config: &AppConfig) -> Result<Box<dyn Transcriber>, String> {
+    match config.stt_engine.as_str() {
+        "server" => {
+            let url = server_url(config)?;
+            Ok(Box::new(ServerTranscriber {
+                client: reqwest::Client::builder()
+                    .timeout(Duration::from_secs(60))
+                    .build()
+                    .unwrap_or_default(),
+                url,
+                model: config.stt_model.clone(),
+                language: language(config),
+            }))
+        }
+        #[cfg(feature = "whisper")]
+        "whisper" => Ok(Box::new(whisper::WhisperTranscriber::load(
+            &model_path(app, config),
+            language(config),
+        )?)),
+        #[cfg(not(feature = "whisper"))]
+        "whisper" => {
+            let _ = app;
+            Err(NO_BUILTIN_WHISPER.to_string())
+        }
+        other => Err(format!("Unknown transcription engine: {}", other)),
+    }
+}
+
+/// whisper.cpp server, faster-whisper-server, LocalAI and the like
+struct ServerTranscriber {
+    client: reqwest::Client,
+    url: String,
+    model: String,
+    language: Option<String>,
+}
+
+impl Transcriber for ServerTranscriber {
+    fn transcribe(&mut self, samples: &[f32]) -> Result<String, String> {
+        let file = reqwest::multipart::Part::bytes(audio::encode_wav(samples)?)
+            .file_name("speech.wav")
+            .mime_str("audio/wav")
+            .map_err(|e| e.to_string())?;
+        let mut form = reqwest::multipart::Form::new()
+            .part("file", file)
+            .text("model", self.model.clone())
+            .text("response_format", "json");
+        if let Some(language) = &self.language {
+            form = form.text("language", language.clone());
+        }
+        let request = self.client.post(&self.url).multipart(form);
+
+        tauri::async_runtime::block_on(async move {
+            let response = request
+                .send()
+                .await
+                .map_err(|e| format!("Transcription server unreachable: {}", e))?;
+            if !response.status().is_success() {
+                let status = response.status().as_u16();
+                let body = response.text().await.unwrap_or_default();
+                return Err(format!(
+                    "Transcription server returned {}: {}",
+                    status,
+                    body.trim()
+                ));
+            }
+            let data: Value = response.json().await.map_err(|e| e.to_string())?;
+            Ok(data["text"].as_str().unwrap_or("").to_string())
+        })
+    }
+}
+
+#[cfg(feature = "whisper")]
+mod whisper {
+    use std::path::Path;
+    use whisper_rs::{
+        FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters, WhisperState,
+    };
+
+    use super::Transcriber;
+
+    pub struct WhisperTranscriber {
+        state: WhisperState,
+        language: Option<String>,
+        threads: i32,
+    }
+
+    impl WhisperTranscriber {
+        pub fn load(path: &Path, language: Option<String>) -> Result<Self, String> {
+            if !path.exists() {
+                return Err(format!("Whisper model not found at {}", path.display()));
+            }
+            let context = WhisperContext::new_with_params(
+                &path.to_string_lossy(),
+                WhisperContextParameters::default(),
+            )
+            .map_err(|e| format!("Failed to load Whisper model: {}", e))?;
+            let state = context.create_state().map_err(|e| e.to_string())?;
+            let threads = std::thread::available_parallelism()
+                .map(|n| n.get().min(8) as i32)
+                .unwrap_or(4);
+            Ok(Self {
+                state,
+                language,
+                threads,
+            })
+        }
+    }
+
+    impl Transcriber for WhisperTranscriber {
+        fn transcribe(&mut self, samples: &[f32]) -> Result<String, String> {
+            let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+            params.set_language(self.language.as_deref());
+            params.set_n_threads(self.threads);
+            // Utterances are unrelated, don't let one bias the next
+            params.set_no_context(true);
+            params.set_print_special(false);
+            params.set_print_progress(false);
+            params.set_print_realtime(false);
+            params.set_print_timestamps(false);
+
+            self.state
+                .full(params, samples)
+                .map_err(|e| format!("Transcription failed: {}", e))?;
+            let segments = self.state.full_n_segments().map_err(|e| e.to_string())?;
+            let mut text = String::new();
+            for segment in 0..segments {
+                text.push_str(
+                    &self
+                        .state
+                        .full_get_segment_text_lossy(segment)
+                        .map_err(|e| e.to_string())?,
+                );
+            }
+            Ok(text)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn server_engine_needs_its_own_url() {
+        let mut config = AppConfig {
+            stt_base_url: None,
+            ..AppConfig::default()
+        };
+        let error = server_url(&config).unwrap_err();
+        if cfg!(feature = "whisper") {
+            assert!(error.contains("No transcription server"));
+        } else {
+            // The default build can only recognize speech offline through a local server
+            assert!(error.contains("`whisper` feature"));
+            assert!(error.contains("local Whisper-compatible server"));
+        }
+
+        config.stt_base_url = Some("  ".to_string());
+        assert!(server_url(&config).is_err());
+
+        config.stt_base_url = Some("http://localhost:8000/v1/".to_string());
+        assert_eq!(
+            server_url(&config).unwrap(),
+            "http://localhost:8000/v1/audio/transcriptions"
+        );
+    }
+}
@@ -0,0 +1,333 @@
+//! Spoken responses
+//! Text is split into sentences that are synthesized on a worker thread and played as each is ready,
+//! so a streamed response starts speaking long before it is complete.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::{audio, synth};
+use crate::services::storage;
+
+/// Release the audio device after this long without speech
+const IDLE_CLOSE: Duration = Duration::from_secs(30);
+
+struct SpeechJob {
+    generation: u64,
+    text: String,
+    settings: synth::SpeechSettings,
+}
+
+/// Managed state for speech output
+#[derive(Default)]
+pub struct SpeechOutput {
+    /// Streamed response text that doesn't end in a full sentence yet
+    partial: Mutex<String>,
+    jobs: Mutex<Option<mpsc::Sender<SpeechJob>>>,
+    /// Samples waiting for the speaker, at its rate
+    buffer: Arc<Mutex<VecDeque<f32>>>,
+    /// Incremented by stop so queued and in-flight sentences are dropped
+    generation: AtomicU64,
+    /// Sentences queued or being synthesized
+    queued: AtomicUsize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpeechStatus {
+    pub speaking: bool,
+    pub muted: bool,
+    /// Sentences waiting to be spoken
+    pub queued: usize,
+}
+
+impl SpeechOutput {
+    fn is_speaking(&self) -> bool {
+        self.queued.load(Ordering::SeqCst) > 0
+            || self.buffer.lock().map(|b| !b.is_empty()).unwrap_or(false)
+    }
+
+    fn stop(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        if let Ok(mut partial) = self.partial.lock() {
+            partial.clear();
+        }
+        if let Ok(mut buffer) = self.buffer.lock() {
+            buffer.clear();
+        }
+    }
+}
+
+/// Synthesize one sentence and queue its samples for the speaker
+fn play(
+    state: &SpeechOutput,
+    speaker: &mut Option<(cpal::Stream, u32)>,
+    job: SpeechJob,
+) -> Result<(), String> {
+    if job.generation != state.generation.load(Ordering::SeqCst) {
+        return Ok(());
+    }
+    let speech = synth::synthesize(&job.settings, &job.text)?;
+
+    let rate = match speaker {
+        Some((_, rate)) => *rate,
+        None => speaker.insert(audio::open_speaker(state.buffer.clone())?).1,
+    };
+    let samples = audio::Resampler::new(speech.sample_rate, rate).process(&speech.samples);
+
+    // Stopped while this sentence was being synthesized
+    if job.generation != state.generation.load(Ordering::SeqCst) {
+        return Ok(());
+    }
+    state
+        .buffer
+        .lock()
+        .map_err(|e| e.to_string())?
+        .extend(samples);
+    Ok(())
+}
+
+/// Worker that owns the audio output; it has to stay on one thread
+fn spawn_worker(app: AppHandle) -> mpsc::Sender<SpeechJob> {
+    let (sender, receiver) = mpsc::channel::<SpeechJob>();
+    std::thread::spawn(move || {
+        let state = app.state::<SpeechOutput>();
+        let mut speaker = None;
+        let mut speaking = false;
+        let mut idle_since = Instant::now();
+
+        loop {
+            let job = match receiver.recv_timeout(Duration::from_millis(100)) {
+                Ok(job) => Some(job),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+
+            let busy = job.is_some() || state.is_speaking();
+            if busy != speaking {
+                speaking = busy;
+                let _ = app.emit("tts://speaking", speaking);
+            }
+
+            if let Some(job) = job {
+                if let Err(e) = play(&state, &mut speaker, job) {
+                    eprintln!("Speech failed: {}", e);
+                    let _ = app.emit("tts://error", &e);
+                }
+                state.queued.fetch_sub(1, Ordering::SeqCst);
+            }
+
+            if busy {
+                idle_since = Instant::now();
+            } else if speaker.is_some() && idle_since.elapsed() > IDLE_CLOSE {
+                speaker = None;
+            }
+        }
+    });
+    sender
+}
+
+/// Queue sentences for speaking, unless muted
+fn enqueue(app: &AppHandle, sentences: Vec<String>) -> Result<(), String> {
+    let config = storage::load_config(app.clone());
+    if config.tts_muted {
+        return Ok(());
+    }
+    let settings = synth::SpeechSettings::from_config(app, &config);
+    let state = app.state::<SpeechOutput>();
+    let generation = state.generation.load(Ordering::SeqCst);
+
+    let mut jobs = state.jobs.lock().map_err(|e| e.to_string())?;
+    for text in sentences.iter().map(|s| synth::speakable(s)) {
+        if text.is_empty() {
+            continue;
+        }
+        let job = SpeechJob {
+            generation,
+            text,
+            settings: settings.clone(),
+        };
+        state.queued.fetch_add(1, Ordering::SeqCst);
+        let sender = jobs.get_or_insert_with(|| spawn_worker(app.clone()));
+        // Start a fresh worker if the last one died
+        if let Err(mpsc::SendError(job)) = sender.send(job) {
+            let sender = jobs.insert(spawn_worker(app.clone()));
+            if sender.send(job).is_err() {
+                state.queued.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Speak text, queued after anything already being said
+#[tauri::command]
+pub fn speak(app: AppHandle, text: String) -> Result<(), String> {
+    let mut text = text;
+    enqueue(&app, synth::take_sentences(&mut text, true))
+}
+
+/// Speak a streamed response as it arrives; each complete sentence is spoken right away
+/// Pass `done` with the last chunk so the remainder is spoken too
+#[tauri::command]
+pub fn speak_partial(app: AppHandle, text: String, done: Option<bool>) -> Result<(), String> {
+    let sentences = {
+        let state = app.state::<SpeechOutput>();
+        let mut partial = state.partial.lock().map_err(|e| e.to_string())?;
+        partial.push_str(&text);
+        synth::take_sentences(&mut partial, done.unwrap_or(false))
+    };
+    enqueue(&app, sentences)
+}
+
+/// Stop speaking and drop everything queued
+#[tauri::command]
+pub fn stop_speaking(state: tauri::State<SpeechOutput>) {
+    state.stop();
+}
+
+/// Mute or unmute speech; muting also stops what is being said
+#[tauri::command]
+pub fn set_tts_muted(app: AppHandle, muted: bool) -> Result<(), String> {
+    let mut config = storage::load_config(app.clone());
+    config.tts_muted = muted;
+    storage::save_config(app.clone(), config)?;
+    if muted {
+        app.state::<SpeechOutput>().stop();
+    }
+    let _ = app.emit("tts://muted", muted);
+    Ok(())
+}
+
+/// Get speech output status
+#[tauri::command]
+pub fn get_speech_status(app: AppHandle) -> SpeechStatus {
+    let state = app.state::<SpeechOutput>();
+    SpeechStatus {
+        speaking: state.is_speaking(),
+        muted: storage::load_config(app.clone()).tts_muted,
+        queued: state.queued.load(Ordering::SeqCst),
+    }
+}
+
+/// List voices of the configured speech engine
+#[tauri::command]
+pub async fn list_tts_voices(app: AppHandle) -> Result<Vec<String>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let config = storage::load_config(app.clone());
+        synth::list_voices(&synth::SpeechSettings::from_config(&app, &config))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Synthesize text to a WAV file, with the same voice and filter as playback
+/// Writes to the captures folder unless a path is given; returns the path of the written file
+#[tauri::command]
+pub async fn export_speech_wav(
+    app: AppHandle,
+    text: String,
+    path: Option<String>,
+) -> Result<String, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let config = storage::load_config(app.clone());
+        let path = match path {
+            Some(path) => std::path::PathBuf::from(path),
+            None => {
+                storage::ensure_data_directories(&config)?;
+                let timestamp = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_millis())
+                    .unwrap_or(0);
+                storage::get_captures_dir(&config).join(format!("speech_{}.wav", timestamp))
+            }
+        };
+
+        write_speech(
+            &synth::SpeechSettings::from_config(&app, &config),
+            &text,
+            &path,
+        )?;
+        Ok(path.to_string_lossy().to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Synthesize the speakable part of `text` into a WAV file at the engine's rate
+fn write_speech(
+    settings: &synth::SpeechSettings,
+    text: &str,
+    path: &std::path::Path,
+) -> Result<(), String> {
+    let text = synth::speakable(text);
+    if text.is_empty() {
+        return Err("Nothing to speak".to_string());
+    }
+    let speech = synth::synthesize(settings, &text)?;
+    audio::write_wav(path, &speech.samples, speech.sample_rate)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::services::test_files::temp_dir;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    /// A stand-in for Piper that prints two samples of raw audio
+    fn fake_piper(dir: &std::path::Path) -> synth::SpeechSettings {
+        let command = dir.join("piper");
+        fs::write(
+            &command,
+            "#!/bin/sh\ncat > /dev/null\nprintf '\\000\\100\\000\\300'\n",
+        )
+        .unwrap();
+        fs::set_permissions(&command, fs::Permissions::from_mode(0o755)).unwrap();
+        fs::write(dir.join("voice.onnx"), "").unwrap();
+        fs::write(
+            dir.join("voice.onnx.json"),
+            r#"{"audio": {"sample_rate": 22050}}"#,
+        )
+        .unwrap();
+
+        synth::SpeechSettings {
+            engine: "piper".to_string(),
+            command: command.to_string_lossy().to_string(),
+            voice: "voice".to_string(),
+            robot: false,
+            voices_dir: dir.to_path_buf(),
+        }
+    }
+
+    #[test]
+    fn exports_speech_at_the_voice_rate() {
+        let dir = temp_dir("export-speech");
+        let settings = fake_piper(&dir);
+        let path = dir.join("speech.wav");
+
+        write_speech(&settings, "**Hello** there", &path).unwrap();
+
+        let mut reader = hound::WavReader::open(&path).unwrap();
+        assert_eq!(reader.spec().sample_rate, 22_050);
+        assert_eq!(reader.spec().channels, 1);
+        let samples: Vec<i16> = reader.samples::<i16>().map(Result::unwrap).collect();
+        assert_eq!(samples, [16383, -16383]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn nothing_speakable_is_an_error() {
+        let dir = temp_dir("export-nothing");
+        let settings = fake_piper(&dir);
+        let path = dir.join("speech.wav");
+
+        let error = write_speech(&settings, "```\nlet x = 1;\n```", &path).unwrap_err();
+        assert_eq!(error, "Nothing to speak");
+        assert!(!path.exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
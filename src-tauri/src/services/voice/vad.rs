@@ -0,0 +1,240 @@
+//! Energy-based voice activity detection, splitting audio into utterances
+
+use std::collections::VecDeque;
+
+use super::audio::SAMPLE_RATE;
+
+/// 30 ms analysis frames
+const FRAME_SAMPLES: usize = SAMPLE_RATE as usize * 30 / 1000;
+const FRAME_MS: u64 = 30;
+/// Voiced frames in a row before an utterance starts
+const START_FRAMES: usize = 3;
+/// Silence that ends an utterance (~800 ms)
+const END_SILENCE_FRAMES: usize = 27;
+/// Audio kept from before the start so the first syllable isn't clipped (~300 ms)
+const PRE_ROLL_FRAMES: usize = 10;
+/// Longest utterance before it is cut (~20 s)
+const MAX_UTTERANCE_FRAMES: usize = 667;
+/// Utterances with less speech than this (~300 ms) are clicks and bumps
+const MIN_VOICED_FRAMES: usize = 10;
+/// RMS below which audio never counts as speech
+const MIN_THRESHOLD: f32 = 0.01;
+/// How far above the background level speech has to be
+const NOISE_FACTOR: f32 = 3.0;
+
+/// A stretch of speech, as 16 kHz mono samples
+pub struct Utterance {
+    pub samples: Vec<f32>,
+    /// Offsets from the start of the stream
+    pub start_ms: u64,
+    pub end_ms: u64,
+}
+
+pub struct Segmenter {
+    /// Samples waiting for a full frame
+    pending: Vec<f32>,
+    pre_roll: VecDeque<Vec<f32>>,
+    current: Option<Vec<f32>>,
+    start_frame: u64,
+    voiced_run: usize,
+    silent_run: usize,
+    voiced_total: usize,
+    noise_floor: f32,
+    frames_seen: u64,
+}
+
+impl Segmenter {
+    pub fn new() -> Self {
+        Self {
+            pending: Vec::new(),
+            pre_roll: VecDeque::new(),
+            current: None,
+            start_frame: 0,
+            voiced_run: 0,
+            silent_run: 0,
+            voiced_total: 0,
+            noise_floor: 0.0,
+            frames_seen: 0,
+        }
+    }
+
+    /// Stream position in milliseconds
+    pub fn elapsed_ms(&self) -> u64 {
+        self.frames_seen * FRAME_MS
+    }
+
+    /// Feed samples; returns the utterances that ended within them
+    pub fn push(&mut self, samples: &[f32]) -> Vec<Utterance> {
+        self.pending.extend_from_slice(samples);
+        let mut finished = Vec::new();
+        let mut offset = 0;
+        while self.pending.len() - offset >= FRAME_SAMPLES {
+            let frame = self.pending[offset..offset + FRAME_SAMPLES].to_vec();
+            offset += FRAME_SAMPLES;
+            if let Some(utterance) = self.process_frame(frame) {
+                finished.push(utterance);
+            }
+        }
+        self.pending.drain(..offset);
+        finished
+    }
+
+    /// End of input; returns the utterance still in progress, if any
+    pub fn flush(&mut self) -> Option<Utterance> {
+        self.pending.clear();
+        self.finish()
+    }
+
+    fn process_frame(&mut self, frame: Vec<f32>) -> Option<Utterance> {
+        let rms = (frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32).sqrt();
+        let voiced = rms > (self.noise_floor * NOISE_FACTOR).max(MIN_THRESHOLD);
+        self.frames_seen += 1;
+
+        let Some(current) = self.current.as_mut() else {
+            // Track the background level: follow drops quickly, rises slowly
+            let rate = if rms < self.noise_floor { 0.2 } else { 0.02 };
+            self.noise_floor += (rms - self.noise_floor) * rate;
+
+            self.pre_roll.push_back(frame);
+            if self.pre_roll.len() > PRE_ROLL_FRAMES {
+                self.pre_roll.pop_front();
+            }
+            self.voiced_run = if voiced { self.voiced_run + 1 } else { 0 };
+            if self.voiced_run >= START_FRAMES {
+                self.start_frame = self.frames_seen - self.pre_roll.len() as u64;
+                self.current = Some(self.pre_roll.drain(..).flatten().collect());
+                self.voiced_total = self.voiced_run;
+                self.voiced_run = 0;
+                self.silent_run = 0;
+            }
+            return None;
+        };
+
+        current.extend_from_slice(&frame);
+        if voiced {
+            self.silent_run = 0;
+            self.voiced_total += 1;
+        } else {
+            self.silent_run += 1;
+        }
+
+        if self.silent_run >= END_SILENCE_FRAMES
+            || current.len() >= MAX_UTTERANCE_FRAMES * FRAME_SAMPLES
+        {
+            return self.finish();
+        }
+        None
+    }
+
+    fn finish(&mut self) -> Option<Utterance> {
+        let samples = self.current.take()?;
+        let voiced_total = std::mem::take(&mut self.voiced_total);
+        self.silent_run = 0;
+        if voiced_total < MIN_VOICED_FRAMES {
+            return None;
+        }
+        Some(Utterance {
+            start_ms: self.start_frame * FRAME_MS,
+            end_ms: self.frames_seen * FRAME_MS,
+            samples,
+        })
+    }
+}
+
+impl Default for Segmenter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn silence(ms: usize) -> Vec<f32> {
+        vec![0.0; SAMPLE_RATE as usize * ms / 1000]
+    }
+
+    fn speech(ms: usize) -> Vec<f32> {
+        (0..SAMPLE_RATE as usize * ms / 1000)
+            .map(|i| 0.3 * (i as f32 * 220.0 * std::f32::consts::TAU / SAMPLE_RATE as f32).sin())
+            .collect()
+    }
+
+    fn segment(parts: &[Vec<f32>]) -> Vec<Utterance> {
+        let mut segmenter = Segmenter::new();
+        let mut utterances = segmenter.push(&parts.concat());
+        utterances.extend(segmenter.flush());
+        utterances
+    }
+
+    #[test]
+    fn silence_has_no_utterances() {
+        assert!(segment(&[silence(3000)]).is_empty());
+    }
+
+    #[test]
+    fn speech_between_pauses_is_one_utterance_with_pre_roll() {
+        let utterances = segment(&[silence(990), speech(990), silence(1500)]);
+
+        assert_eq!(utterances.len(), 1);
+        let utterance = &utterances[0];
+        // Starts a little before the speech, ends once the pause is long enough
+        assert!(utterance.start_ms < 990);
+        assert!(utterance.start_ms >= 990 - PRE_ROLL_FRAMES as u64 * FRAME_MS);
+        assert!(utterance.end_ms > 1980);
+        assert!(utterance.end_ms <= 1980 + (END_SILENCE_FRAMES as u64 + 1) * FRAME_MS);
+        assert_eq!(
+            utterance.samples.len() as u64,
+            (utterance.end_ms - utterance.start_ms) * SAMPLE_RATE as u64 / 1000
+        );
+    }
+
+    #[test]
+    fn pauses_separate_utterances() {
+        let utterances = segment(&[speech(600), silence(1200), speech(600), silence(1200)]);
+        assert_eq!(utterances.len(), 2);
+        assert!(utterances[0].end_ms <= utterances[1].start_ms);
+    }
+
+    #[test]
+    fn clicks_are_ignored() {
+        assert!(segment(&[silence(500), speech(150), silence(1500)]).is_empty());
+    }
+
+    #[test]
+    fn long_speech_is_cut() {
+        let utterances = segment(&[speech(25_000)]);
+
+        assert_eq!(utterances.len(), 2);
+        assert_eq!(
+            utterances[0].samples.len(),
+            MAX_UTTERANCE_FRAMES * FRAME_SAMPLES
+        );
+    }
+
+    #[test]
+    fn flush_returns_speech_in_progress() {
+        let mut segmenter = Segmenter::new();
+        assert!(segmenter.push(&speech(1000)).is_empty());
+        assert!(segmenter.flush().is_some());
+        assert!(segmenter.flush().is_none());
+    }
+
+    #[test]
+    fn quiet_hum_is_not_speech_but_talking_over_it_is() {
+        let hum = |ms: usize| -> Vec<f32> {
+            (0..SAMPLE_RATE as usize * ms / 1000)
+                .map(|i| if i % 2 == 0 { 0.008 } else { -0.008 })
+                .collect()
+        };
+        let talk: Vec<f32> = speech(900)
+            .iter()
+            .zip(hum(900))
+            .map(|(s, h)| s + h)
+            .collect();
+
+        assert!(segment(&[hum(3000)]).is_empty());
+        assert_eq!(segment(&[hum(1000), talk, hum(1500)]).len(), 1);
+    }
+}
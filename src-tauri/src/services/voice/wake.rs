@@ -0,0 +1,111 @@
+//! Wake word spotting in transcripts
+//! Transcribers spell names loosely ("Joshua", "Josh-ua", "Joshuah"), so matching tolerates small edits.
+
+/// Lowercase letters and digits of a word
+fn normalize(word: &str) -> String {
+    word.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Edits allowed for a wake word of this many characters
+fn tolerance(len: usize) -> usize {
+    match len {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+/// Look for the wake word in a transcript
+/// Returns the text spoken after it (possibly empty), or None when it wasn't said
+pub fn find(transcript: &str, wake_word: &str) -> Option<String> {
+    let target: String = wake_word.split_whitespace().map(normalize).collect();
+    let wake_words = wake_word.split_whitespace().count();
+    if target.is_empty() {
+        return None;
+    }
+    let allowed = tolerance(target.chars().count());
+
+    let words: Vec<&str> = transcript.split_whitespace().collect();
+    let normalized: Vec<String> = words.iter().map(|w| normalize(w)).collect();
+    for start in 0..words.len() {
+        // The transcriber may split the wake word into more words or merge it into fewer
+        for len in wake_words.saturating_sub(1).max(1)..=wake_words + 1 {
+            if start + len > words.len() {
+                break;
+            }
+            let candidate = normalized[start..start + len].concat();
+            if !candidate.is_empty() && edit_distance(&candidate, &target) <= allowed {
+                let rest = words[start + len..].join(" ");
+                return Some(
+                    rest.trim_start_matches(|c: char| {
+                        c.is_ascii_punctuation() || c.is_whitespace()
+                    })
+                    .to_string(),
+                );
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn returns_what_follows_the_wake_word() {
+        assert_eq!(
+            find("Hey Joshua, open the pod bay doors.", "Joshua").as_deref(),
+            Some("open the pod bay doors.")
+        );
+        assert_eq!(find("Joshua.", "Joshua").as_deref(), Some(""));
+    }
+
+    #[test]
+    fn tolerates_loose_spelling() {
+        assert_eq!(
+            find("Joshuah what time is it", "Joshua").as_deref(),
+            Some("what time is it")
+        );
+        assert_eq!(find("josh-ua lights", "Joshua").as_deref(), Some("lights"));
+        assert_eq!(find("Josh ua lights", "Joshua").as_deref(), Some("lights"));
+    }
+
+    #[test]
+    fn multi_word_wake_words_may_merge() {
+        assert_eq!(
+            find("Heyjarvis lights on", "Hey Jarvis").as_deref(),
+            Some("lights on")
+        );
+        assert_eq!(
+            find("hey, Jarvis: lights on", "Hey Jarvis").as_deref(),
+            Some("lights on")
+        );
+    }
+
+    #[test]
+    fn other_words_do_not_match() {
+        assert_eq!(find("I like writing Java", "Joshua"), None);
+        // Short wake words must match exactly
+        assert_eq!(find("Mac, play music", "Max"), None);
+        assert_eq!(find("anything", "  "), None);
+    }
+}
@@ -0,0 +1,161 @@
+use async_trait::async_trait;
+use scraper::{ElementRef, Html, Selector};
+
+use super::{SearchProvider, SearchResult};
+
+const ENDPOINT: &str = "https://html.duckduckgo.com/html/";
+
+/// DuckDuckGo's HTML results page, works without an API key
+pub struct DuckDuckGoProvider {
+    endpoint: String,
+}
+
+impl DuckDuckGoProvider {
+    pub fn new() -> Self {
+        Self {
+            endpoint: ENDPOINT.to_string(),
+        }
+    }
+}
+
+/// Result links go through a redirect that carries the real URL in `uddg`
+fn result_url(href: &str) -> Option<String> {
+    let absolute = if href.starts_with("//") {
+        format!("https:{}", href)
+    } else {
+        href.to_string()
+    };
+    let parsed = reqwest::Url::parse(&absolute).ok()?;
+    match parsed.query_pairs().find(|(key, _)| key == "uddg") {
+        Some((_, target)) => Some(target.to_string()),
+        None => Some(absolute),
+    }
+}
+
+/// Text of an element on one line; titles and snippets wrap in the page source
+fn one_line(element: ElementRef) -> String {
+    element.text().collect::<String>().split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn parse_results(html: &str, max_results: usize) -> Vec<SearchResult> {
+    let document = Html::parse_document(html);
+    let (Ok(result), Ok(link), Ok(snippet)) = (
+        Selector::parse(".result"),
+        Selector::parse("a.result__a"),
+        Selector::parse(".result__snippet"),
+    ) else {
+        return Vec::new();
+    };
+
+    document
+        .select(&result)
+        .filter_map(|element| {
+            let anchor = element.select(&link).next()?;
+            let url = result_url(anchor.value().attr("href")?)?;
+            // Sponsored results link back into DuckDuckGo
+            if url.contains("duckduckgo.com/y.js") {
+                return None;
+            }
+            Some(SearchResult {
+                title: one_line(anchor),
+                url,
+                snippet: element
+                    .select(&snippet)
+                    .next()
+                    .map(one_line)
+                    .unwrap_or_default(),
+            })
+        })
+        .take(max_results)
+        .collect()
+}
+
+#[async_trait]
+impl SearchProvider for DuckDuckGoProvider {
+    async fn search(
+        &self,
+        client: &reqwest::Client,
+        query: &str,
+        max_results: usize,
+    ) -> Result<Vec<SearchResult>, String> {
+        let response = client
+            .post(&self.endpoint)
+            .form(&[("q", query)])
+            .send()
+            .await
+            .map_err(|e| format!("DuckDuckGo unreachable: {}", e))?;
+        if !response.status().is_success() {
+            return Err(format!("DuckDuckGo returned {}", response.status().as_u16()));
+        }
+
+        let html = response.text().await.map_err(|e| e.to_string())?;
+        Ok(parse_results(&html, max_results))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_http::{block_on, Reply, TestServer};
+
+    const RESULTS_PAGE: &str = r#"<html><body>
+<div class="result results_links">
+  <h2><a class="result__a" href="//duckduckgo.com/l/?uddg=https%3A%2F%2Fwww.rust-lang.org%2F&amp;rut=abc">Rust
+    Programming   Language</a></h2>
+  <a class="result__snippet">A language empowering <b>everyone</b>.</a>
+</div>
+<div class="result result--ad">
+  <a class="result__a" href="https://duckduckgo.com/y.js?ad_provider=x">Buy Rust</a>
+</div>
+<div class="result">
+  <a class="result__a" href="https://doc.rust-lang.org/book/">The Book</a>
+</div>
+</body></html>"#;
+
+    fn provider(server: &TestServer) -> DuckDuckGoProvider {
+        DuckDuckGoProvider {
+            endpoint: format!("{}/html/", server.url),
+        }
+    }
+
+    #[test]
+    fn parses_results_and_skips_ads() {
+        let results = parse_results(RESULTS_PAGE, 10);
+        let found: Vec<(&str, &str, &str)> = results
+            .iter()
+            .map(|r| (r.title.as_str(), r.url.as_str(), r.snippet.as_str()))
+            .collect();
+
+        assert_eq!(
+            found,
+            [
+                (
+                    "Rust Programming Language",
+                    "https://www.rust-lang.org/",
+                    "A language empowering everyone."
+                ),
+                ("The Book", "https://doc.rust-lang.org/book/", ""),
+            ]
+        );
+        assert_eq!(parse_results(RESULTS_PAGE, 1).len(), 1);
+    }
+
+    #[test]
+    fn posts_the_query_as_a_form() {
+        let server = TestServer::replying(Reply::text(200, "text/html", RESULTS_PAGE));
+        let results = block_on(provider(&server).search(&reqwest::Client::new(), "rust lang", 5)).unwrap();
+
+        assert_eq!(results.len(), 2);
+        let sent = &server.requests()[0];
+        assert_eq!((sent.method.as_str(), sent.path.as_str()), ("POST", "/html/"));
+        assert_eq!(sent.body, "q=rust+lang");
+    }
+
+    #[test]
+    fn error_status_is_reported() {
+        let server = TestServer::replying(Reply::text(429, "text/html", "slow down"));
+        let error = block_on(provider(&server).search(&reqwest::Client::new(), "rust", 5)).unwrap_err();
+
+        assert_eq!(error, "DuckDuckGo returned 429");
+    }
+}
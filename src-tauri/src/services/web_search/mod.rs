@@ -0,0 +1,401 @@
+//! Web search for any model
+//! Results come from a pluggable search provider; pages are fetched and reduced to readable text.
+
+mod duckduckgo;
+mod searxng;
+
+use async_trait::async_trait;
+use ego_tree::iter::Edge;
+use futures_util::StreamExt;
+use scraper::{Html, Node, Selector};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::net::{IpAddr, ToSocketAddrs};
+use std::time::Duration;
+use tauri::AppHandle;
+
+use super::ai::ToolDefinition;
+use super::storage::{self, AppConfig};
+
+const USER_AGENT: &str = "Mozilla/5.0 (compatible; WOPR desktop assistant)";
+/// Results returned when the caller doesn't ask for a number
+const DEFAULT_RESULTS: usize = 5;
+const MAX_RESULTS: usize = 10;
+/// Bytes read from a page before giving up on the rest
+const MAX_PAGE_BYTES: usize = 2 * 1024 * 1024;
+/// Characters of page text handed to the model
+const MAX_PAGE_CHARS: usize = 8000;
+
+/// Elements whose text is never part of the readable content
+const SKIPPED_TAGS: &[&str] = &[
+    "script", "style", "noscript", "template", "svg", "nav", "header", "footer", "aside", "form", "iframe",
+];
+/// Elements that end a line of text
+const BLOCK_TAGS: &[&str] = &[
+    "p", "div", "br", "li", "tr", "h1", "h2", "h3", "h4", "h5", "h6", "pre", "blockquote", "section", "article",
+    "table", "ul", "ol", "dd", "dt", "figcaption",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub title: String,
+    pub url: String,
+    pub snippet: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageText {
+    pub url: String,
+    pub title: Option<String>,
+    pub text: String,
+    /// True when the text was cut to fit the prompt
+    pub truncated: bool,
+}
+
+/// A search backend
+#[async_trait]
+pub trait SearchProvider: Send + Sync {
+    async fn search(&self, client: &reqwest::Client, query: &str, max_results: usize)
+        -> Result<Vec<SearchResult>, String>;
+}
+
+/// Provider from config; None when search is left to the AI vendor's built-in tool
+fn provider_for(config: &AppConfig) -> Result<Option<Box<dyn SearchProvider>>, String> {
+    match config.web_search_provider.as_str() {
+        "searxng" => {
+            let url = config
+                .searxng_url
+                .as_deref()
+                .filter(|u| !u.trim().is_empty())
+                .ok_or("SearXNG URL is not set")?;
+            Ok(Some(Box::new(searxng::SearxngProvider::new(url))))
+        }
+        "duckduckgo" => Ok(Some(Box::new(duckduckgo::DuckDuckGoProvider::new()))),
+        _ => Ok(None),
+    }
+}
+
+/// Whether web search runs through the app's own tool instead of the vendor's
+pub fn uses_backend_search(config: &AppConfig) -> bool {
+    matches!(provider_for(config), Ok(Some(_)))
+}
+
+fn client(timeout: Duration) -> reqwest::Client {
+    reqwest::Client::builder()
+        .user_agent(USER_AGENT)
+        .connect_timeout(Duration::from_secs(5))
+        .timeout(timeout)
+        .redirect(reqwest::redirect::Policy::custom(|attempt| {
+            if attempt.previous().len() >= 5 {
+                attempt.stop()
+            } else if attempt.url().host_str().map(is_private_host).unwrap_or(true) {
+                attempt.error("Redirect to a private address")
+            } else {
+                attempt.follow()
+            }
+        }))
+        .build()
+        .unwrap_or_default()
+}
+
+fn is_private_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            v4.is_loopback() || v4.is_private() || v4.is_link_local() || v4.is_unspecified() || v4.is_broadcast()
+        }
+        IpAddr::V6(v6) => {
+            v6.is_loopback()
+                || v6.is_unspecified()
+                // Unique local (fc00::/7) and link-local (fe80::/10)
+                || (v6.segments()[0] & 0xfe00) == 0xfc00
+                || (v6.segments()[0] & 0xffc0) == 0xfe80
+                || v6.to_ipv4_mapped().map(|v4| is_private_ip(IpAddr::V4(v4))).unwrap_or(false)
+        }
+    }
+}
+
+/// Host names and literals that point at this machine or the local network
+fn is_private_host(host: &str) -> bool {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if host.eq_ignore_ascii_case("localhost") || host.ends_with(".localhost") || host.ends_with(".local") {
+        return true;
+    }
+    host.parse::<IpAddr>().map(is_private_ip).unwrap_or(false)
+}
+
+/// Pages are fetched on the model's behalf, so keep it away from local services
+async fn check_public_url(url: &str) -> Result<reqwest::Url, String> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| format!("Invalid URL: {}", e))?;
+    if parsed.scheme() != "http" && parsed.scheme() != "https" {
+        return Err("Only http and https pages can be fetched".to_string());
+    }
+    let host = parsed.host_str().ok_or("URL has no host")?.to_string();
+    if is_private_host(&host) {
+        return Err("Local and private addresses can't be fetched".to_string());
+    }
+
+    let port = parsed.port_or_known_default().unwrap_or(80);
+    let addresses = tauri::async_runtime::spawn_blocking(move || {
+        (host.as_str(), port)
+            .to_socket_addrs()
+            .map(|addrs| addrs.map(|a| a.ip()).collect::<Vec<_>>())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| format!("Failed to resolve host: {}", e))?;
+    if addresses.into_iter().any(is_private_ip) {
+        return Err("Local and private addresses can't be fetched".to_string());
+    }
+    Ok(parsed)
+}
+
+/// Collapse whitespace within lines and drop empty ones
+fn tidy(text: &str) -> String {
+    text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Title and main text of an HTML page, without navigation, scripts and other chrome
+fn readable_text(html: &str) -> (Option<String>, String) {
+    let document = Html::parse_document(html);
+    let title = Selector::parse("title")
+        .ok()
+        .and_then(|selector| document.select(&selector).next())
+        .map(|title| tidy(&title.text().collect::<String>()))
+        .filter(|title| !title.is_empty());
+
+    // Prefer the article itself when the page marks it up
+    let root = ["article", "main", "[role=main]", "body"]
+        .iter()
+        .filter_map(|s| Selector::parse(s).ok())
+        .find_map(|selector| document.select(&selector).next());
+    let Some(root) = root else {
+        return (title, String::new());
+    };
+
+    let mut text = String::new();
+    let mut skipped = 0usize;
+    for edge in root.traverse() {
+        match edge {
+            Edge::Open(node) => match node.value() {
+                Node::Element(element) if SKIPPED_TAGS.contains(&element.name()) => skipped += 1,
+                Node::Text(fragment) if skipped == 0 => text.push_str(&fragment.text),
+                _ => {}
+            },
+            Edge::Close(node) => {
+                if let Node::Element(element) = node.value() {
+                    if SKIPPED_TAGS.contains(&element.name()) {
+                        skipped = skipped.saturating_sub(1);
+                    } else if BLOCK_TAGS.contains(&element.name()) {
+                        text.push('\n');
+                    }
+                }
+            }
+        }
+    }
+    (title, tidy(&text))
+}
+
+/// Search the web with the configured provider
+pub async fn search(app: &AppHandle, query: &str, max_results: usize) -> Result<Vec<SearchResult>, String> {
+    let config = storage::load_config(app.clone());
+    let provider = provider_for(&config)?.ok_or("No web search provider is configured")?;
+    let query = query.trim();
+    if query.is_empty() {
+        return Err("Search query is empty".to_string());
+    }
+
+    let mut results = provider
+        .search(&client(Duration::from_secs(15)), query, max_results.clamp(1, MAX_RESULTS))
+        .await?;
+    results.truncate(max_results.clamp(1, MAX_RESULTS));
+    Ok(results)
+}
+
+/// Download a page and extract its readable text
+pub async fn fetch_page(url: &str) -> Result<PageText, String> {
+    let parsed = check_public_url(url).await?;
+    let response = client(Duration::from_secs(20))
+        .get(parsed)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch page: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Page returned {}", response.status().as_u16()));
+    }
+
+    let final_url = response.url().to_string();
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("text/html")
+        .to_lowercase();
+    if !content_type.starts_with("text/") && !content_type.contains("html") && !content_type.contains("json") {
+        return Err(format!("Can't read {} content", content_type));
+    }
+
+    // Pages can be huge, stop reading once there is more than enough text
+    let mut body = Vec::new();
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| format!("Failed to read page: {}", e))?;
+        body.extend_from_slice(&chunk);
+        if body.len() >= MAX_PAGE_BYTES {
+            body.truncate(MAX_PAGE_BYTES);
+            break;
+        }
+    }
+    let body = String::from_utf8_lossy(&body);
+
+    let (title, text) = if content_type.contains("html") {
+        readable_text(&body)
+    } else {
+        (None, tidy(&body))
+    };
+    let truncated = text.chars().count() > MAX_PAGE_CHARS;
+    let text = if truncated {
+        text.chars().take(MAX_PAGE_CHARS).collect()
+    } else {
+        text
+    };
+
+    Ok(PageText {
+        url: final_url,
+        title,
+        text,
+        truncated,
+    })
+}
+
+/// Tools offered to the model when web search is on
+pub fn tool_definitions() -> Vec<ToolDefinition> {
+    vec![
+        ToolDefinition {
+            name: "web_search".to_string(),
+            description: "Search the web. Returns numbered results with titles, URLs and snippets. \
+Cite the sources you use in your answer as markdown links."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string", "description": "Search terms" },
+                    "max_results": { "type": "integer", "description": "Number of results, default 5" }
+                },
+                "required": ["query"]
+            }),
+        },
+        ToolDefinition {
+            name: "fetch_page".to_string(),
+            description: "Read the main text of a web page, e.g. a search result that needs more detail.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "url": { "type": "string", "description": "http or https URL" }
+                },
+                "required": ["url"]
+            }),
+        },
+    ]
+}
+
+pub fn is_web_tool(name: &str) -> bool {
+    name == "web_search" || name == "fetch_page"
+}
+
+/// Run a web tool call for the model
+pub async fn call_tool(app: &AppHandle, name: &str, arguments: &Value) -> Result<(String, Option<String>), String> {
+    match name {
+        "web_search" => {
+            let query = arguments["query"].as_str().ok_or("Missing 'query'")?;
+            let max_results = arguments["max_results"].as_u64().map(|n| n as usize).unwrap_or(DEFAULT_RESULTS);
+            let results = search(app, query, max_results).await?;
+            let numbered: Vec<Value> = results
+                .iter()
+                .enumerate()
+                .map(|(i, r)| json!({ "n": i + 1, "title": r.title, "url": r.url, "snippet": r.snippet }))
+                .collect();
+            let output = json!({
+                "results": numbered,
+                "note": "Cite the sources you rely on as markdown links, e.g. [title](url)."
+            });
+            Ok((output.to_string(), None))
+        }
+        "fetch_page" => {
+            let url = arguments["url"].as_str().ok_or("Missing 'url'")?;
+            let page = fetch_page(url).await?;
+            Ok((serde_json::to_string(&page).map_err(|e| e.to_string())?, None))
+        }
+        other => Err(format!("Unknown tool: {}", other)),
+    }
+}
+
+/// Search the web with the configured provider
+#[tauri::command]
+pub async fn web_search(app: AppHandle, query: String, max_results: Option<usize>) -> Result<Vec<SearchResult>, String> {
+    search(&app, &query, max_results.unwrap_or(DEFAULT_RESULTS)).await
+}
+
+/// Fetch a page and return its readable text
+#[tauri::command]
+pub async fn fetch_page_text(url: String) -> Result<PageText, String> {
+    fetch_page(&url).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_http::{block_on, Reply, TestServer};
+
+    #[test]
+    fn private_and_loopback_hosts_are_rejected() {
+        for url in [
+            "http://localhost:8080/admin",
+            "http://printer.local/",
+            "http://app.localhost/",
+            "http://127.0.0.1/",
+            "http://10.0.0.5/",
+            "http://192.168.1.1/",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://0.0.0.0/",
+            "http://[::1]/",
+            "http://[fd00::1]/",
+            "http://[::ffff:127.0.0.1]/",
+        ] {
+            let error = block_on(check_public_url(url)).unwrap_err();
+            assert_eq!(error, "Local and private addresses can't be fetched", "{}", url);
+        }
+    }
+
+    #[test]
+    fn only_web_urls_are_fetched() {
+        assert_eq!(
+            block_on(check_public_url("file:///etc/passwd")).unwrap_err(),
+            "Only http and https pages can be fetched"
+        );
+        assert!(block_on(check_public_url("not a url")).is_err());
+    }
+
+    #[test]
+    fn fetch_page_refuses_a_local_server() {
+        let server = TestServer::replying(Reply::text(200, "text/html", "<p>secret</p>"));
+        let error = block_on(fetch_page(&server.url)).unwrap_err();
+
+        assert_eq!(error, "Local and private addresses can't be fetched");
+        assert!(server.requests().is_empty());
+    }
+
+    #[test]
+    fn readable_text_skips_page_chrome() {
+        let html = "<html><head><title> News </title><script>track()</script></head><body>\
+            <nav>Home | About</nav><article><h1>Headline</h1><p>First   paragraph.</p>\
+            <style>p{}</style><p>Second.</p></article></body></html>";
+        let (title, text) = readable_text(html);
+
+        assert_eq!(title.as_deref(), Some("News"));
+        assert_eq!(text, "Headline\nFirst paragraph.\nSecond.");
+    }
+}
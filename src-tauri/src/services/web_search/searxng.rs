@@ -0,0 +1,104 @@
+use async_trait::async_trait;
+use serde_json::Value;
+
+use super::{SearchProvider, SearchResult};
+
+/// Self-hosted SearXNG instance, queried through its JSON API
+/// The instance must have `json` enabled under `search.formats` in its settings.
+pub struct SearxngProvider {
+    base_url: String,
+}
+
+impl SearxngProvider {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl SearchProvider for SearxngProvider {
+    async fn search(
+        &self,
+        client: &reqwest::Client,
+        query: &str,
+        max_results: usize,
+    ) -> Result<Vec<SearchResult>, String> {
+        let response = client
+            .get(format!("{}/search", self.base_url))
+            .query(&[("q", query), ("format", "json")])
+            .send()
+            .await
+            .map_err(|e| format!("SearXNG unreachable: {}", e))?;
+        match response.status().as_u16() {
+            200..=299 => {}
+            403 => return Err("SearXNG refused JSON output, enable the json format in its settings".to_string()),
+            status => return Err(format!("SearXNG returned {}", status)),
+        }
+
+        let data: Value = response.json().await.map_err(|e| e.to_string())?;
+        Ok(data["results"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|result| {
+                Some(SearchResult {
+                    title: result["title"].as_str()?.to_string(),
+                    url: result["url"].as_str()?.to_string(),
+                    snippet: result["content"].as_str().unwrap_or("").to_string(),
+                })
+            })
+            .take(max_results)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_http::{block_on, Reply, TestServer};
+    use serde_json::json;
+
+    #[test]
+    fn reads_the_json_results() {
+        let server = TestServer::replying(Reply::json(
+            200,
+            json!({
+                "query": "rust",
+                "results": [
+                    { "title": "Rust", "url": "https://www.rust-lang.org/", "content": "A language" },
+                    { "title": "No URL" },
+                    { "title": "Crates", "url": "https://crates.io/" },
+                    { "title": "Extra", "url": "https://example.com/" }
+                ]
+            }),
+        ));
+        let provider = SearxngProvider::new(&format!("{}/", server.url));
+        let results = block_on(provider.search(&reqwest::Client::new(), "rust lang", 2)).unwrap();
+
+        let found: Vec<(&str, &str, &str)> = results
+            .iter()
+            .map(|r| (r.title.as_str(), r.url.as_str(), r.snippet.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("Rust", "https://www.rust-lang.org/", "A language"),
+                ("Crates", "https://crates.io/", ""),
+            ]
+        );
+        let sent = &server.requests()[0];
+        assert_eq!(sent.method, "GET");
+        assert_eq!(sent.path, "/search?q=rust+lang&format=json");
+    }
+
+    #[test]
+    fn explains_a_disabled_json_format() {
+        let server = TestServer::replying(Reply::text(403, "text/html", "Forbidden"));
+        let provider = SearxngProvider::new(&server.url);
+        let error = block_on(provider.search(&reqwest::Client::new(), "rust", 5)).unwrap_err();
+
+        assert_eq!(error, "SearXNG refused JSON output, enable the json format in its settings");
+    }
+}
@@ -0,0 +1,45 @@
+//! Restartable background threads
+//! Starting or stopping bumps a counter. A thread keeps the value it was started with and exits
+//! once the counter has moved on, so a restart never leaves the previous thread running.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Generation counter shared by a worker thread and whoever starts and stops it
+#[derive(Debug, Default)]
+pub struct Generation(AtomicU64);
+
+impl Generation {
+    /// Retire the running thread, if any, and return the value for its replacement
+    pub fn next(&self) -> u64 {
+        self.0.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    /// Retire the running thread without starting another
+    pub fn stop(&self) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Whether a thread started with `generation` should keep going
+    pub fn is_current(&self, generation: u64) -> bool {
+        self.0.load(Ordering::SeqCst) == generation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restarting_retires_the_previous_thread() {
+        let generation = Generation::default();
+        let first = generation.next();
+        assert!(generation.is_current(first));
+
+        let second = generation.next();
+        assert!(!generation.is_current(first));
+        assert!(generation.is_current(second));
+
+        generation.stop();
+        assert!(!generation.is_current(second));
+    }
+}
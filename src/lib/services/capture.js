@@ -40,3 +40,63 @@ export async function getScreenInfo() {
 export async function getAvailableScreens() {
   return await invoke('get_available_screens');
 }
+
+/**
+ * @typedef {Object} ReplayStatus
+ * @property {boolean} running - Whether the replay buffer is sampling
+ * @property {number} fps - Frames captured per second
+ * @property {number} seconds - Seconds of history kept
+ * @property {number|null} monitor_index - Monitor being sampled
+ * @property {number} frame_count - Number of buffered frames
+ * @property {number} buffered_bytes - Memory used by buffered frames
+ * @property {number} span_seconds - Seconds between oldest and newest frame
+ */
+
+/**
+ * @typedef {Object} ReplayFrame
+ * @property {number} timestamp_ms - Capture time (unix milliseconds)
+ * @property {string} image - Base64 encoded JPEG
+ */
+
+/**
+ * Start (or restart) the screen replay buffer
+ * @param {number | null} [fps] - Frames per second (null = config value)
+ * @param {number | null} [seconds] - Seconds of history (null = config value)
+ * @returns {Promise<ReplayStatus>}
+ */
+export async function startReplayBuffer(fps = null, seconds = null) {
+  return await invoke('start_replay_buffer', { fps, seconds });
+}
+
+/**
+ * Stop the replay buffer and discard buffered frames
+ * @returns {Promise<void>}
+ */
+export async function stopReplayBuffer() {
+  return await invoke('stop_replay_buffer');
+}
+
+/**
+ * Get replay buffer status
+ * @returns {Promise<ReplayStatus>}
+ */
+export async function getReplayStatus() {
+  return await invoke('get_replay_status');
+}
+
+/**
+ * Save the last N seconds of screen as an animated GIF in the captures folder
+ * @returns {Promise<string>} Path of the saved clip
+ */
+export async function saveReplayClip() {
+  return await invoke('save_replay_clip');
+}
+
+/**
+ * Get an evenly spaced strip of recent frames for multi-image analysis
+ * @param {number | null} [maxFrames] - Maximum number of frames (default 8)
+ * @returns {Promise<ReplayFrame[]>}
+ */
+export async function getReplayFrames(maxFrames = null) {
+  return await invoke('get_replay_frames', { maxFrames });
+}
@@ -0,0 +1,135 @@
+/**
+ * Conversation history
+ * Conversations are stored by the Rust backend in the data folder, so chats survive reloads
+ */
+
+import { invoke } from '@tauri-apps/api/core';
+
+/**
+ * @typedef {Object} ConversationAttachment
+ * @property {'screenshot'} kind
+ * @property {string} thumbnail - Thumbnail file name (load with getConversationThumbnail)
+ */
+
+/**
+ * @typedef {Object} ConversationMessage
+ * @property {'user' | 'assistant' | 'system'} role
+ * @property {string} content
+ * @property {number} timestamp - Unix milliseconds
+ * @property {string|null} provider - Provider that produced an assistant message
+ * @property {string|null} model
+ * @property {ConversationAttachment[]} attachments
+ */
+
+/**
+ * @typedef {Object} Conversation
+ * @property {string} id
+ * @property {string} title
+ * @property {boolean} custom_title - True once renamed by the user
+ * @property {number} created_at - Unix milliseconds
+ * @property {number} updated_at - Unix milliseconds
+ * @property {ConversationMessage[]} messages
+ */
+
+/**
+ * @typedef {Object} ConversationSummary
+ * @property {string} id
+ * @property {string} title
+ * @property {number} created_at
+ * @property {number} updated_at
+ * @property {number} message_count
+ * @property {string} preview - Start of the last message
+ */
+
+/**
+ * @typedef {Object} ConversationSearchHit
+ * @property {string} id
+ * @property {string} title
+ * @property {number} updated_at
+ * @property {number|null} message_index - Matching message (null = title matched)
+ * @property {string} snippet
+ */
+
+/**
+ * Start a new conversation
+ * @param {string | null} [title] - Leave empty to title it from the first message
+ * @returns {Promise<Conversation>}
+ */
+export async function createConversation(title = null) {
+  return await invoke('create_conversation', { title });
+}
+
+/**
+ * List conversations, most recently active first
+ * @returns {Promise<ConversationSummary[]>}
+ */
+export async function listConversations() {
+  return await invoke('list_conversations');
+}
+
+/**
+ * Get a conversation with its messages
+ * @param {string} id
+ * @returns {Promise<Conversation>}
+ */
+export async function getConversation(id) {
+  return await invoke('get_conversation', { id });
+}
+
+/**
+ * Add a message to a conversation
+ * Assistant replies are saved by the backend when the AI request carries the conversation id
+ * @param {string} id
+ * @param {{ role: string, content: string, provider?: string, model?: string, image?: string }} message - `image` is base64 JPEG
+ * @returns {Promise<ConversationMessage>}
+ */
+export async function appendConversationMessage(id, message) {
+  return await invoke('append_conversation_message', { id, message });
+}
+
+/**
+ * Search conversation titles and messages
+ * @param {string} query
+ * @returns {Promise<ConversationSearchHit[]>}
+ */
+export async function searchConversations(query) {
+  return await invoke('search_conversations', { query });
+}
+
+/**
+ * Rename a conversation
+ * @param {string} id
+ * @param {string} title
+ * @returns {Promise<void>}
+ */
+export async function renameConversation(id, title) {
+  return await invoke('rename_conversation', { id, title });
+}
+
+/**
+ * Delete a conversation and its screenshots
+ * @param {string} id
+ * @returns {Promise<void>}
+ */
+export async function deleteConversation(id) {
+  return await invoke('delete_conversation', { id });
+}
+
+/**
+ * Load a screenshot thumbnail
+ * @param {string} name - Attachment thumbnail name
+ * @returns {Promise<string>} Base64 JPEG
+ */
+export async function getConversationThumbnail(name) {
+  return await invoke('get_conversation_thumbnail', { name });
+}
+
+/**
+ * Export a conversation to the conversations/exports folder
+ * @param {string} id
+ * @param {'markdown' | 'html'} [format='markdown']
+ * @returns {Promise<string>} Path of the written file
+ */
+export async function exportConversation(id, format = 'markdown') {
+  return await invoke('export_conversation', { id, format });
+}
@@ -1,11 +1,11 @@
 /**
  * AI Knowledge Service
  * Manages persistent knowledge/memory for the AI that persists across sessions
+ * Memories are typed entries kept by the Rust memory service
  */
 
-import { loadBrainData, saveBrainData, loadConfig, saveConfig } from './storage.js';
-
-const KNOWLEDGE_FILE = 'knowledge.md';
+import { invoke } from '@tauri-apps/api/core';
+import { loadConfig, saveConfig } from './storage.js';
 
 /**
  * Extract user name from a knowledge item if it contains name info
@@ -49,13 +49,30 @@ async function updateConfigUserName(name) {
 }
 
 /**
- * Load knowledge from the brain storage
+ * @typedef {Object} MemoryEntry
+ * @property {string} id
+ * @property {string} text
+ * @property {string[]} tags
+ * @property {'user' | 'assistant' | 'screen' | 'import'} source - Where the memory came from
+ * @property {number} created_at - Unix milliseconds
+ * @property {number} updated_at - Unix milliseconds
+ * @property {boolean} pinned - Pinned memories get the prompt budget first
+ */
+
+/**
+ * @typedef {Object} AddMemoryResult
+ * @property {MemoryEntry} entry - The new entry, or the existing one it duplicates
+ * @property {boolean} duplicate - True when nothing new was stored
+ */
+
+/**
+ * Load memories rendered for the system prompt ("- item" lines, size-bounded)
+ * @param {number | null} [maxChars] - Prompt budget (null = backend default)
  * @returns {Promise<string>}
  */
-export async function loadKnowledge() {
+export async function loadKnowledge(maxChars = null) {
   try {
-    const data = await loadBrainData(KNOWLEDGE_FILE);
-    return data || '';
+    return await invoke('get_memory_prompt', { maxChars });
   } catch (e) {
     console.error('Failed to load knowledge:', e);
     return '';
@@ -63,73 +80,142 @@ export async function loadKnowledge() {
 }
 
 /**
- * Save knowledge to the brain storage
- * @param {string} content
- * @returns {Promise<void>}
+ * @typedef {Object} BrainHit
+ * @property {'memory' | 'file'} kind
+ * @property {string} source - Memory id or brain file name
+ * @property {string} text
+ * @property {number} score - Cosine similarity to the query
+ */
+
+/**
+ * @typedef {Object} BrainIndexStatus
+ * @property {'builtin' | 'local'} provider - Embedder used for the index (builtin only matches keywords)
+ * @property {string} model
+ * @property {number} items - Indexed memories and file chunks
+ * @property {number} embedded - Items embedded by this update
+ * @property {number} removed - Items dropped by this update
+ */
+
+/**
+ * Load the memories most relevant to a question, pinned ones first
+ * Falls back to the plain memory prompt when the index is unavailable
+ * @param {string} query - The user's message
+ * @param {number | null} [maxChars] - Prompt budget (null = backend default)
+ * @returns {Promise<string>}
  */
-export async function saveKnowledge(content) {
+export async function loadRelevantKnowledge(query, maxChars = null) {
   try {
-    await saveBrainData(KNOWLEDGE_FILE, content);
+    return await invoke('get_relevant_memory_prompt', { query, maxChars });
   } catch (e) {
-    console.error('Failed to save knowledge:', e);
-    throw e;
+    console.error('Brain search failed, using recent memories:', e);
+    return await loadKnowledge(maxChars);
   }
 }
 
 /**
- * Add a new piece of knowledge
- * @param {string} item - The knowledge item to add
- * @returns {Promise<string>} Updated knowledge
+ * Search memories and brain files by meaning (by shared words with the builtin embedder)
+ * @param {string} query
+ * @param {number} [k=5] - Number of results
+ * @returns {Promise<BrainHit[]>}
+ */
+export async function searchBrain(query, k = 5) {
+  return await invoke('search_brain', { query, k });
+}
+
+/**
+ * Bring the brain index up to date
+ * @param {boolean} [full=false] - Re-embed everything instead of only changed items
+ * @returns {Promise<BrainIndexStatus>}
+ */
+export async function rebuildBrainIndex(full = false) {
+  return await invoke('rebuild_brain_index', { full });
+}
+
+/**
+ * List all memories, newest first
+ * @returns {Promise<MemoryEntry[]>}
  */
-export async function addKnowledge(item) {
-  const current = await loadKnowledge();
-  const trimmedItem = item.trim();
+export async function listMemories() {
+  return await invoke('list_memories');
+}
 
-  if (!trimmedItem) return current;
+/**
+ * Add a new piece of knowledge (duplicates of existing memories are not stored again)
+ * @param {string} item - The knowledge item to add
+ * @param {{ tags?: string[], source?: string, pinned?: boolean }} [options]
+ * @returns {Promise<AddMemoryResult | null>} Null if the item is empty
+ */
+export async function addKnowledge(item, options = {}) {
+  const text = item.trim();
+  if (!text) return null;
 
-  // Check if this item already exists (case-insensitive)
-  const lines = current.split('\n').filter(l => l.trim());
-  const itemLower = trimmedItem.toLowerCase();
-  const exists = lines.some(line => {
-    const cleaned = line.replace(/^[-*]\s*/, '').trim().toLowerCase();
-    return cleaned === itemLower;
+  return await invoke('add_memory', {
+    text,
+    tags: options.tags ?? null,
+    source: options.source ?? 'user',
+    pinned: options.pinned ?? null
   });
+}
 
-  if (exists) {
-    return current;
-  }
+/**
+ * Edit a memory
+ * @param {string} id
+ * @param {{ text?: string, tags?: string[], pinned?: boolean }} update
+ * @returns {Promise<MemoryEntry>}
+ */
+export async function updateMemory(id, update) {
+  return await invoke('update_memory', { id, update });
+}
 
-  const newEntry = `- ${trimmedItem}`;
-  const updated = current ? `${current.trim()}\n${newEntry}` : newEntry;
-  await saveKnowledge(updated);
-  return updated;
+/**
+ * Delete a memory by id
+ * @param {string} id
+ * @returns {Promise<void>}
+ */
+export async function deleteMemory(id) {
+  return await invoke('delete_memory', { id });
 }
 
 /**
- * Remove a piece of knowledge
+ * Remove every memory containing the given text
  * @param {string} item - The knowledge item to remove (partial match)
- * @returns {Promise<{removed: boolean, updated: string}>}
+ * @returns {Promise<{removed: boolean, count: number}>}
  */
 export async function removeKnowledge(item) {
-  const current = await loadKnowledge();
-  const itemLower = item.toLowerCase().trim();
+  const query = item.trim();
+  if (!query) return { removed: false, count: 0 };
 
-  if (!itemLower) return { removed: false, updated: current };
-
-  const lines = current.split('\n');
-  const filtered = lines.filter(line => {
-    const cleaned = line.replace(/^[-*]\s*/, '').trim().toLowerCase();
-    return !cleaned.includes(itemLower);
-  });
+  /** @type {number} */
+  const count = await invoke('forget_memories', { query });
+  return { removed: count > 0, count };
+}
 
-  const removed = filtered.length < lines.length;
-  const updated = filtered.join('\n').trim();
+/**
+ * Groups of memories that look like duplicates of each other
+ * @returns {Promise<MemoryEntry[][]>}
+ */
+export async function findDuplicateMemories() {
+  return await invoke('find_duplicate_memories');
+}
 
-  if (removed) {
-    await saveKnowledge(updated);
-  }
+/**
+ * Import the legacy knowledge.md into the memory store
+ * @returns {Promise<number>} Number of memories added
+ */
+export async function importKnowledgeMarkdown() {
+  return await invoke('import_knowledge_markdown');
+}
 
-  return { removed, updated };
+/**
+ * Remove knowledge commands from a stored response without running them
+ * @param {string} text
+ * @returns {string}
+ */
+export function stripKnowledgeCommands(text) {
+  return text
+    .replace(/\[(?:REMEMBER|FORGET):\s*[^\]]+\]/gi, '')
+    .replace(/\n{3,}/g, '\n\n')
+    .trim();
 }
 
 /**
@@ -148,7 +234,7 @@ export async function parseAndExecuteKnowledgeCommands(response) {
 
   while ((match = rememberRegex.exec(response)) !== null) {
     const item = match[1].trim();
-    await addKnowledge(item);
+    await addKnowledge(item, { source: 'assistant' });
     actions.push(`Remembered: ${item}`);
     cleanedResponse = cleanedResponse.replace(match[0], '').trim();
 
@@ -0,0 +1,146 @@
+/**
+ * MCP (Model Context Protocol) servers
+ * Servers are connected by the Rust backend; their tools are offered to the assistant
+ */
+
+import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+import { ask } from '@tauri-apps/plugin-dialog';
+
+/**
+ * @typedef {Object} McpServerConfig
+ * @property {string} name - Unique server name
+ * @property {'stdio' | 'http'} transport - Spawned process or streamable HTTP on localhost
+ * @property {string|null} [command] - Program to run (stdio)
+ * @property {string[]} [args] - Program arguments (stdio)
+ * @property {Record<string, string>} [env] - Extra environment variables (stdio)
+ * @property {string|null} [url] - Endpoint URL (http, localhost only)
+ * @property {boolean} [enabled] - Whether the server is connected
+ * @property {string[]} [auto_approve] - Tools allowed without asking ("*" for all of them)
+ */
+
+/**
+ * @typedef {Object} McpTool
+ * @property {string} name
+ * @property {string} description
+ * @property {Object} input_schema - JSON schema of the arguments
+ * @property {boolean} read_only - The server's read-only hint (calls still need approval)
+ */
+
+/**
+ * @typedef {Object} McpServerStatus
+ * @property {McpServerConfig} config
+ * @property {boolean} connected
+ * @property {string|null} error - Last connection error
+ * @property {McpTool[]} tools
+ * @property {boolean} has_resources
+ */
+
+/**
+ * @typedef {Object} McpResource
+ * @property {string} server
+ * @property {string} uri
+ * @property {string} name
+ * @property {string|null} description
+ * @property {string|null} mime_type
+ */
+
+/**
+ * @typedef {Object} McpApprovalRequest
+ * @property {string} id
+ * @property {string} server
+ * @property {string} tool
+ * @property {string} description
+ * @property {Object} arguments
+ */
+
+/**
+ * List configured MCP servers with their connection state
+ * @returns {Promise<McpServerStatus[]>}
+ */
+export async function listMcpServers() {
+  return await invoke('list_mcp_servers');
+}
+
+/**
+ * Add or replace an MCP server (connects it when enabled)
+ * @param {McpServerConfig} server
+ * @returns {Promise<void>}
+ */
+export async function saveMcpServer(server) {
+  return await invoke('save_mcp_server', { server });
+}
+
+/**
+ * Remove an MCP server
+ * @param {string} name
+ * @returns {Promise<void>}
+ */
+export async function deleteMcpServer(name) {
+  return await invoke('delete_mcp_server', { name });
+}
+
+/**
+ * Enable or disable an MCP server
+ * @param {string} name
+ * @param {boolean} enabled
+ * @returns {Promise<void>}
+ */
+export async function setMcpServerEnabled(name, enabled) {
+  return await invoke('set_mcp_server_enabled', { name, enabled });
+}
+
+/**
+ * Reconnect an MCP server and refresh its tools
+ * @param {string} name
+ * @returns {Promise<void>}
+ */
+export async function reconnectMcpServer(name) {
+  return await invoke('reconnect_mcp_server', { name });
+}
+
+/**
+ * List resources offered by connected servers
+ * @param {string | null} [server] - Only this server (null = all)
+ * @returns {Promise<McpResource[]>}
+ */
+export async function listMcpResources(server = null) {
+  return await invoke('list_mcp_resources', { server });
+}
+
+/**
+ * Answer a tool approval request
+ * @param {string} id - Request id
+ * @param {boolean} approved
+ * @param {boolean} [always=false] - Don't ask again for this tool
+ * @returns {Promise<void>}
+ */
+export async function respondMcpApproval(id, approved, always = false) {
+  return await invoke('respond_mcp_approval', { id, approved, always });
+}
+
+/**
+ * Show a confirmation dialog whenever the assistant wants to run a side-effecting MCP tool
+ * @returns {Promise<() => void>} Unlisten function
+ */
+export async function watchMcpApprovals() {
+  return await listen('mcp://approval-request', async (event) => {
+    /** @type {McpApprovalRequest} */
+    const request = event.payload;
+    const args = JSON.stringify(request.arguments, null, 2);
+
+    let approved = false;
+    try {
+      approved = await ask(
+        `The assistant wants to run "${request.tool}" on the ${request.server} MCP server.\n\n${request.description}\n\nArguments:\n${args}`,
+        { title: 'Allow MCP tool?', kind: 'warning', okLabel: 'Allow', cancelLabel: 'Deny' }
+      );
+    } catch (e) {
+      console.error('Failed to show MCP approval prompt:', e);
+    }
+
+    await respondMcpApproval(request.id, approved).catch((e) => {
+      console.error('Failed to answer MCP approval:', e);
+    });
+  });
+}
@@ -1,8 +1,14 @@
 /**
  * Microphone Service
  * Provides cross-platform microphone device enumeration and selection
+ * Devices come from the Rust backend (stable IDs, hot-plug events), with the browser API as fallback
+ * Events: microphone://level (MicrophoneLevel), microphone://devices-changed (MicrophoneInfo[]),
+ * microphone://test-ended (string)
  */
 
+import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+
 /**
  * @typedef {Object} MicrophoneDevice
  * @property {string} deviceId - Unique device ID
@@ -10,12 +16,120 @@
  * @property {boolean} isDefault - Whether this is the default device
  */
 
+/**
+ * @typedef {Object} MicrophoneInfo
+ * @property {string} id - Stable ID, saved as selected_microphone
+ * @property {string} name
+ * @property {boolean} is_default
+ * @property {number|null} sample_rate
+ * @property {number|null} channels
+ */
+
+/**
+ * @typedef {Object} MicrophoneLevel
+ * @property {number} rms - 0 to 1
+ * @property {number} peak - 0 to 1
+ * @property {number} db - RMS in dBFS
+ */
+
+/**
+ * @param {MicrophoneInfo} info
+ * @returns {MicrophoneDevice}
+ */
+function toDevice(info) {
+  return { deviceId: info.id, label: info.name, isDefault: info.is_default };
+}
+
+/**
+ * List input devices known to the backend
+ * @returns {Promise<MicrophoneInfo[]>}
+ */
+export async function listMicrophones() {
+  return await invoke('list_microphones');
+}
+
+/**
+ * Get the system default input device
+ * @returns {Promise<MicrophoneInfo|null>}
+ */
+export async function getDefaultMicrophone() {
+  return await invoke('get_default_microphone');
+}
+
+/**
+ * Save the microphone used for voice input (validated by the backend)
+ * @param {string|null} id - Device ID, or null for the system default
+ * @returns {Promise<void>}
+ */
+export async function selectMicrophone(id) {
+  return await invoke('select_microphone', { id });
+}
+
+/**
+ * Start the mic test; levels arrive through onMicrophoneLevel
+ * @param {string|null} [id] - Device to test (default: the system default)
+ * @returns {Promise<string>} Name of the device being tested
+ */
+export async function startMicrophoneTest(id = null) {
+  return await invoke('start_microphone_test', { id });
+}
+
+/**
+ * Stop the mic test
+ * @returns {Promise<void>}
+ */
+export async function stopMicrophoneTest() {
+  return await invoke('stop_microphone_test');
+}
+
+/**
+ * Listen for mic test levels (about 20 per second)
+ * @param {(level: MicrophoneLevel) => void} callback
+ * @returns {Promise<() => void>} Unlisten function
+ */
+export async function onMicrophoneLevel(callback) {
+  return await listen('microphone://level', (event) => callback(/** @type {MicrophoneLevel} */ (event.payload)));
+}
+
+/**
+ * Listen for the mic test ending on its own (device unplugged)
+ * @param {(reason: string) => void} callback
+ * @returns {Promise<() => void>} Unlisten function
+ */
+export async function onMicrophoneTestEnded(callback) {
+  return await listen('microphone://test-ended', (event) => callback(/** @type {string} */ (event.payload)));
+}
+
+/**
+ * Listen for microphones being plugged in or removed
+ * @param {(devices: MicrophoneDevice[]) => void} callback
+ * @returns {Promise<() => void>} Unlisten function
+ */
+export async function onMicrophonesChanged(callback) {
+  return await listen('microphone://devices-changed', (event) =>
+    callback(/** @type {MicrophoneInfo[]} */ (event.payload).map(toDevice))
+  );
+}
+
 /**
  * Get list of available audio input devices (microphones)
- * Works on both Windows and macOS via browser MediaDevices API
+ * Uses the backend device list, falling back to the browser MediaDevices API
  * @returns {Promise<MicrophoneDevice[]>}
  */
 export async function getAvailableMicrophones() {
+  try {
+    const devices = await listMicrophones();
+    if (devices.length > 0) {
+      return devices.map(toDevice);
+    }
+  } catch (e) {
+    console.warn('Backend microphone list unavailable, using browser fallback:', e);
+  }
+
+  if (!isMediaDevicesSupported()) {
+    return [];
+  }
+
   try {
     // First, we need to request permission to access audio devices
     // This is required to get device labels on most browsers
@@ -118,7 +118,7 @@ export async function checkBrowserMicrophonePermission() {
 
 /**
  * Check microphone permission (cross-platform)
- * Uses native macOS plugin on macOS, falls back to browser API on other platforms
+ * Uses native macOS plugin on macOS and the backend check on Linux, falls back to browser API
  * @returns {Promise<boolean>}
  */
 export async function checkMicrophonePermissionCrossPlatform() {
@@ -133,7 +133,13 @@ export async function checkMicrophonePermissionCrossPlatform() {
     }
   }
 
-  // Use browser API for Windows/Linux or as fallback
+  // The backend knows whether the sound server is reachable on Linux
+  const native = await checkMicrophonePermission().catch(() => 'unknown');
+  if (native !== 'unknown') {
+    return native === 'granted';
+  }
+
+  // Use browser API for Windows or as fallback
   const state = await checkBrowserMicrophonePermission();
   return state === 'granted';
 }
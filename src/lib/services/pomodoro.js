@@ -0,0 +1,281 @@
+/**
+ * Pomodoro Service
+ * The timer runs in the Rust backend so it survives reloads, a hidden window and restarts
+ * Events: pomodoro://tick (PomodoroState, every second and on every change),
+ * pomodoro://phase (PomodoroState, when a phase ends or is skipped)
+ * pomodoro://distraction (PomodoroDistraction, when an app on the distraction list comes to the front during focus)
+ * Focus sessions are logged as they end, with the apps used, for the stats
+ * Phase lengths and order come from the active profile; "Default" is the pomodoro settings
+ */
+
+import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+
+/**
+ * @typedef {'work' | 'short_break' | 'long_break'} PomodoroPhase
+ */
+
+/** Built-in phase end sounds; a path to a .wav file also works */
+export const POMODORO_SOUNDS = ['chime', 'bell', 'beep'];
+
+/** Name of the profile kept in the pomodoro settings */
+export const DEFAULT_PROFILE = 'Default';
+
+/**
+ * @typedef {Object} PomodoroSounds
+ * @property {string|null} work_end - Played when focus ends (null = silent)
+ * @property {string|null} break_end - Played when a break ends (null = silent)
+ */
+
+/**
+ * @typedef {Object} ProfilePhase
+ * @property {PomodoroPhase} kind
+ * @property {number} minutes
+ */
+
+/**
+ * @typedef {Object} PomodoroProfile
+ * @property {string} name - Unique
+ * @property {ProfilePhase[]} phases - Run in order, then from the start again
+ * @property {PomodoroSounds} sounds
+ * @property {boolean} auto_start_breaks - Start a break as soon as focus ends
+ * @property {boolean} auto_start_work - Start focus as soon as a break ends
+ */
+
+/**
+ * @typedef {Object} PomodoroState
+ * @property {PomodoroPhase} phase
+ * @property {boolean} running - Counting down
+ * @property {boolean} paused - Stopped part-way through the phase
+ * @property {number} remaining_secs
+ * @property {number} phase_secs - Length of the current phase
+ * @property {number} completed_sessions - Work sessions run to the end since the last full reset (skipped ones aren't counted)
+ * @property {number} sessions_until_long_break - Focus phases per pass through the profile
+ * @property {number} focus_secs - Focus time since the last full reset
+ * @property {number|null} ends_at - When the running phase ends (Unix ms)
+ * @property {number|null} session_started_at - When the current focus session started (Unix ms)
+ * @property {string|null} task - Label logged with each focus session
+ * @property {Record<string, number>} session_apps - Seconds per foreground app in the current focus session
+ * @property {string} profile - Profile the timer follows
+ * @property {number} step - Position of the current phase in the profile
+ * @property {boolean} follows_completed - The previous phase was focus that ran out and was counted
+ */
+
+/**
+ * @typedef {Object} AppFocus
+ * @property {string} name
+ * @property {number} seconds
+ * @property {number} percent - Share of the tracked time, 0 to 100
+ * @property {boolean} distraction - On the distraction list
+ */
+
+/**
+ * @typedef {Object} FocusReport
+ * @property {AppFocus[]} apps - Most used first
+ * @property {number} tracked_secs
+ * @property {number} distracted_secs
+ * @property {number|null} focus_score - Time outside distracting apps, 0 to 100 (null when apps can't be sampled)
+ */
+
+/**
+ * @typedef {Object} PomodoroDistraction
+ * @property {string} app
+ * @property {string|null} task
+ * @property {number} remaining_secs
+ */
+
+/**
+ * @typedef {Object} PomodoroSession
+ * @property {string} id
+ * @property {number} started_at - Unix ms
+ * @property {number} ended_at - Unix ms
+ * @property {number} planned_secs
+ * @property {number} actual_secs - Time spent counting down, pauses excluded
+ * @property {boolean} completed - Ran to the end rather than being skipped or reset
+ * @property {string|null} task
+ * @property {FocusReport|null} focus - Apps used, when they could be sampled
+ */
+
+/**
+ * @typedef {Object} DailyPomodoros
+ * @property {string} date - YYYY-MM-DD
+ * @property {number} completed
+ * @property {number} interrupted
+ * @property {number} focus_secs
+ */
+
+/**
+ * @typedef {Object} PomodoroStats
+ * @property {string} from - YYYY-MM-DD
+ * @property {string} to - YYYY-MM-DD
+ * @property {number} completed
+ * @property {number} interrupted
+ * @property {number} completion_rate - 0 to 1
+ * @property {number} focus_secs
+ * @property {DailyPomodoros[]} daily - Every day of the range
+ * @property {number[]} by_hour - Completed sessions by starting hour
+ * @property {number|null} best_hour
+ * @property {number} current_streak - Days in a row with a completed session
+ * @property {number} longest_streak
+ */
+
+/**
+ * @returns {Promise<PomodoroState>}
+ */
+export async function getPomodoroState() {
+  return await invoke('get_pomodoro_state');
+}
+
+/**
+ * Start or resume the current phase
+ * @returns {Promise<PomodoroState>}
+ */
+export async function startPomodoro() {
+  return await invoke('start_pomodoro');
+}
+
+/**
+ * @returns {Promise<PomodoroState>}
+ */
+export async function pausePomodoro() {
+  return await invoke('pause_pomodoro');
+}
+
+/**
+ * End the current phase early and move to the next one; skipped focus isn't counted
+ * @returns {Promise<PomodoroState>}
+ */
+export async function skipPomodoroPhase() {
+  return await invoke('skip_pomodoro_phase');
+}
+
+/**
+ * Go back to the previous phase
+ * @returns {Promise<PomodoroState>}
+ */
+export async function previousPomodoroPhase() {
+  return await invoke('previous_pomodoro_phase');
+}
+
+/**
+ * Restart the current phase, stopped
+ * @returns {Promise<PomodoroState>}
+ */
+export async function resetPomodoroPhase() {
+  return await invoke('reset_pomodoro_phase');
+}
+
+/**
+ * Back to the first focus phase with sessions and focus time cleared
+ * @returns {Promise<PomodoroState>}
+ */
+export async function resetPomodoro() {
+  return await invoke('reset_pomodoro');
+}
+
+/**
+ * Set the task focus sessions are logged under
+ * @param {string|null} task - Empty or null clears it
+ * @returns {Promise<PomodoroState>}
+ */
+export async function setPomodoroTask(task) {
+  return await invoke('set_pomodoro_task', { task });
+}
+
+/**
+ * All timer profiles, Default first
+ * @returns {Promise<PomodoroProfile[]>}
+ */
+export async function listPomodoroProfiles() {
+  return await invoke('list_pomodoro_profiles');
+}
+
+/**
+ * Add or replace a profile (Default is changed through the pomodoro settings)
+ * @param {PomodoroProfile} profile
+ * @returns {Promise<void>}
+ */
+export async function savePomodoroProfile(profile) {
+  return await invoke('save_pomodoro_profile', { profile });
+}
+
+/**
+ * @param {string} name
+ * @returns {Promise<void>}
+ */
+export async function deletePomodoroProfile(name) {
+  return await invoke('delete_pomodoro_profile', { name });
+}
+
+/**
+ * Switch profile; a phase in progress runs out first
+ * @param {string|null} name - null or "Default" for the pomodoro settings
+ * @returns {Promise<PomodoroState>}
+ */
+export async function setActivePomodoroProfile(name) {
+  return await invoke('set_active_pomodoro_profile', { name });
+}
+
+/**
+ * Play a phase end sound
+ * @param {string} sound - Built-in name or .wav path
+ * @returns {Promise<void>}
+ */
+export async function previewPomodoroSound(sound) {
+  return await invoke('preview_pomodoro_sound', { sound });
+}
+
+/**
+ * Apps used and focus score of the current focus session so far
+ * @returns {Promise<FocusReport>}
+ */
+export async function getPomodoroFocus() {
+  return await invoke('get_pomodoro_focus');
+}
+
+/**
+ * Logged focus sessions, oldest first
+ * @param {string} [from] - YYYY-MM-DD (default: 30 days before `to`)
+ * @param {string} [to] - YYYY-MM-DD (default: today)
+ * @returns {Promise<PomodoroSession[]>}
+ */
+export async function getPomodoroHistory(from, to) {
+  return await invoke('get_pomodoro_history', { from: from ?? null, to: to ?? null });
+}
+
+/**
+ * Daily counts, streaks, best time of day and completion rate
+ * @param {string} [from] - YYYY-MM-DD (default: 30 days before `to`)
+ * @param {string} [to] - YYYY-MM-DD (default: today)
+ * @returns {Promise<PomodoroStats>}
+ */
+export async function getPomodoroStats(from, to) {
+  return await invoke('get_pomodoro_stats', { from: from ?? null, to: to ?? null });
+}
+
+/**
+ * Listen for timer updates
+ * @param {(state: PomodoroState) => void} callback
+ * @returns {Promise<() => void>} Unlisten function
+ */
+export async function onPomodoroTick(callback) {
+  return await listen('pomodoro://tick', (event) => callback(/** @type {PomodoroState} */ (event.payload)));
+}
+
+/**
+ * Listen for phase changes
+ * @param {(state: PomodoroState) => void} callback
+ * @returns {Promise<() => void>} Unlisten function
+ */
+export async function onPomodoroPhase(callback) {
+  return await listen('pomodoro://phase', (event) => callback(/** @type {PomodoroState} */ (event.payload)));
+}
+
+/**
+ * Listen for distracting apps coming to the front during focus
+ * @param {(distraction: PomodoroDistraction) => void} callback
+ * @returns {Promise<() => void>} Unlisten function
+ */
+export async function onPomodoroDistraction(callback) {
+  return await listen('pomodoro://distraction', (event) => callback(/** @type {PomodoroDistraction} */ (event.payload)));
+}
@@ -0,0 +1,111 @@
+/**
+ * Prompt templates and personas
+ * Templates are files in the brain's prompts folder, rendered by the Rust backend
+ *
+ * Template syntax: {{variable}} inserts a value, {{#variable}}...{{/variable}} is kept only when it is set.
+ * Variables: persona, persona_upper, user, user_upper, memory, screen_context, recent_messages, time, active_app
+ */
+
+import { invoke } from '@tauri-apps/api/core';
+
+/**
+ * @typedef {Object} Persona
+ * @property {string} name - Unique name, used as the persona name in prompts
+ * @property {string} wake_word - Wake word for voice activation
+ * @property {string|null} [chat_template] - Chat template file (null = the UI preset's default)
+ * @property {string|null} [screen_template] - Screen analysis template file (null = the UI preset's default)
+ * @property {string|null} [voice] - Speech voice (null = tts_voice from config)
+ */
+
+/**
+ * @typedef {Object} EffectivePersona
+ * @property {string} name - Persona name in use
+ * @property {string} wake_word - Wake word in use
+ * @property {string|null} persona - Active persona (null = persona_name and wake_word from config)
+ */
+
+/**
+ * @typedef {Object} PromptPreview
+ * @property {string} rendered - Template rendered with sample values
+ * @property {string[]} errors - Syntax problems and unknown variables
+ */
+
+/**
+ * List template files in the prompts folder (built-in templates are created on first use)
+ * @returns {Promise<string[]>}
+ */
+export async function listPromptTemplates() {
+  return await invoke('list_prompt_templates');
+}
+
+/**
+ * Read a template file
+ * @param {string} name
+ * @returns {Promise<string>}
+ */
+export async function loadPromptTemplate(name) {
+  return await invoke('load_prompt_template', { name });
+}
+
+/**
+ * Write a template file; templates with errors are rejected
+ * @param {string} name
+ * @param {string} content
+ * @returns {Promise<void>}
+ */
+export async function savePromptTemplate(name, content) {
+  return await invoke('save_prompt_template', { name, content });
+}
+
+/**
+ * Validate a template and render it with sample values
+ * @param {string} template
+ * @returns {Promise<PromptPreview>}
+ */
+export async function previewPromptTemplate(template) {
+  return await invoke('preview_prompt_template', { template });
+}
+
+/**
+ * List configured personas
+ * @returns {Promise<Persona[]>}
+ */
+export async function listPersonas() {
+  return await invoke('list_personas');
+}
+
+/**
+ * Add or replace a persona
+ * @param {Persona} persona
+ * @returns {Promise<void>}
+ */
+export async function savePersona(persona) {
+  return await invoke('save_persona', { persona });
+}
+
+/**
+ * Delete a persona
+ * @param {string} name
+ * @returns {Promise<void>}
+ */
+export async function deletePersona(name) {
+  return await invoke('delete_persona', { name });
+}
+
+/**
+ * Switch persona; its name and wake word are used in place of persona_name and wake_word,
+ * which stay as they are in config
+ * @param {string | null} name - null = back to those settings and the preset's default prompts
+ * @returns {Promise<void>}
+ */
+export async function setActivePersona(name) {
+  return await invoke('set_active_persona', { name });
+}
+
+/**
+ * Name and wake word in effect: the active persona's, else the config settings
+ * @returns {Promise<EffectivePersona>}
+ */
+export async function getEffectivePersona() {
+  return await invoke('get_effective_persona');
+}
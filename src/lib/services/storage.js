@@ -11,9 +11,14 @@ import { invoke } from '@tauri-apps/api/core';
  * @typedef {Object} AppConfig
  * @property {string} persona_name - AI persona name
  * @property {string} [user_name] - User's name for personalized responses
- * @property {string} ai_provider - AI provider (openai, anthropic, gemini)
- * @property {string|null} api_key - AI API key
+ * @property {string} ai_provider - AI provider (openai, anthropic, gemini, local)
+ * @property {string|null} [api_key] - New API key to store (write-only, never returned by loadConfig)
+ * @property {string[]} [api_key_providers] - Providers with a key in the secret store
+ * @property {boolean} [has_api_key] - Whether the current provider has a stored key
  * @property {string} ai_model - AI model to use
+ * @property {string} [local_base_url] - Local model server URL (Ollama, llama.cpp, LM Studio)
+ * @property {import('./ai.js').ProviderProfile[]} [provider_profiles] - Named provider profiles
+ * @property {string[]} [fallback_chain] - Profile names tried in order (empty = single provider)
  * @property {number} capture_interval_ms - Screen capture interval
  * @property {boolean} vision_enabled - Whether vision is enabled
  * @property {boolean} voice_enabled - Whether voice is enabled
@@ -24,10 +29,41 @@ import { invoke } from '@tauri-apps/api/core';
  * @property {ThemeConfig} theme - Theme settings
  * @property {string|null} [data_folder_path] - Custom data folder path
  * @property {number|null} [selected_monitor] - Selected monitor index for capture
- * @property {string|null} [selected_microphone] - Selected microphone device ID
+ * @property {string|null} [selected_microphone] - Selected microphone ID (see list_microphones)
  * @property {boolean} [screentime_enabled] - Whether screen time tracking is enabled
  * @property {string} [preset] - UI preset (preset1, preset2, etc.)
  * @property {boolean} [web_search_enabled] - Whether AI web search is enabled
+ * @property {'duckduckgo' | 'searxng' | 'vendor'} [web_search_provider] - Search backend (vendor = the AI provider's built-in search)
+ * @property {string|null} [searxng_url] - SearXNG instance for the searxng provider
+ * @property {'browser' | 'native'} [voice_engine] - Speech recognition (native = offline pipeline in the backend; needs a build with the whisper feature or a local transcription server)
+ * @property {'whisper' | 'server'} [stt_engine] - Transcriber for the native pipeline (whisper only in builds with it)
+ * @property {string|null} [stt_model_path] - ggml Whisper model file (null = models/ggml-base.en.bin in app data)
+ * @property {string|null} [stt_base_url] - OpenAI-compatible transcription server (required for the server engine)
+ * @property {string} [stt_model] - Model name sent to the transcription server
+ * @property {string} [stt_language] - Spoken language code, or "auto"
+ * @property {boolean} [tts_enabled] - Read assistant responses aloud
+ * @property {boolean} [tts_muted] - Speech silenced without turning it off
+ * @property {'espeak' | 'piper'} [tts_engine] - Local speech synthesizer
+ * @property {string|null} [tts_command] - Synthesizer executable (null = espeak-ng or piper on PATH)
+ * @property {string} [tts_voice] - eSpeak voice name, or Piper model name/path
+ * @property {boolean} [tts_robot_filter] - Ring-modulated "robot" voice filter
+ * @property {string[]} [focus_distractions] - Apps that count as distractions during pomodoro focus
+ * @property {string[]} [network_include] - Interfaces counted in the network totals (empty = every physical one, "en*" matches a prefix)
+ * @property {string[]} [network_exclude] - Interfaces never counted in the network totals
+ * @property {boolean} [network_include_virtual] - Count loopback and virtual interfaces in the network totals
+ * @property {string|null} [primary_disk] - Mount point of the disk in the headline gauge (null = the system disk)
+ * @property {boolean} [system_history_persist] - Keep the system metrics history across restarts
+ * @property {boolean} [replay_enabled] - Whether the screen replay buffer is running
+ * @property {number} [replay_fps] - Replay buffer frames per second
+ * @property {number} [replay_seconds] - Seconds of screen history kept
+ * @property {number|null} [daily_spend_cap] - Daily AI spend cap in USD (pauses screen analysis)
+ * @property {number|null} [monthly_spend_cap] - Monthly AI spend cap in USD
+ * @property {'builtin' | 'local'} [embedding_provider] - Embedder for brain search (builtin: offline keyword matching; local: embedding model)
+ * @property {string|null} [embedding_base_url] - OpenAI-compatible embeddings server (required for the server engine)
+ * @property {string} [embedding_model] - Embedding model on that server
+ * @property {number|null} [conversation_retention_days] - Days to keep conversations (null = forever)
+ * @property {import('./prompts.js').Persona[]} [personas] - Selectable personas
+ * @property {string|null} [active_persona] - Persona in use (null = persona_name, wake_word and preset prompts)
  */
 
 /**
@@ -111,6 +147,42 @@ export async function changeDataFolder(newPath, migrate = true) {
   return await invoke('change_data_folder', { newPath, migrate });
 }
 
+/**
+ * @typedef {Object} ApiKeyStatus
+ * @property {string} provider - Provider id
+ * @property {boolean} is_set - Whether a key is stored
+ * @property {'keyring' | 'file' | null} backend - Where the key is stored
+ * @property {string|null} error - Why the encrypted file store couldn't be read (damaged, or the master secret changed)
+ */
+
+/**
+ * Store an API key in the secret store
+ * @param {string} provider - Provider id (openai, anthropic, gemini, local)
+ * @param {string} key - API key
+ * @returns {Promise<ApiKeyStatus>}
+ */
+export async function setApiKey(provider, key) {
+  return await invoke('set_api_key', { provider, key });
+}
+
+/**
+ * Remove a provider's API key from the secret store
+ * @param {string} provider - Provider id
+ * @returns {Promise<void>}
+ */
+export async function deleteApiKey(provider) {
+  return await invoke('delete_api_key', { provider });
+}
+
+/**
+ * Check which providers have a stored API key
+ * @param {string[]} providers - Provider ids to check
+ * @returns {Promise<ApiKeyStatus[]>}
+ */
+export async function getApiKeyStatus(providers) {
+  return await invoke('get_api_key_status', { providers });
+}
+
 /**
  * Get autostart enabled status
  * @returns {Promise<boolean>}
@@ -0,0 +1,145 @@
+/**
+ * System Monitor Service
+ * The backend samples every metric once a second and keeps a rolling history:
+ * 1 s points for 5 minutes, 10 s points for an hour and 1 minute points for a day
+ * Set system_history_persist in the config to keep it across restarts
+ * Alert rules are checked against every reading
+ * Events: system://alert (SystemAlert, when a rule fires)
+ */
+
+import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+
+/**
+ * @typedef {'cpu' | 'cpu_cores' | 'memory' | 'swap' | 'network' | 'disk_io' | 'temperature'} HistoryMetric
+ * cpu, cpu_cores, memory and swap are percentages; network and disk_io bytes per second; temperature °C
+ */
+
+/** Ranges matching the history resolutions */
+export const HISTORY_RANGES = ['5m', '1h', '24h'];
+
+/**
+ * @typedef {Object} HistoryPoint
+ * @property {number} time - Unix ms
+ * @property {(number|null)[]} values - One per series, null when it couldn't be read
+ */
+
+/**
+ * @typedef {Object} SystemHistory
+ * @property {HistoryMetric} metric
+ * @property {number} resolution_secs - Seconds between points
+ * @property {string[]} series - What each value is ("in"/"out", core numbers, ...)
+ * @property {HistoryPoint[]} points - Oldest first
+ */
+
+/**
+ * History of a metric, at the finest resolution that covers the range
+ * @param {HistoryMetric} metric
+ * @param {string} range - e.g. "5m", "1h", "24h"
+ * @returns {Promise<SystemHistory>}
+ */
+export async function getSystemHistory(metric, range) {
+  return await invoke('get_system_history', { metric, range });
+}
+
+/**
+ * @typedef {'cpu' | 'memory' | 'swap' | 'cpu_temperature' | 'gpu_temperature' | 'disk' | 'network_in' | 'network_out'} AlertMetric
+ * cpu, memory, swap and disk (checked per disk) are percentages; temperatures °C; network bytes per second
+ */
+
+/** Metrics an alert rule can watch, with their units */
+export const ALERT_METRICS = /** @type {Array<{ metric: AlertMetric, unit: string }>} */ ([
+  { metric: 'cpu', unit: '%' },
+  { metric: 'memory', unit: '%' },
+  { metric: 'swap', unit: '%' },
+  { metric: 'cpu_temperature', unit: '°C' },
+  { metric: 'gpu_temperature', unit: '°C' },
+  { metric: 'disk', unit: '%' },
+  { metric: 'network_in', unit: 'B/s' },
+  { metric: 'network_out', unit: 'B/s' }
+]);
+
+/**
+ * @typedef {Object} AlertRule
+ * @property {string} id - Empty for a new rule
+ * @property {string} name
+ * @property {AlertMetric} metric
+ * @property {'above' | 'below'} comparison
+ * @property {number} threshold
+ * @property {number} sustained_secs - How long the condition has to hold
+ * @property {number} cooldown_secs - Quiet time after firing
+ * @property {boolean} enabled
+ * @property {boolean} notify - Show a desktop notification
+ */
+
+/**
+ * @typedef {Object} SystemAlert
+ * @property {string} id
+ * @property {string} rule_id
+ * @property {string} rule_name
+ * @property {AlertMetric} metric
+ * @property {'above' | 'below'} comparison
+ * @property {number} threshold
+ * @property {number} value
+ * @property {string|null} subject - Mount point for disk alerts
+ * @property {string} message
+ * @property {number} time - Unix ms
+ */
+
+/**
+ * Rejects while the saved rules file is damaged; the defaults run meanwhile and saving or deleting is refused until it is fixed, removed or reset
+ * @returns {Promise<AlertRule[]>}
+ */
+export async function listAlertRules() {
+  return await invoke('list_alert_rules');
+}
+
+/**
+ * Add a rule (empty id) or replace the one with the same id
+ * @param {AlertRule} rule
+ * @returns {Promise<AlertRule[]>} All rules
+ */
+export async function saveAlertRule(rule) {
+  return await invoke('save_alert_rule', { rule });
+}
+
+/**
+ * @param {string} id
+ * @returns {Promise<AlertRule[]>} The remaining rules
+ */
+export async function deleteAlertRule(id) {
+  return await invoke('delete_alert_rule', { id });
+}
+
+/**
+ * Put the shipped rules back (CPU temperature, memory, swap, disk full), also over a damaged rules file
+ * @returns {Promise<AlertRule[]>}
+ */
+export async function resetAlertRules() {
+  return await invoke('reset_alert_rules');
+}
+
+/**
+ * Fired alerts, newest first
+ * @param {number} [limit]
+ * @returns {Promise<SystemAlert[]>}
+ */
+export async function getAlertHistory(limit) {
+  return await invoke('get_alert_history', { limit: limit ?? null });
+}
+
+/**
+ * @returns {Promise<void>}
+ */
+export async function clearAlertHistory() {
+  return await invoke('clear_alert_history');
+}
+
+/**
+ * Listen for alerts as they fire
+ * @param {(alert: SystemAlert) => void} callback
+ * @returns {Promise<() => void>} Unlisten function
+ */
+export async function onSystemAlert(callback) {
+  return await listen('system://alert', (event) => callback(/** @type {SystemAlert} */ (event.payload)));
+}
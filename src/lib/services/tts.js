@@ -0,0 +1,85 @@
+/**
+ * Speech Output Service
+ * Responses are spoken by a local synthesizer (eSpeak NG or Piper) in the Rust backend
+ * Events: tts://speaking (boolean), tts://muted (boolean), tts://error (string)
+ */
+
+import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+
+/**
+ * @typedef {Object} SpeechStatus
+ * @property {boolean} speaking
+ * @property {boolean} muted
+ * @property {number} queued - Sentences waiting to be spoken
+ */
+
+/**
+ * Speak text after anything already being said
+ * @param {string} text
+ * @returns {Promise<void>}
+ */
+export async function speak(text) {
+  return await invoke('speak', { text });
+}
+
+/**
+ * Speak a streamed response as it arrives; complete sentences are spoken right away
+ * @param {string} text - Next chunk of the response
+ * @param {boolean} [done=false] - Last chunk, speak whatever is left
+ * @returns {Promise<void>}
+ */
+export async function speakPartial(text, done = false) {
+  return await invoke('speak_partial', { text, done });
+}
+
+/**
+ * Stop speaking and drop queued sentences
+ * @returns {Promise<void>}
+ */
+export async function stopSpeaking() {
+  return await invoke('stop_speaking');
+}
+
+/**
+ * Mute or unmute speech (saved in config)
+ * @param {boolean} muted
+ * @returns {Promise<void>}
+ */
+export async function setTtsMuted(muted) {
+  return await invoke('set_tts_muted', { muted });
+}
+
+/**
+ * @returns {Promise<SpeechStatus>}
+ */
+export async function getSpeechStatus() {
+  return await invoke('get_speech_status');
+}
+
+/**
+ * List voices of the configured engine (eSpeak voices, or Piper models in the voices folder)
+ * @returns {Promise<string[]>}
+ */
+export async function listTtsVoices() {
+  return await invoke('list_tts_voices');
+}
+
+/**
+ * Synthesize text to a WAV file with the current voice and filter
+ * @param {string} text
+ * @param {string} [path] - Output file (default: the captures folder)
+ * @returns {Promise<string>} Path of the written file
+ */
+export async function exportSpeechWav(text, path) {
+  return await invoke('export_speech_wav', { text, path: path ?? null });
+}
+
+/**
+ * Listen for speaking state changes
+ * @param {(speaking: boolean) => void} callback
+ * @returns {Promise<() => void>} Unlisten function
+ */
+export async function onSpeakingChange(callback) {
+  return await listen('tts://speaking', (event) => callback(/** @type {boolean} */ (event.payload)));
+}
@@ -1,14 +1,45 @@
 /**
- * Voice Input Service using Web Speech API
+ * Voice Input Service
+ * Uses the webview's Web Speech API, or the offline pipeline in the Rust backend (engine 'native')
  * Supports always-listening wake word detection with smart command capture
  */
 
+import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+
+/**
+ * @typedef {Object} Transcript
+ * @property {string} text - What was said
+ * @property {number} start_ms - Offset of the utterance in the stream or file
+ * @property {number} end_ms
+ * @property {boolean} wake_word - Whether the wake word was heard
+ * @property {string|null} command - Command for the assistant, if this utterance carried one
+ */
+
+/**
+ * @typedef {Object} VoiceStatus
+ * @property {boolean} listening
+ * @property {boolean} command_mode - Waiting for a command after a bare wake word
+ * @property {string|null} engine - Transcription engine in use
+ * @property {string|null} device - Microphone in use
+ * @property {string|null} error - Last pipeline error
+ */
+
 /** @type {any} */
 let recognition = null;
 let isListening = false;
 let wakeWord = 'joshua';
 let alwaysListening = false;
 
+/** @type {'browser' | 'native'} */
+let engine = 'browser';
+
+/** @type {Array<() => void>} */
+let nativeUnlisteners = [];
+
+/** @type {string | null} */
+let lastNativeError = null;
+
 // Command mode state
 let inCommandMode = false;
 let commandBuffer = '';
@@ -38,9 +69,13 @@ let onCommandModeCallback = null;
 
 /**
  * Check if speech recognition is supported
+ * @param {string} [engineName] - 'browser' or 'native' (defaults to the current engine)
  * @returns {boolean}
  */
-export function isSupported() {
+export function isSupported(engineName = engine) {
+  if (engineName === 'native') {
+    return true;
+  }
   // @ts-ignore - webkitSpeechRecognition is vendor-prefixed
   return 'webkitSpeechRecognition' in window || 'SpeechRecognition' in window;
 }
@@ -104,16 +139,23 @@ function exitCommandMode() {
  * @param {string} [options.language='en-US'] - Recognition language
  * @param {string} [options.wakeWord='joshua'] - Wake word to listen for
  * @param {boolean} [options.alwaysListen=false] - Always listen for wake word
+ * @param {string} [options.engine='browser'] - 'browser' (Web Speech API) or 'native' (offline backend pipeline)
  */
 export function init(options = {}) {
+  engine = options.engine === 'native' ? 'native' : 'browser';
+  wakeWord = (options.wakeWord || 'joshua').toLowerCase();
+  alwaysListening = options.alwaysListen || false;
+
+  if (engine === 'native') {
+    // Wake word, language and microphone come from config in the backend
+    return true;
+  }
+
   if (!isSupported()) {
     console.error('Speech recognition not supported');
     return false;
   }
 
-  wakeWord = (options.wakeWord || 'joshua').toLowerCase();
-  alwaysListening = options.alwaysListen || false;
-
   // @ts-ignore - webkitSpeechRecognition is vendor-prefixed
   const SpeechRecognition = window.SpeechRecognition || window.webkitSpeechRecognition;
   recognition = new SpeechRecognition();
@@ -260,6 +302,11 @@ function scheduleRestart() {
  * @returns {boolean} Whether listening started successfully
  */
 export function startListening() {
+  if (engine === 'native') {
+    startNativeListening();
+    return true;
+  }
+
   if (!recognition) {
     init({ alwaysListen: alwaysListening, wakeWord });
   }
@@ -292,6 +339,11 @@ export function stopListening() {
     exitCommandMode();
   }
 
+  if (engine === 'native') {
+    stopNativeListening();
+    return;
+  }
+
   if (recognition && isListening) {
     recognition.stop();
   }
@@ -309,7 +361,7 @@ export function startAlwaysListening(newWakeWord) {
 
   alwaysListening = true;
 
-  if (!recognition) {
+  if (!recognition && engine === 'browser') {
     init({ alwaysListen: true, wakeWord });
   }
 
@@ -431,6 +483,10 @@ export function destroy() {
     recognition = null;
   }
 
+  if (engine === 'native') {
+    stopNativeListening();
+  }
+
   onCommandCallback = null;
   onErrorCallback = null;
   onStartCallback = null;
@@ -439,3 +495,93 @@ export function destroy() {
   onCommandModeCallback = null;
   isListening = false;
 }
+
+/**
+ * Subscribe to the backend pipeline's events, mapped onto the same callbacks as the browser engine
+ */
+async function listenNative() {
+  if (nativeUnlisteners.length > 0) {
+    return;
+  }
+
+  nativeUnlisteners = await Promise.all([
+    listen('voice://state', (/** @type {{ payload: VoiceStatus }} */ event) => {
+      const status = event.payload;
+      if (status.listening && !isListening) {
+        isListening = true;
+        if (onStartCallback) onStartCallback();
+      } else if (!status.listening && isListening) {
+        isListening = false;
+        if (onEndCallback) onEndCallback();
+      }
+      // Status updates repeat the last error, report it once
+      if (status.error && status.error !== lastNativeError && onErrorCallback) {
+        onErrorCallback(status.error);
+      }
+      lastNativeError = status.error;
+    }),
+    listen('voice://wake', (/** @type {{ payload: Transcript }} */ event) => {
+      if (onWakeWordCallback) {
+        onWakeWordCallback(true, event.payload.text);
+      }
+    }),
+    listen('voice://command-mode', (/** @type {{ payload: boolean }} */ event) => {
+      inCommandMode = event.payload;
+      if (onCommandModeCallback) {
+        onCommandModeCallback(inCommandMode);
+      }
+    }),
+    listen('voice://command', (/** @type {{ payload: string }} */ event) => {
+      if (onCommandCallback) {
+        onCommandCallback(event.payload);
+      }
+    })
+  ]);
+}
+
+/**
+ * Start the backend pipeline on the configured microphone
+ */
+async function startNativeListening() {
+  try {
+    await listenNative();
+    await invoke('start_voice_listening');
+  } catch (e) {
+    console.error('Failed to start voice pipeline:', e);
+    if (onErrorCallback) {
+      onErrorCallback(String(e));
+    }
+  }
+}
+
+/**
+ * Stop the backend pipeline and drop event listeners
+ */
+function stopNativeListening() {
+  invoke('stop_voice_listening').catch((e) => console.error('Failed to stop voice pipeline:', e));
+  nativeUnlisteners.forEach((unlisten) => unlisten());
+  nativeUnlisteners = [];
+  if (isListening) {
+    isListening = false;
+    if (onEndCallback) onEndCallback();
+  }
+}
+
+/**
+ * Get the backend pipeline status
+ * @returns {Promise<VoiceStatus>}
+ */
+export async function getVoiceStatus() {
+  return await invoke('get_voice_status');
+}
+
+/**
+ * Run a WAV file through the backend pipeline, as if it were spoken into the microphone
+ * Emits the same events as live listening, so the registered callbacks fire too
+ * @param {string} path
+ * @returns {Promise<Transcript[]>}
+ */
+export async function processVoiceFile(path) {
+  await listenNative();
+  return await invoke('process_voice_file', { path });
+}
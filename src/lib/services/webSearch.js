@@ -0,0 +1,41 @@
+/**
+ * Web search
+ * Searches run in the Rust backend through the configured provider (DuckDuckGo or a SearXNG instance),
+ * so they work with every AI model, including local ones
+ */
+
+import { invoke } from '@tauri-apps/api/core';
+
+/**
+ * @typedef {Object} SearchResult
+ * @property {string} title
+ * @property {string} url
+ * @property {string} snippet
+ */
+
+/**
+ * @typedef {Object} PageText
+ * @property {string} url - Final URL after redirects
+ * @property {string|null} title
+ * @property {string} text - Readable text, without navigation and scripts
+ * @property {boolean} truncated - True when the text was cut short
+ */
+
+/**
+ * Search the web with the configured provider
+ * @param {string} query
+ * @param {number} [maxResults=5]
+ * @returns {Promise<SearchResult[]>}
+ */
+export async function webSearch(query, maxResults = 5) {
+  return await invoke('web_search', { query, maxResults });
+}
+
+/**
+ * Fetch a public web page and extract its readable text
+ * @param {string} url
+ * @returns {Promise<PageText>}
+ */
+export async function fetchPageText(url) {
+  return await invoke('fetch_page_text', { url });
+}